@@ -1,3 +1,4 @@
+use crate::image::{OutputColourSpace, PreviewFormat};
 use snafu::Snafu;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -20,6 +21,31 @@ pub struct Configuration {
     /// The height of the output image.
     #[structopt(short = "h", long = "height", default_value = "1080")]
     pub height: u32,
+
+    /// The number of sub-pixel samples along each side of a pixel.
+    #[structopt(long = "sub-pixels", default_value = "2")]
+    pub sub_pixels: u8,
+
+    /// Render with spectral sampling rather than RGB, for dispersion and spectral lights. Needs more sub-pixel
+    /// samples for a clean image.
+    #[structopt(long = "spectral")]
+    pub spectral: bool,
+
+    /// Scale the image so its log-average luminance maps to middle grey before saving.
+    #[structopt(short = "a", long = "auto-exposure")]
+    pub auto_exposure: bool,
+
+    /// The colour space of the output image; one of srgb, display-p3 or rec2020.
+    #[structopt(long = "output-colour-space", default_value = "srgb")]
+    pub output_colour_space: OutputColourSpace,
+
+    /// Show the finished image in the terminal; one of blocks, sixel or kitty.
+    #[structopt(long = "preview")]
+    pub preview: Option<PreviewFormat>,
+
+    /// With --preview, also show snapshots of the image while it renders.
+    #[structopt(long = "progressive", requires = "preview")]
+    pub progressive: bool,
 }
 
 pub fn from_command_line() -> Result<Configuration> {
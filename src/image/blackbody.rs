@@ -0,0 +1,87 @@
+use crate::image::{cie, Colour};
+use crate::maths::Scalar;
+
+const PLANCK: f64 = 6.626_070_15e-34;
+const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+const BOLTZMANN: f64 = 1.380_649e-23;
+
+const WAVELENGTH_STEP: Scalar = 5.0;
+
+/// Spectral radiance of a black body at the given wavelength in nanometres, from Planck's law.
+#[cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+pub fn planck(wavelength: Scalar, kelvin: Scalar) -> Scalar {
+    // Calculated in double precision as the intermediate values are well outside the range of an f32.
+    let metres = wavelength as f64 * 1e-9;
+    let numerator = 2.0 * PLANCK * SPEED_OF_LIGHT * SPEED_OF_LIGHT;
+    let denominator = metres.powi(5) * ((PLANCK * SPEED_OF_LIGHT / (metres * BOLTZMANN * kelvin as f64)).exp() - 1.0);
+    return (numerator / denominator) as Scalar;
+}
+
+/// The colour of a black body at the given temperature, normalised so the brightest channel is 1. Temperatures that
+/// fall outside the sRGB gamut have their negative channels clipped to 0.
+pub fn colour(kelvin: Scalar) -> Colour {
+    let mut x = 0.0;
+    let mut y = 0.0;
+    let mut z = 0.0;
+
+    let mut wavelength = cie::MINIMUM_WAVELENGTH;
+    while wavelength <= cie::MAXIMUM_WAVELENGTH {
+        let radiance = planck(wavelength, kelvin);
+        let (x_bar, y_bar, z_bar) = cie::colour_matching(wavelength);
+        x += radiance * x_bar;
+        y += radiance * y_bar;
+        z += radiance * z_bar;
+        wavelength += WAVELENGTH_STEP;
+    }
+
+    let colour = cie::xyz_to_colour(x, y, z);
+    let colour = Colour::new(colour.r.max(0.0), colour.g.max(0.0), colour.b.max(0.0));
+
+    let brightest = colour.r.max(colour.g).max(colour.b);
+    if brightest <= 0.0 {
+        return Colour::black();
+    }
+
+    return colour / brightest;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn planck_peak_follows_wiens_law() {
+        // Wien's displacement law puts the peak for 5800K at ~500nm.
+        assert!(planck(500.0, 5800.0) > planck(400.0, 5800.0));
+        assert!(planck(500.0, 5800.0) > planck(600.0, 5800.0));
+    }
+
+    #[test]
+    fn colour_6504k_is_near_white() {
+        // D65 sits slightly off the Planckian locus, so the black body is a little magenta in comparison.
+        let colour = colour(6504.0);
+
+        assert_abs_diff_eq!(colour.r, 1.0, epsilon = 0.01);
+        assert_abs_diff_eq!(colour.g, 1.0, epsilon = 0.1);
+        assert_abs_diff_eq!(colour.b, 1.0, epsilon = 0.01);
+    }
+
+    #[test]
+    fn colour_low_temperature_is_red() {
+        let colour = colour(2000.0);
+
+        assert_abs_diff_eq!(colour.r, 1.0, epsilon = 0.001);
+        assert!(colour.g < 0.3);
+        assert!(colour.b < 0.05);
+    }
+
+    #[test]
+    fn colour_high_temperature_is_blue() {
+        let colour = colour(15000.0);
+
+        assert_abs_diff_eq!(colour.b, 1.0, epsilon = 0.001);
+        assert!(colour.r < 0.5);
+        assert!(colour.g < 0.6);
+    }
+}
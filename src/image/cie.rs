@@ -0,0 +1,63 @@
+use crate::image::Colour;
+use crate::maths::Scalar;
+
+pub const MINIMUM_WAVELENGTH: Scalar = 380.0;
+pub const MAXIMUM_WAVELENGTH: Scalar = 780.0;
+
+/// CIE 1931 2° standard observer colour matching functions for a wavelength in nanometres. Uses the multi-lobe
+/// Gaussian fit from Wyman, Sloan and Shirley, "Simple Analytic Approximations to the CIE XYZ Color Matching Functions".
+pub fn colour_matching(wavelength: Scalar) -> (Scalar, Scalar, Scalar) {
+    let x = 1.056 * gaussian(wavelength, 599.8, 37.9, 31.0) + 0.362 * gaussian(wavelength, 442.0, 16.0, 26.7) - 0.065 * gaussian(wavelength, 501.1, 20.4, 26.2);
+    let y = 0.821 * gaussian(wavelength, 568.8, 46.9, 40.5) + 0.286 * gaussian(wavelength, 530.9, 16.3, 31.1);
+    let z = 1.217 * gaussian(wavelength, 437.0, 11.8, 36.0) + 0.681 * gaussian(wavelength, 459.0, 26.0, 13.8);
+
+    return (x, y, z);
+}
+
+fn gaussian(x: Scalar, mean: Scalar, sigma_below: Scalar, sigma_above: Scalar) -> Scalar {
+    let sigma = if x < mean { sigma_below } else { sigma_above };
+    let t = (x - mean) / sigma;
+    return (-0.5 * t * t).exp();
+}
+
+/// Converts CIE XYZ to linear RGB with sRGB primaries and a D65 white point.
+pub fn xyz_to_colour(x: Scalar, y: Scalar, z: Scalar) -> Colour {
+    return Colour::new(
+        3.240_454 * x - 1.537_138 * y - 0.498_531 * z,
+        -0.969_266 * x + 1.876_011 * y + 0.041_556 * z,
+        0.055_643 * x - 0.204_026 * y + 1.057_225 * z,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        wavelength,
+        expected_x,
+        expected_y,
+        expected_z,
+        case(450.0, 0.336, 0.038, 1.772),
+        case(555.0, 0.512, 1.000, 0.006),
+        case(600.0, 1.062, 0.631, 0.001)
+    )]
+    fn colour_matching(wavelength: Scalar, expected_x: Scalar, expected_y: Scalar, expected_z: Scalar) {
+        let (x, y, z) = super::colour_matching(wavelength);
+
+        assert_abs_diff_eq!(x, expected_x, epsilon = 0.05);
+        assert_abs_diff_eq!(y, expected_y, epsilon = 0.05);
+        assert_abs_diff_eq!(z, expected_z, epsilon = 0.05);
+    }
+
+    #[test]
+    fn xyz_to_colour_d65_white() {
+        let colour = xyz_to_colour(0.9505, 1.0, 1.089);
+
+        assert_abs_diff_eq!(colour.r, 1.0, epsilon = 0.01);
+        assert_abs_diff_eq!(colour.g, 1.0, epsilon = 0.01);
+        assert_abs_diff_eq!(colour.b, 1.0, epsilon = 0.01);
+    }
+}
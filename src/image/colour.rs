@@ -3,7 +3,7 @@ use crate::maths::Scalar;
 use std::fmt;
 use std::ops::{Add, Div, Mul, Sub};
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Colour {
     pub r: Scalar,
     pub g: Scalar,
@@ -15,24 +15,62 @@ impl Colour {
         return Colour { r, g, b };
     }
 
+    /// Creates a colour from a hue in degrees and saturation and value in the range 0 to 1.
+    pub fn from_hsv(hue: Scalar, saturation: Scalar, value: Scalar) -> Colour {
+        let chroma = value * saturation;
+        return Colour::from_hue_and_chroma(hue, chroma, value - chroma);
+    }
+
+    /// Creates a colour from a hue in degrees and saturation and lightness in the range 0 to 1.
+    pub fn from_hsl(hue: Scalar, saturation: Scalar, lightness: Scalar) -> Colour {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        return Colour::from_hue_and_chroma(hue, chroma, lightness - chroma / 2.0);
+    }
+
+    fn from_hue_and_chroma(hue: Scalar, chroma: Scalar, minimum: Scalar) -> Colour {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        return Colour::new(r + minimum, g + minimum, b + minimum);
+    }
+
+    /// Creates a colour from sRGB encoded components, removing the sRGB transfer function.
+    pub fn from_srgb(r: Scalar, g: Scalar, b: Scalar) -> Colour {
+        return Colour::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+    }
+
+    /// The sRGB encoded components of the colour, applying the sRGB transfer function; the inverse of `from_srgb`.
+    pub fn to_srgb(&self) -> Colour {
+        return Colour::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b));
+    }
+
     pub fn average(colours: &[Colour]) -> Colour {
-        return colours.iter().sum::<Colour>() / colours.len() as f32;
+        return colours.iter().sum::<Colour>() / colours.len() as Scalar;
     }
 
     pub fn to_rgb(&self) -> Rgb {
         let rgb = self.clamp() * 255.0;
         return Rgb {
-            r: rgb.r as u8,
-            g: rgb.g as u8,
-            b: rgb.b as u8,
+            r: rgb.r.round() as u8,
+            g: rgb.g.round() as u8,
+            b: rgb.b.round() as u8,
         };
     }
 
     pub fn clamp(&self) -> Colour {
         return Colour {
-            r: self.r.min(1.0).max(0.0),
-            g: self.g.min(1.0).max(0.0),
-            b: self.b.min(1.0).max(0.0),
+            r: self.r.clamp(0.0, 1.0),
+            g: self.g.clamp(0.0, 1.0),
+            b: self.b.clamp(0.0, 1.0),
         };
     }
 
@@ -41,6 +79,20 @@ impl Colour {
     }
 }
 
+fn srgb_to_linear(component: Scalar) -> Scalar {
+    if component <= 0.04045 {
+        return component / 12.92;
+    }
+    return ((component + 0.055) / 1.055).powf(2.4);
+}
+
+fn linear_to_srgb(component: Scalar) -> Scalar {
+    if component <= 0.0031308 {
+        return component * 12.92;
+    }
+    return 1.055 * component.powf(1.0 / 2.4) - 0.055;
+}
+
 // TODO: Clamp on operators.
 macro_rules! colour_operator {
     ($trait:ident, $method:ident, $operator:tt) => {
@@ -202,7 +254,7 @@ mod tests {
     case(1.0, 1.0, 1.0, 0xff, 0xff, 0xff),
     case(-1.0, -0.1, -50.0, 0x00, 0x00, 0x00),
     case(2.0, 1.1, 100.0, 0xff, 0xff, 0xff),
-    case(0.25, 0.5, 0.75, 0x3f, 0x7f, 0xbf)
+    case(0.25, 0.5, 0.75, 0x40, 0x80, 0xbf)
     )]
     fn to_rgb(x: Scalar, y: Scalar, z: Scalar, expected_r: u8, expected_g: u8, expected_b: u8) {
         let colour = Colour::new(x, y, z);
@@ -221,6 +273,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn add_value_reference() {
         let actual = Colour::new(0.1, 0.2, 1.5) + &Colour::new(0.5, 0.1, -0.3);
         let expected = Colour::new(0.6, 0.3, 1.2);
@@ -228,6 +281,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn add_reference_value() {
         let actual = &Colour::new(0.1, 0.2, 1.5) + Colour::new(0.5, 0.1, -0.3);
         let expected = Colour::new(0.6, 0.3, 1.2);
@@ -235,6 +289,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn add_reference_reference() {
         let actual = &Colour::new(0.1, 0.2, 1.5) + &Colour::new(0.5, 0.1, -0.3);
         let expected = Colour::new(0.6, 0.3, 1.2);
@@ -265,7 +320,7 @@ mod tests {
 
     #[test]
     fn sum_by_reference() {
-        let colours = vec![Colour::new(0.1, 0.2, 1.5), Colour::new(0.5, 0.1, -0.3), Colour::new(0.3, 0.2, 0.3)];
+        let colours = [Colour::new(0.1, 0.2, 1.5), Colour::new(0.5, 0.1, -0.3), Colour::new(0.3, 0.2, 0.3)];
         let actual: Colour = colours.iter().sum();
         let expected = Colour::new(0.9, 0.5, 1.5);
         assert_eq(actual, expected);
@@ -286,6 +341,60 @@ mod tests {
         assert_eq(actual, expected);
     }
 
+    #[rstest(
+        hue,
+        saturation,
+        value,
+        expected,
+        case(0.0, 1.0, 1.0, Colour::new(1.0, 0.0, 0.0)),
+        case(120.0, 1.0, 1.0, Colour::new(0.0, 1.0, 0.0)),
+        case(240.0, 1.0, 0.5, Colour::new(0.0, 0.0, 0.5)),
+        case(60.0, 0.5, 1.0, Colour::new(1.0, 1.0, 0.5)),
+        case(300.0, 1.0, 1.0, Colour::new(1.0, 0.0, 1.0)),
+        case(-60.0, 1.0, 1.0, Colour::new(1.0, 0.0, 1.0)),
+        case(180.0, 0.0, 0.3, Colour::new(0.3, 0.3, 0.3))
+    )]
+    fn from_hsv(hue: Scalar, saturation: Scalar, value: Scalar, expected: Colour) {
+        assert_eq(Colour::from_hsv(hue, saturation, value), expected);
+    }
+
+    #[rstest(
+        hue,
+        saturation,
+        lightness,
+        expected,
+        case(0.0, 1.0, 0.5, Colour::new(1.0, 0.0, 0.0)),
+        case(120.0, 1.0, 0.25, Colour::new(0.0, 0.5, 0.0)),
+        case(240.0, 1.0, 0.75, Colour::new(0.5, 0.5, 1.0)),
+        case(30.0, 1.0, 0.5, Colour::new(1.0, 0.5, 0.0)),
+        case(0.0, 0.0, 1.0, Colour::new(1.0, 1.0, 1.0))
+    )]
+    fn from_hsl(hue: Scalar, saturation: Scalar, lightness: Scalar, expected: Colour) {
+        assert_eq(Colour::from_hsl(hue, saturation, lightness), expected);
+    }
+
+    #[rstest(
+        r,
+        g,
+        b,
+        expected,
+        case(0.0, 0.0, 0.0, Colour::new(0.0, 0.0, 0.0)),
+        case(1.0, 1.0, 1.0, Colour::new(1.0, 1.0, 1.0)),
+        case(0.5, 0.04, 0.8, Colour::new(0.214, 0.0031, 0.604))
+    )]
+    fn from_srgb(r: Scalar, g: Scalar, b: Scalar, expected: Colour) {
+        assert_eq(Colour::from_srgb(r, g, b), expected);
+    }
+
+    #[rstest(r, g, b, case(0.0, 0.0, 0.0), case(1.0, 1.0, 1.0), case(0.5, 0.04, 0.8), case(0.002, 0.3, 0.9))]
+    fn to_srgb(r: Scalar, g: Scalar, b: Scalar) {
+        let colour = Colour::from_srgb(r, g, b).to_srgb();
+
+        assert_abs_diff_eq!(colour.r, r, epsilon = 0.0001);
+        assert_abs_diff_eq!(colour.g, g, epsilon = 0.0001);
+        assert_abs_diff_eq!(colour.b, b, epsilon = 0.0001);
+    }
+
     fn assert_eq(actual: Colour, expected: Colour) {
         assert_abs_diff_eq!(actual.r, expected.r, epsilon = 0.01);
         assert_abs_diff_eq!(actual.g, expected.g, epsilon = 0.01);
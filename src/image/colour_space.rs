@@ -0,0 +1,344 @@
+use crate::image::{icc, Colour};
+use crate::maths::Scalar;
+use nalgebra::{Matrix3, Vector3};
+use std::fmt;
+use std::str::FromStr;
+
+type ColourMatrix = Matrix3<Scalar>;
+
+// Bradford cone response matrix, used to adapt between white points.
+#[rustfmt::skip]
+const BRADFORD: [Scalar; 9] = [
+    0.8951, 0.2664, -0.1614,
+    -0.7502, 1.7135, 0.0367,
+    0.0389, -0.0685, 1.0296,
+];
+
+const D65: (Scalar, Scalar) = (0.3127, 0.3290);
+const D60: (Scalar, Scalar) = (0.32168, 0.33767);
+const D50: (Scalar, Scalar) = (0.3457, 0.3585);
+
+/// The CIE xy chromaticities of the primaries and white point of an RGB colour space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Primaries {
+    pub red: (Scalar, Scalar),
+    pub green: (Scalar, Scalar),
+    pub blue: (Scalar, Scalar),
+    pub white: (Scalar, Scalar),
+}
+
+/// The colour space scene colours are given in and shading is done in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WorkingColourSpace {
+    LinearSrgb,
+    Rec2020,
+    AcesCg,
+}
+
+/// The colour space images are written in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputColourSpace {
+    Srgb,
+    DisplayP3,
+    Rec2020,
+}
+
+/// How the components of images in an output colour space encode linear light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransferFunction {
+    /// Used by Display P3 as well as sRGB.
+    Srgb,
+    /// The ITU-R BT.2020 curve, which is the same as BT.709's.
+    Rec2020,
+}
+
+/// Converts colours from one set of primaries to another, adapting the white point if necessary.
+#[derive(Debug, Copy, Clone)]
+pub struct ColourSpaceConversion {
+    matrix: ColourMatrix,
+}
+
+impl Primaries {
+    pub const SRGB: Primaries = Primaries {
+        red: (0.64, 0.33),
+        green: (0.30, 0.60),
+        blue: (0.15, 0.06),
+        white: D65,
+    };
+
+    pub const DISPLAY_P3: Primaries = Primaries {
+        red: (0.680, 0.320),
+        green: (0.265, 0.690),
+        blue: (0.150, 0.060),
+        white: D65,
+    };
+
+    pub const REC2020: Primaries = Primaries {
+        red: (0.708, 0.292),
+        green: (0.170, 0.797),
+        blue: (0.131, 0.046),
+        white: D65,
+    };
+
+    /// ACES AP1, as used by ACEScg.
+    pub const ACES_AP1: Primaries = Primaries {
+        red: (0.713, 0.293),
+        green: (0.165, 0.830),
+        blue: (0.128, 0.044),
+        white: D60,
+    };
+
+    pub fn rgb_to_xyz(&self) -> ColourMatrix {
+        let primaries = ColourMatrix::from_columns(&[xy_to_xyz(self.red), xy_to_xyz(self.green), xy_to_xyz(self.blue)]);
+
+        // Scale each primary so that RGB white maps to the white point.
+        let scale = primaries.try_inverse().unwrap() * xy_to_xyz(self.white);
+
+        return primaries * ColourMatrix::from_diagonal(&scale);
+    }
+
+    pub fn xyz_to_rgb(&self) -> ColourMatrix {
+        return self.rgb_to_xyz().try_inverse().unwrap();
+    }
+
+    /// Adapts XYZ colours from the white point to D50, the white point of ICC profiles.
+    pub fn adaptation_to_d50(&self) -> ColourMatrix {
+        return chromatic_adaptation(self.white, D50);
+    }
+
+    /// The contribution of each of the red, green and blue channels to luminance.
+    pub fn luminance_weights(&self) -> Colour {
+        let matrix = self.rgb_to_xyz();
+        return Colour::new(matrix[(1, 0)], matrix[(1, 1)], matrix[(1, 2)]);
+    }
+}
+
+fn xy_to_xyz((x, y): (Scalar, Scalar)) -> Vector3<Scalar> {
+    return Vector3::new(x / y, 1.0, (1.0 - x - y) / y);
+}
+
+fn chromatic_adaptation(from: (Scalar, Scalar), to: (Scalar, Scalar)) -> ColourMatrix {
+    if from == to {
+        return ColourMatrix::identity();
+    }
+
+    let bradford = ColourMatrix::from_row_slice(&BRADFORD);
+    let from_cone = bradford * xy_to_xyz(from);
+    let to_cone = bradford * xy_to_xyz(to);
+    let scale = ColourMatrix::from_diagonal(&to_cone.component_div(&from_cone));
+
+    return bradford.try_inverse().unwrap() * scale * bradford;
+}
+
+impl ColourSpaceConversion {
+    pub fn new(from: &Primaries, to: &Primaries) -> ColourSpaceConversion {
+        let matrix = to.xyz_to_rgb() * chromatic_adaptation(from.white, to.white) * from.rgb_to_xyz();
+        return ColourSpaceConversion { matrix };
+    }
+
+    /// Conversion from CIE XYZ with a D65 white point.
+    pub fn from_xyz(to: &Primaries) -> ColourSpaceConversion {
+        let matrix = to.xyz_to_rgb() * chromatic_adaptation(D65, to.white);
+        return ColourSpaceConversion { matrix };
+    }
+
+    pub fn convert(&self, colour: &Colour) -> Colour {
+        let converted = self.matrix * Vector3::new(colour.r, colour.g, colour.b);
+        return Colour::new(converted.x, converted.y, converted.z);
+    }
+}
+
+impl WorkingColourSpace {
+    pub fn primaries(&self) -> Primaries {
+        return match self {
+            WorkingColourSpace::LinearSrgb => Primaries::SRGB,
+            WorkingColourSpace::Rec2020 => Primaries::REC2020,
+            WorkingColourSpace::AcesCg => Primaries::ACES_AP1,
+        };
+    }
+
+    /// Converts a colour with linear sRGB primaries into this colour space.
+    pub fn from_linear_srgb(&self, colour: &Colour) -> Colour {
+        if *self == WorkingColourSpace::LinearSrgb {
+            return *colour;
+        }
+        return ColourSpaceConversion::new(&Primaries::SRGB, &self.primaries()).convert(colour);
+    }
+}
+
+impl Default for WorkingColourSpace {
+    fn default() -> WorkingColourSpace {
+        return WorkingColourSpace::LinearSrgb;
+    }
+}
+
+impl OutputColourSpace {
+    pub fn primaries(&self) -> Primaries {
+        return match self {
+            OutputColourSpace::Srgb => Primaries::SRGB,
+            OutputColourSpace::DisplayP3 => Primaries::DISPLAY_P3,
+            OutputColourSpace::Rec2020 => Primaries::REC2020,
+        };
+    }
+
+    pub fn transfer_function(&self) -> TransferFunction {
+        return match self {
+            OutputColourSpace::Srgb | OutputColourSpace::DisplayP3 => TransferFunction::Srgb,
+            OutputColourSpace::Rec2020 => TransferFunction::Rec2020,
+        };
+    }
+
+    /// A profile to embed in images so viewers show their colours correctly. sRGB doesn't need one, as it's what
+    /// viewers assume.
+    pub fn icc_profile(&self) -> Option<Vec<u8>> {
+        return match self {
+            OutputColourSpace::Srgb => None,
+            _ => Some(icc::profile(&self.to_string(), &self.primaries(), self.transfer_function())),
+        };
+    }
+}
+
+impl TransferFunction {
+    pub fn encode(&self, colour: &Colour) -> Colour {
+        return match self {
+            TransferFunction::Srgb => colour.to_srgb(),
+            TransferFunction::Rec2020 => Colour::new(rec2020_encode(colour.r), rec2020_encode(colour.g), rec2020_encode(colour.b)),
+        };
+    }
+}
+
+fn rec2020_encode(component: Scalar) -> Scalar {
+    if component < 0.018 {
+        return component * 4.5;
+    }
+    return 1.099 * component.powf(0.45) - 0.099;
+}
+
+impl FromStr for OutputColourSpace {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<OutputColourSpace, String> {
+        return match text.to_ascii_lowercase().as_str() {
+            "srgb" => Ok(OutputColourSpace::Srgb),
+            "display-p3" | "p3" => Ok(OutputColourSpace::DisplayP3),
+            "rec2020" => Ok(OutputColourSpace::Rec2020),
+            _ => Err(format!("unknown colour space {:?}; expected srgb, display-p3 or rec2020", text)),
+        };
+    }
+}
+
+impl fmt::Display for OutputColourSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            OutputColourSpace::Srgb => write!(f, "sRGB"),
+            OutputColourSpace::DisplayP3 => write!(f, "Display P3"),
+            OutputColourSpace::Rec2020 => write!(f, "Rec. 2020"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn assert_colour(actual: Colour, expected: Colour) {
+        assert_abs_diff_eq!(actual.r, expected.r, epsilon = 0.001);
+        assert_abs_diff_eq!(actual.g, expected.g, epsilon = 0.001);
+        assert_abs_diff_eq!(actual.b, expected.b, epsilon = 0.001);
+    }
+
+    #[test]
+    fn srgb_to_xyz() {
+        let matrix = Primaries::SRGB.rgb_to_xyz();
+
+        #[rustfmt::skip]
+        let expected = ColourMatrix::new(
+            0.4124, 0.3576, 0.1805,
+            0.2126, 0.7152, 0.0722,
+            0.0193, 0.1192, 0.9505,
+        );
+
+        assert_abs_diff_eq!(matrix, expected, epsilon = 0.001);
+    }
+
+    #[test]
+    fn srgb_luminance_weights() {
+        assert_colour(Primaries::SRGB.luminance_weights(), Colour::new(0.2126, 0.7152, 0.0722));
+    }
+
+    #[test]
+    fn srgb_to_rec2020() {
+        let conversion = ColourSpaceConversion::new(&Primaries::SRGB, &Primaries::REC2020);
+
+        assert_colour(conversion.convert(&Colour::new(1.0, 0.0, 0.0)), Colour::new(0.6274, 0.0691, 0.0164));
+        assert_colour(conversion.convert(&Colour::new(0.0, 1.0, 0.0)), Colour::new(0.3293, 0.9195, 0.0880));
+        assert_colour(conversion.convert(&Colour::new(0.0, 0.0, 1.0)), Colour::new(0.0433, 0.0114, 0.8956));
+    }
+
+    #[test]
+    fn acescg_to_srgb() {
+        let conversion = ColourSpaceConversion::new(&Primaries::ACES_AP1, &Primaries::SRGB);
+
+        assert_colour(conversion.convert(&Colour::new(1.0, 0.0, 0.0)), Colour::new(1.7051, -0.1302, -0.0240));
+    }
+
+    #[rstest(
+        from,
+        to,
+        case(Primaries::SRGB, Primaries::SRGB),
+        case(Primaries::SRGB, Primaries::DISPLAY_P3),
+        case(Primaries::REC2020, Primaries::SRGB),
+        case(Primaries::ACES_AP1, Primaries::SRGB),
+        case(Primaries::SRGB, Primaries::ACES_AP1),
+        case(Primaries::ACES_AP1, Primaries::REC2020)
+    )]
+    fn white_is_preserved(from: Primaries, to: Primaries) {
+        let conversion = ColourSpaceConversion::new(&from, &to);
+
+        assert_colour(conversion.convert(&Colour::new(1.0, 1.0, 1.0)), Colour::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn from_xyz_d65_white() {
+        let conversion = ColourSpaceConversion::from_xyz(&Primaries::ACES_AP1);
+
+        assert_colour(conversion.convert(&Colour::new(0.9505, 1.0, 1.089)), Colour::new(1.0, 1.0, 1.0));
+    }
+
+    #[rstest(
+        text,
+        expected,
+        case("srgb", Ok(OutputColourSpace::Srgb)),
+        case("sRGB", Ok(OutputColourSpace::Srgb)),
+        case("display-p3", Ok(OutputColourSpace::DisplayP3)),
+        case("p3", Ok(OutputColourSpace::DisplayP3)),
+        case("rec2020", Ok(OutputColourSpace::Rec2020))
+    )]
+    fn output_colour_space_from_str(text: &str, expected: Result<OutputColourSpace, String>) {
+        assert_eq!(text.parse::<OutputColourSpace>(), expected);
+    }
+
+    #[rstest(
+        transfer_function,
+        linear,
+        expected,
+        case(TransferFunction::Srgb, 0.0, 0.0),
+        case(TransferFunction::Srgb, 0.2140, 0.5),
+        case(TransferFunction::Srgb, 1.0, 1.0),
+        case(TransferFunction::Rec2020, 0.01, 0.045),
+        case(TransferFunction::Rec2020, 0.2597, 0.5),
+        case(TransferFunction::Rec2020, 1.0, 1.0)
+    )]
+    fn encode(transfer_function: TransferFunction, linear: Scalar, expected: Scalar) {
+        let encoded = transfer_function.encode(&Colour::new(linear, linear, linear));
+
+        assert_colour(encoded, Colour::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn output_colour_space_from_str_invalid() {
+        assert!("adobe".parse::<OutputColourSpace>().is_err());
+    }
+}
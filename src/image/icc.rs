@@ -0,0 +1,270 @@
+use crate::image::{Primaries, TransferFunction};
+use crate::maths::Scalar;
+
+// The D50 white point of the profile connection space, as XYZ.
+const D50: [Scalar; 3] = [0.9642, 1.0, 0.8249];
+
+// Transfer functions decode as the parameters g, a, b, c and d of an ICC parametric curve of type 3, where
+// Y = (aX + b)^g for X >= d and Y = cX otherwise.
+const SRGB_CURVE: [Scalar; 5] = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+const REC2020_CURVE: [Scalar; 5] = [1.0 / 0.45, 1.0 / 1.099, 0.099 / 1.099, 1.0 / 4.5, 0.081];
+
+/// An ICC version 4 display profile for RGB images with the given primaries and transfer function, so viewers know
+/// which colours the pixels are.
+pub fn profile(description: &str, primaries: &Primaries, transfer_function: TransferFunction) -> Vec<u8> {
+    let adaptation = primaries.adaptation_to_d50();
+    let colorants = adaptation * primaries.rgb_to_xyz();
+    let curve = parametric_curve(curve_parameters(transfer_function));
+
+    // The red, green and blue curves are the same so share one tag.
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", text(description)),
+        (b"cprt", text("No copyright, use freely")),
+        (b"wtpt", xyz(&D50)),
+        (b"chad", matrix(adaptation.transpose().as_slice())),
+        (b"rXYZ", xyz(colorants.column(0).as_slice())),
+        (b"gXYZ", xyz(colorants.column(1).as_slice())),
+        (b"bXYZ", xyz(colorants.column(2).as_slice())),
+        (b"rTRC", curve),
+    ];
+    let shared: [&[u8; 4]; 2] = [b"gTRC", b"bTRC"];
+
+    let table_size = 4 + 12 * (tags.len() + shared.len());
+    let mut data = vec![];
+    let mut table = vec![];
+    let mut offset = 128 + table_size;
+    for (signature, tag) in &tags {
+        table.push((*signature, offset, tag.len()));
+        data.extend_from_slice(tag);
+        offset += tag.len();
+        // Tags start on four byte boundaries.
+        while !offset.is_multiple_of(4) {
+            data.push(0);
+            offset += 1;
+        }
+    }
+    let (_, curve_offset, curve_size) = table[table.len() - 1];
+    table.extend(shared.iter().map(|&signature| (signature, curve_offset, curve_size)));
+
+    let size = 128 + table_size + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]);
+    profile.extend_from_slice(&[4, 0x30, 0, 0]);
+    profile.extend_from_slice(b"mntrRGB XYZ ");
+    // 2020-01-01 00:00:00, so profiles are the same every time.
+    for part in &[2020u16, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&part.to_be_bytes());
+    }
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 24]);
+    // Perceptual rendering intent.
+    profile.extend_from_slice(&[0; 4]);
+    for &component in &D50 {
+        profile.extend_from_slice(&s15_fixed_16(component));
+    }
+    profile.extend_from_slice(&[0; 48]);
+
+    profile.extend_from_slice(&(table.len() as u32).to_be_bytes());
+    for (signature, offset, size) in table {
+        profile.extend_from_slice(signature);
+        profile.extend_from_slice(&(offset as u32).to_be_bytes());
+        profile.extend_from_slice(&(size as u32).to_be_bytes());
+    }
+    profile.extend_from_slice(&data);
+    return profile;
+}
+
+/// A PNG iCCP chunk embedding a profile.
+pub fn png_chunk(name: &str, profile: &[u8]) -> Vec<u8> {
+    let mut data = name.as_bytes().to_vec();
+    // A null separator then zlib compression, the only method.
+    data.extend_from_slice(&[0, 0]);
+    data.extend(zlib_stored(profile));
+
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(b"iCCP");
+    chunk.extend(data);
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+    return chunk;
+}
+
+// Zlib data with the bytes in uncompressed blocks; profiles are small enough not to be worth compressing.
+fn zlib_stored(bytes: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = match bytes.is_empty() {
+        true => vec![&[]],
+        false => bytes.chunks(u16::MAX as usize).collect(),
+    };
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push((index == blocks.len() - 1) as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(bytes).to_be_bytes());
+    return zlib;
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    return (b << 16) | a;
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    return !crc;
+}
+
+fn s15_fixed_16(value: Scalar) -> [u8; 4] {
+    return ((value * 65536.0).round() as i32).to_be_bytes();
+}
+
+// A multi-localised Unicode tag with a single English string.
+fn text(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect();
+
+    let mut tag = b"mluc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&1u32.to_be_bytes());
+    tag.extend_from_slice(&12u32.to_be_bytes());
+    tag.extend_from_slice(b"enUS");
+    tag.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+    tag.extend_from_slice(&28u32.to_be_bytes());
+    tag.extend_from_slice(&utf16);
+    return tag;
+}
+
+fn xyz(xyz: &[Scalar]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    tag.extend(xyz.iter().flat_map(|&component| s15_fixed_16(component)));
+    return tag;
+}
+
+// Row by row.
+fn matrix(elements: &[Scalar]) -> Vec<u8> {
+    let mut tag = b"sf32\0\0\0\0".to_vec();
+    tag.extend(elements.iter().flat_map(|&element| s15_fixed_16(element)));
+    return tag;
+}
+
+fn curve_parameters(transfer_function: TransferFunction) -> &'static [Scalar; 5] {
+    return match transfer_function {
+        TransferFunction::Srgb => &SRGB_CURVE,
+        TransferFunction::Rec2020 => &REC2020_CURVE,
+    };
+}
+
+fn parametric_curve(parameters: &[Scalar; 5]) -> Vec<u8> {
+    let mut tag = b"para\0\0\0\0".to_vec();
+    tag.extend_from_slice(&3u16.to_be_bytes());
+    tag.extend_from_slice(&[0; 2]);
+    tag.extend(parameters.iter().flat_map(|&parameter| s15_fixed_16(parameter)));
+    return tag;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Colour;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn read_u32(profile: &[u8], offset: usize) -> u32 {
+        return u32::from_be_bytes([profile[offset], profile[offset + 1], profile[offset + 2], profile[offset + 3]]);
+    }
+
+    fn read_s15_fixed_16(profile: &[u8], offset: usize) -> Scalar {
+        return read_u32(profile, offset) as i32 as Scalar / 65536.0;
+    }
+
+    fn find_tag(profile: &[u8], signature: &[u8; 4]) -> usize {
+        let count = read_u32(profile, 128) as usize;
+        let entry = (0..count)
+            .map(|index| 132 + 12 * index)
+            .find(|&entry| &profile[entry..entry + 4] == signature)
+            .unwrap();
+        return read_u32(profile, entry + 4) as usize;
+    }
+
+    #[test]
+    fn header() {
+        let profile = profile("Display P3", &Primaries::DISPLAY_P3, TransferFunction::Srgb);
+
+        assert_eq!(read_u32(&profile, 0) as usize, profile.len());
+        assert_eq!(&profile[12..24], b"mntrRGB XYZ ");
+        assert_eq!(&profile[36..40], b"acsp");
+    }
+
+    #[test]
+    fn colorants_add_up_to_d50() {
+        let profile = profile("Rec. 2020", &Primaries::REC2020, TransferFunction::Rec2020);
+
+        let tags = [b"rXYZ", b"gXYZ", b"bXYZ"].map(|signature| find_tag(&profile, signature));
+        for (axis, &expected) in D50.iter().enumerate() {
+            let total: Scalar = tags.iter().map(|&tag| read_s15_fixed_16(&profile, tag + 8 + 4 * axis)).sum();
+            assert_abs_diff_eq!(total, expected, epsilon = 0.001);
+        }
+    }
+
+    // What a viewer decodes from the curve in the profile.
+    fn decode(parameters: &[Scalar; 5], encoded: Scalar) -> Scalar {
+        let [g, a, b, c, d] = *parameters;
+        return match encoded >= d {
+            true => (a * encoded + b).powf(g),
+            false => c * encoded,
+        };
+    }
+
+    #[rstest(transfer_function, case(TransferFunction::Srgb), case(TransferFunction::Rec2020))]
+    fn curves_decode_what_is_encoded(transfer_function: TransferFunction) {
+        let parameters = curve_parameters(transfer_function);
+
+        for step in 0..=100 {
+            let linear = step as Scalar / 100.0;
+            let encoded = transfer_function.encode(&Colour::new(linear, linear, linear)).r;
+            assert_abs_diff_eq!(decode(parameters, encoded), linear, epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn rec2020_has_its_own_curve() {
+        let profile = profile("Rec. 2020", &Primaries::REC2020, TransferFunction::Rec2020);
+
+        let red = find_tag(&profile, b"rTRC");
+        let gamma = read_s15_fixed_16(&profile, red + 12);
+
+        assert_abs_diff_eq!(gamma, 1.0 / 0.45, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn crc32_and_adler32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn curves_are_shared() {
+        let profile = profile("Display P3", &Primaries::DISPLAY_P3, TransferFunction::Srgb);
+
+        let red = find_tag(&profile, b"rTRC");
+
+        assert_eq!(&profile[red..red + 4], b"para");
+        assert_eq!(find_tag(&profile, b"gTRC"), red);
+        assert_eq!(find_tag(&profile, b"bTRC"), red);
+        assert!(red.is_multiple_of(4));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::image::{Colour, RgbImage};
+use crate::image::{Colour, ColourSpaceConversion, ImageStatistics, Primaries, RgbImage, TransferFunction};
+use crate::maths::Scalar;
 
 #[derive(Clone)]
 pub struct Image {
@@ -24,8 +25,13 @@ impl Image {
         };
     }
 
-    pub fn to_rgb_image(&self) -> RgbImage {
-        return RgbImage::from_rgb_pixels(self.width, self.height, &mut self.pixels.iter().map(|colour| colour.to_rgb()));
+    /// The image in eight bit colour, encoded with the transfer function of the colour space it is in.
+    pub fn to_rgb_image(&self, transfer_function: TransferFunction) -> RgbImage {
+        return RgbImage::from_rgb_pixels(
+            self.width,
+            self.height,
+            &mut self.pixels.iter().map(|colour| transfer_function.encode(colour).to_rgb()),
+        );
     }
 
     pub fn set_pixel(&mut self, x: u32, y: u32, colour: Colour) {
@@ -35,4 +41,24 @@ impl Image {
     pub fn get_pixel(&self, x: u32, y: u32) -> Colour {
         return self.pixels[(x + y * self.width) as usize];
     }
+
+    pub fn pixels(&self) -> &[Colour] {
+        return &self.pixels;
+    }
+
+    pub fn statistics(&self, primaries: &Primaries) -> ImageStatistics {
+        return ImageStatistics::new(self, primaries);
+    }
+
+    pub fn convert(&mut self, conversion: &ColourSpaceConversion) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = conversion.convert(pixel);
+        }
+    }
+
+    pub fn apply_exposure(&mut self, exposure: Scalar) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = *pixel * exposure;
+        }
+    }
 }
@@ -1,14 +1,35 @@
+pub mod blackbody;
+
+pub mod cie;
+
 mod colour;
 pub use self::colour::Colour;
 
+mod colour_space;
+pub use self::colour_space::*;
+
+mod icc;
+
+#[allow(clippy::module_inception)]
 pub mod image;
 pub use self::image::Image;
 
+mod named_colours;
+
 pub mod rgb;
 pub use self::rgb::Rgb;
 
 pub mod rgb_image;
 pub use self::rgb_image::RgbImage;
+
+pub mod spectrum;
+pub use self::spectrum::{SampledSpectrum, SampledWavelengths, Spectrum};
+
+mod statistics;
+pub use self::statistics::*;
+
+pub mod terminal_preview;
+pub use self::terminal_preview::{PreviewFormat, ProgressivePreview, TerminalPreview, TerminalSize};
 use snafu::Snafu;
 
 use std::path::PathBuf;
@@ -1,3 +1,4 @@
+use crate::image::named_colours::NAMED_COLOURS;
 use crate::image::Colour;
 use crate::maths::Scalar;
 use std::fmt;
@@ -22,6 +23,33 @@ impl Rgb {
         };
     }
 
+    /// Parses a CSS style hex colour, either `#rrggbb` or the `#rgb` shorthand.
+    pub fn parse_hex(text: &str) -> Option<Rgb> {
+        let digits = text.strip_prefix('#')?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        return match digits.len() {
+            6 => Some(Rgb::from_hex(u32::from_str_radix(digits, 16).ok()?)),
+            3 => {
+                let short = u32::from_str_radix(digits, 16).ok()?;
+                let (r, g, b) = ((short >> 8) & 0xf, (short >> 4) & 0xf, short & 0xf);
+                Some(Rgb::new((r * 0x11) as u8, (g * 0x11) as u8, (b * 0x11) as u8))
+            }
+            _ => None,
+        };
+    }
+
+    /// Looks up a CSS named colour, ignoring case.
+    pub fn from_name(name: &str) -> Option<Rgb> {
+        let lower = name.to_ascii_lowercase();
+        return NAMED_COLOURS
+            .binary_search_by(|(colour_name, _)| colour_name.cmp(&lower.as_str()))
+            .ok()
+            .map(|index| Rgb::from_hex(NAMED_COLOURS[index].1));
+    }
+
     pub fn to_colour(&self) -> Colour {
         return Colour::new(self.r as Scalar / 255.0, self.g as Scalar / 255.0, self.b as Scalar / 255.0);
     }
@@ -62,6 +90,34 @@ mod tests {
         assert_eq!(rgb.b, 0xde);
     }
 
+    #[rstest(
+        text,
+        expected,
+        case("#1278de", Some(Rgb::new(0x12, 0x78, 0xde))),
+        case("#1278DE", Some(Rgb::new(0x12, 0x78, 0xde))),
+        case("#f80", Some(Rgb::new(0xff, 0x88, 0x00))),
+        case("1278de", None),
+        case("#1278d", None),
+        case("#12 8de", None),
+        case("#gg78de", None)
+    )]
+    fn parse_hex(text: &str, expected: Option<Rgb>) {
+        assert_eq!(Rgb::parse_hex(text), expected);
+    }
+
+    #[rstest(
+        name,
+        expected,
+        case("aliceblue", Some(Rgb::from_hex(0xf0f8ff))),
+        case("rebeccapurple", Some(Rgb::from_hex(0x663399))),
+        case("DarkOrange", Some(Rgb::from_hex(0xff8c00))),
+        case("yellowgreen", Some(Rgb::from_hex(0x9acd32))),
+        case("notacolour", None)
+    )]
+    fn from_name(name: &str, expected: Option<Rgb>) {
+        assert_eq!(Rgb::from_name(name), expected);
+    }
+
     #[rstest(
         r,
         g,
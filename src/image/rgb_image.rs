@@ -1,9 +1,12 @@
-use crate::image::{Error, Image, Rgb};
+use crate::image::{icc, Error, Image, OutputColourSpace, Rgb};
 use image::codecs::png::PngEncoder;
 use image::ColorType;
-use std::fs::File;
+use std::fs;
 use std::path::Path;
 
+// The end of the signature and the header chunk that start every PNG.
+const PNG_HEADER_END: usize = 8 + 25;
+
 pub struct RgbImage {
     pub width: u32,
     pub height: u32,
@@ -27,11 +30,43 @@ impl RgbImage {
         };
     }
 
+    pub fn get_pixel(&self, x: u32, y: u32) -> &Rgb {
+        return &self.pixels[(x + y * self.width) as usize];
+    }
+
+    /// Resizes the image, averaging the pixels that fall into each output pixel when shrinking.
+    pub fn resize(&self, width: u32, height: u32) -> RgbImage {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+
+        for y in 0..height {
+            let (top, bottom) = source_range(y, height, self.height);
+            for x in 0..width {
+                let (left, right) = source_range(x, width, self.width);
+
+                let (mut r, mut g, mut b, mut count) = (0u32, 0u32, 0u32, 0u32);
+                for source_y in top..bottom {
+                    for source_x in left..right {
+                        let pixel = self.get_pixel(source_x, source_y);
+                        r += pixel.r as u32;
+                        g += pixel.g as u32;
+                        b += pixel.b as u32;
+                        count += 1;
+                    }
+                }
+
+                pixels.push(Rgb::new((r / count) as u8, (g / count) as u8, (b / count) as u8));
+            }
+        }
+
+        return RgbImage { width, height, pixels };
+    }
+
     pub fn to_image(&self) -> Image {
         return Image::from_colour_pixels(self.width, self.height, &mut self.pixels.iter().map(|rgb| rgb.to_colour()));
     }
 
-    pub fn save(&self, path: &Path) -> Result<(), Error> {
+    /// Saves the image as a PNG, with a profile for colour spaces other than sRGB.
+    pub fn save(&self, path: &Path, colour_space: OutputColourSpace) -> Result<(), Error> {
         let mut buffer: Vec<u8> = Vec::with_capacity((self.width * self.height * 3) as usize);
         for y in 0..self.height as usize {
             for x in 0..self.width as usize {
@@ -42,20 +77,19 @@ impl RgbImage {
             }
         }
 
-        let file;
-        match File::create(path) {
-            Ok(f) => file = f,
-            Err(e) => {
-                return Err(Error::CannotSaveImage {
-                    path: path.to_path_buf(),
-                    reason: e.to_string(),
-                })
-            }
+        let mut png = vec![];
+        if let Err(e) = PngEncoder::new(&mut png).encode(&buffer, self.width, self.height, ColorType::Rgb8) {
+            return Err(Error::CannotSaveImage {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            });
+        }
+        if let Some(profile) = colour_space.icc_profile() {
+            // The profile has to come before the image data, so straight after the header, which is always first.
+            png.splice(PNG_HEADER_END..PNG_HEADER_END, icc::png_chunk(&colour_space.to_string(), &profile));
         }
 
-        let encoder = PngEncoder::new(file);
-
-        return match encoder.encode(&buffer, self.width, self.height, ColorType::Rgb8) {
+        return match fs::write(path, png) {
             Ok(()) => Ok(()),
             Err(e) => Err(Error::CannotSaveImage {
                 path: path.to_path_buf(),
@@ -64,3 +98,36 @@ impl RgbImage {
         };
     }
 }
+
+// The range of source pixels covered by an output pixel; always at least one pixel so enlarging works too.
+fn source_range(index: u32, size: u32, source_size: u32) -> (u32, u32) {
+    let start = index * source_size / size;
+    let end = ((index + 1) * source_size / size).max(start + 1);
+    return (start, end.min(source_size));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_averages_when_shrinking() {
+        let pixels = vec![Rgb::new(0, 0, 0), Rgb::new(100, 200, 50), Rgb::new(50, 0, 0), Rgb::new(50, 0, 250)];
+        let image = RgbImage::from_rgb_pixels(2, 2, &mut pixels.into_iter());
+
+        let resized = image.resize(1, 1);
+
+        assert_eq!(resized.get_pixel(0, 0), &Rgb::new(50, 50, 75));
+    }
+
+    #[test]
+    fn resize_repeats_when_enlarging() {
+        let pixels = vec![Rgb::new(10, 20, 30), Rgb::new(40, 50, 60)];
+        let image = RgbImage::from_rgb_pixels(2, 1, &mut pixels.into_iter());
+
+        let resized = image.resize(4, 2);
+
+        assert_eq!(resized.get_pixel(1, 1), &Rgb::new(10, 20, 30));
+        assert_eq!(resized.get_pixel(2, 0), &Rgb::new(40, 50, 60));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::image::{blackbody, cie, Colour, ColourSpaceConversion, Primaries};
+use crate::maths::Scalar;
+use std::ops::{Add, Mul};
+
+/// The number of wavelengths carried by each spectral sample.
+pub const SPECTRUM_SAMPLES: usize = 4;
+
+// Integral of the CIE Y colour matching function over the visible range; normalises XYZ so a constant spectrum of 1
+// has a luminance of 1.
+const CIE_Y_INTEGRAL: Scalar = 106.92;
+
+// Scales the D65 table so it has a luminance of 1, i.e. it maps to RGB white.
+const D65_NORMALISATION: Scalar = 0.010_116;
+
+const INTEGRATION_STEP: Scalar = 5.0;
+
+// CIE standard illuminant D65 from 380nm to 780nm in 10nm steps.
+const D65: [Scalar; 41] = [
+    49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861, 115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405,
+    104.046, 100.0, 96.3342, 95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778, 78.2842, 69.7213, 71.6091, 74.349,
+    61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054, 63.3828,
+];
+
+// Basis spectra from Smits, "An RGB to Spectrum Conversion for Reflectances", sampled in 10 bins from 380nm to 720nm.
+const SMITS_MINIMUM_WAVELENGTH: Scalar = 380.0;
+const SMITS_MAXIMUM_WAVELENGTH: Scalar = 720.0;
+const SMITS_WHITE: [Scalar; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [Scalar; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [Scalar; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [Scalar; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [Scalar; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [Scalar; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [Scalar; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// A set of wavelengths chosen by hero wavelength sampling; the first is the hero and the rest are evenly spaced
+/// rotations of it through the visible range.
+#[derive(Debug, Copy, Clone)]
+pub struct SampledWavelengths {
+    pub lambda: [Scalar; SPECTRUM_SAMPLES],
+    pub pdf: Scalar,
+}
+
+/// Values of a spectrum at each of a set of `SampledWavelengths`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SampledSpectrum {
+    pub values: [Scalar; SPECTRUM_SAMPLES],
+}
+
+/// A spectral power distribution for a light.
+pub enum Spectrum {
+    Blackbody {
+        kelvin: Scalar,
+        scale: Scalar,
+    },
+    /// Piecewise linear between (wavelength, value) pairs sorted by wavelength.
+    Sampled {
+        samples: Vec<(Scalar, Scalar)>,
+    },
+}
+
+impl SampledWavelengths {
+    /// Samples wavelengths for a uniform random number `u` in the range [0, 1).
+    pub fn sample(u: Scalar) -> SampledWavelengths {
+        let range = cie::MAXIMUM_WAVELENGTH - cie::MINIMUM_WAVELENGTH;
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+        for (index, wavelength) in lambda.iter_mut().enumerate() {
+            let offset = (u + index as Scalar / SPECTRUM_SAMPLES as Scalar).fract();
+            *wavelength = cie::MINIMUM_WAVELENGTH + offset * range;
+        }
+
+        return SampledWavelengths { lambda, pdf: 1.0 / range };
+    }
+
+    pub fn hero(&self) -> Scalar {
+        return self.lambda[0];
+    }
+}
+
+impl SampledSpectrum {
+    pub fn new(values: [Scalar; SPECTRUM_SAMPLES]) -> SampledSpectrum {
+        return SampledSpectrum { values };
+    }
+
+    pub fn from_function(wavelengths: &SampledWavelengths, function: impl Fn(Scalar) -> Scalar) -> SampledSpectrum {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+        for (value, wavelength) in values.iter_mut().zip(wavelengths.lambda.iter()) {
+            *value = function(*wavelength);
+        }
+        return SampledSpectrum { values };
+    }
+
+    /// Upsamples an RGB reflectance, such as a material colour.
+    pub fn from_reflectance(colour: &Colour, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return SampledSpectrum::from_function(wavelengths, |wavelength| reflectance(colour, wavelength));
+    }
+
+    /// Upsamples an RGB emission, such as a light colour. White maps to D65 so it renders as white.
+    pub fn from_illuminant(colour: &Colour, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return SampledSpectrum::from_function(wavelengths, |wavelength| reflectance(colour, wavelength) * d65(wavelength));
+    }
+
+    /// Keeps only the hero wavelength, for paths whose direction depends on the wavelength. The hero is scaled up so
+    /// the estimate stays unbiased.
+    pub fn terminate_secondary(&self) -> SampledSpectrum {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+        values[0] = self.values[0] * SPECTRUM_SAMPLES as Scalar;
+        return SampledSpectrum { values };
+    }
+
+    pub fn to_xyz(&self, wavelengths: &SampledWavelengths) -> (Scalar, Scalar, Scalar) {
+        let mut x = 0.0;
+        let mut y = 0.0;
+        let mut z = 0.0;
+
+        for (value, wavelength) in self.values.iter().zip(wavelengths.lambda.iter()) {
+            let (x_bar, y_bar, z_bar) = cie::colour_matching(*wavelength);
+            x += value * x_bar;
+            y += value * y_bar;
+            z += value * z_bar;
+        }
+
+        let scale = 1.0 / (wavelengths.pdf * SPECTRUM_SAMPLES as Scalar * CIE_Y_INTEGRAL);
+        return (x * scale, y * scale, z * scale);
+    }
+
+    pub fn to_colour(&self, wavelengths: &SampledWavelengths) -> Colour {
+        let (x, y, z) = self.to_xyz(wavelengths);
+        return cie::xyz_to_colour(x, y, z);
+    }
+}
+
+impl Add<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    #[inline]
+    fn add(self, other: SampledSpectrum) -> SampledSpectrum {
+        let mut values = self.values;
+        for (value, other) in values.iter_mut().zip(other.values.iter()) {
+            *value += other;
+        }
+        return SampledSpectrum { values };
+    }
+}
+
+impl Mul<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    #[inline]
+    fn mul(self, other: SampledSpectrum) -> SampledSpectrum {
+        let mut values = self.values;
+        for (value, other) in values.iter_mut().zip(other.values.iter()) {
+            *value *= other;
+        }
+        return SampledSpectrum { values };
+    }
+}
+
+impl Mul<Scalar> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    #[inline]
+    fn mul(self, other: Scalar) -> SampledSpectrum {
+        let mut values = self.values;
+        for value in values.iter_mut() {
+            *value *= other;
+        }
+        return SampledSpectrum { values };
+    }
+}
+
+impl Mul<SampledSpectrum> for Scalar {
+    type Output = SampledSpectrum;
+
+    #[inline]
+    fn mul(self, other: SampledSpectrum) -> SampledSpectrum {
+        return other * self;
+    }
+}
+
+impl Spectrum {
+    /// A black body spectrum scaled so its brightest RGB channel equals `intensity`, matching `blackbody::colour`.
+    pub fn blackbody(kelvin: Scalar, intensity: Scalar) -> Spectrum {
+        let unscaled = Spectrum::Blackbody { kelvin, scale: 1.0 };
+        let colour = unscaled.to_colour(&Primaries::SRGB);
+        let brightest = colour.r.max(colour.g).max(colour.b);
+
+        return Spectrum::Blackbody {
+            kelvin,
+            scale: intensity / brightest,
+        };
+    }
+
+    pub fn sampled(mut samples: Vec<(Scalar, Scalar)>) -> Spectrum {
+        samples.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+        return Spectrum::Sampled { samples };
+    }
+
+    pub fn value(&self, wavelength: Scalar) -> Scalar {
+        return match self {
+            Spectrum::Blackbody { kelvin, scale } => scale * blackbody::planck(wavelength, *kelvin),
+            Spectrum::Sampled { samples } => interpolate_samples(samples, wavelength),
+        };
+    }
+
+    pub fn sample(&self, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return SampledSpectrum::from_function(wavelengths, |wavelength| self.value(wavelength));
+    }
+
+    /// The RGB colour of this spectrum, used when rendering without spectral sampling.
+    pub fn to_colour(&self, primaries: &Primaries) -> Colour {
+        let mut x = 0.0;
+        let mut y = 0.0;
+        let mut z = 0.0;
+
+        let mut wavelength = cie::MINIMUM_WAVELENGTH;
+        while wavelength <= cie::MAXIMUM_WAVELENGTH {
+            let value = self.value(wavelength);
+            let (x_bar, y_bar, z_bar) = cie::colour_matching(wavelength);
+            x += value * x_bar;
+            y += value * y_bar;
+            z += value * z_bar;
+            wavelength += INTEGRATION_STEP;
+        }
+
+        let scale = INTEGRATION_STEP / CIE_Y_INTEGRAL;
+        let xyz = Colour::new(x * scale, y * scale, z * scale);
+        return ColourSpaceConversion::from_xyz(primaries).convert(&xyz);
+    }
+}
+
+fn interpolate_samples(samples: &[(Scalar, Scalar)], wavelength: Scalar) -> Scalar {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let index = samples.partition_point(|sample| sample.0 < wavelength);
+    if index == 0 {
+        return samples[0].1;
+    }
+    if index == samples.len() {
+        return samples[samples.len() - 1].1;
+    }
+
+    let (from_wavelength, from_value) = samples[index - 1];
+    let (to_wavelength, to_value) = samples[index];
+    let t = (wavelength - from_wavelength) / (to_wavelength - from_wavelength);
+    return from_value + t * (to_value - from_value);
+}
+
+/// The normalised D65 illuminant at the given wavelength.
+pub fn d65(wavelength: Scalar) -> Scalar {
+    let position = ((wavelength - cie::MINIMUM_WAVELENGTH) / 10.0).max(0.0).min((D65.len() - 1) as Scalar);
+    let index = (position as usize).min(D65.len() - 2);
+    let t = position - index as Scalar;
+    return D65_NORMALISATION * (D65[index] + t * (D65[index + 1] - D65[index]));
+}
+
+// Smits' method; builds the spectrum from white plus the secondary and primary basis spectra needed to make up the
+// difference between the channels.
+fn reflectance(colour: &Colour, wavelength: Scalar) -> Scalar {
+    let (r, g, b) = (colour.r, colour.g, colour.b);
+    let basis = |table: &[Scalar; 10]| smits_basis(table, wavelength);
+
+    if r <= g && r <= b {
+        let white = r * basis(&SMITS_WHITE);
+        if g <= b {
+            return white + (g - r) * basis(&SMITS_CYAN) + (b - g) * basis(&SMITS_BLUE);
+        }
+        return white + (b - r) * basis(&SMITS_CYAN) + (g - b) * basis(&SMITS_GREEN);
+    }
+
+    if g <= r && g <= b {
+        let white = g * basis(&SMITS_WHITE);
+        if r <= b {
+            return white + (r - g) * basis(&SMITS_MAGENTA) + (b - r) * basis(&SMITS_BLUE);
+        }
+        return white + (b - g) * basis(&SMITS_MAGENTA) + (r - b) * basis(&SMITS_RED);
+    }
+
+    let white = b * basis(&SMITS_WHITE);
+    if r <= g {
+        return white + (r - b) * basis(&SMITS_YELLOW) + (g - r) * basis(&SMITS_GREEN);
+    }
+    return white + (g - b) * basis(&SMITS_YELLOW) + (r - g) * basis(&SMITS_RED);
+}
+
+fn smits_basis(table: &[Scalar; 10], wavelength: Scalar) -> Scalar {
+    // Linear interpolation between the bin centres.
+    let bin_width = (SMITS_MAXIMUM_WAVELENGTH - SMITS_MINIMUM_WAVELENGTH) / table.len() as Scalar;
+    let position = ((wavelength - SMITS_MINIMUM_WAVELENGTH) / bin_width - 0.5)
+        .max(0.0)
+        .min((table.len() - 1) as Scalar);
+    let index = (position as usize).min(table.len() - 2);
+    let t = position - index as Scalar;
+    return table[index] + t * (table[index + 1] - table[index]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    // Integrates a spectrum function the same way the renderer does, but with many stratified wavelength samples.
+    fn render_spectrum(function: impl Fn(&SampledWavelengths) -> SampledSpectrum) -> Colour {
+        let count = 1000;
+        let mut total = Colour::black();
+        for index in 0..count {
+            let wavelengths = SampledWavelengths::sample(index as Scalar / count as Scalar);
+            total = total + function(&wavelengths).to_colour(&wavelengths);
+        }
+        return total / count as Scalar;
+    }
+
+    fn assert_colour(actual: Colour, expected: Colour, epsilon: Scalar) {
+        assert_abs_diff_eq!(actual.r, expected.r, epsilon = epsilon);
+        assert_abs_diff_eq!(actual.g, expected.g, epsilon = epsilon);
+        assert_abs_diff_eq!(actual.b, expected.b, epsilon = epsilon);
+    }
+
+    #[test]
+    fn sample_wavelengths() {
+        let wavelengths = SampledWavelengths::sample(0.5);
+
+        assert_abs_diff_eq!(wavelengths.hero(), 580.0, epsilon = 0.001);
+        assert_abs_diff_eq!(wavelengths.lambda[1], 680.0, epsilon = 0.001);
+        assert_abs_diff_eq!(wavelengths.lambda[2], 380.0, epsilon = 0.001);
+        assert_abs_diff_eq!(wavelengths.lambda[3], 480.0, epsilon = 0.001);
+        assert_abs_diff_eq!(wavelengths.pdf, 1.0 / 400.0, epsilon = 0.000_001);
+    }
+
+    #[test]
+    fn d65_is_white() {
+        let colour = render_spectrum(|wavelengths| SampledSpectrum::from_function(wavelengths, d65));
+
+        assert_colour(colour, Colour::new(1.0, 1.0, 1.0), 0.02);
+    }
+
+    #[rstest(
+        colour,
+        case(Colour::new(1.0, 1.0, 1.0)),
+        case(Colour::new(0.5, 0.5, 0.5)),
+        case(Colour::new(0.8, 0.2, 0.1)),
+        case(Colour::new(0.2, 0.7, 0.3)),
+        case(Colour::new(0.1, 0.3, 0.9))
+    )]
+    fn illuminant_round_trip(colour: Colour) {
+        let actual = render_spectrum(|wavelengths| SampledSpectrum::from_illuminant(&colour, wavelengths));
+
+        assert_colour(actual, colour, 0.06);
+    }
+
+    #[test]
+    fn terminate_secondary() {
+        let spectrum = SampledSpectrum::new([1.0, 2.0, 3.0, 4.0]).terminate_secondary();
+
+        assert_eq!(spectrum.values, [4.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn blackbody_matches_colour() {
+        let spectrum = Spectrum::blackbody(3000.0, 2.0);
+
+        let expected = blackbody::colour(3000.0) * 2.0;
+
+        assert_colour(spectrum.to_colour(&Primaries::SRGB), expected, 0.02);
+    }
+
+    #[rstest(wavelength, expected, case(400.0, 1.0), case(350.0, 1.0), case(450.0, 2.0), case(600.0, 0.5), case(700.0, 0.5))]
+    fn sampled_value(wavelength: Scalar, expected: Scalar) {
+        let spectrum = Spectrum::sampled(vec![(600.0, 0.5), (400.0, 1.0), (500.0, 3.0)]);
+
+        assert_abs_diff_eq!(spectrum.value(wavelength), expected, epsilon = 0.001);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::image::{Colour, Image, Primaries};
+use crate::maths::Scalar;
+use std::fmt;
+
+// Pixels darker than this are treated as black and left out of the log-average; otherwise an empty background
+// drags the log-average towards zero and auto-exposure blows out everything else.
+const BLACK_THRESHOLD: Scalar = 0.0001;
+
+// Luminance of middle grey; auto-exposure scales the log-average luminance to this.
+const MIDDLE_GREY: Scalar = 0.18;
+
+const HISTOGRAM_BINS: usize = 16;
+
+const HISTOGRAM_WIDTH: usize = 50;
+
+#[allow(clippy::manual_non_exhaustive)]
+pub struct ImageStatistics {
+    pub minimum_luminance: Scalar,
+    pub maximum_luminance: Scalar,
+    pub mean_luminance: Scalar,
+    pub log_average_luminance: Scalar,
+    pub clipped_percentage: Scalar,
+    pub histogram: LuminanceHistogram,
+    _private: (),
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+pub struct LuminanceHistogram {
+    pub maximum: Scalar,
+    pub bins: Vec<usize>,
+    _private: (),
+}
+
+impl ImageStatistics {
+    /// Statistics for an image whose pixels are in the colour space with the given primaries.
+    pub fn new(image: &Image, primaries: &Primaries) -> ImageStatistics {
+        let pixels = image.pixels();
+        let weights = primaries.luminance_weights();
+        let luminances: Vec<Scalar> = pixels.iter().map(|pixel| luminance(pixel, &weights)).collect();
+        let count = pixels.len().max(1) as Scalar;
+
+        let mut minimum_luminance = Scalar::MAX;
+        let mut maximum_luminance: Scalar = 0.0;
+        let mut total_luminance = 0.0;
+        let mut total_log_luminance = 0.0;
+        let mut non_black = 0;
+        let mut clipped = 0;
+
+        for (pixel, &luminance) in pixels.iter().zip(&luminances) {
+            minimum_luminance = minimum_luminance.min(luminance);
+            maximum_luminance = maximum_luminance.max(luminance);
+            total_luminance += luminance;
+
+            if luminance > BLACK_THRESHOLD {
+                total_log_luminance += luminance.ln();
+                non_black += 1;
+            }
+
+            if is_clipped(pixel) {
+                clipped += 1;
+            }
+        }
+
+        if pixels.is_empty() {
+            minimum_luminance = 0.0;
+        }
+
+        let log_average_luminance = match non_black {
+            0 => 0.0,
+            _ => (total_log_luminance / non_black as Scalar).exp(),
+        };
+
+        return ImageStatistics {
+            minimum_luminance,
+            maximum_luminance,
+            mean_luminance: total_luminance / count,
+            log_average_luminance,
+            clipped_percentage: 100.0 * clipped as Scalar / count,
+            histogram: LuminanceHistogram::new(&luminances, maximum_luminance),
+            _private: (),
+        };
+    }
+
+    /// The exposure multiplier that maps the log-average luminance to middle grey.
+    pub fn auto_exposure(&self) -> Scalar {
+        if self.log_average_luminance <= BLACK_THRESHOLD {
+            return 1.0;
+        }
+
+        return MIDDLE_GREY / self.log_average_luminance;
+    }
+}
+
+impl LuminanceHistogram {
+    fn new(luminances: &[Scalar], maximum_luminance: Scalar) -> LuminanceHistogram {
+        // Always cover the displayable range so histograms from different renders line up.
+        let maximum = maximum_luminance.max(1.0);
+        let mut bins = vec![0; HISTOGRAM_BINS];
+
+        for luminance in luminances {
+            let bin = (luminance / maximum * HISTOGRAM_BINS as Scalar) as usize;
+            bins[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        return LuminanceHistogram { maximum, bins, _private: () };
+    }
+
+    pub fn bin_width(&self) -> Scalar {
+        return self.maximum / self.bins.len() as Scalar;
+    }
+}
+
+fn luminance(colour: &Colour, weights: &Colour) -> Scalar {
+    return (colour.r * weights.r + colour.g * weights.g + colour.b * weights.b).max(0.0);
+}
+
+// Negative components are colours outside the output gamut, which get clipped just like over-bright ones.
+fn is_clipped(colour: &Colour) -> bool {
+    return colour.r > 1.0 || colour.g > 1.0 || colour.b > 1.0 || colour.r < 0.0 || colour.g < 0.0 || colour.b < 0.0;
+}
+
+impl fmt::Display for ImageStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Minimum luminance: {:.4}", self.minimum_luminance)?;
+        writeln!(f, "Maximum luminance: {:.4}", self.maximum_luminance)?;
+        writeln!(f, "Mean luminance: {:.4}", self.mean_luminance)?;
+        writeln!(f, "Log-average luminance: {:.4}", self.log_average_luminance)?;
+        writeln!(f, "Clipped pixels: {:.2}%", self.clipped_percentage)?;
+        return write!(f, "{}", self.histogram);
+    }
+}
+
+impl fmt::Display for LuminanceHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let largest = self.bins.iter().copied().max().unwrap_or(0).max(1);
+        let bin_width = self.bin_width();
+
+        for (index, count) in self.bins.iter().enumerate() {
+            let bar = "#".repeat(count * HISTOGRAM_WIDTH / largest);
+            writeln!(
+                f,
+                "{:>7.3} - {:>7.3} | {:<width$} {}",
+                index as Scalar * bin_width,
+                (index + 1) as Scalar * bin_width,
+                bar,
+                count,
+                width = HISTOGRAM_WIDTH
+            )?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn image(pixels: Vec<Colour>) -> Image {
+        return Image::from_colour_pixels(pixels.len() as u32, 1, &mut pixels.into_iter());
+    }
+
+    fn srgb_statistics(image: &Image) -> ImageStatistics {
+        return ImageStatistics::new(image, &Primaries::SRGB);
+    }
+
+    #[test]
+    fn luminance_statistics() {
+        let statistics = srgb_statistics(&image(vec![
+            Colour::new(0.0, 0.0, 0.0),
+            Colour::new(0.5, 0.5, 0.5),
+            Colour::new(1.0, 1.0, 1.0),
+            Colour::new(2.5, 2.5, 2.5),
+        ]));
+
+        assert_abs_diff_eq!(statistics.minimum_luminance, 0.0, epsilon = 0.001);
+        assert_abs_diff_eq!(statistics.maximum_luminance, 2.5, epsilon = 0.001);
+        assert_abs_diff_eq!(statistics.mean_luminance, 1.0, epsilon = 0.001);
+        assert_abs_diff_eq!(statistics.clipped_percentage, 25.0, epsilon = 0.001);
+    }
+
+    #[test]
+    fn log_average_luminance() {
+        let statistics = srgb_statistics(&image(vec![Colour::new(0.1, 0.1, 0.1), Colour::new(1.0, 1.0, 1.0)]));
+
+        // Geometric mean of 0.1 and 1.0.
+        assert_abs_diff_eq!(statistics.log_average_luminance, Scalar::sqrt(0.1), epsilon = 0.001);
+    }
+
+    #[test]
+    fn log_average_luminance_ignores_black() {
+        let statistics = srgb_statistics(&image(vec![Colour::black(), Colour::new(0.1, 0.1, 0.1), Colour::new(1.0, 1.0, 1.0)]));
+
+        assert_abs_diff_eq!(statistics.log_average_luminance, Scalar::sqrt(0.1), epsilon = 0.001);
+    }
+
+    #[test]
+    fn clipped_percentage_counts_any_channel() {
+        let statistics = srgb_statistics(&image(vec![Colour::new(1.5, 0.0, 0.0), Colour::new(0.2, 0.2, 0.2)]));
+
+        assert_abs_diff_eq!(statistics.clipped_percentage, 50.0, epsilon = 0.001);
+    }
+
+    #[test]
+    fn clipped_percentage_counts_out_of_gamut() {
+        let statistics = srgb_statistics(&image(vec![Colour::new(0.5, -0.1, 0.0), Colour::new(0.2, 0.2, 0.2)]));
+
+        assert_abs_diff_eq!(statistics.clipped_percentage, 50.0, epsilon = 0.001);
+    }
+
+    #[test]
+    fn luminance_uses_colour_space_weights() {
+        let green = image(vec![Colour::new(0.0, 1.0, 0.0)]);
+
+        assert_abs_diff_eq!(srgb_statistics(&green).mean_luminance, 0.7152, epsilon = 0.001);
+        assert_abs_diff_eq!(ImageStatistics::new(&green, &Primaries::REC2020).mean_luminance, 0.6780, epsilon = 0.001);
+    }
+
+    #[test]
+    fn histogram() {
+        let statistics = srgb_statistics(&image(vec![
+            Colour::new(0.0, 0.0, 0.0),
+            Colour::new(0.01, 0.01, 0.01),
+            Colour::new(1.0, 1.0, 1.0),
+            Colour::new(2.0, 2.0, 2.0),
+        ]));
+
+        let histogram = &statistics.histogram;
+
+        assert_abs_diff_eq!(histogram.maximum, 2.0, epsilon = 0.001);
+        assert_eq!(histogram.bins.len(), HISTOGRAM_BINS);
+        assert_eq!(histogram.bins.iter().sum::<usize>(), 4);
+        assert_eq!(histogram.bins[0], 2);
+        assert_eq!(histogram.bins[HISTOGRAM_BINS / 2], 1);
+        assert_eq!(histogram.bins[HISTOGRAM_BINS - 1], 1);
+    }
+
+    #[test]
+    fn auto_exposure() {
+        let statistics = srgb_statistics(&image(vec![Colour::new(0.36, 0.36, 0.36); 4]));
+
+        assert_abs_diff_eq!(statistics.auto_exposure(), 0.5, epsilon = 0.001);
+    }
+
+    #[test]
+    fn auto_exposure_black_image() {
+        let statistics = srgb_statistics(&image(vec![Colour::black(); 4]));
+
+        assert_abs_diff_eq!(statistics.auto_exposure(), 1.0, epsilon = 0.001);
+    }
+}
@@ -0,0 +1,420 @@
+use crate::image::{Image, Rgb, RgbImage, TransferFunction};
+use parking_lot::Mutex;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+// Used when the terminal size cannot be detected, e.g. when output is redirected.
+const DEFAULT_COLUMNS: u32 = 80;
+const DEFAULT_ROWS: u32 = 24;
+
+// Terminals do not report the pixel size of a cell, so sixel and kitty output assume a typical one.
+const CELL_WIDTH: u32 = 10;
+const CELL_HEIGHT: u32 = 20;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Kitty requires the payload to be split into chunks of at most this many bytes.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// How a preview is drawn in the terminal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PreviewFormat {
+    /// ANSI 24-bit colour with upper half block characters, two pixels per character cell. Works in most terminals.
+    HalfBlocks,
+    /// DEC sixel graphics, using a 6x6x6 colour cube palette.
+    Sixel,
+    /// The kitty terminal graphics protocol.
+    Kitty,
+}
+
+/// The size of the terminal in character cells.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerminalSize {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// Draws images in the terminal, scaled to fit.
+pub struct TerminalPreview {
+    format: PreviewFormat,
+    size: TerminalSize,
+}
+
+/// Redraws snapshots of an image over the previous snapshot, for watching a render progress.
+pub struct ProgressivePreview {
+    preview: TerminalPreview,
+    drawn_rows: Mutex<u32>,
+}
+
+// Where a scaled image sits in the terminal.
+struct Layout {
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+}
+
+impl TerminalSize {
+    pub fn detect() -> TerminalSize {
+        return match terminal_size::terminal_size() {
+            Some((terminal_size::Width(columns), terminal_size::Height(rows))) => TerminalSize {
+                columns: columns as u32,
+                rows: rows as u32,
+            },
+            None => TerminalSize {
+                columns: DEFAULT_COLUMNS,
+                rows: DEFAULT_ROWS,
+            },
+        };
+    }
+}
+
+impl TerminalPreview {
+    pub fn new(format: PreviewFormat, size: TerminalSize) -> TerminalPreview {
+        return TerminalPreview { format, size };
+    }
+
+    /// The escape sequences and characters that draw the image, ending on a new line.
+    pub fn draw(&self, image: &RgbImage) -> String {
+        return self.draw_with_rows(image).0;
+    }
+
+    fn draw_with_rows(&self, image: &RgbImage) -> (String, u32) {
+        let layout = self.layout(image);
+        let scaled = image.resize(layout.width, layout.height);
+
+        let text = match self.format {
+            PreviewFormat::HalfBlocks => half_blocks(&scaled),
+            PreviewFormat::Sixel => sixel(&scaled),
+            PreviewFormat::Kitty => kitty(&scaled, &layout),
+        };
+
+        return (text, layout.rows);
+    }
+
+    fn layout(&self, image: &RgbImage) -> Layout {
+        // Leave a row free for the cursor so drawing the image does not scroll it off the top.
+        let columns = self.size.columns.max(1);
+        let rows = self.size.rows.saturating_sub(1).max(1);
+
+        return match self.format {
+            PreviewFormat::HalfBlocks => {
+                // Half blocks are roughly square, so the image is fitted as pixels with two per row.
+                let (width, height) = fit(image, columns, rows * 2);
+                let height = height.div_ceil(2) * 2;
+                Layout {
+                    width,
+                    height,
+                    columns: width,
+                    rows: height / 2,
+                }
+            }
+            PreviewFormat::Sixel | PreviewFormat::Kitty => {
+                let (width, height) = fit(image, columns * CELL_WIDTH, rows * CELL_HEIGHT);
+                Layout {
+                    width,
+                    height,
+                    columns: width.div_ceil(CELL_WIDTH),
+                    rows: height.div_ceil(CELL_HEIGHT),
+                }
+            }
+        };
+    }
+}
+
+impl ProgressivePreview {
+    pub fn new(preview: TerminalPreview) -> ProgressivePreview {
+        return ProgressivePreview {
+            preview,
+            drawn_rows: Mutex::new(0),
+        };
+    }
+
+    pub fn show(&self, image: &Image) {
+        let mut drawn_rows = self.drawn_rows.lock();
+
+        // Terminals show sRGB.
+        let (text, rows) = self.preview.draw_with_rows(&image.to_rgb_image(TransferFunction::Srgb));
+        if *drawn_rows > 0 {
+            print!("\x1b[{}A", *drawn_rows);
+        }
+        print!("{}", text);
+
+        *drawn_rows = rows;
+    }
+}
+
+// The largest size with the image's aspect ratio that fits in the given bounds.
+fn fit(image: &RgbImage, maximum_width: u32, maximum_height: u32) -> (u32, u32) {
+    let scale = (maximum_width as f64 / image.width as f64).min(maximum_height as f64 / image.height as f64);
+
+    let width = ((image.width as f64 * scale) as u32).clamp(1, maximum_width);
+    let height = ((image.height as f64 * scale) as u32).clamp(1, maximum_height);
+    return (width, height);
+}
+
+fn half_blocks(image: &RgbImage) -> String {
+    let mut text = String::new();
+
+    for y in (0..image.height).step_by(2) {
+        for x in 0..image.width {
+            let top = image.get_pixel(x, y);
+            let bottom = image.get_pixel(x, (y + 1).min(image.height - 1));
+            write!(
+                text,
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+            )
+            .unwrap();
+        }
+        text.push_str("\x1b[0m\n");
+    }
+
+    return text;
+}
+
+fn palette_index(rgb: &Rgb) -> usize {
+    let level = |component: u8| (component as usize * 5 + 127) / 255;
+    return level(rgb.r) * 36 + level(rgb.g) * 6 + level(rgb.b);
+}
+
+fn sixel(image: &RgbImage) -> String {
+    let mut text = String::new();
+
+    // Start sixel mode with square pixels, and give the image size so the terminal need not infer it.
+    write!(text, "\x1bP0;1q\"1;1;{};{}", image.width, image.height).unwrap();
+
+    // Palette components are percentages.
+    for index in 0..216 {
+        let (r, g, b) = (index / 36, index / 6 % 6, index % 6);
+        write!(text, "#{};2;{};{};{}", index, r * 20, g * 20, b * 20).unwrap();
+    }
+
+    let indices: Vec<usize> = (0..image.height)
+        .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+        .map(|(x, y)| palette_index(image.get_pixel(x, y)))
+        .collect();
+
+    // Each band is six rows of pixels, drawn once for each colour it uses.
+    for band_top in (0..image.height).step_by(6) {
+        let band_bottom = (band_top + 6).min(image.height);
+
+        let mut used = [false; 216];
+        for y in band_top..band_bottom {
+            for x in 0..image.width {
+                used[indices[(x + y * image.width) as usize]] = true;
+            }
+        }
+
+        for colour in (0..216).filter(|&colour| used[colour]) {
+            write!(text, "#{}", colour).unwrap();
+
+            let sixels = (0..image.width).map(|x| {
+                let mut bits = 0u8;
+                for y in band_top..band_bottom {
+                    if indices[(x + y * image.width) as usize] == colour {
+                        bits |= 1 << (y - band_top);
+                    }
+                }
+                return (63 + bits) as char;
+            });
+            write_run_length_encoded(&mut text, sixels);
+
+            // Return to the start of the band for the next colour.
+            text.push('$');
+        }
+
+        text.push('-');
+    }
+
+    text.push_str("\x1b\\\n");
+    return text;
+}
+
+fn write_run_length_encoded(text: &mut String, characters: impl Iterator<Item = char>) {
+    let mut run: Option<(char, usize)> = None;
+
+    let flush = |text: &mut String, character: char, count: usize| match count {
+        1..=3 => text.extend(std::iter::repeat_n(character, count)),
+        _ => write!(text, "!{}{}", count, character).unwrap(),
+    };
+
+    for character in characters {
+        run = match run {
+            Some((previous, count)) if previous == character => Some((previous, count + 1)),
+            Some((previous, count)) => {
+                flush(text, previous, count);
+                Some((character, 1))
+            }
+            None => Some((character, 1)),
+        };
+    }
+
+    if let Some((character, count)) = run {
+        flush(text, character, count);
+    }
+}
+
+fn kitty(image: &RgbImage, layout: &Layout) -> String {
+    let mut data = Vec::with_capacity((image.width * image.height * 3) as usize);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let pixel = image.get_pixel(x, y);
+            data.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+        }
+    }
+
+    let payload = base64(&data);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+    let mut text = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = if index + 1 < chunks.len() { 1 } else { 0 };
+        if index == 0 {
+            // Transmit and display 24-bit RGB data, scaled by the terminal to the cells the layout allows for.
+            write!(
+                text,
+                "\x1b_Ga=T,f=24,s={},v={},c={},r={},m={};",
+                image.width, image.height, layout.columns, layout.rows, more
+            )
+            .unwrap();
+        } else {
+            write!(text, "\x1b_Gm={};", more).unwrap();
+        }
+        text.push_str(std::str::from_utf8(chunk).unwrap());
+        text.push_str("\x1b\\");
+    }
+
+    text.push('\n');
+    return text;
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let combined = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(BASE64[(combined >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+
+    return text;
+}
+
+impl FromStr for PreviewFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<PreviewFormat, String> {
+        return match text.to_ascii_lowercase().as_str() {
+            "blocks" | "half-blocks" => Ok(PreviewFormat::HalfBlocks),
+            "sixel" => Ok(PreviewFormat::Sixel),
+            "kitty" => Ok(PreviewFormat::Kitty),
+            _ => Err(format!("unknown preview format {:?}; expected blocks, sixel or kitty", text)),
+        };
+    }
+}
+
+impl fmt::Display for PreviewFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            PreviewFormat::HalfBlocks => write!(f, "half blocks"),
+            PreviewFormat::Sixel => write!(f, "sixel"),
+            PreviewFormat::Kitty => write!(f, "kitty"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn image(width: u32, height: u32, rgb: Rgb) -> RgbImage {
+        return RgbImage::from_rgb_pixels(width, height, &mut std::iter::repeat_n(rgb, (width * height) as usize));
+    }
+
+    fn preview(format: PreviewFormat, columns: u32, rows: u32) -> TerminalPreview {
+        return TerminalPreview::new(format, TerminalSize { columns, rows });
+    }
+
+    #[test]
+    fn half_blocks_fit_terminal() {
+        let (text, rows) = preview(PreviewFormat::HalfBlocks, 40, 11).draw_with_rows(&image(400, 200, Rgb::new(1, 2, 3)));
+
+        // 40 columns wide gives 20 pixels high, so 10 rows.
+        assert_eq!(rows, 10);
+        assert_eq!(text.lines().count(), 10);
+        assert_eq!(text.matches('\u{2580}').count(), 400);
+        assert!(text.starts_with("\x1b[38;2;1;2;3m\x1b[48;2;1;2;3m\u{2580}"));
+    }
+
+    #[test]
+    fn half_blocks_fit_short_terminal() {
+        let (text, rows) = preview(PreviewFormat::HalfBlocks, 100, 6).draw_with_rows(&image(400, 200, Rgb::new(0, 0, 0)));
+
+        // 5 usable rows give 10 pixels high, so 20 wide.
+        assert_eq!(rows, 5);
+        assert_eq!(text.matches('\u{2580}').count(), 100);
+    }
+
+    #[test]
+    fn sixel() {
+        let text = preview(PreviewFormat::Sixel, 2, 2).draw(&image(20, 20, Rgb::new(255, 0, 0)));
+
+        assert!(text.starts_with("\x1bP0;1q\"1;1;20;20"));
+        assert!(text.ends_with("\x1b\\\n"));
+        // Four bands of red (palette index 180), full bands then a partial one of two rows.
+        assert_eq!(text.matches("#180!20~$-").count(), 3);
+        assert_eq!(text.matches("#180!20B$-").count(), 1);
+    }
+
+    #[test]
+    fn kitty() {
+        let text = preview(PreviewFormat::Kitty, 80, 25).draw(&image(2, 1, Rgb::new(255, 0, 0)));
+
+        assert!(text.starts_with("\x1b_Ga=T,f=24,s="));
+        assert!(text.ends_with("\x1b\\\n"));
+    }
+
+    #[test]
+    fn kitty_chunks_payload() {
+        let text = preview(PreviewFormat::Kitty, 20, 11).draw(&image(200, 100, Rgb::new(0, 0, 0)));
+
+        assert_eq!(text.matches("\x1b_G").count(), 20);
+        assert_eq!(text.matches("m=0;").count(), 1);
+    }
+
+    #[rstest(
+        data,
+        expected,
+        case(b"", ""),
+        case(b"f", "Zg=="),
+        case(b"fo", "Zm8="),
+        case(b"foo", "Zm9v"),
+        case(b"foobar", "Zm9vYmFy")
+    )]
+    fn base64_encoding(data: &[u8], expected: &str) {
+        assert_eq!(base64(data), expected);
+    }
+
+    #[rstest(
+        text,
+        expected,
+        case("blocks", Ok(PreviewFormat::HalfBlocks)),
+        case("half-blocks", Ok(PreviewFormat::HalfBlocks)),
+        case("Sixel", Ok(PreviewFormat::Sixel)),
+        case("kitty", Ok(PreviewFormat::Kitty)),
+        case("ascii", Err("unknown preview format \"ascii\"; expected blocks, sixel or kitty".to_string()))
+    )]
+    fn preview_format_from_str(text: &str, expected: Result<PreviewFormat, String>) {
+        assert_eq!(text.parse::<PreviewFormat>(), expected);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::configuration::from_command_line;
-use crate::rendering::algorithms::RayTracing;
-use crate::rendering::{render, SubPixelSampling};
-use crate::scene::io::json::load;
+use crate::image::{ColourSpaceConversion, Image, ProgressivePreview, TerminalPreview, TerminalSize};
+use crate::rendering::algorithms::{RayTracing, SpectralRayTracing};
+use crate::rendering::{render_with_progress, SubPixelSampling};
+use crate::scene::io::load;
 use std::time::Instant;
 
 // Some modules declared as pub to suppress dead code warnings.
@@ -10,6 +11,7 @@ pub mod image;
 pub mod maths;
 pub mod rendering;
 pub mod scene;
+pub mod shapes;
 
 fn main() {
     let configuration = from_command_line().unwrap();
@@ -17,25 +19,59 @@ fn main() {
     println!("Scene: {:?}", configuration.scene);
     println!("Output file: {:?}", configuration.output);
     println!("Image size: {}x{}", configuration.width, configuration.height);
+    println!("Output colour space: {}", configuration.output_colour_space);
 
     let scene = match time_function("load scene", || load(&configuration.scene)) {
         Ok(t) => t,
         Err(e) => {
-            println!("Could not load scene{:?}: {}", configuration.scene, e.to_string());
+            println!("Could not load scene{:?}: {}", configuration.scene, e);
             return;
         }
     };
 
-    let algorithm = RayTracing::new();
+    let sampling = || SubPixelSampling::Square(configuration.sub_pixels);
 
-    let image = time_function("render", || render(&algorithm, &configuration, &scene, SubPixelSampling::Square(2)));
+    let progressive_preview = configuration
+        .preview
+        .filter(|_| configuration.progressive)
+        .map(|format| ProgressivePreview::new(TerminalPreview::new(format, TerminalSize::detect())));
+    let progress = |snapshot: &Image| {
+        if let Some(preview) = &progressive_preview {
+            preview.show(snapshot);
+        }
+    };
+
+    let mut image = match configuration.spectral {
+        true => time_function("render", || {
+            render_with_progress(&SpectralRayTracing::new(scene.colour_space), &configuration, &scene, sampling(), &progress)
+        }),
+        false => time_function("render", || {
+            render_with_progress(&RayTracing::new(), &configuration, &scene, sampling(), &progress)
+        }),
+    };
 
-    let rgb = time_function("to_rgb_image", || image.to_rgb_image());
+    let output_primaries = configuration.output_colour_space.primaries();
+    image.convert(&ColourSpaceConversion::new(&scene.colour_space.primaries(), &output_primaries));
+
+    let statistics = time_function("statistics", || image.statistics(&output_primaries));
+    print!("{}", statistics);
+
+    if configuration.auto_exposure {
+        let exposure = statistics.auto_exposure();
+        println!("Auto exposure: x{:.3} ({:+.2} stops)", exposure, exposure.log2());
+        image.apply_exposure(exposure);
+    }
+
+    let rgb = time_function("to_rgb_image", || image.to_rgb_image(configuration.output_colour_space.transfer_function()));
 
     time_function("save", || {
-        rgb.save(&configuration.output)
-            .unwrap_or_else(|e| println!("Could not save image: {}", e.to_string()))
+        rgb.save(&configuration.output, configuration.output_colour_space)
+            .unwrap_or_else(|e| println!("Could not save image: {}", e))
     });
+
+    if let Some(format) = configuration.preview {
+        print!("{}", TerminalPreview::new(format, TerminalSize::detect()).draw(&rgb));
+    }
 }
 
 fn time_function<TResult>(name: &str, function: impl Fn() -> TResult) -> TResult {
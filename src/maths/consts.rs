@@ -1,6 +1,9 @@
 use crate::maths::Scalar;
-use std::f64::consts;
+
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts::*;
+#[cfg(feature = "f64")]
+pub use std::f64::consts::*;
 
 pub const GOLDEN_ANGLE: Scalar = 2.399963229728653322231555506633;
-pub const PI: Scalar = consts::PI as Scalar;
-pub const TWO_PI: Scalar = consts::TAU as Scalar;
+pub const TWO_PI: Scalar = TAU;
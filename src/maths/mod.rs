@@ -1,16 +1,27 @@
 pub mod consts;
+pub mod noise;
+pub mod packet;
+pub mod polynomial;
 pub mod ray;
 pub mod sphere;
 pub mod vector;
 
+mod transformation;
+pub use self::transformation::Transformation;
+
+pub type Affine = nalgebra::Affine3<Scalar>;
 pub type Coordinates = nalgebra::Point2<Scalar>;
 pub type Isometry = nalgebra::Isometry3<Scalar>;
 pub type Perspective = nalgebra::Perspective3<Scalar>;
 pub type Point = nalgebra::Point3<Scalar>;
 pub type Plane = ncollide3d::shape::Plane<Scalar>;
+pub type Rotation = nalgebra::UnitQuaternion<Scalar>;
 pub type Ray = ncollide3d::query::Ray<Scalar>;
 pub type RayIntersection = ncollide3d::query::RayIntersection<Scalar>;
+#[cfg(not(feature = "f64"))]
 pub type Scalar = f32;
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
 pub type Sphere = ncollide3d::shape::Ball<Scalar>;
 pub type TransformationMatrix = nalgebra::Matrix4<Scalar>;
 pub type Vector = nalgebra::Vector3<Scalar>;
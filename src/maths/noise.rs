@@ -0,0 +1,108 @@
+use crate::maths::{consts, Coordinates, Scalar};
+use nalgebra::Vector2;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Two dimensional Perlin gradient noise. The same seed always gives the same noise.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    // A shuffled permutation of 0..256, repeated so lookups of a lookup don't need wrapping.
+    permutation: Vec<usize>,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Noise {
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+        permutation.extend_from_within(..);
+        return Noise { permutation };
+    }
+
+    /// Noise at a point, between about -1 and 1. It is zero at whole coordinates and varies over about one unit.
+    pub fn value(&self, point: &Coordinates) -> Scalar {
+        let (x, y) = (point.x.floor(), point.y.floor());
+        let (fx, fy) = (point.x - x, point.y - y);
+        let (cell_x, cell_y) = ((x as i64 & 255) as usize, (y as i64 & 255) as usize);
+
+        let corner = |dx: usize, dy: usize| {
+            let hash = self.permutation[self.permutation[cell_x + dx] + cell_y + dy];
+            return gradient(hash).dot(&Vector2::new(fx - dx as Scalar, fy - dy as Scalar));
+        };
+
+        let (u, v) = (fade(fx), fade(fy));
+        let bottom = lerp(corner(0, 0), corner(1, 0), u);
+        let top = lerp(corner(0, 1), corner(1, 1), u);
+        // Scaled so the extremes, in the middle of cells with all the gradients pointing in, come out near ±1.
+        return lerp(bottom, top, v) * consts::SQRT_2;
+    }
+
+    /// Fractal noise from `octaves` layers of noise, each at twice the frequency of the last and `persistence` times its
+    /// amplitude. It is normalised to between about -1 and 1.
+    pub fn fractal(&self, point: &Coordinates, octaves: usize, persistence: Scalar) -> Scalar {
+        let (mut total, mut amplitude, mut frequency, mut maximum) = (0.0, 1.0, 1.0, 0.0);
+        for octave in 0..octaves {
+            // Offset each octave so their zeroes at whole coordinates don't line up.
+            let offset = octave as Scalar * 17.31;
+            total += amplitude * self.value(&Coordinates::new(point.x * frequency + offset, point.y * frequency + offset));
+            maximum += amplitude;
+            amplitude *= persistence;
+            frequency *= 2.0;
+        }
+
+        return match maximum > 0.0 {
+            true => total / maximum,
+            false => 0.0,
+        };
+    }
+}
+
+// One of eight evenly spaced unit gradients.
+fn gradient(hash: usize) -> Vector2<Scalar> {
+    let angle = (hash % 8) as Scalar * consts::FRAC_PI_4;
+    return Vector2::new(angle.cos(), angle.sin());
+}
+
+// Eases between cells so the noise has a continuous gradient.
+fn fade(t: Scalar) -> Scalar {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn lerp(a: Scalar, b: Scalar, t: Scalar) -> Scalar {
+    return a + (b - a) * t;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn zero_at_whole_coordinates() {
+        let noise = Noise::new(1);
+
+        assert_abs_diff_eq!(noise.value(&Coordinates::new(3.0, -7.0)), 0.0);
+    }
+
+    #[test]
+    fn same_seed_same_noise() {
+        let point = Coordinates::new(1.3, 2.7);
+
+        assert_eq!(Noise::new(42).value(&point), Noise::new(42).value(&point));
+        assert_ne!(Noise::new(42).value(&point), Noise::new(43).value(&point));
+    }
+
+    #[test]
+    fn continuous_and_in_range() {
+        let noise = Noise::new(7);
+
+        for step in 0..1000 {
+            let point = Coordinates::new(step as Scalar * 0.013, step as Scalar * 0.007);
+            let value = noise.fractal(&point, 5, 0.5);
+            let next = noise.fractal(&Coordinates::new(point.x + 0.0001, point.y), 5, 0.5);
+
+            assert!(value.abs() <= 1.0);
+            assert_abs_diff_eq!(value, next, epsilon = 0.01);
+        }
+    }
+}
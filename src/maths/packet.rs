@@ -0,0 +1,489 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use ncollide3d::bounding_volume::AABB;
+use ncollide3d::shape::FeatureId;
+use std::array;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, BitAnd, BitOr, Div, Mul, Neg, Not, Sub};
+
+/// The number of rays traced together in a packet.
+pub const PACKET_SIZE: usize = 4;
+
+/// The SIMD type holding one scalar for each ray of a packet.
+#[cfg(not(feature = "f64"))]
+type Simd = wide::f32x4;
+#[cfg(feature = "f64")]
+type Simd = wide::f64x4;
+
+/// A value for each ray of a packet, held in a SIMD register so operations work on all the lanes at once.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lanes(Simd);
+
+/// Whether something is true for each ray of a packet. Set lanes have every bit set, as SIMD comparisons produce.
+#[derive(Copy, Clone)]
+pub struct Mask(Simd);
+
+/// A vector for each ray of a packet, stored as a structure of arrays so each component is one set of lanes.
+#[derive(Debug, Copy, Clone)]
+pub struct PacketVector {
+    pub x: Lanes,
+    pub y: Lanes,
+    pub z: Lanes,
+}
+
+/// Rays traced together, usually from neighbouring pixels so they tend to hit the same objects.
+pub struct RayPacket {
+    pub rays: [Ray; PACKET_SIZE],
+    pub origins: PacketVector,
+    pub directions: PacketVector,
+    inverse_directions: PacketVector,
+}
+
+impl Lanes {
+    pub fn new(values: [Scalar; PACKET_SIZE]) -> Lanes {
+        return Lanes(Simd::new(values));
+    }
+
+    pub fn splat(value: Scalar) -> Lanes {
+        return Lanes(Simd::splat(value));
+    }
+
+    pub fn lane(self, lane: usize) -> Scalar {
+        return self.0.as_array()[lane];
+    }
+
+    pub fn set_lane(&mut self, lane: usize, value: Scalar) {
+        self.0.as_mut_array()[lane] = value;
+    }
+
+    pub fn min(self, other: Lanes) -> Lanes {
+        return Lanes(self.0.min(other.0));
+    }
+
+    pub fn max(self, other: Lanes) -> Lanes {
+        return Lanes(self.0.max(other.0));
+    }
+
+    pub fn abs(self) -> Lanes {
+        return Lanes(self.0.abs());
+    }
+
+    pub fn sqrt(self) -> Lanes {
+        return Lanes(self.0.sqrt());
+    }
+
+    pub fn lt(self, other: Lanes) -> Mask {
+        return Mask(self.0.simd_lt(other.0));
+    }
+
+    pub fn le(self, other: Lanes) -> Mask {
+        return Mask(self.0.simd_le(other.0));
+    }
+
+    pub fn gt(self, other: Lanes) -> Mask {
+        return Mask(self.0.simd_gt(other.0));
+    }
+
+    pub fn ge(self, other: Lanes) -> Mask {
+        return Mask(self.0.simd_ge(other.0));
+    }
+
+    pub fn eq(self, other: Lanes) -> Mask {
+        return Mask(self.0.simd_eq(other.0));
+    }
+
+    /// Picks each lane from `if_true` where the mask is set and `if_false` where it isn't.
+    pub fn select(mask: Mask, if_true: Lanes, if_false: Lanes) -> Lanes {
+        return Lanes(mask.0.select(if_true.0, if_false.0));
+    }
+}
+
+macro_rules! lanes_operator {
+    ($trait:ident, $method:ident, $operator:tt) => {
+        impl $trait for Lanes {
+            type Output = Lanes;
+
+            fn $method(self, other: Lanes) -> Lanes {
+                return Lanes(self.0 $operator other.0);
+            }
+        }
+    };
+}
+
+lanes_operator!(Add, add, +);
+lanes_operator!(Sub, sub, -);
+lanes_operator!(Mul, mul, *);
+lanes_operator!(Div, div, /);
+
+impl Neg for Lanes {
+    type Output = Lanes;
+
+    fn neg(self) -> Lanes {
+        return Lanes(-self.0);
+    }
+}
+
+impl Mask {
+    pub fn new(values: [bool; PACKET_SIZE]) -> Mask {
+        return Mask(Simd::new(values.map(|value| if value { 1.0 } else { 0.0 })).simd_eq(Simd::splat(1.0)));
+    }
+
+    pub fn splat(value: bool) -> Mask {
+        return Mask::new([value; PACKET_SIZE]);
+    }
+
+    pub fn lane(self, lane: usize) -> bool {
+        return self.0.to_bitmask() & (1 << lane) != 0;
+    }
+
+    pub fn any(self) -> bool {
+        return self.0.any();
+    }
+}
+
+impl PartialEq for Mask {
+    fn eq(&self, other: &Mask) -> bool {
+        return self.0.to_bitmask() == other.0.to_bitmask();
+    }
+}
+
+impl Debug for Mask {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        return formatter.debug_list().entries((0..PACKET_SIZE).map(|lane| self.lane(lane))).finish();
+    }
+}
+
+impl BitAnd for Mask {
+    type Output = Mask;
+
+    fn bitand(self, other: Mask) -> Mask {
+        return Mask(self.0 & other.0);
+    }
+}
+
+impl BitOr for Mask {
+    type Output = Mask;
+
+    fn bitor(self, other: Mask) -> Mask {
+        return Mask(self.0 | other.0);
+    }
+}
+
+impl Not for Mask {
+    type Output = Mask;
+
+    fn not(self) -> Mask {
+        return Mask(!self.0);
+    }
+}
+
+impl PacketVector {
+    /// The same vector in every lane.
+    pub fn splat(vector: &Vector) -> PacketVector {
+        return PacketVector {
+            x: Lanes::splat(vector.x),
+            y: Lanes::splat(vector.y),
+            z: Lanes::splat(vector.z),
+        };
+    }
+
+    pub fn from_fn(function: impl Fn(usize) -> Vector) -> PacketVector {
+        let vectors: [Vector; PACKET_SIZE] = array::from_fn(function);
+        return PacketVector {
+            x: Lanes::new(vectors.map(|vector| vector.x)),
+            y: Lanes::new(vectors.map(|vector| vector.y)),
+            z: Lanes::new(vectors.map(|vector| vector.z)),
+        };
+    }
+
+    pub fn lane(&self, lane: usize) -> Vector {
+        return Vector::new(self.x.lane(lane), self.y.lane(lane), self.z.lane(lane));
+    }
+
+    pub fn component(&self, axis: usize) -> Lanes {
+        return match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        };
+    }
+
+    pub fn dot(&self, other: &PacketVector) -> Lanes {
+        return self.x * other.x + self.y * other.y + self.z * other.z;
+    }
+
+    pub fn cross(&self, other: &PacketVector) -> PacketVector {
+        return PacketVector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        };
+    }
+}
+
+impl Add for PacketVector {
+    type Output = PacketVector;
+
+    fn add(self, other: PacketVector) -> PacketVector {
+        return PacketVector {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        };
+    }
+}
+
+impl Sub for PacketVector {
+    type Output = PacketVector;
+
+    fn sub(self, other: PacketVector) -> PacketVector {
+        return PacketVector {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        };
+    }
+}
+
+impl Mul<Lanes> for PacketVector {
+    type Output = PacketVector;
+
+    fn mul(self, scale: Lanes) -> PacketVector {
+        return PacketVector {
+            x: self.x * scale,
+            y: self.y * scale,
+            z: self.z * scale,
+        };
+    }
+}
+
+impl Neg for PacketVector {
+    type Output = PacketVector;
+
+    fn neg(self) -> PacketVector {
+        return PacketVector {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        };
+    }
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; PACKET_SIZE]) -> RayPacket {
+        let directions = PacketVector::from_fn(|lane| rays[lane].dir);
+        let one = Lanes::splat(1.0);
+        return RayPacket {
+            origins: PacketVector::from_fn(|lane| rays[lane].origin.coords),
+            inverse_directions: PacketVector {
+                x: one / directions.x,
+                y: one / directions.y,
+                z: one / directions.z,
+            },
+            directions,
+            rays,
+        };
+    }
+
+    /// The packet in the space of a shape with the given position.
+    pub fn inverse_transform_by(&self, m: &Isometry) -> RayPacket {
+        return RayPacket::new(self.rays.map(|ray| ray.inverse_transform_by(m)));
+    }
+
+    /// Which rays pass through the box before their maximum times of impact, by the slab method.
+    pub fn hits_bounds(&self, bounds: &AABB<Scalar>, max_tois: Lanes) -> Mask {
+        let (mut near, mut far) = (Lanes::splat(0.0), max_tois);
+        for axis in 0..3 {
+            let (origin, direction, inverse) = (
+                self.origins.component(axis),
+                self.directions.component(axis),
+                self.inverse_directions.component(axis),
+            );
+            let (minimum, maximum) = (Lanes::splat(bounds.mins[axis]), Lanes::splat(bounds.maxs[axis]));
+
+            let t0 = (minimum - origin) * inverse;
+            let t1 = (maximum - origin) * inverse;
+
+            // Rays parallel to the axis are either always or never between its bounds.
+            let parallel = direction.eq(Lanes::splat(0.0));
+            let between = origin.ge(minimum) & origin.le(maximum);
+            let (always, never) = (Lanes::splat(Scalar::NEG_INFINITY), Lanes::splat(Scalar::INFINITY));
+            let enter = Lanes::select(parallel, Lanes::select(between, always, never), t0.min(t1));
+            let exit = Lanes::select(parallel, Lanes::select(between, never, always), t0.max(t1));
+
+            near = near.max(enter);
+            far = far.min(exit);
+        }
+
+        return near.le(far);
+    }
+
+    /// Intersections with the surface of a sphere, the same as ncollide's for each ray when not solid.
+    pub fn intersect_sphere(&self, centre: &Point, radius: Scalar, max_tois: Lanes) -> [Option<RayIntersection>; PACKET_SIZE] {
+        let offset = self.origins - PacketVector::splat(&centre.coords);
+
+        let a = self.directions.dot(&self.directions);
+        let b = offset.dot(&self.directions);
+        let c = offset.dot(&offset) - Lanes::splat(radius * radius);
+        let delta = b * b - a * c;
+
+        let zero = Lanes::splat(0.0);
+        // Rays starting outside and pointing away, or missing entirely.
+        let missed = (c.gt(zero) & b.gt(zero)) | delta.lt(zero);
+        let t = (-b - delta.sqrt()) / a;
+        // Rays starting inside hit the surface on their way out.
+        let inside = t.le(zero);
+        let toi = Lanes::select(inside, (-b + delta.sqrt()) / a, t);
+        let hits = !missed & toi.le(max_tois);
+
+        return array::from_fn(|lane| {
+            if !hits.lane(lane) {
+                return None;
+            }
+
+            let normal = (self.rays[lane].point_at(toi.lane(lane)) - centre).normalize();
+            let normal = if inside.lane(lane) { -normal } else { normal };
+            return Some(RayIntersection::new(toi.lane(lane), normal, FeatureId::Face(0)));
+        });
+    }
+
+    /// Intersections with a plane through the origin of `m`, the same as ncollide's for each ray when not solid.
+    pub fn intersect_plane(&self, m: &Isometry, normal: &Unit<Vector>, max_tois: Lanes) -> [Option<RayIntersection>; PACKET_SIZE] {
+        let local = self.inverse_transform_by(m);
+        let normal_lanes = PacketVector::splat(normal);
+
+        let zero = Lanes::splat(0.0);
+        let distance = -local.origins.dot(&normal_lanes);
+        // Rays starting behind the plane hit its back.
+        let behind = distance.gt(zero);
+        let t = distance / local.directions.dot(&normal_lanes);
+        let hits = t.ge(zero) & t.le(max_tois);
+
+        return array::from_fn(|lane| {
+            if !hits.lane(lane) {
+                return None;
+            }
+
+            let normal = m * normal.into_inner();
+            let normal = if behind.lane(lane) { -normal } else { normal };
+            return Some(RayIntersection::new(t.lane(lane), normal, FeatureId::Face(0)));
+        });
+    }
+
+    /// Times of impact with both sides of a triangle, infinite for rays that miss it. Follows ncollide's method.
+    pub fn intersect_triangle(&self, a: &Point, b: &Point, c: &Point) -> Lanes {
+        let ab = PacketVector::splat(&(b - a));
+        let ac = PacketVector::splat(&(c - a));
+        let normal = ab.cross(&ac);
+
+        let zero = Lanes::splat(0.0);
+        let d = normal.dot(&self.directions);
+        let ap = self.origins - PacketVector::splat(&a.coords);
+        let t = ap.dot(&normal);
+        // Rays starting on the side the ray points away from can't reach the triangle.
+        let away = (t.lt(zero) & d.lt(zero)) | (t.gt(zero) & d.gt(zero));
+
+        let e = -self.directions.cross(&ap);
+        let behind = t.lt(zero);
+        let v = Lanes::select(behind, -ac.dot(&e), ac.dot(&e));
+        let w = Lanes::select(behind, ab.dot(&e), -ab.dot(&e));
+        let d = d.abs();
+        let inside = v.ge(zero) & v.le(d) & w.ge(zero) & (v + w).le(d);
+
+        let hits = !d.eq(zero) & !away & inside;
+        return Lanes::select(hits, t.abs() / d, Lanes::splat(Scalar::INFINITY));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::{Plane, Rotation, Sphere};
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+    use ncollide3d::shape::Triangle;
+
+    fn packet() -> RayPacket {
+        return RayPacket::new([
+            Ray::new(Point::new(0.0, 0.0, -10.0), Vector::z()),
+            Ray::new(Point::new(0.5, 0.5, -10.0), Vector::z()),
+            Ray::new(Point::new(5.0, 0.0, -10.0), Vector::z()),
+            Ray::new(Point::new(0.0, 0.2, 0.0), Vector::new(0.0, 0.6, 0.8)),
+        ]);
+    }
+
+    fn assert_same(actual: &[Option<RayIntersection>; PACKET_SIZE], expected: impl Fn(&Ray) -> Option<RayIntersection>, packet: &RayPacket) {
+        for (lane, actual) in actual.iter().enumerate() {
+            let expected = expected(&packet.rays[lane]);
+            assert_eq!(actual.is_some(), expected.is_some(), "lane {}", lane);
+            if let (Some(actual), Some(expected)) = (actual, expected) {
+                assert_abs_diff_eq!(actual.toi, expected.toi, epsilon = 0.0001);
+                assert_abs_diff_eq!(actual.normal, expected.normal, epsilon = 0.0001);
+                assert_eq!(actual.feature, expected.feature);
+            }
+        }
+    }
+
+    #[test]
+    fn lanes() {
+        let a = Lanes::new([1.0, 2.0, 3.0, 4.0]);
+        let b = Lanes::new([4.0, 3.0, 2.0, 1.0]);
+
+        assert_eq!(a + b, Lanes::splat(5.0));
+        assert_eq!(a * b, Lanes::new([4.0, 6.0, 6.0, 4.0]));
+        assert_eq!(a.min(b), Lanes::new([1.0, 2.0, 2.0, 1.0]));
+        assert_eq!(a.lt(b), Mask::new([true, true, false, false]));
+        assert_eq!(Lanes::select(a.lt(b), a, b), Lanes::new([1.0, 2.0, 2.0, 1.0]));
+        assert_eq!(!a.lt(b) & a.ge(Lanes::splat(4.0)), Mask::new([false, false, false, true]));
+        assert!(a.lt(b).lane(1) && !a.lt(b).lane(2));
+
+        let mut c = a;
+        c.set_lane(2, 7.0);
+        assert_eq!(c, Lanes::new([1.0, 2.0, 7.0, 4.0]));
+    }
+
+    #[test]
+    fn hits_bounds() {
+        let bounds = AABB::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+
+        let hits = packet().hits_bounds(&bounds, Lanes::new([100.0, 100.0, 100.0, 0.5]));
+
+        assert_eq!(hits, Mask::new([true, true, false, true]));
+        assert_eq!(packet().hits_bounds(&bounds, Lanes::splat(5.0)), Mask::new([false, false, false, true]));
+    }
+
+    #[test]
+    fn intersect_sphere() {
+        let (packet, centre, sphere) = (packet(), Point::new(0.0, 0.5, 0.0), Sphere::new(1.0));
+        let m = Isometry::translation(centre.x, centre.y, centre.z);
+
+        let actual = packet.intersect_sphere(&centre, sphere.radius, Lanes::splat(100.0));
+
+        assert_same(&actual, |ray| sphere.toi_and_normal_with_ray(&m, ray, 100.0, false), &packet);
+    }
+
+    #[test]
+    fn intersect_plane() {
+        let (packet, plane) = (packet(), Plane::new(Vector::y_axis()));
+        let m = Isometry::from_parts(Vector::new(0.0, 1.0, 0.0).into(), Rotation::from_axis_angle(&Vector::x_axis(), -1.0));
+
+        let actual = packet.intersect_plane(&m, &plane.normal, Lanes::splat(100.0));
+
+        assert_same(&actual, |ray| plane.toi_and_normal_with_ray(&m, ray, 100.0, false), &packet);
+    }
+
+    #[test]
+    fn intersect_triangle() {
+        let packet = packet();
+        let triangle = Triangle::new(Point::new(-1.0, -1.0, 0.0), Point::new(2.0, -1.0, 0.0), Point::new(-1.0, 2.0, 0.0));
+
+        let actual = packet.intersect_triangle(&triangle.a, &triangle.b, &triangle.c);
+
+        for (lane, ray) in packet.rays.iter().enumerate() {
+            let expected = triangle.toi_with_ray(&Isometry::identity(), ray, Scalar::MAX, true);
+            assert_eq!(actual.lane(lane).is_finite(), expected.is_some(), "lane {}", lane);
+            if let Some(expected) = expected {
+                assert_abs_diff_eq!(actual.lane(lane), expected, epsilon = 0.0001);
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Roots are found in double precision whatever the scalar type, as quartics lose a lot of precision when solved
+// directly. Coefficients are given from the highest power down, and real roots are returned in ascending order.
+
+const EPSILON: f64 = 1e-12;
+
+// Newton's method steps taken to polish the roots of quartics.
+const POLISHING_ITERATIONS: usize = 3;
+
+/// Solves a x² + b x + c = 0. Repeated roots are returned once.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return match b.abs() < EPSILON {
+            true => vec![],
+            false => vec![-c / b],
+        };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    if discriminant == 0.0 {
+        return vec![-b / (2.0 * a)];
+    }
+
+    // Avoids cancellation when b is much larger than a and c.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (x0, x1) = (q / a, c / q);
+    return match x0 < x1 {
+        true => vec![x0, x1],
+        false => vec![x1, x0],
+    };
+}
+
+/// Solves a x³ + b x² + c x + d = 0.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_quadratic(b, c, d);
+    }
+
+    // Substituting x = t - b / 3a gives the depressed cubic t³ + p t + q = 0.
+    let (b, c, d) = (b / a, c / a, d / a);
+    let shift = -b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    let mut roots = match discriminant {
+        // Three real roots, from the trigonometric solution.
+        discriminant if discriminant < 0.0 => {
+            let r = (-p / 3.0).sqrt();
+            let phi = (-q / (2.0 * r * r * r)).clamp(-1.0, 1.0).acos();
+            (0..3)
+                .map(|k| 2.0 * r * ((phi - 2.0 * std::f64::consts::PI * k as f64) / 3.0).cos() + shift)
+                .collect()
+        }
+        _ => {
+            let sqrt_discriminant = discriminant.sqrt();
+            let u = (-q / 2.0 + sqrt_discriminant).cbrt();
+            let v = (-q / 2.0 - sqrt_discriminant).cbrt();
+            vec![u + v + shift]
+        }
+    };
+
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    return roots;
+}
+
+/// Solves a x⁴ + b x³ + c x² + d x + e = 0 with Ferrari's method, then polishes the roots with Newton's method. Roots
+/// that are close together, such as where a ray grazes a surface, can be lost or merged.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // Substituting x = y - b / 4a gives the depressed quartic y⁴ + p y² + q y + r = 0.
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    let shift = -b / 4.0;
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+
+    let mut roots = match q.abs() < EPSILON {
+        // A quadratic in y².
+        true => solve_quadratic(1.0, p, r)
+            .into_iter()
+            .filter(|&z| z >= 0.0)
+            .flat_map(|z| [-z.sqrt(), z.sqrt()])
+            .collect::<Vec<_>>(),
+        false => {
+            // The largest root of the resolvent cubic is positive, and factors the quartic into two quadratics.
+            let m = *solve_cubic(1.0, 2.0 * p, p * p - 4.0 * r, -q * q).last().unwrap();
+            let s = m.max(EPSILON).sqrt();
+            let mut roots = solve_quadratic(1.0, s, (p + m - q / s) / 2.0);
+            roots.extend(solve_quadratic(1.0, -s, (p + m + q / s) / 2.0));
+            roots
+        }
+    };
+
+    for root in roots.iter_mut() {
+        *root = polish(*root + shift, &[1.0, b, c, d, e]);
+    }
+
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots.dedup_by(|x, y| (*x - *y).abs() < 1e-9);
+    return roots;
+}
+
+fn polish(mut root: f64, coefficients: &[f64]) -> f64 {
+    for _ in 0..POLISHING_ITERATIONS {
+        let (value, derivative) = evaluate(root, coefficients);
+        if derivative == 0.0 {
+            break;
+        }
+
+        let polished = root - value / derivative;
+        // Newton's method can run away near repeated roots, where the derivative is close to zero.
+        if !polished.is_finite() || evaluate(polished, coefficients).0.abs() > value.abs() {
+            break;
+        }
+        root = polished;
+    }
+
+    return root;
+}
+
+/// The value and derivative of a polynomial at x, using Horner's method.
+pub fn evaluate(x: f64, coefficients: &[f64]) -> (f64, f64) {
+    let mut value = 0.0;
+    let mut derivative = 0.0;
+    for coefficient in coefficients {
+        derivative = derivative * x + value;
+        value = value * x + coefficient;
+    }
+
+    return (value, derivative);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "Expected roots {:?}, got {:?}.", expected, actual);
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert_abs_diff_eq!(actual, expected, epsilon = 1e-9);
+        }
+    }
+
+    #[rstest(
+        coefficients,
+        expected,
+        case([1.0, -3.0, 2.0], &[1.0, 2.0]),
+        case([2.0, 0.0, 2.0], &[]),
+        case([1.0, -2.0, 1.0], &[1.0]),
+        case([0.0, 2.0, -1.0], &[0.5])
+    )]
+    fn quadratic(coefficients: [f64; 3], expected: &[f64]) {
+        assert_roots(solve_quadratic(coefficients[0], coefficients[1], coefficients[2]), expected);
+    }
+
+    #[rstest(
+        coefficients,
+        expected,
+        // (x - 1)(x - 2)(x - 3)
+        case([1.0, -6.0, 11.0, -6.0], &[1.0, 2.0, 3.0]),
+        // (x - 2)(x² + 1)
+        case([2.0, -4.0, 2.0, -4.0], &[2.0]),
+        case([0.0, 1.0, -3.0, 2.0], &[1.0, 2.0])
+    )]
+    fn cubic(coefficients: [f64; 4], expected: &[f64]) {
+        assert_roots(solve_cubic(coefficients[0], coefficients[1], coefficients[2], coefficients[3]), expected);
+    }
+
+    #[rstest(
+        coefficients,
+        expected,
+        // (x + 2)(x - 1)(x - 3)(x - 4)
+        case([1.0, -6.0, 3.0, 26.0, -24.0], &[-2.0, 1.0, 3.0, 4.0]),
+        // (x² - 1)(x² - 4), which has no odd terms.
+        case([1.0, 0.0, -5.0, 0.0, 4.0], &[-2.0, -1.0, 1.0, 2.0]),
+        // (x - 1)(x - 2)(x² + 1)
+        case([1.0, -3.0, 3.0, -3.0, 2.0], &[1.0, 2.0]),
+        // (x² + 1)(x² + 2)
+        case([1.0, 0.0, 3.0, 0.0, 2.0], &[]),
+        // Widely spread roots, 0.001, 1, 10 and 1000, scaled.
+        case([3.0, -3033.003, 33033.033, -30033.03, 30.0], &[0.001, 1.0, 10.0, 1000.0])
+    )]
+    fn quartic(coefficients: [f64; 5], expected: &[f64]) {
+        assert_roots(
+            solve_quartic(coefficients[0], coefficients[1], coefficients[2], coefficients[3], coefficients[4]),
+            expected,
+        );
+    }
+
+    #[test]
+    fn evaluate_value_and_derivative() {
+        // 2x³ - x + 5 at 2.
+        assert_eq!(evaluate(2.0, &[2.0, 0.0, -1.0, 5.0]), (19.0, 23.0));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::maths::{Affine, Isometry, Point, Rotation, Vector};
+use nalgebra::{Matrix3, Translation3};
+
+/// An affine transformation that scales, then rotates, then translates. Scaling can be non-uniform, so unlike an
+/// isometry it does not preserve angles and normals must be transformed with `transform_normal`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transformation {
+    pub translation: Vector,
+    pub rotation: Rotation,
+    pub scale: Vector,
+}
+
+impl Transformation {
+    pub fn new(position: Point, rotation: Rotation, scale: Vector) -> Transformation {
+        assert!(scale.iter().all(|&component| component != 0.0), "Scale components must be non-zero.");
+        return Transformation {
+            translation: position.coords,
+            rotation,
+            scale,
+        };
+    }
+
+    pub fn translation(position: Point) -> Transformation {
+        return Transformation::new(position, Rotation::identity(), Vector::new(1.0, 1.0, 1.0));
+    }
+
+    /// The rotation and translation, without the scale.
+    pub fn isometry(&self) -> Isometry {
+        return Isometry::from_parts(Translation3::from(self.translation), self.rotation);
+    }
+
+    /// This transformation followed by `outer`, if that is a single transformation. It isn't when `outer` scales
+    /// non-uniformly something this rotates, as that skews it.
+    pub fn then(&self, outer: &Transformation) -> Option<Transformation> {
+        let uniform = outer.scale.x == outer.scale.y && outer.scale.y == outer.scale.z;
+        if !uniform && self.rotation != Rotation::identity() {
+            return None;
+        }
+
+        return Some(Transformation::new(
+            outer.transform_point(&Point::from(self.translation)),
+            outer.rotation * self.rotation,
+            outer.scale.component_mul(&self.scale),
+        ));
+    }
+
+    pub fn is_scaled(&self) -> bool {
+        return self.scale != Vector::new(1.0, 1.0, 1.0);
+    }
+
+    pub fn to_affine(&self) -> Affine {
+        let scale = Matrix3::from_diagonal(&self.scale).to_homogeneous();
+        return Affine::from_matrix_unchecked(self.isometry().to_homogeneous() * scale);
+    }
+
+    pub fn transform_point(&self, point: &Point) -> Point {
+        return self.isometry() * Point::from(point.coords.component_mul(&self.scale));
+    }
+
+    pub fn inverse_transform_point(&self, point: &Point) -> Point {
+        return Point::from(self.isometry().inverse_transform_point(point).coords.component_div(&self.scale));
+    }
+
+    /// Transforms a normal by the inverse transpose of the linear part, so it stays perpendicular to the surface. The
+    /// result is not normalised.
+    pub fn transform_normal(&self, normal: &Vector) -> Vector {
+        return self.rotation * normal.component_div(&self.scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts::FRAC_PI_2;
+    use approx::assert_abs_diff_eq;
+
+    fn assert_vector(actual: Vector, expected: Vector) {
+        assert_abs_diff_eq!(actual.x, expected.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(actual.y, expected.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(actual.z, expected.z, epsilon = 0.0001);
+    }
+
+    fn transformation() -> Transformation {
+        return Transformation::new(
+            Point::new(1.0, 2.0, 3.0),
+            Rotation::from_axis_angle(&Vector::z_axis(), FRAC_PI_2),
+            Vector::new(2.0, 1.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn transform_point() {
+        // Scaled to (2, 1, 1), rotated to (-1, 2, 1), then translated.
+        assert_vector(transformation().transform_point(&Point::new(1.0, 1.0, 1.0)).coords, Vector::new(0.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn transform_point_matches_affine() {
+        let point = Point::new(0.5, -2.0, 3.0);
+
+        assert_vector(transformation().transform_point(&point).coords, (transformation().to_affine() * point).coords);
+    }
+
+    #[test]
+    fn inverse_transform_point() {
+        let point = Point::new(0.5, -2.0, 3.0);
+
+        assert_vector(
+            transformation().inverse_transform_point(&transformation().transform_point(&point)).coords,
+            point.coords,
+        );
+    }
+
+    #[test]
+    fn transform_normal_stays_perpendicular() {
+        let transformation = transformation();
+        // The plane x + y = 0, containing the tangent (1, -1, 0).
+        let normal = Vector::new(1.0, 1.0, 0.0);
+        let tangent = Vector::new(1.0, -1.0, 0.0);
+
+        let transformed_tangent = transformation.transform_point(&Point::from(tangent)) - transformation.transform_point(&Point::origin());
+        let transformed_normal = transformation.transform_normal(&normal);
+
+        assert_abs_diff_eq!(transformed_normal.dot(&transformed_tangent), 0.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn then() {
+        let inner = Transformation::new(
+            Point::new(0.0, 1.0, 0.0),
+            Rotation::from_axis_angle(&Vector::x_axis(), FRAC_PI_2),
+            Vector::new(1.0, 2.0, 3.0),
+        );
+        let outer = Transformation::new(
+            Point::new(5.0, 0.0, 0.0),
+            Rotation::from_axis_angle(&Vector::y_axis(), FRAC_PI_2),
+            Vector::repeat(2.0),
+        );
+        let point = Point::new(1.0, -1.0, 0.5);
+
+        let combined = inner.then(&outer).unwrap();
+
+        assert_vector(
+            combined.transform_point(&point).coords,
+            outer.transform_point(&inner.transform_point(&point)).coords,
+        );
+    }
+
+    #[test]
+    fn then_non_uniform_scale_of_rotation() {
+        let inner = Transformation::new(Point::origin(), Rotation::from_axis_angle(&Vector::x_axis(), FRAC_PI_2), Vector::repeat(1.0));
+        let outer = Transformation::new(Point::origin(), Rotation::identity(), Vector::new(1.0, 2.0, 1.0));
+
+        assert!(inner.then(&outer).is_none());
+        assert!(outer.then(&inner).is_some());
+    }
+
+    #[test]
+    fn is_scaled() {
+        assert!(transformation().is_scaled());
+        assert!(!Transformation::translation(Point::new(1.0, 2.0, 3.0)).is_scaled());
+    }
+}
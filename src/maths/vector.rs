@@ -1,10 +1,24 @@
-use crate::maths::{Unit, Vector};
+use crate::maths::{Scalar, Unit, Vector};
 
 pub fn reflect(vector: &Vector, about: &Unit<Vector>) -> Vector {
     let about_vector = &about.into_inner();
     return 2.0 * vector.dot(about_vector) * about_vector - vector;
 }
 
+/// Refracts `vector` through a surface whose normal faces against it. `eta` is the refractive index of the medium being
+/// left divided by that of the medium being entered. Returns `None` on total internal reflection.
+pub fn refract(vector: &Vector, normal: &Unit<Vector>, eta: Scalar) -> Option<Vector> {
+    let normal_vector = normal.into_inner();
+    let cos_incident = -vector.dot(&normal_vector);
+    let sin_squared_transmitted = eta * eta * (1.0 - cos_incident * cos_incident);
+    if sin_squared_transmitted > 1.0 {
+        return None;
+    }
+
+    let cos_transmitted = (1.0 - sin_squared_transmitted).sqrt();
+    return Some(eta * vector + (eta * cos_incident - cos_transmitted) * normal_vector);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -17,7 +31,7 @@ mod tests {
     about,
     expected_reflected_direction,
     case(Vector::x(), Unit::new_normalize(Vector::new(1.0, 0.0, 1.0)), Vector::z()),
-    case(Vector::z(), Unit::new_normalize(Vector::new((45.0f32 / 2.0).to_radians().tan(), 0.0, 1.0)), Matrix::normalize(&Vector::new(1.0, 0.0, 1.0)))
+    case(Vector::z(), Unit::new_normalize(Vector::new(Scalar::to_radians(45.0 / 2.0).tan(), 0.0, 1.0)), Matrix::normalize(&Vector::new(1.0, 0.0, 1.0)))
     )]
     fn reflect(direction: Vector, about: Unit<Vector>, expected_reflected_direction: Vector) {
         let reflection = super::reflect(&direction, &about);
@@ -28,4 +42,33 @@ mod tests {
         assert_abs_diff_eq!(reflection.y, expected.y, epsilon = 0.01);
         assert_abs_diff_eq!(reflection.z, expected.z, epsilon = 0.01);
     }
+
+    #[test]
+    fn refract_straight_through() {
+        let refraction = refract(&-Vector::y(), &Unit::new_normalize(Vector::y()), 1.0 / 1.5).unwrap();
+
+        assert_abs_diff_eq!(refraction.x, 0.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(refraction.y, -1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(refraction.z, 0.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn refract_obeys_snells_law() {
+        let incident = Matrix::normalize(&Vector::new(1.0, -1.0, 0.0));
+        let eta = 1.0 / 1.5;
+
+        let refraction = refract(&incident, &Unit::new_normalize(Vector::y()), eta).unwrap();
+
+        let sin_incident = Scalar::to_radians(45.0).sin();
+        assert_abs_diff_eq!(refraction.norm(), 1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(refraction.x, sin_incident * eta, epsilon = 0.0001);
+        assert!(refraction.y < 0.0);
+    }
+
+    #[test]
+    fn refract_total_internal_reflection() {
+        let incident = Matrix::normalize(&Vector::new(1.0, -1.0, 0.0));
+
+        assert!(refract(&incident, &Unit::new_normalize(Vector::y()), 1.5).is_none());
+    }
 }
@@ -4,6 +4,7 @@ use crate::rendering::algorithms::Algorithm;
 use crate::scene::Scene;
 
 /// Renders a flat gradient. Useful for testing image file output is correct.
+#[allow(clippy::manual_non_exhaustive)]
 pub struct Gradient {
     pub from: Colour,
     pub to: Colour,
@@ -1,13 +1,23 @@
 mod ray_tracing;
 pub use self::ray_tracing::RayTracing;
 
+mod spectral_ray_tracing;
+pub use self::spectral_ray_tracing::SpectralRayTracing;
+
 mod gradient;
 pub use self::gradient::Gradient;
 
 use crate::image::Colour;
+use crate::maths::packet::PACKET_SIZE;
 use crate::maths::Coordinates;
 use crate::scene::Scene;
 
 pub trait Algorithm {
     fn render_point(&self, scene: &Scene, camera_space_coordinates: &Coordinates) -> Colour;
+
+    /// Renders a packet of points, usually in neighbouring pixels. By default they are rendered one at a time;
+    /// algorithms can trace their primary rays together instead.
+    fn render_points(&self, scene: &Scene, camera_space_coordinates: &[Coordinates; PACKET_SIZE]) -> [Colour; PACKET_SIZE] {
+        return camera_space_coordinates.map(|coordinates| self.render_point(scene, &coordinates));
+    }
 }
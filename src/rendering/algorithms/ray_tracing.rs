@@ -1,7 +1,8 @@
 use crate::image::Colour;
+use crate::maths::packet::{RayPacket, PACKET_SIZE};
 use crate::maths::{vector, Coordinates, Ray, Scalar};
 use crate::rendering::algorithms::Algorithm;
-use crate::scene::{RayCollision, Scene};
+use crate::scene::{RayCollision, Scene, REFERENCE_WAVELENGTH};
 use nalgebra::{distance, Unit};
 
 // Cutoff used for contributions from light intensity and reflections. If the amount is going to be less than one
@@ -20,41 +21,56 @@ impl RayTracing {
     }
 }
 
+impl Default for RayTracing {
+    fn default() -> RayTracing {
+        return RayTracing::new();
+    }
+}
+
 impl Algorithm for RayTracing {
     fn render_point(&self, scene: &Scene, camera_space_coordinates: &Coordinates) -> Colour {
         let ray = scene.camera.ray_to(camera_space_coordinates);
         return trace_ray(scene, &ray, 0);
     }
+
+    fn render_points(&self, scene: &Scene, camera_space_coordinates: &[Coordinates; PACKET_SIZE]) -> [Colour; PACKET_SIZE] {
+        let packet = RayPacket::new(camera_space_coordinates.map(|coordinates| scene.camera.ray_to(&coordinates)));
+
+        // Only the primary rays are coherent; shadow and reflection rays go their own ways so are traced one at a time.
+        return scene.first_collisions_with_packet(&packet).map(|collision| match collision {
+            Some(collision) => calculate_colour(scene, &collision, 0),
+            None => scene.background_colour,
+        });
+    }
 }
 
 fn trace_ray(scene: &Scene, ray: &Ray, recursion_depth: usize) -> Colour {
-    if let Some(collision) = scene.first_collision_with_ray(&ray) {
+    if let Some(collision) = scene.first_collision_with_ray(ray) {
         return calculate_colour(scene, &collision, recursion_depth);
     }
 
-    return scene.background_colour.clone();
+    return scene.background_colour;
 }
 
 fn calculate_colour(scene: &Scene, collision: &RayCollision, recursion_depth: usize) -> Colour {
-    let material = collision.object.texture.material_at_point(&collision.intersection);
+    let material = collision.material();
 
-    let mut colour = material.ambient_colour * &scene.ambient_light;
+    let mut colour = material.ambient_colour * scene.ambient_light;
 
     for light in &scene.lights {
         // Sample rays from the light to the point_of_intersection.
         for light_ray in light.sample_rays_to(&collision.intersection) {
+            let light_distance = distance(&light_ray.origin, &collision.intersection);
             // This could be approximated and done once per light using the position of the light.
-            let intensity = light.attenuation.get_intensity(distance(&light_ray.origin, &collision.intersection));
+            let intensity = light.attenuation.get_intensity(light_distance);
             if intensity < MINIMUM_INTENSITY {
                 continue;
             }
 
-            // See if there is another object closer; if so it will be blocking the light ray.
-            // TODO: An object could block itself! Check the collision point is the same.
-            if let Some(other) = scene.first_collision_with_ray(&light_ray) {
-                if collision.object != other.object {
-                    continue;
-                }
+            // Anything between the point and the light blocks the light ray, including other parts of the same object.
+            let shadow_ray = collision.spawn_ray(-light_ray.dir);
+            if scene.is_occluded(&shadow_ray, distance(&shadow_ray.origin, &light_ray.origin)) {
+                continue;
             }
 
             // Nothing blocking, however if light . normal is negative then the light must have hit the back of the surface
@@ -85,11 +101,22 @@ fn calculate_colour(scene: &Scene, collision: &RayCollision, recursion_depth: us
         // Trace a ray out from the collision point.
         let reflection_ray = collision.reflection_ray();
 
-        // Need to exclude the current object or we might collide with that due to floating point imprecision.
-        if let Some(reflection_collision) = scene.first_collision_with_ray_excluding(&reflection_ray, collision.object) {
+        if let Some(reflection_collision) = scene.first_collision_with_ray(&reflection_ray) {
             colour = colour + material.reflectivity * calculate_colour(scene, &reflection_collision, recursion_depth + 1);
         }
     }
 
-    return colour.clamp();
+    // Light passing through transparent objects. Without spectral sampling there is no dispersion, so refract using
+    // the index at a single wavelength.
+    if recursion_depth < RECURSION_DEPTH && material.transparency > MINIMUM_INTENSITY {
+        let refractive_index = material.refractive_index.at(REFERENCE_WAVELENGTH);
+        if let Some(transmitted_ray) = scene.transmitted_ray(collision, refractive_index) {
+            colour = colour + material.transparency * trace_ray(scene, &transmitted_ray, recursion_depth + 1);
+        }
+    }
+
+    // Not clamped, as the spectral ray tracer doesn't clamp either. The image statistics need the full range to report
+    // clipped pixels and the histogram above white, and auto-exposure can only bring highlights back into range if they
+    // haven't already been cut off at 1. Colours are clamped when the image is converted for output.
+    return colour;
 }
@@ -0,0 +1,153 @@
+use crate::image::{Colour, ColourSpaceConversion, Primaries, SampledSpectrum, SampledWavelengths, WorkingColourSpace};
+use crate::maths::packet::{RayPacket, PACKET_SIZE};
+use crate::maths::{vector, Coordinates, Ray, Scalar};
+use crate::rendering::algorithms::Algorithm;
+use crate::scene::{Light, RayCollision, Scene};
+use nalgebra::{distance, Unit};
+use rand::{thread_rng, Rng};
+
+// Same cutoffs as the RGB ray tracer.
+const MINIMUM_INTENSITY: Scalar = 1.0 / 256.0;
+
+const RECURSION_DEPTH: usize = 10;
+
+/// Ray tracing where each sample carries a set of wavelengths rather than an RGB colour, chosen by hero wavelength
+/// sampling. Material and light colours are upsampled to spectra, and samples are converted to XYZ and then RGB at the
+/// film. Refraction through materials with a dispersive refractive index splits light into its colours.
+pub struct SpectralRayTracing {
+    // Upsampling is defined for sRGB, so colours are converted there from the working colour space and back at the film.
+    to_srgb: ColourSpaceConversion,
+    from_srgb: ColourSpaceConversion,
+}
+
+impl SpectralRayTracing {
+    pub fn new(colour_space: WorkingColourSpace) -> SpectralRayTracing {
+        return SpectralRayTracing {
+            to_srgb: ColourSpaceConversion::new(&colour_space.primaries(), &Primaries::SRGB),
+            from_srgb: ColourSpaceConversion::new(&Primaries::SRGB, &colour_space.primaries()),
+        };
+    }
+
+    fn reflectance(&self, colour: &Colour, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return SampledSpectrum::from_reflectance(&self.to_srgb_gamut(colour), wavelengths);
+    }
+
+    fn illuminant(&self, colour: &Colour, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return SampledSpectrum::from_illuminant(&self.to_srgb_gamut(colour), wavelengths);
+    }
+
+    // Wide gamut colours can fall outside sRGB; negative components cannot be upsampled so are clipped.
+    fn to_srgb_gamut(&self, colour: &Colour) -> Colour {
+        let srgb = self.to_srgb.convert(colour);
+        return Colour::new(srgb.r.max(0.0), srgb.g.max(0.0), srgb.b.max(0.0));
+    }
+}
+
+impl Algorithm for SpectralRayTracing {
+    fn render_point(&self, scene: &Scene, camera_space_coordinates: &Coordinates) -> Colour {
+        let wavelengths = SampledWavelengths::sample(thread_rng().gen());
+        let ray = scene.camera.ray_to(camera_space_coordinates);
+        let srgb = self.trace_ray(scene, &ray, &wavelengths, 0).to_colour(&wavelengths);
+        return self.from_srgb.convert(&srgb);
+    }
+
+    fn render_points(&self, scene: &Scene, camera_space_coordinates: &[Coordinates; PACKET_SIZE]) -> [Colour; PACKET_SIZE] {
+        let packet = RayPacket::new(camera_space_coordinates.map(|coordinates| scene.camera.ray_to(&coordinates)));
+
+        // As for the RGB ray tracer only the primary rays are traced together. Each still gets its own wavelengths.
+        return scene.first_collisions_with_packet(&packet).map(|collision| {
+            let wavelengths = SampledWavelengths::sample(thread_rng().gen());
+            let spectrum = match collision {
+                Some(collision) => self.calculate_spectrum(scene, &collision, &wavelengths, 0),
+                None => self.illuminant(&scene.background_colour, &wavelengths),
+            };
+            return self.from_srgb.convert(&spectrum.to_colour(&wavelengths));
+        });
+    }
+}
+
+impl SpectralRayTracing {
+    fn trace_ray(&self, scene: &Scene, ray: &Ray, wavelengths: &SampledWavelengths, recursion_depth: usize) -> SampledSpectrum {
+        if let Some(collision) = scene.first_collision_with_ray(ray) {
+            return self.calculate_spectrum(scene, &collision, wavelengths, recursion_depth);
+        }
+
+        return self.illuminant(&scene.background_colour, wavelengths);
+    }
+
+    fn light_spectrum(&self, light: &Light, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        return match &light.spectrum {
+            Some(spectrum) => spectrum.sample(wavelengths),
+            None => self.illuminant(&light.colour, wavelengths),
+        };
+    }
+
+    fn calculate_spectrum(&self, scene: &Scene, collision: &RayCollision, wavelengths: &SampledWavelengths, recursion_depth: usize) -> SampledSpectrum {
+        let material = collision.material();
+
+        let ambient = self.reflectance(&material.ambient_colour, wavelengths);
+        let mut spectrum = ambient * self.illuminant(&scene.ambient_light, wavelengths);
+
+        let diffuse = self.reflectance(&material.diffuse_colour, wavelengths);
+        let specular = self.reflectance(&material.specular_colour, wavelengths);
+
+        for light in &scene.lights {
+            let emitted = self.light_spectrum(light, wavelengths);
+
+            for light_ray in light.sample_rays_to(&collision.intersection) {
+                let light_distance = distance(&light_ray.origin, &collision.intersection);
+                let intensity = light.attenuation.get_intensity(light_distance);
+                if intensity < MINIMUM_INTENSITY {
+                    continue;
+                }
+
+                let shadow_ray = collision.spawn_ray(-light_ray.dir);
+                if scene.is_occluded(&shadow_ray, distance(&shadow_ray.origin, &light_ray.origin)) {
+                    continue;
+                }
+
+                let light_dot_normal = -light_ray.dir.dot(&collision.normal);
+                if light_dot_normal <= 0.0 {
+                    continue;
+                }
+
+                let incoming = emitted * (light.sample_factor * intensity);
+
+                spectrum = spectrum + diffuse * incoming * light_dot_normal;
+
+                if material.shininess > 0.0 {
+                    let reflection = vector::reflect(&light_ray.dir, &collision.normal);
+                    let to_viewer = Unit::new_normalize(collision.intersection - scene.camera.position);
+                    let r_dot_v = reflection.dot(&to_viewer);
+                    if r_dot_v > 0.0 {
+                        spectrum = spectrum + specular * incoming * r_dot_v.powf(material.shininess);
+                    }
+                }
+            }
+        }
+
+        if recursion_depth < RECURSION_DEPTH && material.reflectivity > MINIMUM_INTENSITY {
+            let reflection_ray = collision.reflection_ray();
+
+            if let Some(reflection_collision) = scene.first_collision_with_ray(&reflection_ray) {
+                spectrum = spectrum + material.reflectivity * self.calculate_spectrum(scene, &reflection_collision, wavelengths, recursion_depth + 1);
+            }
+        }
+
+        if recursion_depth < RECURSION_DEPTH && material.transparency > MINIMUM_INTENSITY {
+            // With dispersion each wavelength would take a different path, so only the hero wavelength is followed.
+            let dispersive = material.refractive_index.is_dispersive();
+            let refractive_index = material.refractive_index.at(wavelengths.hero());
+
+            if let Some(transmitted_ray) = scene.transmitted_ray(collision, refractive_index) {
+                let mut transmitted = self.trace_ray(scene, &transmitted_ray, wavelengths, recursion_depth + 1);
+                if dispersive {
+                    transmitted = transmitted.terminate_secondary();
+                }
+                spectrum = spectrum + material.transparency * transmitted;
+            }
+        }
+
+        return spectrum;
+    }
+}
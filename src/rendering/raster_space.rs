@@ -1,5 +1,6 @@
 use crate::maths::{Coordinates, Scalar};
 
+#[allow(clippy::manual_non_exhaustive)]
 pub struct RasterSpace {
     pub width: Scalar,
     pub height: Scalar,
@@ -1,36 +1,80 @@
 use crate::configuration::Configuration;
 use crate::image::{Colour, Image};
-use crate::maths::Coordinates;
+use crate::maths::packet::PACKET_SIZE;
+use crate::maths::{Coordinates, Scalar};
 use crate::rendering::algorithms::Algorithm;
 use crate::rendering::{RasterSpace, SubPixelSampling};
 use crate::scene::Scene;
 use parking_lot::Mutex;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::array;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
+// How many snapshots of the image in progress are passed to the progress callback.
+const PROGRESS_SNAPSHOTS: u32 = 10;
+
+// Pixels are rendered in blocks with a pixel per ray of a packet, so the primary rays through them can be traced together.
+const BLOCK_WIDTH: u32 = 2;
+const BLOCK_HEIGHT: u32 = PACKET_SIZE as u32 / BLOCK_WIDTH;
+
 pub fn render<TAlgorithm: Algorithm + Sync>(algorithm: &TAlgorithm, configuration: &Configuration, scene: &Scene, sampling: SubPixelSampling) -> Image {
+    return render_with_progress(algorithm, configuration, scene, sampling, &|_| {});
+}
+
+/// Renders the scene, periodically passing a snapshot of the partially rendered image to the progress callback.
+pub fn render_with_progress<TAlgorithm: Algorithm + Sync>(
+    algorithm: &TAlgorithm,
+    configuration: &Configuration,
+    scene: &Scene,
+    sampling: SubPixelSampling,
+    progress: &(dyn Fn(&Image) + Sync),
+) -> Image {
     let image = Arc::new(Mutex::new(Image::new(configuration.width, configuration.height)));
 
     let raster_space = RasterSpace::new(configuration.width, configuration.height);
 
     let sampling_offsets = sampling.pixel_offsets();
 
-    (0..configuration.width * configuration.height).into_par_iter().for_each_init(
-        || sampling_offsets.iter().map(|_| Colour::black()).collect::<Vec<Colour>>(),
-        |samples, pixel| {
-            let y = pixel / configuration.width;
-            let x = pixel - y * configuration.width;
+    let (width, height) = (configuration.width, configuration.height);
+    let pixel_count = width * height;
+    let snapshot_interval = (pixel_count / PROGRESS_SNAPSHOTS).max(1);
+    let completed = AtomicU32::new(0);
 
-            for sample in 0..sampling_offsets.len() {
-                let offset = &sampling_offsets[sample];
+    let blocks_across = width.div_ceil(BLOCK_WIDTH);
+    let block_count = blocks_across * height.div_ceil(BLOCK_HEIGHT);
 
-                let raster_coords = Coordinates::new(x as f32 + offset.x, y as f32 + offset.y);
+    (0..block_count).into_par_iter().for_each_init(
+        || sampling_offsets.iter().map(|_| [Colour::black(); PACKET_SIZE]).collect::<Vec<_>>(),
+        |samples, block| {
+            let (left, top) = (block % blocks_across * BLOCK_WIDTH, block / blocks_across * BLOCK_HEIGHT);
+            let pixels: [(u32, u32); PACKET_SIZE] = array::from_fn(|lane| (left + lane as u32 % BLOCK_WIDTH, top + lane as u32 / BLOCK_WIDTH));
 
-                let screen_space_coords = raster_space.to_screen_space(raster_coords);
+            for (sample, offset) in sampling_offsets.iter().enumerate() {
+                // Blocks over the edge of the image render pixels inside it again in place of those outside.
+                let screen_space_coords = pixels.map(|(x, y)| {
+                    let raster_coords = Coordinates::new(x.min(width - 1) as Scalar + offset.x, y.min(height - 1) as Scalar + offset.y);
+                    return raster_space.to_screen_space(raster_coords);
+                });
+
+                samples[sample] = algorithm.render_points(scene, &screen_space_coords);
+            }
+
+            let mut locked = image.lock();
+            let mut rendered = 0;
+            for (lane, &(x, y)) in pixels.iter().enumerate().filter(|(_, (x, y))| *x < width && *y < height) {
+                locked.set_pixel(x, y, Colour::average(&samples.iter().map(|colours| colours[lane]).collect::<Vec<_>>()));
+                rendered += 1;
+            }
 
-                samples[sample] = algorithm.render_point(&scene, &screen_space_coords);
+            let previous = completed.fetch_add(rendered, Ordering::Relaxed);
+            let done = previous + rendered;
+            if done / snapshot_interval > previous / snapshot_interval && done < pixel_count {
+                // Other blocks can carry on rendering while the callback runs, e.g. saving the snapshot.
+                let snapshot = locked.clone();
+                drop(locked);
+                progress(&snapshot);
             }
-            image.lock().set_pixel(x, y, Colour::average(&samples));
         },
     );
 
@@ -19,8 +19,8 @@ impl SubPixelSampling {
                 for x in 0..number {
                     for y in 0..number {
                         result.push(Coordinates::new(
-                            x as f32 * size_of_sub_pixel + offset_from_side,
-                            y as f32 * size_of_sub_pixel + offset_from_side,
+                            x as Scalar * size_of_sub_pixel + offset_from_side,
+                            y as Scalar * size_of_sub_pixel + offset_from_side,
                         ));
                     }
                 }
@@ -0,0 +1,559 @@
+use crate::maths::packet::{Lanes, Mask, RayPacket, PACKET_SIZE};
+use crate::maths::{Point, Ray, RayIntersection, Scalar, Vector};
+use ncollide3d::bounding_volume::{BoundingVolume, AABB};
+
+// Items are split into this many bins along an axis when looking for the best split.
+const BINS: usize = 16;
+
+// Leaves are split further if it is cheaper by the surface area heuristic, or always if they have more items than this.
+const MAXIMUM_LEAF_SIZE: usize = 8;
+
+// The cost of visiting a node relative to intersecting an item, for the surface area heuristic.
+const TRAVERSAL_COST: Scalar = 1.0;
+
+// Deeper nodes are always leaves, so traversal can use a fixed size stack.
+const MAXIMUM_DEPTH: usize = 64;
+
+// Bounds this large in any direction are treated as infinite, e.g. those of planes.
+const UNBOUNDED_EXTENT: Scalar = Scalar::MAX / 4.0;
+
+/// A bounding volume hierarchy over items with bounding boxes, e.g. the objects in a scene, to quickly find the items a
+/// ray might hit. It is built top down, splitting by the surface area heuristic, and flattened into an array of nodes in
+/// depth first order. Items with unbounded boxes, such as planes, are kept aside and always tested.
+#[derive(Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    // The indices of the bounded items, in the order the leaves refer to them.
+    items: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+// 32 bytes, so two fit in a cache line.
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: AABB<Scalar>,
+    // For leaves the index of their first item in `items`; for interior nodes the index of their second child, as the
+    // first comes straight after them.
+    offset: u32,
+    // Zero for interior nodes.
+    count: u16,
+    // The axis interior nodes are split along, to visit the nearest child first.
+    axis: u8,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        return self.count > 0;
+    }
+}
+
+#[derive(Clone)]
+struct Bin {
+    bounds: Option<AABB<Scalar>>,
+    count: usize,
+}
+
+impl Bin {
+    fn add(&mut self, bounds: &AABB<Scalar>) {
+        self.bounds = Some(merge(self.bounds.as_ref(), bounds));
+        self.count += 1;
+    }
+}
+
+impl Bvh {
+    /// Builds the hierarchy for items with the given bounds; the items are referred to by their indices in `bounds`.
+    pub fn new(bounds: &[AABB<Scalar>]) -> Bvh {
+        let (unbounded, mut items): (Vec<usize>, Vec<usize>) = (0..bounds.len()).partition(|&index| is_unbounded(&bounds[index]));
+        let centroids: Vec<Point> = bounds.iter().map(|aabb| aabb.center()).collect();
+
+        let mut nodes = Vec::with_capacity(2 * items.len());
+        if !items.is_empty() {
+            let length = items.len();
+            build(&mut nodes, &mut items, 0, length, bounds, &centroids, 0);
+        }
+
+        return Bvh { nodes, items, unbounded };
+    }
+
+    /// The closest intersection, before `max_toi`, of the ray with any item. `intersect` finds the intersection with
+    /// an item, if it is before the time it is given, which is the closest intersection so far.
+    pub fn first_intersection<F>(&self, ray: &Ray, max_toi: Scalar, mut intersect: F) -> Option<(usize, RayIntersection)>
+    where
+        F: FnMut(usize, Scalar) -> Option<RayIntersection>,
+    {
+        let mut closest: Option<(usize, RayIntersection)> = None;
+        self.traverse(ray, max_toi, |item, max_toi| {
+            if let Some(intersection) = intersect(item, *max_toi).filter(|intersection| intersection.toi < *max_toi) {
+                *max_toi = intersection.toi;
+                closest = Some((item, intersection));
+            }
+            return false;
+        });
+
+        return closest;
+    }
+
+    /// Whether the ray hits any item before `max_toi`, stopping at the first item `intersects` says it hits rather than
+    /// looking for the closest.
+    pub fn any_intersection<F>(&self, ray: &Ray, max_toi: Scalar, mut intersects: F) -> bool
+    where
+        F: FnMut(usize, Scalar) -> bool,
+    {
+        return self.traverse(ray, max_toi, |item, max_toi| intersects(item, *max_toi));
+    }
+
+    /// The closest intersections, before `max_toi`, of each ray of a packet with any item. The rays are traced
+    /// together through the hierarchy, so this is quicker than tracing them one at a time when they are coherent.
+    /// `intersect` finds the intersections of the rays with an item, if they are before the times it is given, but only
+    /// needs to for the rays in the mask, which might reach the item.
+    pub fn first_intersections<F>(&self, packet: &RayPacket, max_toi: Scalar, mut intersect: F) -> [Option<(usize, RayIntersection)>; PACKET_SIZE]
+    where
+        F: FnMut(usize, Lanes, Mask) -> [Option<RayIntersection>; PACKET_SIZE],
+    {
+        let mut closest: [Option<(usize, RayIntersection)>; PACKET_SIZE] = [None; PACKET_SIZE];
+        let mut max_tois = Lanes::splat(max_toi);
+        let mut test = |item: usize, mask: Mask, max_tois: &mut Lanes| {
+            let intersections = intersect(item, *max_tois, mask);
+            for (lane, intersection) in intersections.iter().enumerate() {
+                if let Some(intersection) = intersection.filter(|intersection| mask.lane(lane) && intersection.toi < max_tois.lane(lane)) {
+                    max_tois.set_lane(lane, intersection.toi);
+                    closest[lane] = Some((item, intersection));
+                }
+            }
+        };
+
+        for &item in &self.unbounded {
+            test(item, Mask::splat(true), &mut max_tois);
+        }
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        // Coherent rays mostly point the same way, so the first decides which child is nearest.
+        let direction = packet.rays[0].dir;
+        let mut stack = [0u32; MAXIMUM_DEPTH + 1];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let index = stack[stack_size] as usize;
+            let node = &self.nodes[index];
+            let mask = packet.hits_bounds(&node.bounds, max_tois);
+            if !mask.any() {
+                continue;
+            }
+
+            if node.is_leaf() {
+                let first = node.offset as usize;
+                for &item in &self.items[first..first + node.count as usize] {
+                    test(item, mask, &mut max_tois);
+                }
+                continue;
+            }
+
+            let (near, far) = match direction[node.axis as usize] < 0.0 {
+                true => (node.offset, index as u32 + 1),
+                false => (index as u32 + 1, node.offset),
+            };
+            stack[stack_size] = far;
+            stack[stack_size + 1] = near;
+            stack_size += 2;
+        }
+
+        return closest;
+    }
+
+    // Visits the items whose bounds the ray passes through before `max_toi`, nearest nodes first, until `visit` returns
+    // true, which is returned. `visit` can shorten the ray to skip nodes behind a hit.
+    fn traverse<F>(&self, ray: &Ray, max_toi: Scalar, mut visit: F) -> bool
+    where
+        F: FnMut(usize, &mut Scalar) -> bool,
+    {
+        let mut max_toi = max_toi;
+        for &item in &self.unbounded {
+            if visit(item, &mut max_toi) {
+                return true;
+            }
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inverse_direction = Vector::repeat(1.0).component_div(&ray.dir);
+        let mut stack = [0u32; MAXIMUM_DEPTH + 1];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let index = stack[stack_size] as usize;
+            let node = &self.nodes[index];
+            if !hits_bounds(&node.bounds, ray, &inverse_direction, max_toi) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                let first = node.offset as usize;
+                for &item in &self.items[first..first + node.count as usize] {
+                    if visit(item, &mut max_toi) {
+                        return true;
+                    }
+                }
+                continue;
+            }
+
+            // Push the far child first so the near one is visited first, and can shorten the ray for the far one.
+            let (near, far) = match ray.dir[node.axis as usize] < 0.0 {
+                true => (node.offset, index as u32 + 1),
+                false => (index as u32 + 1, node.offset),
+            };
+            stack[stack_size] = far;
+            stack[stack_size + 1] = near;
+            stack_size += 2;
+        }
+
+        return false;
+    }
+}
+
+fn build(nodes: &mut Vec<Node>, items: &mut [usize], start: usize, end: usize, bounds: &[AABB<Scalar>], centroids: &[Point], depth: usize) {
+    let node_index = nodes.len();
+    let node_bounds = items[start..end]
+        .iter()
+        .fold(bounds[items[start]], |merged, &item| merged.merged(&bounds[item]));
+    let count = end - start;
+    nodes.push(Node {
+        bounds: node_bounds,
+        offset: start as u32,
+        count: count as u16,
+        axis: 0,
+    });
+
+    if count == 1 || depth == MAXIMUM_DEPTH {
+        return;
+    }
+
+    let middle = match split(&mut items[start..end], &node_bounds, bounds, centroids) {
+        Some((axis, middle)) => {
+            nodes[node_index].axis = axis as u8;
+            start + middle
+        }
+        None => return,
+    };
+
+    build(nodes, items, start, middle, bounds, centroids, depth + 1);
+    nodes[node_index].offset = nodes.len() as u32;
+    nodes[node_index].count = 0;
+    build(nodes, items, middle, end, bounds, centroids, depth + 1);
+}
+
+// Partitions the items by the cheapest split by the surface area heuristic, returning the axis and the number of items
+// on the first side, or None if they are better left as a leaf.
+fn split(items: &mut [usize], node_bounds: &AABB<Scalar>, bounds: &[AABB<Scalar>], centroids: &[Point]) -> Option<(usize, usize)> {
+    let centroid_bounds = items.iter().fold(AABB::new(centroids[items[0]], centroids[items[0]]), |merged, &item| {
+        return merged.merged(&AABB::new(centroids[item], centroids[item]));
+    });
+    let extents = centroid_bounds.maxs - centroid_bounds.mins;
+    let axis = extents.imax();
+    let leaf_cost = items.len() as Scalar;
+    if extents[axis] <= 0.0 {
+        // The items are all in the same place, so can't be split.
+        return match items.len() > MAXIMUM_LEAF_SIZE {
+            true => Some((axis, items.len() / 2)),
+            false => None,
+        };
+    }
+
+    let bin_of = |item: usize| {
+        let position = (centroids[item][axis] - centroid_bounds.mins[axis]) / extents[axis];
+        return ((position * BINS as Scalar) as usize).min(BINS - 1);
+    };
+    let mut bins = vec![Bin { bounds: None, count: 0 }; BINS];
+    for &item in items.iter() {
+        bins[bin_of(item)].add(&bounds[item]);
+    }
+
+    // The cost of splitting after each bin, from the areas and counts on either side, relative to the node's area.
+    let (mut best_cost, mut best_bin) = (Scalar::MAX, 0);
+    for bin in 0..BINS - 1 {
+        let side = |bins: &[Bin]| {
+            let merged = bins.iter().fold(None, |merged: Option<AABB<Scalar>>, bin| match &bin.bounds {
+                Some(bounds) => Some(merge(merged.as_ref(), bounds)),
+                None => merged,
+            });
+            let count: usize = bins.iter().map(|bin| bin.count).sum();
+            return merged.map_or(0.0, |merged| surface_area(&merged) * count as Scalar);
+        };
+        let cost = TRAVERSAL_COST + (side(&bins[..=bin]) + side(&bins[bin + 1..])) / surface_area(node_bounds);
+        if cost < best_cost {
+            best_cost = cost;
+            best_bin = bin;
+        }
+    }
+
+    if best_cost >= leaf_cost && items.len() <= MAXIMUM_LEAF_SIZE {
+        return None;
+    }
+
+    let mut middle = partition(items, |&item| bin_of(item) <= best_bin);
+    if middle == 0 || middle == items.len() {
+        middle = items.len() / 2;
+    }
+    return Some((axis, middle));
+}
+
+// Moves the items matching the predicate to the front, returning how many there are.
+fn partition(items: &mut [usize], predicate: impl Fn(&usize) -> bool) -> usize {
+    let mut first_false = 0;
+    for index in 0..items.len() {
+        if predicate(&items[index]) {
+            items.swap(index, first_false);
+            first_false += 1;
+        }
+    }
+    return first_false;
+}
+
+fn merge(merged: Option<&AABB<Scalar>>, bounds: &AABB<Scalar>) -> AABB<Scalar> {
+    return merged.map_or(*bounds, |merged| merged.merged(bounds));
+}
+
+fn surface_area(bounds: &AABB<Scalar>) -> Scalar {
+    let extents = bounds.maxs - bounds.mins;
+    return 2.0 * (extents.x * extents.y + extents.y * extents.z + extents.z * extents.x);
+}
+
+fn is_unbounded(bounds: &AABB<Scalar>) -> bool {
+    return (bounds.maxs - bounds.mins).iter().any(|&extent| extent.is_nan() || extent >= UNBOUNDED_EXTENT);
+}
+
+// Whether the ray passes through the box before `max_toi`, by the slab method.
+fn hits_bounds(bounds: &AABB<Scalar>, ray: &Ray, inverse_direction: &Vector, max_toi: Scalar) -> bool {
+    let (mut near, mut far) = (0.0 as Scalar, max_toi);
+    for axis in 0..3 {
+        // Rays parallel to the axis are either always or never between its bounds.
+        if ray.dir[axis] == 0.0 {
+            if ray.origin[axis] < bounds.mins[axis] || ray.origin[axis] > bounds.maxs[axis] {
+                return false;
+            }
+            continue;
+        }
+
+        let t0 = (bounds.mins[axis] - ray.origin[axis]) * inverse_direction[axis];
+        let t1 = (bounds.maxs[axis] - ray.origin[axis]) * inverse_direction[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+        if near > far {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::{Isometry, Plane, Sphere};
+    use ncollide3d::query::RayCast;
+    use ncollide3d::shape::Shape;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_spheres(count: usize) -> Vec<(Isometry, Sphere)> {
+        let mut random = StdRng::seed_from_u64(1);
+        return (0..count)
+            .map(|_| {
+                let position = Vector::new(random.gen_range(-50.0, 50.0), random.gen_range(-50.0, 50.0), random.gen_range(-50.0, 50.0));
+                (
+                    Isometry::translation(position.x, position.y, position.z),
+                    Sphere::new(random.gen_range(0.1, 2.0)),
+                )
+            })
+            .collect();
+    }
+
+    fn random_rays(count: usize) -> Vec<Ray> {
+        let mut random = StdRng::seed_from_u64(2);
+        return (0..count)
+            .map(|_| {
+                let origin = Point::new(random.gen_range(-60.0, 60.0), random.gen_range(-60.0, 60.0), -80.0);
+                let target = Point::new(random.gen_range(-50.0, 50.0), random.gen_range(-50.0, 50.0), random.gen_range(-50.0, 50.0));
+                Ray::new(origin, (target - origin).normalize())
+            })
+            .collect();
+    }
+
+    fn bvh(spheres: &[(Isometry, Sphere)]) -> Bvh {
+        return Bvh::new(&spheres.iter().map(|(m, sphere)| sphere.aabb(m)).collect::<Vec<_>>());
+    }
+
+    fn first_intersection(bvh: &Bvh, spheres: &[(Isometry, Sphere)], ray: &Ray) -> Option<(usize, RayIntersection)> {
+        return bvh.first_intersection(ray, Scalar::MAX, |index, max_toi| {
+            let (m, sphere) = &spheres[index];
+            return sphere.toi_and_normal_with_ray(m, ray, max_toi, true);
+        });
+    }
+
+    fn brute_force(spheres: &[(Isometry, Sphere)], ray: &Ray) -> Option<(usize, Scalar)> {
+        return spheres
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (m, sphere))| Some((index, sphere.toi_with_ray(m, ray, Scalar::MAX, true)?)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let spheres = random_spheres(1000);
+        let bvh = bvh(&spheres);
+
+        for ray in random_rays(1000) {
+            let expected = brute_force(&spheres, &ray);
+
+            let actual = first_intersection(&bvh, &spheres, &ray).map(|(index, intersection)| (index, intersection.toi));
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn any_intersection_matches_brute_force() {
+        let spheres = random_spheres(1000);
+        let bvh = bvh(&spheres);
+
+        for ray in random_rays(1000) {
+            let expected = brute_force(&spheres, &ray).is_some_and(|(_, toi)| toi < 100.0);
+
+            let actual = bvh.any_intersection(&ray, 100.0, |index, max_toi| {
+                let (m, sphere) = &spheres[index];
+                return sphere.intersects_ray(m, &ray, max_toi);
+            });
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn first_intersections_match_single_rays() {
+        let spheres = random_spheres(1000);
+        let bvh = bvh(&spheres);
+
+        for rays in random_rays(1000).chunks_exact(PACKET_SIZE) {
+            let packet = RayPacket::new([rays[0], rays[1], rays[2], rays[3]]);
+
+            let actual = bvh.first_intersections(&packet, Scalar::MAX, |index, max_tois, _| {
+                let (m, sphere) = &spheres[index];
+                return packet.intersect_sphere(&Point::from(m.translation.vector), sphere.radius, max_tois);
+            });
+
+            for (ray, actual) in rays.iter().zip(actual.iter()) {
+                let expected = first_intersection(&bvh, &spheres, ray).map(|(index, intersection)| (index, intersection.toi));
+                assert_eq!(actual.map(|(index, intersection)| (index, intersection.toi)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = Bvh::new(&[]);
+
+        assert!(bvh.first_intersection(&random_rays(1)[0], Scalar::MAX, |_, _| panic!()).is_none());
+    }
+
+    #[test]
+    fn unbounded_items_are_always_tested() {
+        let plane = Plane::new(Vector::y_axis());
+        let sphere = Sphere::new(1.0);
+        let (plane_position, sphere_position) = (Isometry::translation(0.0, -1.0, 0.0), Isometry::translation(0.0, 5.0, 0.0));
+        let bvh = Bvh::new(&[sphere.aabb(&sphere_position), plane.aabb(&plane_position)]);
+        let intersect = |ray: &Ray, index: usize, max_toi: Scalar| match index {
+            0 => sphere.toi_and_normal_with_ray(&sphere_position, ray, max_toi, true),
+            _ => plane.toi_and_normal_with_ray(&plane_position, ray, max_toi, true),
+        };
+
+        let down = Ray::new(Point::new(0.0, 10.0, 0.0), -Vector::y());
+        let (index, intersection) = bvh
+            .first_intersection(&down, Scalar::MAX, |index, max_toi| intersect(&down, index, max_toi))
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(intersection.toi, 4.0);
+
+        let beside = Ray::new(Point::new(3.0, 10.0, 0.0), -Vector::y());
+        let (index, intersection) = bvh
+            .first_intersection(&beside, Scalar::MAX, |index, max_toi| intersect(&beside, index, max_toi))
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(intersection.toi, 11.0);
+    }
+
+    #[test]
+    fn coincident_items() {
+        // Items that can't be separated by splitting still end up in leaves that are searched.
+        let spheres: Vec<(Isometry, Sphere)> = (0..100)
+            .map(|index| (Isometry::identity(), Sphere::new(1.0 + index as Scalar * 0.01)))
+            .collect();
+        let bvh = bvh(&spheres);
+
+        let (index, _) = first_intersection(&bvh, &spheres, &Ray::new(Point::new(0.0, 0.0, -10.0), Vector::z())).unwrap();
+
+        assert_eq!(index, 99);
+    }
+
+    #[test]
+    fn axis_parallel_rays_on_boundaries() {
+        let spheres = vec![
+            (Isometry::translation(0.0, 0.0, 0.0), Sphere::new(1.0)),
+            (Isometry::translation(2.0, 0.0, 0.0), Sphere::new(1.0)),
+        ];
+        let bvh = bvh(&spheres);
+
+        // Along the boundary of the boxes of both spheres, touching both.
+        let ray = Ray::new(Point::new(1.0, 0.0, -10.0), Vector::z());
+
+        assert!(first_intersection(&bvh, &spheres, &ray).is_some());
+    }
+
+    /// Compares the hierarchy with ncollide's collision world, which the scene used to use. Run with
+    /// `cargo test --release benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark() {
+        use ncollide3d::pipeline::{CollisionGroups, GeometricQueryType};
+        use ncollide3d::shape::ShapeHandle;
+        use ncollide3d::world::CollisionWorld;
+        use std::time::Instant;
+
+        let rays = random_rays(100_000);
+        for &count in &[100, 1_000, 10_000, 100_000] {
+            let spheres = random_spheres(count);
+
+            let start = Instant::now();
+            let mut world = CollisionWorld::<Scalar, ()>::new(0.01);
+            let groups = CollisionGroups::new();
+            for (m, sphere) in &spheres {
+                world.add(*m, ShapeHandle::new(*sphere), groups, GeometricQueryType::Contacts(0.0, 0.0), ());
+            }
+            world.update();
+            let world_build = start.elapsed();
+
+            let start = Instant::now();
+            let world_hits = rays
+                .iter()
+                .filter(|ray| world.first_interference_with_ray(ray, Scalar::MAX, &groups).is_some())
+                .count();
+            let world_trace = start.elapsed();
+
+            let start = Instant::now();
+            let bvh = bvh(&spheres);
+            let bvh_build = start.elapsed();
+
+            let start = Instant::now();
+            let bvh_hits = rays.iter().filter(|ray| first_intersection(&bvh, &spheres, ray).is_some()).count();
+            let bvh_trace = start.elapsed();
+
+            assert_eq!(world_hits, bvh_hits);
+            println!(
+                "{:>7} spheres: collision world built in {:>10.3?}, traced in {:>10.3?}; BVH built in {:>10.3?}, traced in {:>10.3?}",
+                count, world_build, world_trace, bvh_build, bvh_trace
+            );
+        }
+    }
+}
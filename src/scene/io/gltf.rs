@@ -0,0 +1,544 @@
+use crate::image::{Colour, WorkingColourSpace};
+use crate::maths::{consts, Point, Scalar, TransformationMatrix, Vector};
+use crate::scene::{Attenuation, Camera, Light, Material, Object, RefractiveIndex, Scene, Texture};
+use crate::shapes::{Mesh, Shading, Subdivision};
+use ::gltf::buffer::Data;
+use ::gltf::camera::Projection;
+use ::gltf::khr_lights_punctual::{self, Kind};
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::{Mode, Primitive};
+use ::gltf::{Gltf, Node};
+use nalgebra::Matrix3;
+use ncollide3d::bounding_volume::{BoundingVolume, AABB};
+use snafu::Snafu;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Roughness is clamped to at least this before converting it to a shininess, which is infinite for perfectly smooth
+// surfaces.
+const MINIMUM_ROUGHNESS: Scalar = 0.1;
+
+// Directional lights are placed this many times the radius of the scene away from its centre, far enough that their
+// rays are close to parallel without losing precision in shadow rays.
+const DIRECTIONAL_LIGHT_DISTANCE: Scalar = 100.0;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read {:?}; {}.", path, reason))]
+    CannotRead { path: PathBuf, reason: String },
+
+    #[snafu(display("Invalid glTF file {:?}; {}.", path, reason))]
+    Invalid { path: PathBuf, reason: String },
+
+    #[snafu(display("No scene to load from {:?}.", path))]
+    NoScene { path: PathBuf },
+
+    #[snafu(display("Unable to subdivide {:?}; {}.", path, reason))]
+    CannotSubdivide { path: PathBuf, reason: String },
+
+    #[snafu(display("No faces to load from {:?}.", path))]
+    NoFaces { path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The contents of a glTF scene, in the scene's coordinates.
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+    pub lights: Vec<Light>,
+    /// The first perspective camera in the hierarchy, if any.
+    pub camera: Option<Camera>,
+}
+
+/// A mesh with a texture giving each face its material.
+pub struct GltfMesh {
+    pub mesh: Mesh,
+    pub texture: Texture,
+}
+
+/// Loads a glTF file as a whole scene, with an object for each node's mesh. Files without a camera are viewed from the
+/// front, along the negative z axis, and files without lights are lit from the camera.
+pub fn load(path: &Path) -> Result<Scene, Box<dyn std::error::Error>> {
+    let mut scene = Scene::new();
+    let model = load_model(path, Shading::Smooth { crease_angle: consts::PI }, None, scene.colour_space, false)?;
+
+    let meshes = model.meshes;
+    scene.camera = model.camera.unwrap_or_else(|| framing_camera(&bounds(&meshes)));
+    if model.lights.is_empty() {
+        scene.add_light(Light::point(scene.camera.position, Colour::new(1.0, 1.0, 1.0), Attenuation::None));
+    }
+    scene.add_lights(model.lights);
+    scene.add_objects(meshes.into_iter().map(|mesh| Object::new(mesh.mesh, Point::origin(), mesh.texture)));
+
+    return Ok(scene);
+}
+
+/// Loads the default scene of a glTF or GLB file, or its first scene if it has no default. Buffers are relative to the
+/// file. Each node's mesh is loaded separately, or all of them joined into one if `join` is set. Only triangles are
+/// loaded; points and lines have no surface to render. Crease edges of a subdivision are pairs of indices of vertices
+/// of the primitives, one after another, in the order they are loaded; the file's normals are then not used.
+pub fn load_model(path: &Path, shading: Shading, subdivision: Option<&Subdivision>, colour_space: WorkingColourSpace, join: bool) -> Result<GltfModel> {
+    let cannot_read = |error: ::gltf::Error| Error::CannotRead {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    };
+    let Gltf { document, blob } = Gltf::open(path).map_err(cannot_read)?;
+    let buffers = ::gltf::import_buffers(&document, path.parent(), blob).map_err(cannot_read)?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| Error::NoScene { path: path.to_path_buf() })?;
+
+    let mut builder = Builder {
+        buffers: &buffers,
+        colour_space,
+        join,
+        parts: vec![],
+        lights: vec![],
+        camera: None,
+    };
+    for node in scene.nodes() {
+        builder.add_node(&node, &TransformationMatrix::identity()).map_err(|reason| Error::Invalid {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+    }
+
+    let meshes: Vec<GltfMesh> = builder
+        .parts
+        .into_iter()
+        .filter(|part| !part.indices.is_empty())
+        .map(|part| part.into_mesh(shading, subdivision))
+        .collect::<Result<_, _>>()
+        .map_err(|reason| Error::CannotSubdivide {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+    if meshes.is_empty() {
+        return Err(Error::NoFaces { path: path.to_path_buf() });
+    }
+
+    let bounds = bounds(&meshes);
+    let lights = builder
+        .lights
+        .iter()
+        .map(|(light, transformation)| to_light(light, transformation, &bounds, colour_space))
+        .collect();
+
+    return Ok(GltfModel {
+        meshes,
+        lights,
+        camera: builder.camera,
+    });
+}
+
+struct Builder<'a> {
+    buffers: &'a [Data],
+    colour_space: WorkingColourSpace,
+    join: bool,
+    parts: Vec<Part>,
+    lights: Vec<(khr_lights_punctual::Light<'a>, TransformationMatrix)>,
+    camera: Option<Camera>,
+}
+
+impl<'a> Builder<'a> {
+    fn add_node(&mut self, node: &Node<'a>, parent: &TransformationMatrix) -> Result<(), String> {
+        let local = node.transform().matrix();
+        let transformation = parent * TransformationMatrix::from_fn(|row, column| local[column][row] as Scalar);
+
+        if let Some(mesh) = node.mesh() {
+            if !self.join || self.parts.is_empty() {
+                self.parts.push(Part::default());
+            }
+            let part = self.parts.last_mut().unwrap();
+            for primitive in mesh.primitives() {
+                part.add_primitive(&primitive, self.buffers, &transformation, self.colour_space)?;
+            }
+        }
+
+        if let Some(light) = node.light() {
+            self.lights.push((light, transformation));
+        }
+
+        if let (None, Some(camera)) = (&self.camera, node.camera()) {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                // Cameras look down their negative z axis.
+                let position = transformation.transform_point(&Point::origin());
+                let forward = transformation.transform_vector(&-Vector::z());
+                let up = transformation.transform_vector(&Vector::y());
+                let field_of_view = (perspective.yfov() as Scalar).to_degrees();
+                self.camera = Some(Camera::new(position, &(position + forward), &up, field_of_view));
+            }
+        }
+
+        for child in node.children() {
+            self.add_node(&child, &transformation)?;
+        }
+        return Ok(());
+    }
+}
+
+/// The triangles of one or more meshes, on their way to becoming a single `Mesh`.
+#[derive(Default)]
+struct Part {
+    positions: Vec<Point>,
+    normals: Vec<Vector>,
+    indices: Vec<[usize; 3]>,
+    materials: Vec<Material>,
+    // The index into `materials` of each glTF material used so far, with `None` for the default material.
+    material_indices: HashMap<Option<usize>, usize>,
+    // The index into `materials` of each face.
+    faces: Vec<usize>,
+}
+
+impl Part {
+    fn add_primitive(
+        &mut self,
+        primitive: &Primitive,
+        buffers: &[Data],
+        transformation: &TransformationMatrix,
+        colour_space: WorkingColourSpace,
+    ) -> Result<(), String> {
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions: Vec<Point> = match reader.read_positions() {
+            Some(positions) => positions
+                .map(|[x, y, z]| transformation.transform_point(&Point::new(x as Scalar, y as Scalar, z as Scalar)))
+                .collect(),
+            None => return Ok(()),
+        };
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if let Some(index) = indices.iter().find(|&&index| index >= positions.len()) {
+            return Err(format!("index {} is out of range", index));
+        }
+
+        let triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect(),
+            // Every other triangle of a strip is wound the other way.
+            Mode::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(index, triangle)| match index % 2 {
+                    0 => [triangle[0], triangle[1], triangle[2]],
+                    _ => [triangle[1], triangle[0], triangle[2]],
+                })
+                .collect(),
+            Mode::TriangleFan => indices.windows(2).skip(1).map(|pair| [indices[0], pair[0], pair[1]]).collect(),
+            _ => return Ok(()),
+        };
+        // Strips often join pieces with degenerate triangles.
+        let triangles = triangles.into_iter().filter(|[a, b, c]| a != b && b != c && a != c);
+
+        let material = self.material_index(&primitive.material(), colour_space);
+        match reader.read_normals() {
+            Some(normals) => {
+                // Normals are transformed by the inverse transpose, so they stay perpendicular to scaled surfaces.
+                let linear = Matrix3::from_fn(|row, column| transformation[(row, column)]);
+                let normal_matrix = linear.try_inverse().unwrap_or(linear).transpose();
+
+                let offset = self.positions.len();
+                self.positions.extend(positions);
+                self.normals
+                    .extend(normals.map(|[x, y, z]| normal_matrix * Vector::new(x as Scalar, y as Scalar, z as Scalar)));
+                for triangle in triangles {
+                    self.indices.push(triangle.map(|index| offset + index));
+                    self.faces.push(material);
+                }
+            }
+            None => {
+                // Without normals the faces must be flat, so each gets its own vertices.
+                for triangle in triangles {
+                    let [a, b, c] = triangle.map(|index| positions[index]);
+                    let normal = (b - a).cross(&(c - a));
+                    let offset = self.positions.len();
+                    self.positions.extend([a, b, c]);
+                    self.normals.extend([normal; 3]);
+                    self.indices.push([offset, offset + 1, offset + 2]);
+                    self.faces.push(material);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn material_index(&mut self, material: &::gltf::Material, colour_space: WorkingColourSpace) -> usize {
+        let materials = &mut self.materials;
+        return *self.material_indices.entry(material.index()).or_insert_with(|| {
+            materials.push(to_material(material, colour_space));
+            materials.len() - 1
+        });
+    }
+
+    fn into_mesh(self, shading: Shading, subdivision: Option<&Subdivision>) -> Result<GltfMesh, String> {
+        if let Some(subdivision) = subdivision {
+            let (mesh, origins) = subdivision.to_mesh(&self.positions, &self.indices, shading)?;
+            let faces = origins.iter().map(|&origin| self.faces[origin]).collect();
+            return Ok(GltfMesh {
+                mesh,
+                texture: Texture::PerFace {
+                    materials: self.materials,
+                    faces,
+                },
+            });
+        }
+
+        return Ok(GltfMesh {
+            mesh: Mesh::new(self.positions, self.indices, Some(self.normals), None, shading),
+            texture: Texture::PerFace {
+                materials: self.materials,
+                faces: self.faces,
+            },
+        });
+    }
+}
+
+/// Approximates a metallic-roughness material. Smooth metals become mirrors in their base colour and rough ones
+/// diffuse; smooth dielectrics get white highlights. Transmission, or transparency when alpha blended, lets light
+/// through. Textures are not supported, so only the factors are used.
+fn to_material(material: &::gltf::Material, colour_space: WorkingColourSpace) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let [red, green, blue, alpha] = pbr.base_color_factor();
+    let base = colour_space.from_linear_srgb(&Colour::new(red as Scalar, green as Scalar, blue as Scalar));
+    let metallic = pbr.metallic_factor() as Scalar;
+    let roughness = pbr.roughness_factor() as Scalar;
+    let transmission = material.transmission().map_or(0.0, |transmission| transmission.transmission_factor() as Scalar);
+
+    let (transparency, refractive_index) = match (transmission > 0.0, material.alpha_mode()) {
+        (true, _) => (
+            transmission * (1.0 - metallic),
+            RefractiveIndex::Constant(material.ior().unwrap_or(1.5) as Scalar),
+        ),
+        (false, AlphaMode::Blend) => (1.0 - alpha as Scalar, RefractiveIndex::default()),
+        (false, _) => (0.0, RefractiveIndex::default()),
+    };
+
+    let reflectivity = metallic * (1.0 - roughness);
+    let diffuse = base * ((1.0 - reflectivity) * (1.0 - transparency));
+    let specular = (Colour::new(1.0, 1.0, 1.0) * (1.0 - metallic) + base * metallic) * (1.0 - roughness);
+    // The Phong exponent with the same highlight width as a microfacet distribution of the roughness.
+    let width = roughness.max(MINIMUM_ROUGHNESS).powi(2);
+
+    return Material {
+        ambient_colour: diffuse,
+        diffuse_colour: diffuse,
+        specular_colour: specular,
+        shininess: 2.0 / (width * width) - 2.0,
+        reflectivity,
+        transparency,
+        refractive_index,
+    };
+}
+
+/// Point lights fall off with the inverse square of distance, with the intensity in candela as the multiplier. Spot
+/// lights are treated as point lights, and directional lights as point lights far away.
+fn to_light(light: &khr_lights_punctual::Light, transformation: &TransformationMatrix, bounds: &AABB<Scalar>, colour_space: WorkingColourSpace) -> Light {
+    let [red, green, blue] = light.color();
+    let colour = colour_space.from_linear_srgb(&Colour::new(red as Scalar, green as Scalar, blue as Scalar));
+    let intensity = light.intensity() as Scalar;
+
+    return match light.kind() {
+        Kind::Directional => {
+            let direction = transformation.transform_vector(&-Vector::z()).normalize();
+            let radius = (bounds.maxs - bounds.mins).norm() / 2.0;
+            let position = bounds.center() - direction * radius * DIRECTIONAL_LIGHT_DISTANCE;
+            Light::point(position, colour * intensity, Attenuation::None)
+        }
+        Kind::Point | Kind::Spot { .. } => Light::point(
+            transformation.transform_point(&Point::origin()),
+            colour,
+            Attenuation::InverseSquared { multiplier: intensity },
+        ),
+    };
+}
+
+fn bounds(meshes: &[GltfMesh]) -> AABB<Scalar> {
+    return meshes
+        .iter()
+        .map(|mesh| *mesh.mesh.triangles().aabb())
+        .reduce(|bounds, other| bounds.merged(&other))
+        .unwrap();
+}
+
+/// A camera looking along the negative z axis at the whole of the bounds.
+fn framing_camera(bounds: &AABB<Scalar>) -> Camera {
+    let field_of_view: Scalar = 30.0;
+    let centre = bounds.center();
+    let radius = (bounds.maxs - bounds.mins).norm() / 2.0;
+    let distance = radius / (field_of_view / 2.0).to_radians().sin();
+
+    return Camera::new(centre + Vector::z() * distance, &centre, &Vector::y(), field_of_view);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::{Isometry, Ray};
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+    use std::borrow::Cow;
+    use std::fs;
+
+    // A unit square facing the z axis, made twice the size by a child node of one moved to z = -5, and another without
+    // normals drawn as a fan at x = 3. There is a camera, a point light and a directional light.
+    const GLTF: &str = r#"{
+        "asset": {"version": "2.0"},
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {"KHR_lights_punctual": {"lights": [
+            {"type": "point", "color": [1, 1, 1], "intensity": 8},
+            {"type": "directional", "intensity": 2}
+        ]}},
+        "scene": 0,
+        "scenes": [{"nodes": [0, 2, 3, 4, 5]}],
+        "nodes": [
+            {"translation": [0, 0, -5], "children": [1]},
+            {"scale": [2, 2, 2], "mesh": 0},
+            {"translation": [0, 1, 3], "camera": 0},
+            {"translation": [0, 4, 0], "extensions": {"KHR_lights_punctual": {"light": 0}}},
+            {"extensions": {"KHR_lights_punctual": {"light": 1}}},
+            {"translation": [3, 0, -5], "mesh": 1}
+        ],
+        "cameras": [{"type": "perspective", "perspective": {"yfov": 0.5, "znear": 0.1}}],
+        "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0.5, 0, 1], "metallicFactor": 1, "roughnessFactor": 0}}],
+        "meshes": [
+            {"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1}, "indices": 2, "material": 0}]},
+            {"primitives": [{"attributes": {"POSITION": 0}, "mode": 6}]}
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [-0.5, -0.5, 0], "max": [0.5, 0.5, 0]},
+            {"bufferView": 1, "componentType": 5126, "count": 4, "type": "VEC3"},
+            {"bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR"}
+        ],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 48, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 96, "byteLength": 12}
+        ],
+        "buffers": [{BUFFER "byteLength": 108}]
+    }"#;
+
+    fn buffer(indices: [u16; 6]) -> Vec<u8> {
+        let positions: [f32; 12] = [-0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.5, 0.5, 0.0, -0.5, 0.5, 0.0];
+        let normals: [f32; 12] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+
+        let mut bytes = vec![];
+        for value in positions.iter().chain(normals.iter()) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in indices {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    fn write_gltf(name: &str, indices: [u16; 6]) -> PathBuf {
+        let directory = std::env::temp_dir();
+        let name = format!("rust-rendering-gltf-{}-{}", name, std::process::id());
+        fs::write(directory.join(format!("{}.bin", name)), buffer(indices)).unwrap();
+
+        let path = directory.join(format!("{}.gltf", name));
+        fs::write(&path, GLTF.replace("BUFFER", &format!(r#""uri": "{}.bin","#, name))).unwrap();
+        return path;
+    }
+
+    fn write_glb(name: &str) -> PathBuf {
+        let mut json = GLTF.replace("BUFFER", "").into_bytes();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut binary = buffer([0, 1, 2, 0, 2, 3]);
+        binary.resize(binary.len().div_ceil(4) * 4, 0);
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        for (chunk, kind) in [(json, b"JSON"), (binary, b"BIN\0")] {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(&chunk);
+        }
+
+        let path = std::env::temp_dir().join(format!("rust-rendering-gltf-{}-{}.glb", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        return path;
+    }
+
+    fn load_square(path: &Path, join: bool) -> GltfModel {
+        return load_model(path, Shading::Smooth { crease_angle: consts::PI }, None, WorkingColourSpace::LinearSrgb, join).unwrap();
+    }
+
+    fn cast(mesh: &GltfMesh, origin: Point) -> (Scalar, Vector, Cow<'_, Material>) {
+        let ray = Ray::new(origin, -Vector::z());
+        let intersection = mesh.mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        let point = ray.point_at(intersection.toi);
+        let material = mesh.texture.material_at(&point, &point, intersection.feature);
+        return (intersection.toi, intersection.normal, material);
+    }
+
+    #[test]
+    fn hierarchy() {
+        let model = load_square(&write_gltf("hierarchy", [0, 1, 2, 0, 2, 3]), false);
+
+        assert_eq!(model.meshes.len(), 2);
+
+        // Scaled to twice the size by the child node, so the corner is covered.
+        let (toi, normal, material) = cast(&model.meshes[0], Point::new(0.9, 0.9, 0.0));
+        assert_abs_diff_eq!(toi, 5.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(normal, Vector::z(), epsilon = 0.0001);
+        // A smooth metal is a mirror in its base colour.
+        assert_abs_diff_eq!(material.reflectivity, 1.0);
+        assert_abs_diff_eq!(material.specular_colour.g, 0.5);
+        assert_abs_diff_eq!(material.diffuse_colour.r, 0.0);
+
+        // The fan without normals is flat, with the default material, which is rough so diffuse.
+        let (toi, normal, material) = cast(&model.meshes[1], Point::new(3.4, -0.4, 0.0));
+        assert_abs_diff_eq!(toi, 5.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(normal, Vector::z(), epsilon = 0.0001);
+        assert_abs_diff_eq!(material.reflectivity, 0.0);
+        assert_abs_diff_eq!(material.diffuse_colour.r, 1.0);
+        assert_abs_diff_eq!(material.shininess, 0.0);
+    }
+
+    #[test]
+    fn lights_and_camera() {
+        let model = load_square(&write_gltf("lights", [0, 1, 2, 0, 2, 3]), false);
+
+        assert_abs_diff_eq!(model.camera.unwrap().position, Point::new(0.0, 1.0, 3.0), epsilon = 0.0001);
+        assert_eq!(model.lights.len(), 2);
+        let point = &model.lights[0];
+        assert_abs_diff_eq!(point.position, Point::new(0.0, 4.0, 0.0), epsilon = 0.0001);
+        assert_abs_diff_eq!(point.attenuation.get_intensity(2.0), 2.0, epsilon = 0.0001);
+        // Directional lights shine down the negative z axis unless rotated, so are placed far along the positive one.
+        let directional = &model.lights[1];
+        assert!(directional.position.z > 100.0);
+        assert_abs_diff_eq!(directional.colour.r, 2.0);
+    }
+
+    #[test]
+    fn binary_joined() {
+        let model = load_square(&write_glb("binary"), true);
+
+        assert_eq!(model.meshes.len(), 1);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.mesh.triangles().faces().len(), 4);
+        assert_abs_diff_eq!(cast(mesh, Point::new(-0.9, 0.9, 0.0)).2.reflectivity, 1.0);
+        assert_abs_diff_eq!(cast(mesh, Point::new(3.4, -0.4, 0.0)).2.reflectivity, 0.0);
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let path = write_gltf("range", [0, 1, 2, 0, 2, 9]);
+
+        let error = load_model(&path, Shading::Flat, None, WorkingColourSpace::LinearSrgb, false).err().unwrap();
+
+        assert!(matches!(error, Error::Invalid { reason, .. } if reason == "index 9 is out of range"));
+    }
+
+    #[test]
+    fn whole_scene() {
+        let scene = crate::scene::io::load(&write_gltf("scene", [0, 1, 2, 0, 2, 3])).unwrap();
+
+        assert_abs_diff_eq!(scene.camera.position, Point::new(0.0, 1.0, 3.0), epsilon = 0.0001);
+        assert_eq!(scene.lights.len(), 2);
+    }
+}
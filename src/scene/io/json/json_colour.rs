@@ -1,12 +1,187 @@
-use crate::image::Colour;
-use crate::scene::io::json::JsonScalar;
+use crate::image::{blackbody, Colour, Rgb};
+use crate::scene::io::json::{JsonContext, JsonScalar};
 use serde::Deserialize;
+use snafu::Snafu;
+use std::convert::TryFrom;
 
+/// A colour in a scene file. Can be given as linear `[r, g, b]` components in the scene's working colour space, or in
+/// a notation defined in terms of sRGB: a `"#rrggbb"` hex string, a CSS colour name, or an object with one of `hsv`,
+/// `hsl`, `srgb` or `blackbody`.
 #[derive(Deserialize, Copy, Clone)]
-pub struct JsonColour(JsonScalar, JsonScalar, JsonScalar);
+#[serde(try_from = "JsonColourSpecification")]
+pub struct JsonColour {
+    colour: Colour,
+    in_working_space: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonColourSpecification {
+    Linear(JsonScalar, JsonScalar, JsonScalar),
+    Text(String),
+    Object(JsonColourObject),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonColourObject {
+    Hsv(JsonScalar, JsonScalar, JsonScalar),
+    Hsl(JsonScalar, JsonScalar, JsonScalar),
+    Srgb(JsonScalar, JsonScalar, JsonScalar),
+    Blackbody {
+        kelvin: JsonScalar,
+        #[serde(default = "default_intensity")]
+        intensity: JsonScalar,
+    },
+}
+
+fn default_intensity() -> JsonScalar {
+    return 1.0;
+}
+
+#[derive(Debug, Snafu)]
+pub enum ColourError {
+    #[snafu(display("Invalid hex colour {:?}; expected #rrggbb or #rgb.", text))]
+    InvalidHexColour { text: String },
+
+    #[snafu(display("Unknown colour name {:?}.", name))]
+    UnknownColourName { name: String },
+
+    #[snafu(display("Invalid black body temperature {}; must be positive.", kelvin))]
+    InvalidTemperature { kelvin: JsonScalar },
+}
+
+impl TryFrom<JsonColourSpecification> for JsonColour {
+    type Error = ColourError;
+
+    fn try_from(specification: JsonColourSpecification) -> Result<JsonColour, ColourError> {
+        let colour = match specification {
+            JsonColourSpecification::Linear(r, g, b) => {
+                return Ok(JsonColour {
+                    colour: Colour::new(r, g, b),
+                    in_working_space: true,
+                })
+            }
+            JsonColourSpecification::Text(text) => parse_text(text.trim())?,
+            JsonColourSpecification::Object(object) => match object {
+                JsonColourObject::Hsv(h, s, v) => from_srgb(Colour::from_hsv(h, s, v)),
+                JsonColourObject::Hsl(h, s, l) => from_srgb(Colour::from_hsl(h, s, l)),
+                JsonColourObject::Srgb(r, g, b) => Colour::from_srgb(r, g, b),
+                JsonColourObject::Blackbody { kelvin, intensity } => {
+                    if kelvin <= 0.0 {
+                        return Err(ColourError::InvalidTemperature { kelvin });
+                    }
+                    blackbody::colour(kelvin) * intensity
+                }
+            },
+        };
+
+        return Ok(JsonColour {
+            colour,
+            in_working_space: false,
+        });
+    }
+}
+
+fn parse_text(text: &str) -> Result<Colour, ColourError> {
+    if text.starts_with('#') {
+        return match Rgb::parse_hex(text) {
+            Some(rgb) => Ok(from_srgb(rgb.to_colour())),
+            None => Err(ColourError::InvalidHexColour { text: text.to_string() }),
+        };
+    }
+
+    return match Rgb::from_name(text) {
+        Some(rgb) => Ok(from_srgb(rgb.to_colour())),
+        None => Err(ColourError::UnknownColourName { name: text.to_string() }),
+    };
+}
+
+// Hex, named, HSV and HSL colours are sRGB encoded, like in CSS.
+fn from_srgb(colour: Colour) -> Colour {
+    return Colour::from_srgb(colour.r, colour.g, colour.b);
+}
 
 impl JsonColour {
-    pub fn to_colour(&self) -> Colour {
-        return Colour::new(self.0, self.1, self.2);
+    pub fn to_colour(&self, context: &JsonContext) -> Colour {
+        if self.in_working_space {
+            return self.colour;
+        }
+        return context.colour_space.from_linear_srgb(&self.colour);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Image, Rgb, TransferFunction, WorkingColourSpace};
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        json,
+        expected,
+        case("[0.25, 0.5, 2.0]", Colour::new(0.25, 0.5, 2.0)),
+        case("\"#ff8800\"", Colour::new(1.0, 0.246, 0.0)),
+        case("\"#f80\"", Colour::new(1.0, 0.246, 0.0)),
+        case("\"CornflowerBlue\"", Colour::new(0.127, 0.301, 0.846)),
+        case("{\"hsv\": [120, 1, 0.5]}", Colour::new(0.0, 0.214, 0.0)),
+        case("{\"hsl\": [240, 1, 0.5]}", Colour::new(0.0, 0.0, 1.0)),
+        case("{\"srgb\": [1.0, 0.5, 0.0]}", Colour::new(1.0, 0.214, 0.0)),
+        case("{\"blackbody\": {\"kelvin\": 6504}}", Colour::new(1.0, 0.94, 0.99)),
+        case("{\"blackbody\": {\"kelvin\": 6504, \"intensity\": 2.0}}", Colour::new(2.0, 1.89, 1.99))
+    )]
+    fn deserialize(json: &str, expected: Colour) {
+        let colour = serde_json::from_str::<JsonColour>(json).unwrap().to_colour(&JsonContext::default());
+
+        assert_abs_diff_eq!(colour.r, expected.r, epsilon = 0.01);
+        assert_abs_diff_eq!(colour.g, expected.g, epsilon = 0.01);
+        assert_abs_diff_eq!(colour.b, expected.b, epsilon = 0.01);
+    }
+
+    // Colours given in sRGB come out of the renderer as they went in.
+    #[rstest(
+        json,
+        expected,
+        case("\"#ff8800\"", Rgb::new(0xff, 0x88, 0x00)),
+        case("\"CornflowerBlue\"", Rgb::new(0x64, 0x95, 0xed)),
+        case("{\"srgb\": [0.2, 0.4, 0.6]}", Rgb::new(0x33, 0x66, 0x99))
+    )]
+    fn round_trip(json: &str, expected: Rgb) {
+        let colour = serde_json::from_str::<JsonColour>(json).unwrap().to_colour(&JsonContext::default());
+        let image = Image::from_colour_pixels(1, 1, &mut std::iter::once(colour));
+
+        assert_eq!(image.to_rgb_image(TransferFunction::Srgb).get_pixel(0, 0), &expected);
+    }
+
+    #[rstest(
+        json,
+        expected,
+        case("[1.0, 0.0, 0.0]", Colour::new(1.0, 0.0, 0.0)),
+        case("\"#ff0000\"", Colour::new(0.627, 0.069, 0.016))
+    )]
+    fn deserialize_in_rec2020(json: &str, expected: Colour) {
+        let context = JsonContext {
+            colour_space: WorkingColourSpace::Rec2020,
+            ..JsonContext::default()
+        };
+        let colour = serde_json::from_str::<JsonColour>(json).unwrap().to_colour(&context);
+
+        assert_abs_diff_eq!(colour.r, expected.r, epsilon = 0.001);
+        assert_abs_diff_eq!(colour.g, expected.g, epsilon = 0.001);
+        assert_abs_diff_eq!(colour.b, expected.b, epsilon = 0.001);
+    }
+
+    #[rstest(
+        json,
+        expected_error,
+        case("\"#ff880\"", "Invalid hex colour"),
+        case("\"notacolour\"", "Unknown colour name"),
+        case("{\"blackbody\": {\"kelvin\": -5}}", "Invalid black body temperature")
+    )]
+    fn deserialize_invalid(json: &str, expected_error: &str) {
+        let error = serde_json::from_str::<JsonColour>(json).err().unwrap();
+
+        assert!(error.to_string().contains(expected_error), "{}", error);
     }
 }
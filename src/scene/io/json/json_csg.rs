@@ -0,0 +1,102 @@
+use crate::maths::Point;
+use crate::scene::io::json::{to_transformation, JsonPoint, JsonRotation, JsonScale, JsonShape, ObjectError};
+use crate::shapes::{Csg, CsgChild, CsgOperation};
+use serde::Deserialize;
+
+#[derive(Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonCsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// A shape within a CSG shape, positioned relative to it. Its texture comes from the object the CSG shape belongs to.
+#[derive(Deserialize)]
+pub struct JsonCsgChild {
+    pub position: Option<JsonPoint>,
+
+    pub rotation: Option<JsonRotation>,
+
+    pub scale: Option<JsonScale>,
+
+    #[serde(flatten)]
+    pub shape: JsonShape,
+}
+
+impl JsonCsgOperation {
+    pub fn to_operation(self) -> CsgOperation {
+        return match self {
+            JsonCsgOperation::Union => CsgOperation::Union,
+            JsonCsgOperation::Intersection => CsgOperation::Intersection,
+            JsonCsgOperation::Difference => CsgOperation::Difference,
+        };
+    }
+}
+
+impl JsonCsgChild {
+    pub fn to_csg_child(&self) -> Result<CsgChild, ObjectError> {
+        let position = self.position.as_ref().map_or(Point::origin(), |position| position.to_point());
+        let transformation = to_transformation(position, self.rotation.as_ref(), self.scale.as_ref())?;
+
+        return Ok(CsgChild::new(self.shape.to_solid()?, transformation));
+    }
+}
+
+pub fn to_csg(operation: JsonCsgOperation, left: &JsonCsgChild, right: &JsonCsgChild) -> Result<Csg, ObjectError> {
+    return Ok(Csg::new(operation.to_operation(), left.to_csg_child()?, right.to_csg_child()?));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maths::{Isometry, Point, Ray, Scalar, Vector};
+    use crate::scene::io::json::{JsonContext, JsonObject};
+    use approx::assert_abs_diff_eq;
+
+    const LENS: &str = r#"{
+        "position": [0, 0, 0],
+        "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+        "csg": {
+            "op": "difference",
+            "left": {
+                "csg": {
+                    "op": "intersection",
+                    "left": {"sphere": {"radius": 2}, "position": [-1.5, 0, 0]},
+                    "right": {"sphere": {"radius": 2}, "position": [1.5, 0, 0]}
+                }
+            },
+            "right": {"cylinder": {"height": 2, "radius": 0.1}, "rotation": {"euler_degrees": [0, 0, 90]}}
+        }
+    }"#;
+
+    #[test]
+    fn nested() {
+        let object = serde_json::from_str::<JsonObject>(LENS).unwrap().to_object(&JsonContext::default()).unwrap();
+        let shape = object.shape.as_ray_cast().unwrap();
+
+        // Along the axis of the hole drilled through the lens.
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::x());
+        assert!(shape.toi_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).is_none());
+
+        let ray = Ray::new(Point::new(-5.0, 0.2, 0.0), Vector::x());
+        let toi = shape.toi_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_abs_diff_eq!(toi, 5.0 - Scalar::sqrt(4.0 - 0.04) + 1.5, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn open_shapes_are_not_solid() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "csg": {"op": "union", "left": {"sphere": {"radius": 1}}, "right": {"plane": {"normal": [0, 1, 0]}}}
+        }"#;
+
+        let error = serde_json::from_str::<JsonObject>(json)
+            .unwrap()
+            .to_object(&JsonContext::default())
+            .err()
+            .unwrap();
+
+        assert_eq!(error.to_string(), "Only closed shapes can be combined with CSG, not planes.");
+    }
+}
@@ -0,0 +1,117 @@
+use crate::maths::noise::Noise;
+use crate::scene::io::json::{read_heights, JsonContext, JsonScalar, ObjectError};
+use crate::shapes::{Displacement, HeightMap};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Moves the surface of a mesh along its normals, after splitting it into triangles with edges no longer than
+/// `edge_length`.
+#[derive(Deserialize)]
+pub struct JsonDisplacement {
+    #[serde(flatten)]
+    pub heights: JsonHeightMap,
+
+    /// The distance a height of one moves the surface; negative to move it inwards.
+    pub scale: JsonScalar,
+
+    pub edge_length: JsonScalar,
+
+    /// The size of one repeat of an image, or about one bump of noise, in texture coordinates or, for meshes without
+    /// them, the mesh's own units; defaults to 1.
+    pub size: Option<JsonScalar>,
+}
+
+/// Heights between zero and one.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonHeightMap {
+    /// A greyscale image relative to the scene file, from black for the lowest to white for the highest.
+    Image(PathBuf),
+    Noise(JsonNoiseHeights),
+}
+
+/// Fractal noise.
+#[derive(Deserialize)]
+pub struct JsonNoiseHeights {
+    /// The number of layers of finer and finer detail; defaults to 6.
+    pub octaves: Option<usize>,
+
+    /// How much smaller each layer of detail is than the last; defaults to 0.5.
+    pub persistence: Option<JsonScalar>,
+
+    /// Defaults to 0.
+    pub seed: Option<u64>,
+}
+
+impl JsonDisplacement {
+    pub fn to_displacement(&self, context: &JsonContext) -> Result<Displacement, ObjectError> {
+        let size = self.size.unwrap_or(1.0);
+        if self.edge_length <= 0.0 || size <= 0.0 {
+            return Err(ObjectError::InvalidDisplacement);
+        }
+
+        let heights = match &self.heights {
+            JsonHeightMap::Image(path) => {
+                let (heights, width) = read_heights(&context.directory.join(path))?;
+                HeightMap::Image { heights, width }
+            }
+            JsonHeightMap::Noise(noise) => HeightMap::Noise {
+                noise: Noise::new(noise.seed.unwrap_or(0)),
+                octaves: noise.octaves.unwrap_or(6),
+                persistence: noise.persistence.unwrap_or(0.5),
+            },
+        };
+        return Ok(Displacement::new(heights, self.scale, self.edge_length, size));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn load(json: &str, context: &JsonContext) -> Result<Displacement, ObjectError> {
+        return serde_json::from_str::<JsonDisplacement>(json).unwrap().to_displacement(context);
+    }
+
+    #[test]
+    fn noise() {
+        let displacement = load("{\"noise\": {\"seed\": 2}, \"scale\": 0.1, \"edge_length\": 0.05}", &JsonContext::default()).unwrap();
+
+        assert_eq!(displacement.scale, 0.1);
+        assert_eq!(displacement.size, 1.0);
+        match displacement.heights {
+            HeightMap::Noise { octaves, persistence, .. } => assert_eq!((octaves, persistence), (6, 0.5)),
+            HeightMap::Image { .. } => panic!("Expected noise."),
+        }
+    }
+
+    #[test]
+    fn image() {
+        let directory = std::env::temp_dir().join("rust-rendering-displacement-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        GrayImage::from_fn(2, 1, |x, _| Luma([255 * (x as u8)]))
+            .save(directory.join("heights.png"))
+            .unwrap();
+        let context = JsonContext {
+            directory,
+            ..JsonContext::default()
+        };
+
+        let displacement = load("{\"image\": \"heights.png\", \"scale\": 1, \"edge_length\": 1, \"size\": 2}", &context).unwrap();
+
+        match displacement.heights {
+            HeightMap::Image { heights, width } => assert_eq!((heights, width), (vec![0.0, 1.0], 2)),
+            HeightMap::Noise { .. } => panic!("Expected an image."),
+        }
+    }
+
+    #[test]
+    fn zero_edge_length() {
+        let error = load("{\"noise\": {}, \"scale\": 0.1, \"edge_length\": 0}", &JsonContext::default())
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ObjectError::InvalidDisplacement));
+    }
+}
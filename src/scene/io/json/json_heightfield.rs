@@ -0,0 +1,204 @@
+use crate::maths::noise::Noise;
+use crate::maths::{Coordinates, Scalar, Vector};
+use crate::scene::io::json::{JsonContext, JsonScalar, ObjectError};
+use crate::shapes::Heightfield;
+use image::{ColorType, GenericImageView, ImageBuffer, Luma, Primitive};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Where the heights of a heightfield come from. Either way they are between zero and one, scaled by the height of the
+/// heightfield's size.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonHeights {
+    /// A greyscale image relative to the scene file, from black for the lowest to white for the highest. Colour images
+    /// are converted to greyscale. The top of the image is towards the positive z axis.
+    Image(PathBuf),
+    Noise(JsonNoise),
+}
+
+/// Fractal noise on a square grid of `resolution` by `resolution` heights.
+#[derive(Deserialize)]
+pub struct JsonNoise {
+    pub resolution: usize,
+
+    /// The number of hills across the heightfield, roughly; defaults to 4.
+    pub frequency: Option<JsonScalar>,
+
+    /// The number of layers of finer and finer detail; defaults to 6.
+    pub octaves: Option<usize>,
+
+    /// How much smaller each layer of detail is than the last; defaults to 0.5.
+    pub persistence: Option<JsonScalar>,
+
+    /// Defaults to 0.
+    pub seed: Option<u64>,
+}
+
+impl JsonHeights {
+    pub fn to_heightfield(&self, size: Vector, context: &JsonContext) -> Result<Heightfield, ObjectError> {
+        return match self {
+            JsonHeights::Image(path) => load_image(&context.directory.join(path), size),
+            JsonHeights::Noise(noise) => noise.to_heightfield(size),
+        };
+    }
+}
+
+impl JsonNoise {
+    pub fn to_heightfield(&self, size: Vector) -> Result<Heightfield, ObjectError> {
+        if self.resolution < 2 {
+            return Err(ObjectError::HeightfieldTooSmall);
+        }
+
+        let noise = Noise::new(self.seed.unwrap_or(0));
+        let frequency = self.frequency.unwrap_or(4.0);
+        let (octaves, persistence) = (self.octaves.unwrap_or(6), self.persistence.unwrap_or(0.5));
+
+        return Ok(Heightfield::from_function(self.resolution, self.resolution, size, |x, z| {
+            let height = noise.fractal(&Coordinates::new(x * frequency, z * frequency), octaves, persistence);
+            return (height + 1.0) / 2.0;
+        }));
+    }
+}
+
+fn load_image(path: &PathBuf, size: Vector) -> Result<Heightfield, ObjectError> {
+    let (heights, width) = read_heights(path)?;
+    if width < 2 || heights.len() < 2 * width {
+        return Err(ObjectError::HeightfieldTooSmall);
+    }
+    return Ok(Heightfield::new(heights, width, size));
+}
+
+/// Reads the heights of a greyscale image, from zero for black to one for white, in rows from the bottom of the image
+/// up, with the width of a row.
+pub fn read_heights(path: &PathBuf) -> Result<(Vec<Scalar>, usize), ObjectError> {
+    let image = image::open(path).map_err(|error| ObjectError::CannotReadHeightmap {
+        path: path.clone(),
+        reason: error.to_string(),
+    })?;
+
+    // Rows run along the positive z axis of heightfields, so start from the bottom of the image. Converting eight bit
+    // images to sixteen bits doesn't quite map white to the maximum, so keep them as eight bits.
+    let heights = match image.color() {
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => to_heights(&image.to_luma16(), u16::MAX as Scalar),
+        _ => to_heights(&image.to_luma8(), u8::MAX as Scalar),
+    };
+    return Ok((heights, image.width() as usize));
+}
+
+fn to_heights<T: Primitive + Into<Scalar> + 'static>(image: &ImageBuffer<Luma<T>, Vec<T>>, maximum: Scalar) -> Vec<Scalar> {
+    return image.rows().rev().flat_map(|row| row.map(|pixel| pixel[0].into() / maximum)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maths::{Point, Ray, Scalar, Vector};
+    use crate::scene::io::json::{JsonContext, JsonObject, ObjectError};
+    use crate::scene::Object;
+    use approx::assert_abs_diff_eq;
+    use image::{GrayImage, Luma};
+    use rstest::rstest;
+    use std::error::Error;
+
+    fn load(json: &str, context: &JsonContext) -> Result<Object, Box<dyn Error>> {
+        return serde_json::from_str::<JsonObject>(json).unwrap().to_object(context);
+    }
+
+    fn height_at(object: &Object, x: Scalar, z: Scalar) -> Scalar {
+        let ray = Ray::new(Point::new(x, 100.0, z), -Vector::y());
+        let toi = object
+            .shape
+            .as_ray_cast()
+            .unwrap()
+            .toi_with_ray(&object.transformation.isometry(), &ray, Scalar::MAX, true);
+        return 100.0 - toi.unwrap();
+    }
+
+    #[test]
+    fn image() {
+        let directory = std::env::temp_dir().join("rust-rendering-heightfield-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        // Black along the top row and white along the bottom.
+        GrayImage::from_fn(3, 2, |_, y| Luma([255 * (y as u8)]))
+            .save(directory.join("heights.png"))
+            .unwrap();
+
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "heightfield": {"size": [2, 5, 1], "image": "heights.png"}
+        }"#;
+        let context = JsonContext {
+            directory,
+            ..JsonContext::default()
+        };
+        let object = load(json, &context).unwrap();
+
+        assert_abs_diff_eq!(height_at(&object, 0.0, -0.5), 5.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(height_at(&object, 0.0, 0.5), 0.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn missing_image() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "heightfield": {"size": [2, 5, 1], "image": "missing.png"}
+        }"#;
+
+        let error = load(json, &JsonContext::default()).err().unwrap();
+
+        assert!(error.to_string().starts_with("Unable to read heightmap \"missing.png\""));
+    }
+
+    #[rstest(width, height, case(1, 3), case(3, 1))]
+    fn image_too_small(width: u32, height: u32) {
+        let directory = std::env::temp_dir().join(format!("rust-rendering-heightfield-test-{}x{}", width, height));
+        std::fs::create_dir_all(&directory).unwrap();
+        GrayImage::new(width, height).save(directory.join("heights.png")).unwrap();
+
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "heightfield": {"size": [2, 5, 1], "image": "heights.png"}
+        }"#;
+        let context = JsonContext {
+            directory,
+            ..JsonContext::default()
+        };
+        let error = load(json, &context).err().unwrap();
+
+        assert!(matches!(error.downcast_ref::<ObjectError>(), Some(ObjectError::HeightfieldTooSmall)));
+    }
+
+    #[rstest(resolution, case(0), case(1))]
+    fn noise_too_small(resolution: usize) {
+        let json = format!(
+            r#"{{
+                "position": [0, 0, 0],
+                "texture": {{"solid": {{"ambient_colour": [1, 1, 1]}}}},
+                "heightfield": {{"size": [10, 2, 10], "noise": {{"resolution": {}}}}}
+            }}"#,
+            resolution
+        );
+
+        let error = load(&json, &JsonContext::default()).err().unwrap();
+
+        assert!(matches!(error.downcast_ref::<ObjectError>(), Some(ObjectError::HeightfieldTooSmall)));
+    }
+
+    #[test]
+    fn noise() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "heightfield": {"size": [10, 2, 10], "noise": {"resolution": 65, "seed": 3}}
+        }"#;
+        let object = load(json, &JsonContext::default()).unwrap();
+
+        let heights: Vec<Scalar> = (0..10).map(|step| height_at(&object, step as Scalar - 4.5, 1.0)).collect();
+
+        assert!(heights.iter().all(|height| (0.0..=2.0).contains(height)));
+        assert!(heights.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::scene::io::json::{to_transformation, JsonContext, JsonObject, JsonPoint, JsonRotation, JsonScale, JsonTexture, ObjectError};
+use crate::scene::Object;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+/// An object, or a group of objects, that can be added to the scene many times by instances. Positions are relative
+/// to each instance.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum JsonPrototype {
+    Group(Vec<JsonObject>),
+    Object(Box<JsonObject>),
+}
+
+/// A copy of a prototype. All instances share the prototype's shapes, so even large meshes are only loaded once.
+#[derive(Deserialize)]
+pub struct JsonInstance {
+    pub prototype: String,
+
+    pub position: JsonPoint,
+
+    pub rotation: Option<JsonRotation>,
+
+    /// Non-uniform scales can't be used on prototypes with rotated objects.
+    pub scale: Option<JsonScale>,
+
+    /// Replaces the textures of all the prototype's objects.
+    pub texture: Option<JsonTexture>,
+}
+
+impl JsonPrototype {
+    pub fn to_objects(&self, context: &JsonContext) -> Result<Vec<Object>, Box<dyn Error>> {
+        return match self {
+            JsonPrototype::Group(objects) => objects.iter().map(|object| object.to_object(context)).collect(),
+            JsonPrototype::Object(object) => Ok(vec![object.to_object(context)?]),
+        };
+    }
+}
+
+impl JsonInstance {
+    pub fn to_objects(&self, prototypes: &HashMap<String, Vec<Object>>, context: &JsonContext) -> Result<Vec<Object>, ObjectError> {
+        let prototype = prototypes
+            .get(&self.prototype)
+            .ok_or_else(|| ObjectError::UnknownPrototype { name: self.prototype.clone() })?;
+
+        let transformation = to_transformation(self.position.to_point(), self.rotation.as_ref(), self.scale.as_ref())?;
+        let texture = self.texture.as_ref().map(|texture| Arc::new(texture.to_texture(context)));
+
+        return prototype
+            .iter()
+            .map(|object| {
+                return object
+                    .instance(&transformation, texture.clone())
+                    .ok_or_else(|| ObjectError::SkewedInstance { name: self.prototype.clone() });
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Colour;
+    use crate::maths::{Point, Ray, Vector};
+    use crate::scene::io::json::JsonScene;
+    use crate::scene::Scene;
+    use crate::shapes::Scaled;
+    use approx::assert_abs_diff_eq;
+    use std::path::Path;
+
+    const SCENE: &str = r#"{
+        "lights": [],
+        "objects": [],
+        "prototypes": {
+            "tree": [
+                {"position": [0, 1, 0], "cylinder": {"height": 2, "radius": 0.2}, "texture": {"solid": {"ambient_colour": [0.5, 0.3, 0]}}},
+                {"position": [0, 3, 0], "sphere": {"radius": 1}, "texture": {"solid": {"ambient_colour": [0, 1, 0]}}}
+            ],
+            "ball": {"position": [0, 0, 0], "sphere": {"radius": 0.5}, "texture": {"solid": {"ambient_colour": [1, 1, 1]}}}
+        },
+        "instances": [
+            {"prototype": "tree", "position": [-5, 0, 10]},
+            {"prototype": "tree", "position": [5, 0, 10], "scale": 2, "texture": {"solid": {"ambient_colour": [1, 0, 0]}}},
+            {"prototype": "ball", "position": [0, 0, 20], "rotation": {"euler_degrees": [0, 90, 0]}, "scale": [1, 1, 4]}
+        ]
+    }"#;
+
+    fn load(json: &str) -> Result<Scene, Box<dyn Error>> {
+        return serde_json::from_str::<JsonScene>(json).unwrap().to_scene(Path::new(""));
+    }
+
+    fn colour_along(scene: &Scene, origin: Point) -> Colour {
+        let ray = Ray::new(origin, Vector::z());
+        return scene.first_collision_with_ray(&ray).unwrap().material().ambient_colour;
+    }
+
+    #[test]
+    fn instances() {
+        let scene = load(SCENE).unwrap();
+
+        // The first tree as given, the second twice the size and red.
+        assert_eq!(colour_along(&scene, Point::new(-5.0, 1.0, 0.0)), Colour::new(0.5, 0.3, 0.0));
+        assert_eq!(colour_along(&scene, Point::new(-5.0, 3.0, 0.0)), Colour::new(0.0, 1.0, 0.0));
+        assert_eq!(colour_along(&scene, Point::new(5.0, 7.5, 0.0)), Colour::new(1.0, 0.0, 0.0));
+
+        // The ball is stretched along the z axis then turned to lie along the x axis.
+        let ray = Ray::new(Point::new(-10.0, 0.0, 20.0), Vector::x());
+        let collision = scene.first_collision_with_ray(&ray).unwrap();
+        assert_abs_diff_eq!(collision.intersection.x, -2.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn instances_share_shapes() {
+        let scene = serde_json::from_str::<JsonScene>(SCENE).unwrap();
+        let context = JsonContext::default();
+        let prototypes = HashMap::from([("tree".to_string(), scene.prototypes["tree"].to_objects(&context).unwrap())]);
+
+        let first = scene.instances[0].to_objects(&prototypes, &context).unwrap();
+        let second = scene.instances[1].to_objects(&prototypes, &context).unwrap();
+
+        // The second is scaled, so its shapes are wrapped in a scaling shape around the same shapes.
+        let shape = |object: &Object| object.shape.as_ref() as *const _ as *const u8;
+        let scaled = |object: &Object| object.shape.as_shape::<Scaled>().unwrap().shape().as_ref() as *const _ as *const u8;
+        assert_eq!(shape(&first[0]), shape(&prototypes["tree"][0]));
+        assert_eq!(scaled(&second[1]), shape(&prototypes["tree"][1]));
+        assert!(Arc::ptr_eq(&first[1].texture, &prototypes["tree"][1].texture));
+        assert!(Arc::ptr_eq(&second[0].texture, &second[1].texture));
+    }
+
+    #[test]
+    fn unknown_prototype() {
+        let json = SCENE.replace(r#""prototype": "ball""#, r#""prototype": "bush""#);
+
+        let error = load(&json).err().unwrap();
+
+        assert_eq!(error.to_string(), "There is no prototype called \"bush\".");
+    }
+
+    #[test]
+    fn skewed_instance() {
+        let json = SCENE.replace(r#""scale": 2"#, r#""scale": [1, 2, 1]"#).replace(
+            r#""texture": {"solid": {"ambient_colour": [0, 1, 0]}}"#,
+            r#""texture": {"solid": {"ambient_colour": [0, 1, 0]}}, "rotation": {"euler_degrees": [0, 45, 0]}"#,
+        );
+
+        let error = load(&json).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Instances of \"tree\" cannot be scaled non-uniformly as it has rotated objects."
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::scene::io::json::{JsonColour, JsonPoint, JsonScalar};
+use crate::image::Colour;
+use crate::scene::io::json::{JsonColour, JsonContext, JsonPoint, JsonScalar, JsonSpectrum};
 use crate::scene::light::LightSampling;
 use crate::scene::{Attenuation, Light};
 use serde::Deserialize;
@@ -6,7 +7,9 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct JsonLight {
     pub position: JsonPoint,
-    pub colour: JsonColour,
+    pub colour: Option<JsonColour>,
+    /// Spectral power distribution; replaces `colour` when given.
+    pub spectrum: Option<JsonSpectrum>,
     pub attenuation: Option<JsonAttenuation>,
     #[serde(flatten)]
     pub light_type: Option<JsonLightType>,
@@ -69,13 +72,13 @@ fn default_cache_samples(light_type: &JsonLightSamplingType) -> bool {
 }
 
 impl JsonLight {
-    pub fn to_light(&self) -> Light {
+    pub fn to_light(&self, context: &JsonContext) -> Light {
         let position = self.position.to_point();
-        let colour = self.colour.to_colour();
+        let colour = self.colour.map_or(Colour::new(1.0, 1.0, 1.0), |c| c.to_colour(context));
         let attenuation = self.attenuation.as_ref().map_or(Attenuation::None, |a| a.to_attenuation());
         let light_type = self.light_type.as_ref().unwrap_or(&JsonLightType::Point);
 
-        return match light_type {
+        let light = match light_type {
             JsonLightType::Point => Light::point(position, colour, attenuation),
             JsonLightType::Spherical {
                 radius,
@@ -83,11 +86,16 @@ impl JsonLight {
                 samples,
                 cache_samples,
             } => {
-                let cache = cache_samples.unwrap_or(default_cache_samples(&sampling));
+                let cache = cache_samples.unwrap_or(default_cache_samples(sampling));
 
-                return Light::spherical(position, colour, *radius, attenuation, sampling.to_light_sampling(), *samples, cache);
+                Light::spherical(position, colour, *radius, attenuation, sampling.to_light_sampling(), *samples, cache)
             }
         };
+
+        return match &self.spectrum {
+            Some(spectrum) => light.with_spectrum(spectrum.to_spectrum(), context.colour_space),
+            None => light,
+        };
     }
 }
 
@@ -1,5 +1,5 @@
-use crate::scene::io::json::{JsonColour, JsonScalar};
-use crate::scene::Material;
+use crate::scene::io::json::{JsonColour, JsonContext, JsonScalar};
+use crate::scene::{Material, RefractiveIndex};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -11,6 +11,23 @@ pub struct JsonMaterial {
     pub shininess: JsonScalar,
     #[serde(default = "default_reflectivity")]
     pub reflectivity: JsonScalar,
+    #[serde(default = "default_transparency")]
+    pub transparency: JsonScalar,
+    pub refractive_index: Option<JsonRefractiveIndex>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum JsonRefractiveIndex {
+    Constant(JsonScalar),
+    Dispersive(JsonDispersion),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonDispersion {
+    Cauchy { a: JsonScalar, b: JsonScalar },
+    Sellmeier { b: [JsonScalar; 3], c: [JsonScalar; 3] },
 }
 
 fn default_shininess() -> JsonScalar {
@@ -21,14 +38,30 @@ fn default_reflectivity() -> JsonScalar {
     return 0.0;
 }
 
+fn default_transparency() -> JsonScalar {
+    return 0.0;
+}
+
 impl JsonMaterial {
-    pub fn to_material(&self) -> Material {
+    pub fn to_material(&self, context: &JsonContext) -> Material {
         return Material {
-            ambient_colour: self.ambient_colour.to_colour(),
-            diffuse_colour: self.diffuse_colour.unwrap_or(self.ambient_colour).to_colour(),
-            specular_colour: self.specular_colour.unwrap_or(self.ambient_colour).to_colour(),
+            ambient_colour: self.ambient_colour.to_colour(context),
+            diffuse_colour: self.diffuse_colour.unwrap_or(self.ambient_colour).to_colour(context),
+            specular_colour: self.specular_colour.unwrap_or(self.ambient_colour).to_colour(context),
             shininess: self.shininess,
             reflectivity: self.reflectivity,
+            transparency: self.transparency,
+            refractive_index: self.refractive_index.as_ref().map_or(RefractiveIndex::default(), |r| r.to_refractive_index()),
+        };
+    }
+}
+
+impl JsonRefractiveIndex {
+    pub fn to_refractive_index(&self) -> RefractiveIndex {
+        return match self {
+            JsonRefractiveIndex::Constant(n) => RefractiveIndex::Constant(*n),
+            JsonRefractiveIndex::Dispersive(JsonDispersion::Cauchy { a, b }) => RefractiveIndex::Cauchy { a: *a, b: *b },
+            JsonRefractiveIndex::Dispersive(JsonDispersion::Sellmeier { b, c }) => RefractiveIndex::Sellmeier { b: *b, c: *c },
         };
     }
 }
@@ -0,0 +1,95 @@
+use crate::maths::Scalar;
+use crate::scene::io::json::{JsonPoint, JsonScalar};
+use crate::shapes::{MetaballSource, Metaballs};
+use serde::Deserialize;
+
+/// A source of a metaball surface, around either a centre or a segment from `start` to `end`. `radius` is how far its
+/// influence reaches, rather than the radius of the surface; a lone source of strength one at the default threshold
+/// has a surface at about 0.7 times its radius.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum JsonMetaballSource {
+    Point {
+        centre: JsonPoint,
+        radius: JsonScalar,
+        strength: Option<JsonScalar>,
+    },
+    Segment {
+        start: JsonPoint,
+        end: JsonPoint,
+        radius: JsonScalar,
+        strength: Option<JsonScalar>,
+    },
+}
+
+impl JsonMetaballSource {
+    pub fn radius(&self) -> JsonScalar {
+        return match self {
+            JsonMetaballSource::Point { radius, .. } | JsonMetaballSource::Segment { radius, .. } => *radius,
+        };
+    }
+
+    pub fn to_source(&self) -> MetaballSource {
+        return match self {
+            JsonMetaballSource::Point { centre, radius, strength } => MetaballSource::Point {
+                centre: centre.to_point(),
+                radius: *radius,
+                strength: strength.unwrap_or(1.0),
+            },
+            JsonMetaballSource::Segment { start, end, radius, strength } => MetaballSource::Segment {
+                start: start.to_point(),
+                end: end.to_point(),
+                radius: *radius,
+                strength: strength.unwrap_or(1.0),
+            },
+        };
+    }
+}
+
+/// Metaballs from their sources, with a default threshold of a quarter.
+pub fn to_metaballs(sources: &[JsonMetaballSource], threshold: Option<JsonScalar>) -> Metaballs {
+    let threshold: Scalar = threshold.unwrap_or(0.25);
+    return Metaballs::new(sources.iter().map(|source| source.to_source()).collect(), threshold);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maths::{Point, Scalar};
+    use crate::scene::io::json::{JsonContext, JsonObject};
+    use crate::shapes::{MetaballSource, Metaballs};
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn metaballs() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "metaballs": {
+                "sources": [
+                    {"centre": [-1, 0, 0], "radius": 1.5},
+                    {"centre": [1, 0, 0], "radius": 1.5, "strength": 2},
+                    {"start": [-1, 0, 0], "end": [1, 0, 0], "radius": 0.5}
+                ],
+                "threshold": 0.5
+            }
+        }"#;
+
+        let object = serde_json::from_str::<JsonObject>(json).unwrap().to_object(&JsonContext::default()).unwrap();
+
+        let metaballs = object.shape.as_shape::<Metaballs>().unwrap();
+        assert_eq!(metaballs.sources().len(), 3);
+        assert_eq!(
+            metaballs.sources()[1],
+            MetaballSource::Point {
+                centre: Point::new(1.0, 0.0, 0.0),
+                radius: 1.5,
+                strength: 2.0
+            }
+        );
+        assert!(matches!(metaballs.sources()[2], MetaballSource::Segment { strength, .. } if strength == 1.0));
+        // Just inside the surface of the first source on its own.
+        let inside = 1.5 * (1.0 - (0.5 as Scalar).sqrt()).sqrt() - 0.001;
+        assert!(metaballs.field(&Point::new(-1.0, inside, 0.0)) > 0.5);
+        assert_abs_diff_eq!(metaballs.field(&Point::new(-1.0, 2.0, 0.0)), 0.0);
+    }
+}
@@ -1,12 +1,31 @@
-use crate::scene::io::json::{JsonPoint, JsonScalar, JsonTexture, JsonVector};
-use crate::scene::Object;
+use crate::maths::{Point, Rotation, Sphere, Transformation, Vector};
+use crate::scene::io::json::{
+    to_bezier_surface, to_csg, to_metaballs, JsonContext, JsonCsgChild, JsonCsgOperation, JsonDisplacement, JsonHeights, JsonMetaballSource, JsonNurbs,
+    JsonPoint, JsonRotation, JsonScalar, JsonScale, JsonSdf, JsonShading, JsonSubdivision, JsonTexture, JsonVector,
+};
+use crate::scene::io::{gltf, obj, ply, stl};
+use crate::scene::{Object, Texture};
+use crate::shapes::{teapot, Capsule, Cone, Cylinder, DistanceField, Mesh, Patches, Solid, Torus};
+use ncollide3d::bounding_volume::AABB;
+use ncollide3d::shape::Cuboid;
 use serde::Deserialize;
+use snafu::Snafu;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Deserialize)]
 pub struct JsonObject {
     pub position: JsonPoint,
 
-    pub texture: JsonTexture,
+    pub rotation: Option<JsonRotation>,
+
+    /// Either a single scale for all axes or one for each axis, applied before rotating.
+    pub scale: Option<JsonScale>,
+
+    /// Optional for meshes loaded from OBJ or glTF files, which have their own materials, or PLY files with vertex
+    /// colours; required otherwise.
+    pub texture: Option<JsonTexture>,
 
     #[serde(flatten)]
     pub shape: JsonShape,
@@ -15,18 +34,473 @@ pub struct JsonObject {
 #[derive(Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum JsonShape {
-    Sphere { radius: JsonScalar },
-    Plane { normal: JsonVector },
+    Sphere {
+        radius: JsonScalar,
+    },
+    Plane {
+        normal: JsonVector,
+    },
+    // The shapes below are along or facing up the y axis; use a rotation to point them elsewhere.
+    Cuboid {
+        size: JsonVector,
+    },
+    Cylinder {
+        height: JsonScalar,
+        radius: JsonScalar,
+    },
+    Cone {
+        height: JsonScalar,
+        radius: JsonScalar,
+    },
+    /// The height is of the cylindrical part, not including the hemispherical ends.
+    Capsule {
+        height: JsonScalar,
+        radius: JsonScalar,
+    },
+    Disc {
+        radius: JsonScalar,
+    },
+    Rectangle {
+        width: JsonScalar,
+        depth: JsonScalar,
+    },
+    Torus {
+        major_radius: JsonScalar,
+        minor_radius: JsonScalar,
+    },
+    /// A landscape of heights over the x–z plane; `size` is its width, the height of the highest possible height, and its
+    /// depth.
+    Heightfield {
+        size: JsonVector,
+        #[serde(flatten)]
+        heights: JsonHeights,
+    },
+    /// A blobby surface around points and segments, which blend together where they are close.
+    Metaballs {
+        sources: Vec<JsonMetaballSource>,
+        threshold: Option<JsonScalar>,
+    },
+    /// The normals are not used if the triangles are subdivided. Displacement happens after any subdivision.
+    Triangles {
+        positions: Vec<JsonPoint>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<JsonVector>>,
+        #[serde(flatten)]
+        shading: JsonShading,
+        subdivision: Option<JsonSubdivision>,
+        displacement: Option<JsonDisplacement>,
+    },
+    /// Two shapes combined by union, intersection or difference. Either can be another CSG shape; all must be closed.
+    Csg {
+        op: JsonCsgOperation,
+        left: Box<JsonCsgChild>,
+        right: Box<JsonCsgChild>,
+    },
+    /// A signed distance field, traced within `bounds`, which are centred on the object's position and default to the
+    /// field's own. Fields that overestimate distances, such as twists, need a `step_scale` below one.
+    Sdf {
+        field: JsonSdf,
+        bounds: Option<JsonVector>,
+        max_steps: Option<usize>,
+        step_scale: Option<JsonScalar>,
+    },
+    /// Bicubic Bezier patches, each of four rows of four control points.
+    Bezier {
+        patches: Vec<[[JsonPoint; 4]; 4]>,
+    },
+    /// NURBS surfaces, such as those exported from CAD programs.
+    Nurbs {
+        surfaces: Vec<JsonNurbs>,
+    },
+    /// The Utah teapot, sitting on the x–z plane with its spout towards the positive x axis.
+    Teapot {},
+    /// A Wavefront OBJ, PLY, STL or glTF file, by its extension, relative to the scene file. Only the given groups of an
+    /// OBJ file are loaded, if any are given. A glTF file's meshes are all loaded together; its cameras and lights are
+    /// ignored. Subdividing smooths the mesh as it is loaded, before any displacement.
+    Mesh {
+        path: PathBuf,
+        groups: Option<Vec<String>>,
+        #[serde(flatten)]
+        shading: JsonShading,
+        subdivision: Option<JsonSubdivision>,
+        displacement: Option<JsonDisplacement>,
+    },
+}
+
+#[derive(Debug, Snafu)]
+pub enum ObjectError {
+    #[snafu(display("Objects must have a texture, other than meshes loaded from OBJ or glTF files or PLY files with colours."))]
+    MissingTexture,
+
+    #[snafu(display("Objects cannot be scaled by zero."))]
+    ZeroScale,
+
+    #[snafu(display("Only closed shapes can be combined with CSG, not {}.", shape))]
+    NotSolid { shape: String },
+
+    #[snafu(display("Distance fields that repeat forever need bounds."))]
+    UnboundedField,
+
+    #[snafu(display("There is no prototype called {:?}.", name))]
+    UnknownPrototype { name: String },
+
+    #[snafu(display("Instances of {:?} cannot be scaled non-uniformly as it has rotated objects.", name))]
+    SkewedInstance { name: String },
+
+    #[snafu(display("Unable to read heightmap {:?}; {}.", path, reason))]
+    CannotReadHeightmap { path: PathBuf, reason: String },
+
+    #[snafu(display("Heightfields need at least two rows and columns of heights."))]
+    HeightfieldTooSmall,
+
+    #[snafu(display("Invalid NURBS surface; {}.", reason))]
+    InvalidSurface { reason: String },
+
+    #[snafu(display("Subdivision needs at least one level."))]
+    NoSubdivisionLevels,
+
+    #[snafu(display("Displacement needs a positive edge length and size."))]
+    InvalidDisplacement,
+
+    #[snafu(display("Invalid triangles; {}.", reason))]
+    InvalidTriangles { reason: String },
+
+    #[snafu(display("Invalid {}; {}.", shape, reason))]
+    InvalidShape { shape: String, reason: String },
+
+    #[snafu(display("Unable to subdivide; {}.", reason))]
+    CannotSubdivide { reason: String },
+}
+
+// A mesh and any texture loaded with it.
+type MeshWithTexture = (Mesh, Option<Texture>);
+
+pub fn to_transformation(position: Point, rotation: Option<&JsonRotation>, scale: Option<&JsonScale>) -> Result<Transformation, ObjectError> {
+    let scale = scale.map_or(Vector::new(1.0, 1.0, 1.0), |scale| scale.to_scale());
+    if scale.iter().any(|&component| component == 0.0) {
+        return Err(ObjectError::ZeroScale);
+    }
+
+    let rotation = rotation.map_or(Rotation::identity(), |rotation| rotation.to_rotation(&position));
+    return Ok(Transformation::new(position, rotation, scale));
 }
 
 impl JsonObject {
-    pub fn to_object(&self) -> Object {
+    pub fn to_object(&self, context: &JsonContext) -> Result<Object, Box<dyn Error>> {
+        let object = self.to_untransformed_object(context)?;
+
+        if self.rotation.is_none() && self.scale.is_none() {
+            return Ok(object);
+        }
+
+        let transformation = to_transformation(self.position.to_point(), self.rotation.as_ref(), self.scale.as_ref())?;
+        return Ok(object.with_transformation(transformation));
+    }
+
+    fn to_untransformed_object(&self, context: &JsonContext) -> Result<Object, Box<dyn Error>> {
         let position = self.position.to_point();
-        let texture = self.texture.to_texture();
+        let texture = self.texture.as_ref().map(|texture| texture.to_texture(context));
+
+        if let Some((mesh, model_texture)) = self.shape.to_mesh(context)? {
+            return Ok(Object::new(mesh, position, texture.or(model_texture).ok_or(ObjectError::MissingTexture)?));
+        }
 
-        return match &self.shape {
+        let texture = texture.ok_or(ObjectError::MissingTexture)?;
+
+        self.shape.validate()?;
+        return Ok(match &self.shape {
             JsonShape::Sphere { radius } => Object::new_sphere(position, *radius, texture),
             JsonShape::Plane { normal } => Object::new_plane(position, normal.to_vector(), texture),
+            JsonShape::Cuboid { size } => Object::new_cuboid(position, size.to_vector(), texture),
+            JsonShape::Cylinder { height, radius } => Object::new_cylinder(position, *height, *radius, texture),
+            JsonShape::Cone { height, radius } => Object::new_cone(position, *height, *radius, texture),
+            JsonShape::Capsule { height, radius } => Object::new_capsule(position, *height, *radius, texture),
+            JsonShape::Disc { radius } => Object::new_disc(position, *radius, texture),
+            JsonShape::Rectangle { width, depth } => Object::new_rectangle(position, *width, *depth, texture),
+            JsonShape::Torus { major_radius, minor_radius } => Object::new_torus(position, *major_radius, *minor_radius, texture),
+            JsonShape::Csg { op, left, right } => Object::new(to_csg(*op, left, right)?, position, texture),
+            JsonShape::Sdf {
+                field,
+                bounds,
+                max_steps,
+                step_scale,
+            } => Object::new(to_distance_field(field, bounds.as_ref(), *max_steps, *step_scale)?, position, texture),
+            JsonShape::Metaballs { sources, threshold } => Object::new(to_metaballs(sources, *threshold), position, texture),
+            JsonShape::Heightfield { size, heights } => Object::new(heights.to_heightfield(size.to_vector(), context)?, position, texture),
+            JsonShape::Bezier { patches } => Object::new(Patches::new(patches.iter().map(to_bezier_surface).collect()), position, texture),
+            JsonShape::Nurbs { surfaces } => {
+                let surfaces = surfaces.iter().map(|surface| surface.to_surface()).collect::<Result<_, _>>()?;
+                Object::new(Patches::new(surfaces), position, texture)
+            }
+            JsonShape::Teapot {} => Object::new(Patches::new(teapot()), position, texture),
+            JsonShape::Triangles { .. } | JsonShape::Mesh { .. } => unreachable!(),
+        });
+    }
+}
+
+impl JsonShape {
+    /// The mesh of a mesh shape, subdivided and displaced if needed, with any texture loaded with it. Returns `None` for
+    /// other shapes.
+    fn to_mesh(&self, context: &JsonContext) -> Result<Option<MeshWithTexture>, Box<dyn Error>> {
+        let (mesh, texture, shading, displacement) = match self {
+            JsonShape::Mesh {
+                path,
+                groups,
+                shading,
+                subdivision,
+                displacement,
+            } => {
+                let path = context.directory.join(path);
+                let subdivision = subdivision.as_ref().map(|subdivision| subdivision.to_subdivision()).transpose()?;
+                let subdivision = subdivision.as_ref();
+                let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+                let (mesh, texture) = match extension.as_deref() {
+                    Some("ply") => {
+                        let model = ply::load(&path, shading.to_shading(), subdivision, context.colour_space)?;
+                        (model.mesh, model.texture)
+                    }
+                    Some("stl") => (stl::load(&path, shading.to_shading(), subdivision)?, None),
+                    Some("gltf") | Some("glb") => {
+                        let mesh = gltf::load_model(&path, shading.to_shading(), subdivision, context.colour_space, true)?
+                            .meshes
+                            .remove(0);
+                        (mesh.mesh, Some(mesh.texture))
+                    }
+                    _ => {
+                        let model = obj::load(&path, groups.as_deref(), shading.to_shading(), subdivision, context.colour_space)?;
+                        (model.mesh, Some(model.texture))
+                    }
+                };
+                (mesh, texture, shading, displacement)
+            }
+            JsonShape::Triangles {
+                positions,
+                indices,
+                normals,
+                shading,
+                subdivision,
+                displacement,
+            } => {
+                validate_triangles(positions.len(), indices, normals.as_deref())?;
+                let positions: Vec<Point> = positions.iter().map(|point| point.to_point()).collect();
+                let mesh = match subdivision {
+                    Some(subdivision) => {
+                        subdivision
+                            .to_subdivision()?
+                            .to_mesh(&positions, indices, shading.to_shading())
+                            .map_err(|reason| ObjectError::CannotSubdivide { reason })?
+                            .0
+                    }
+                    None => Mesh::new(
+                        positions,
+                        indices.clone(),
+                        normals.as_ref().map(|normals| normals.iter().map(|normal| normal.to_vector()).collect()),
+                        None,
+                        shading.to_shading(),
+                    ),
+                };
+                (mesh, None, shading, displacement)
+            }
+            _ => return Ok(None),
+        };
+
+        let displacement = match displacement {
+            Some(displacement) => displacement.to_displacement(context)?,
+            None => return Ok(Some((mesh, texture))),
         };
+        let (mesh, origins) = displacement.displace(&mesh, shading.to_shading());
+        let texture = texture.map(|texture| match texture {
+            Texture::PerFace { materials, faces } => Texture::PerFace {
+                materials,
+                faces: origins.iter().map(|&origin| faces[origin]).collect(),
+            },
+            Texture::PerVertex {
+                positions,
+                colours,
+                polygons,
+                faces,
+            } => Texture::PerVertex {
+                positions,
+                colours,
+                polygons,
+                faces: origins.iter().map(|&origin| faces[origin]).collect(),
+            },
+            texture => texture,
+        });
+        return Ok(Some((mesh, texture)));
+    }
+
+    /// Checks what the shapes' constructors assert, so bad scenes are errors rather than panics.
+    fn validate(&self) -> Result<(), ObjectError> {
+        match self {
+            JsonShape::Cylinder { height, radius } if *height <= 0.0 || *radius <= 0.0 => {
+                return Err(invalid_shape("cylinder", "the height and radius must be positive"));
+            }
+            JsonShape::Cone { height, radius } if *height <= 0.0 || *radius <= 0.0 => {
+                return Err(invalid_shape("cone", "the height and radius must be positive"));
+            }
+            JsonShape::Capsule { height, radius } if *height < 0.0 || *radius <= 0.0 => {
+                return Err(invalid_shape("capsule", "the radius must be positive and the height not negative"));
+            }
+            JsonShape::Disc { radius } if *radius <= 0.0 => return Err(invalid_shape("disc", "the radius must be positive")),
+            JsonShape::Rectangle { width, depth } if *width <= 0.0 || *depth <= 0.0 => {
+                return Err(invalid_shape("rectangle", "the width and depth must be positive"));
+            }
+            JsonShape::Metaballs { sources, .. } if sources.is_empty() => {
+                return Err(invalid_shape("metaballs", "there must be at least one source"));
+            }
+            JsonShape::Metaballs { sources, .. } if sources.iter().any(|source| source.radius() <= 0.0) => {
+                return Err(invalid_shape("metaballs", "the radii of the sources must be positive"));
+            }
+            JsonShape::Metaballs {
+                threshold: Some(threshold), ..
+            } if *threshold <= 0.0 => {
+                return Err(invalid_shape("metaballs", "the threshold must be positive"));
+            }
+            JsonShape::Torus { major_radius, minor_radius } if *minor_radius <= 0.0 || *major_radius < 0.0 => {
+                return Err(invalid_shape("torus", "the minor radius must be positive and the major radius not negative"));
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    /// The shape for use in CSG, which needs closed shapes.
+    pub fn to_solid(&self) -> Result<Arc<dyn Solid>, ObjectError> {
+        self.validate()?;
+        return Ok(match self {
+            JsonShape::Sphere { radius } => Arc::new(Sphere::new(*radius)),
+            JsonShape::Cuboid { size } => Arc::new(Cuboid::new(size.to_vector() / 2.0)),
+            JsonShape::Cylinder { height, radius } => Arc::new(Cylinder::new(height / 2.0, *radius)),
+            JsonShape::Cone { height, radius } => Arc::new(Cone::new(height / 2.0, *radius)),
+            JsonShape::Capsule { height, radius } => Arc::new(Capsule::new(height / 2.0, *radius)),
+            JsonShape::Torus { major_radius, minor_radius } => Arc::new(Torus::new(*major_radius, *minor_radius)),
+            JsonShape::Csg { op, left, right } => Arc::new(to_csg(*op, left, right)?),
+            JsonShape::Metaballs { sources, threshold } => Arc::new(to_metaballs(sources, *threshold)),
+            JsonShape::Plane { .. } => return Err(not_solid("planes")),
+            JsonShape::Disc { .. } => return Err(not_solid("discs")),
+            JsonShape::Rectangle { .. } => return Err(not_solid("rectangles")),
+            JsonShape::Triangles { .. } | JsonShape::Mesh { .. } => return Err(not_solid("meshes")),
+            JsonShape::Sdf { .. } => return Err(not_solid("distance fields")),
+            JsonShape::Heightfield { .. } => return Err(not_solid("heightfields")),
+            JsonShape::Bezier { .. } | JsonShape::Nurbs { .. } | JsonShape::Teapot {} => return Err(not_solid("patches")),
+        });
+    }
+}
+
+fn to_distance_field(
+    field: &JsonSdf,
+    bounds: Option<&JsonVector>,
+    max_steps: Option<usize>,
+    step_scale: Option<JsonScalar>,
+) -> Result<DistanceField, ObjectError> {
+    let step_scale = step_scale.unwrap_or(1.0);
+    if step_scale <= 0.0 {
+        return Err(invalid_shape("distance field", "the step scale must be positive"));
+    }
+
+    let field = field.to_sdf();
+    let bounds = match bounds {
+        Some(size) => {
+            let half_extents = size.to_vector() / 2.0;
+            AABB::new(Point::from(-half_extents), Point::from(half_extents))
+        }
+        None => field.bounds().ok_or(ObjectError::UnboundedField)?,
+    };
+
+    return Ok(DistanceField::new(field, bounds, max_steps.unwrap_or(256), step_scale));
+}
+
+// Checks what `Mesh::new` asserts, so bad scenes are errors rather than panics.
+fn validate_triangles(positions: usize, indices: &[[usize; 3]], normals: Option<&[JsonVector]>) -> Result<(), ObjectError> {
+    if indices.is_empty() {
+        return Err(invalid_triangles("there must be at least one triangle".to_string()));
+    }
+    if let Some(index) = indices.iter().flatten().find(|&&index| index >= positions) {
+        return Err(invalid_triangles(format!("index {} is out of range of the {} positions", index, positions)));
+    }
+    if normals.is_some_and(|normals| normals.len() != positions) {
+        return Err(invalid_triangles("there must be one normal per position".to_string()));
+    }
+    return Ok(());
+}
+
+fn invalid_triangles(reason: String) -> ObjectError {
+    return ObjectError::InvalidTriangles { reason };
+}
+
+fn invalid_shape(shape: &str, reason: &str) -> ObjectError {
+    return ObjectError::InvalidShape {
+        shape: shape.to_string(),
+        reason: reason.to_string(),
+    };
+}
+
+fn not_solid(shape: &str) -> ObjectError {
+    return ObjectError::NotSolid { shape: shape.to_string() };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::io::json::{JsonContext, JsonObject, ObjectError};
+    use rstest::rstest;
+
+    fn error(shape: &str) -> ObjectError {
+        let json = format!(
+            r#"{{"position": [0, 0, 0], "texture": {{"solid": {{"ambient_colour": [1, 1, 1]}}}}, {}}}"#,
+            shape
+        );
+        let error = serde_json::from_str::<JsonObject>(&json)
+            .unwrap()
+            .to_object(&JsonContext::default())
+            .err()
+            .unwrap();
+        return *error.downcast::<ObjectError>().unwrap();
+    }
+
+    #[rstest(
+        triangles,
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": []}"#),
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": [[0, 1, 3]]}"#),
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": [[0, 1, 2]], "normals": [[0, 0, 1]]}"#),
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": [[0, 1, 3]], "subdivision": {"scheme": "loop"}}"#)
+    )]
+    fn invalid_triangles(triangles: &str) {
+        let error = error(&format!(r#""triangles": {}"#, triangles));
+
+        assert!(matches!(error, ObjectError::InvalidTriangles { .. }));
+    }
+
+    #[rstest(
+        triangles,
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": [[0, 0, 1]], "subdivision": {"scheme": "loop"}}"#),
+        case(r#"{"positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "indices": [[0, 1, 2]], "subdivision": {"scheme": "loop", "creases": [[0, 5]]}}"#)
+    )]
+    fn cannot_subdivide(triangles: &str) {
+        let error = error(&format!(r#""triangles": {}"#, triangles));
+
+        assert!(matches!(error, ObjectError::CannotSubdivide { .. }));
+    }
+
+    #[rstest(
+        shape,
+        case(r#""cylinder": {"height": 0, "radius": 1}"#),
+        case(r#""cone": {"height": 1, "radius": 0}"#),
+        case(r#""capsule": {"height": -1, "radius": 1}"#),
+        case(r#""disc": {"radius": 0}"#),
+        case(r#""rectangle": {"width": 1, "depth": 0}"#),
+        case(r#""torus": {"major_radius": 1, "minor_radius": 0}"#),
+        case(r#""metaballs": {"sources": []}"#),
+        case(r#""metaballs": {"sources": [{"centre": [0, 0, 0], "radius": 0}]}"#),
+        case(r#""metaballs": {"sources": [{"centre": [0, 0, 0], "radius": 1}], "threshold": 0}"#),
+        case(r#""torus": {"major_radius": -1, "minor_radius": 0.5}"#),
+        case(r#""sdf": {"field": {"sphere": {"radius": 1}}, "step_scale": 0}"#),
+        case(r#""sdf": {"field": {"sphere": {"radius": 1}}, "step_scale": -0.5}"#),
+        case(r#""csg": {"op": "union", "left": {"sphere": {"radius": 1}}, "right": {"torus": {"major_radius": 1, "minor_radius": 0}}}"#)
+    )]
+    fn invalid_shape(shape: &str) {
+        let error = error(shape);
+
+        assert!(matches!(error, ObjectError::InvalidShape { .. }));
     }
 }
@@ -0,0 +1,154 @@
+use crate::scene::io::json::{JsonPoint, JsonScalar, ObjectError};
+use crate::shapes::NurbsSurface;
+use serde::Deserialize;
+
+/// A NURBS surface with rows of control points along the u direction, each row running along the v direction. Each
+/// direction needs as many knots as control points plus its degree plus one; the weights default to one.
+#[derive(Deserialize)]
+pub struct JsonNurbs {
+    pub degrees: [usize; 2],
+    pub knots: [Vec<JsonScalar>; 2],
+    pub control_points: Vec<Vec<JsonPoint>>,
+    pub weights: Option<Vec<Vec<JsonScalar>>>,
+}
+
+impl JsonNurbs {
+    pub fn to_surface(&self) -> Result<NurbsSurface, ObjectError> {
+        let counts = [self.control_points.len(), self.control_points.first().map_or(0, |row| row.len())];
+        if self.control_points.iter().any(|row| row.len() != counts[1]) {
+            return Err(invalid_surface("the rows of control points are different lengths"));
+        }
+        for ((&count, &degree), knots) in counts.iter().zip(self.degrees.iter()).zip(self.knots.iter()) {
+            if count <= degree {
+                return Err(invalid_surface("there must be more control points than the degree in each direction"));
+            }
+            if knots.len() != count + degree + 1 {
+                return Err(invalid_surface("there must be as many knots as control points plus the degree plus one"));
+            }
+            if knots.windows(2).any(|pair| pair[0] > pair[1]) {
+                return Err(invalid_surface("knots cannot decrease"));
+            }
+        }
+        if let Some(weights) = &self.weights {
+            if weights.len() != counts[0] || weights.iter().any(|row| row.len() != counts[1]) {
+                return Err(invalid_surface("there must be one weight per control point"));
+            }
+            if weights.iter().flatten().any(|&weight| weight <= 0.0) {
+                return Err(invalid_surface("weights must be positive"));
+            }
+        }
+
+        let control_points = self
+            .control_points
+            .iter()
+            .map(|row| row.iter().map(|point| point.to_point()).collect())
+            .collect();
+        return Ok(NurbsSurface::new(self.degrees, self.knots.clone(), control_points, self.weights.clone()));
+    }
+}
+
+pub fn to_bezier_surface(patch: &[[JsonPoint; 4]; 4]) -> NurbsSurface {
+    return NurbsSurface::bezier(&[0, 1, 2, 3].map(|row| [0, 1, 2, 3].map(|column| patch[row][column].to_point())));
+}
+
+fn invalid_surface(reason: &str) -> ObjectError {
+    return ObjectError::InvalidSurface { reason: reason.to_string() };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maths::{Isometry, Point, Ray, Scalar, Vector};
+    use crate::scene::io::json::{JsonContext, JsonObject};
+    use crate::shapes::Patches;
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+
+    #[test]
+    fn bezier() {
+        // A flat square of side three, raised to y = 1.
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "bezier": {
+                "patches": [[
+                    [[0, 1, 0], [0, 1, 1], [0, 1, 2], [0, 1, 3]],
+                    [[1, 1, 0], [1, 1, 1], [1, 1, 2], [1, 1, 3]],
+                    [[2, 1, 0], [2, 1, 1], [2, 1, 2], [2, 1, 3]],
+                    [[3, 1, 0], [3, 1, 1], [3, 1, 2], [3, 1, 3]]
+                ]]
+            }
+        }"#;
+
+        let object = serde_json::from_str::<JsonObject>(json).unwrap().to_object(&JsonContext::default()).unwrap();
+
+        let patches = object.shape.as_shape::<Patches>().unwrap();
+        assert_eq!(patches.surfaces().len(), 1);
+        let ray = Ray::new(Point::new(1.0, 5.0, 2.0), -Vector::y());
+        let intersection = patches.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_abs_diff_eq!(intersection.toi, 4.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal, Vector::y(), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn nurbs() {
+        // A half cylinder of radius one around the y axis, from two quarters with weights.
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "nurbs": {
+                "surfaces": [{
+                    "degrees": [2, 1],
+                    "knots": [[0, 0, 0, 0.5, 0.5, 1, 1, 1], [0, 0, 1, 1]],
+                    "control_points": [
+                        [[1, 0, 0], [1, 1, 0]],
+                        [[1, 0, 1], [1, 1, 1]],
+                        [[0, 0, 1], [0, 1, 1]],
+                        [[-1, 0, 1], [-1, 1, 1]],
+                        [[-1, 0, 0], [-1, 1, 0]]
+                    ],
+                    "weights": [[1, 1], [0.70710678, 0.70710678], [1, 1], [0.70710678, 0.70710678], [1, 1]]
+                }]
+            }
+        }"#;
+
+        let object = serde_json::from_str::<JsonObject>(json).unwrap().to_object(&JsonContext::default()).unwrap();
+
+        let patches = object.shape.as_shape::<Patches>().unwrap();
+        let ray = Ray::new(Point::new(-0.6, 0.5, 5.0), -Vector::z());
+        let intersection = patches.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_abs_diff_eq!(intersection.toi, 5.0 - 0.8, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal, Vector::new(-0.6, 0.0, 0.8), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn teapot() {
+        let json = r#"{"position": [0, 0, 0], "texture": {"solid": {"ambient_colour": [1, 1, 1]}}, "teapot": {}}"#;
+
+        let object = serde_json::from_str::<JsonObject>(json).unwrap().to_object(&JsonContext::default()).unwrap();
+
+        assert_eq!(object.shape.as_shape::<Patches>().unwrap().surfaces().len(), 32);
+    }
+
+    #[test]
+    fn invalid_knots() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "nurbs": {
+                "surfaces": [{
+                    "degrees": [1, 1],
+                    "knots": [[0, 1, 0, 1], [0, 0, 1, 1]],
+                    "control_points": [[[0, 0, 0], [0, 0, 1]], [[1, 0, 0], [1, 0, 1]]]
+                }]
+            }
+        }"#;
+
+        let error = serde_json::from_str::<JsonObject>(json)
+            .unwrap()
+            .to_object(&JsonContext::default())
+            .err()
+            .unwrap();
+
+        assert_eq!(error.to_string(), "Invalid NURBS surface; knots cannot decrease.");
+    }
+}
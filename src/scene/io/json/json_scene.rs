@@ -1,6 +1,8 @@
-use crate::scene::io::json::{JsonCamera, JsonColour, JsonLight, JsonObject};
+use crate::image::WorkingColourSpace;
+use crate::scene::io::json::{JsonCamera, JsonColour, JsonContext, JsonInstance, JsonLight, JsonObject, JsonPrototype};
 use crate::scene::Scene;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
@@ -8,13 +10,38 @@ use std::path::Path;
 
 #[derive(Deserialize)]
 pub struct JsonScene {
+    pub colour_space: Option<JsonColourSpace>,
     pub ambient_light: Option<JsonColour>,
     pub background_colour: Option<JsonColour>,
     pub camera: Option<JsonCamera>,
     pub objects: Vec<JsonObject>,
+    /// Objects, or groups of objects, that are only in the scene through instances.
+    #[serde(default)]
+    pub prototypes: HashMap<String, JsonPrototype>,
+    #[serde(default)]
+    pub instances: Vec<JsonInstance>,
     pub lights: Vec<JsonLight>,
 }
 
+#[derive(Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonColourSpace {
+    #[serde(rename = "linear srgb")]
+    LinearSrgb,
+    Rec2020,
+    AcesCg,
+}
+
+impl JsonColourSpace {
+    pub fn to_colour_space(&self) -> WorkingColourSpace {
+        return match self {
+            JsonColourSpace::LinearSrgb => WorkingColourSpace::LinearSrgb,
+            JsonColourSpace::Rec2020 => WorkingColourSpace::Rec2020,
+            JsonColourSpace::AcesCg => WorkingColourSpace::AcesCg,
+        };
+    }
+}
+
 impl JsonScene {
     pub fn load(path: &Path) -> Result<JsonScene, Box<dyn Error>> {
         let file = File::open(path)?;
@@ -25,25 +52,44 @@ impl JsonScene {
         return Ok(json_scene);
     }
 
-    pub fn to_scene(&self) -> Scene {
+    pub fn to_scene(&self, directory: &Path) -> Result<Scene, Box<dyn Error>> {
         let mut scene = Scene::new();
 
+        if let Some(colour_space) = &self.colour_space {
+            scene.colour_space = colour_space.to_colour_space();
+        }
+
+        let context = JsonContext {
+            colour_space: scene.colour_space,
+            directory: directory.to_path_buf(),
+        };
+
         if let Some(ambient_light) = &self.ambient_light {
-            scene.ambient_light = ambient_light.to_colour();
+            scene.ambient_light = ambient_light.to_colour(&context);
         }
 
         if let Some(background_colour) = &self.background_colour {
-            scene.background_colour = background_colour.to_colour();
+            scene.background_colour = background_colour.to_colour(&context);
         }
 
         if let Some(camera) = &self.camera {
             scene.camera = camera.to_camera();
         }
 
-        scene.add_lights(self.lights.iter().map(|light| light.to_light()));
+        scene.add_lights(self.lights.iter().map(|light| light.to_light(&context)));
 
-        scene.add_objects(self.objects.iter().map(|object| object.to_object()));
+        let mut objects = self.objects.iter().map(|object| object.to_object(&context)).collect::<Result<Vec<_>, _>>()?;
+
+        let prototypes = self
+            .prototypes
+            .iter()
+            .map(|(name, prototype)| Ok((name.clone(), prototype.to_objects(&context)?)))
+            .collect::<Result<HashMap<_, _>, Box<dyn Error>>>()?;
+        for instance in &self.instances {
+            objects.extend(instance.to_objects(&prototypes, &context)?);
+        }
+        scene.add_objects(objects);
 
-        return scene;
+        return Ok(scene);
     }
 }
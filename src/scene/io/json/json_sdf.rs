@@ -0,0 +1,197 @@
+use crate::maths::Point;
+use crate::scene::io::json::{JsonRotation, JsonScalar, JsonVector};
+use crate::shapes::Sdf;
+use serde::Deserialize;
+
+/// A signed distance field, built up from primitives, combinations and distortions of other fields.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonSdf {
+    Sphere {
+        radius: JsonScalar,
+    },
+    /// `rounding` rounds off the edges without changing the overall size.
+    Cuboid {
+        size: JsonVector,
+        rounding: Option<JsonScalar>,
+    },
+    Torus {
+        major_radius: JsonScalar,
+        minor_radius: JsonScalar,
+    },
+    Cylinder {
+        height: JsonScalar,
+        radius: JsonScalar,
+    },
+    /// The height is of the cylindrical part, not including the hemispherical ends.
+    Capsule {
+        height: JsonScalar,
+        radius: JsonScalar,
+    },
+    /// The fields are blended over `smoothness` if it is given, or joined with sharp edges otherwise.
+    Union {
+        left: Box<JsonSdf>,
+        right: Box<JsonSdf>,
+        smoothness: Option<JsonScalar>,
+    },
+    Subtraction {
+        left: Box<JsonSdf>,
+        right: Box<JsonSdf>,
+        smoothness: Option<JsonScalar>,
+    },
+    Intersection {
+        left: Box<JsonSdf>,
+        right: Box<JsonSdf>,
+        smoothness: Option<JsonScalar>,
+    },
+    Translate {
+        offset: JsonVector,
+        field: Box<JsonSdf>,
+    },
+    /// Look at rotations are from the centre of the field.
+    Rotate {
+        rotation: JsonRotation,
+        field: Box<JsonSdf>,
+    },
+    /// A period of zero on an axis doesn't repeat the field along it.
+    Repeat {
+        period: JsonVector,
+        field: Box<JsonSdf>,
+    },
+    Twist {
+        degrees_per_unit: JsonScalar,
+        field: Box<JsonSdf>,
+    },
+    Mandelbulb {
+        power: Option<JsonScalar>,
+        iterations: Option<usize>,
+    },
+    Menger {
+        size: JsonScalar,
+        iterations: usize,
+    },
+}
+
+impl JsonSdf {
+    pub fn to_sdf(&self) -> Sdf {
+        let combine = |left: &JsonSdf, right: &JsonSdf, smoothness: &Option<JsonScalar>| {
+            return (Box::new(left.to_sdf()), Box::new(right.to_sdf()), smoothness.unwrap_or(0.0));
+        };
+
+        return match self {
+            JsonSdf::Sphere { radius } => Sdf::Sphere { radius: *radius },
+            JsonSdf::Cuboid { size, rounding } => Sdf::Cuboid {
+                half_extents: size.to_vector() / 2.0,
+                rounding: rounding.unwrap_or(0.0),
+            },
+            JsonSdf::Torus { major_radius, minor_radius } => Sdf::Torus {
+                major_radius: *major_radius,
+                minor_radius: *minor_radius,
+            },
+            JsonSdf::Cylinder { height, radius } => Sdf::Cylinder {
+                half_height: height / 2.0,
+                radius: *radius,
+            },
+            JsonSdf::Capsule { height, radius } => Sdf::Capsule {
+                half_height: height / 2.0,
+                radius: *radius,
+            },
+            JsonSdf::Union { left, right, smoothness } => {
+                let (left, right, smoothness) = combine(left, right, smoothness);
+                Sdf::Union { left, right, smoothness }
+            }
+            JsonSdf::Subtraction { left, right, smoothness } => {
+                let (left, right, smoothness) = combine(left, right, smoothness);
+                Sdf::Subtraction { left, right, smoothness }
+            }
+            JsonSdf::Intersection { left, right, smoothness } => {
+                let (left, right, smoothness) = combine(left, right, smoothness);
+                Sdf::Intersection { left, right, smoothness }
+            }
+            JsonSdf::Translate { offset, field } => Sdf::Translate {
+                offset: offset.to_vector(),
+                field: Box::new(field.to_sdf()),
+            },
+            JsonSdf::Rotate { rotation, field } => Sdf::Rotate {
+                rotation: rotation.to_rotation(&Point::origin()),
+                field: Box::new(field.to_sdf()),
+            },
+            JsonSdf::Repeat { period, field } => Sdf::Repeat {
+                period: period.to_vector(),
+                field: Box::new(field.to_sdf()),
+            },
+            JsonSdf::Twist { degrees_per_unit, field } => Sdf::Twist {
+                rate: degrees_per_unit.to_radians(),
+                field: Box::new(field.to_sdf()),
+            },
+            JsonSdf::Mandelbulb { power, iterations } => Sdf::Mandelbulb {
+                power: power.unwrap_or(8.0),
+                iterations: iterations.unwrap_or(10),
+            },
+            JsonSdf::Menger { size, iterations } => Sdf::Menger {
+                half_size: size / 2.0,
+                iterations: *iterations,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::{Ray, Scalar, Vector};
+    use crate::scene::io::json::{JsonContext, JsonObject, ObjectError};
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn smooth_union() {
+        let json = r#"{"union": {"left": {"sphere": {"radius": 1}}, "right": {"cuboid": {"size": [1, 4, 1]}}, "smoothness": 0.5}}"#;
+
+        let sdf = serde_json::from_str::<JsonSdf>(json).unwrap().to_sdf();
+
+        match &sdf {
+            Sdf::Union { smoothness, .. } => assert_abs_diff_eq!(*smoothness, 0.5),
+            _ => panic!("Expected a union, got {:?}", sdf),
+        }
+        assert_abs_diff_eq!(sdf.distance(&Point::new(0.0, 3.0, 0.0)), 1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn object() {
+        let json = r#"{
+            "position": [0, 0, 5],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "sdf": {"field": {"twist": {"degrees_per_unit": 45, "field": {"cuboid": {"size": [1, 2, 1], "rounding": 0.1}}}}}
+        }"#;
+
+        let object = serde_json::from_str::<JsonObject>(json).unwrap().to_object(&JsonContext::default()).unwrap();
+        let ray = Ray::new(Point::origin(), Vector::z());
+
+        let toi = object
+            .shape
+            .as_ray_cast()
+            .unwrap()
+            .toi_with_ray(&object.transformation.isometry(), &ray, Scalar::MAX, true);
+
+        assert_abs_diff_eq!(toi.unwrap(), 4.5, epsilon = 0.001);
+    }
+
+    #[test]
+    fn unbounded_field_needs_bounds() {
+        let json = r#"{
+            "position": [0, 0, 0],
+            "texture": {"solid": {"ambient_colour": [1, 1, 1]}},
+            "sdf": {"field": {"repeat": {"period": [2, 0, 2], "field": {"sphere": {"radius": 0.5}}}}}
+        }"#;
+
+        let error = serde_json::from_str::<JsonObject>(json)
+            .unwrap()
+            .to_object(&JsonContext::default())
+            .err()
+            .unwrap();
+
+        assert!(matches!(error.downcast_ref::<ObjectError>(), Some(ObjectError::UnboundedField)));
+        let bounded = json.replace(r#""sdf": {"#, r#""sdf": {"bounds": [10, 1, 10], "#);
+        assert!(serde_json::from_str::<JsonObject>(&bounded).unwrap().to_object(&JsonContext::default()).is_ok());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::maths::consts;
+use crate::scene::io::json::JsonScalar;
+use crate::shapes::Shading;
+use serde::Deserialize;
+
+#[derive(Deserialize, Default)]
+pub struct JsonShading {
+    pub shading: Option<JsonShadingType>,
+
+    pub crease_angle_degrees: Option<JsonScalar>,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonShadingType {
+    Flat,
+    Smooth,
+}
+
+impl JsonShading {
+    /// Meshes are smooth shaded without creases unless specified otherwise.
+    pub fn to_shading(&self) -> Shading {
+        return match self.shading.unwrap_or(JsonShadingType::Smooth) {
+            JsonShadingType::Flat => Shading::Flat,
+            JsonShadingType::Smooth => Shading::Smooth {
+                crease_angle: self.crease_angle_degrees.map_or(consts::PI, |degrees| degrees.to_radians()),
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn default_is_smooth() {
+        let shading = serde_json::from_str::<JsonShading>("{}").unwrap().to_shading();
+
+        assert_eq!(shading, Shading::Smooth { crease_angle: consts::PI });
+    }
+
+    #[test]
+    fn flat() {
+        let shading = serde_json::from_str::<JsonShading>("{\"shading\": \"flat\"}").unwrap().to_shading();
+
+        assert_eq!(shading, Shading::Flat);
+    }
+
+    #[test]
+    fn crease_angle() {
+        let shading = serde_json::from_str::<JsonShading>("{\"shading\": \"smooth\", \"crease_angle_degrees\": 90}")
+            .unwrap()
+            .to_shading();
+
+        match shading {
+            Shading::Smooth { crease_angle } => assert_abs_diff_eq!(crease_angle, consts::FRAC_PI_2),
+            Shading::Flat => panic!("Expected smooth shading."),
+        }
+    }
+}
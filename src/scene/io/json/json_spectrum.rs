@@ -0,0 +1,69 @@
+use crate::image::Spectrum;
+use crate::scene::io::json::{ColourError, JsonScalar};
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+/// The spectrum of a light, either a black body or sampled at wavelengths.
+#[derive(Deserialize)]
+#[serde(try_from = "JsonSpectrumSpecification")]
+pub struct JsonSpectrum {
+    specification: JsonSpectrumSpecification,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonSpectrumSpecification {
+    Blackbody {
+        kelvin: JsonScalar,
+        #[serde(default = "default_intensity")]
+        intensity: JsonScalar,
+    },
+    /// [wavelength in nanometres, value] pairs, interpolated linearly.
+    Sampled(Vec<(JsonScalar, JsonScalar)>),
+}
+
+fn default_intensity() -> JsonScalar {
+    return 1.0;
+}
+
+impl TryFrom<JsonSpectrumSpecification> for JsonSpectrum {
+    type Error = ColourError;
+
+    fn try_from(specification: JsonSpectrumSpecification) -> Result<JsonSpectrum, ColourError> {
+        if let JsonSpectrumSpecification::Blackbody { kelvin, .. } = specification {
+            if kelvin <= 0.0 {
+                return Err(ColourError::InvalidTemperature { kelvin });
+            }
+        }
+        return Ok(JsonSpectrum { specification });
+    }
+}
+
+impl JsonSpectrum {
+    pub fn to_spectrum(&self) -> Spectrum {
+        return match &self.specification {
+            JsonSpectrumSpecification::Blackbody { kelvin, intensity } => Spectrum::blackbody(*kelvin, *intensity),
+            JsonSpectrumSpecification::Sampled(samples) => Spectrum::sampled(samples.clone()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn blackbody() {
+        let spectrum = serde_json::from_str::<JsonSpectrum>(r#"{"blackbody": {"kelvin": 6504}}"#).unwrap();
+
+        assert!(matches!(spectrum.to_spectrum(), Spectrum::Blackbody { kelvin, .. } if kelvin == 6504.0));
+    }
+
+    #[rstest(json, case(r#"{"blackbody": {"kelvin": 0}}"#), case(r#"{"blackbody": {"kelvin": -5, "intensity": 2}}"#))]
+    fn invalid_temperature(json: &str) {
+        let error = serde_json::from_str::<JsonSpectrum>(json).err().unwrap();
+
+        assert!(error.to_string().contains("Invalid black body temperature"), "{}", error);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::scene::io::json::ObjectError;
+use crate::shapes::{Subdivision, SubdivisionScheme};
+use serde::Deserialize;
+
+/// Smooths a mesh as it is loaded.
+#[derive(Deserialize)]
+pub struct JsonSubdivision {
+    pub scheme: JsonSubdivisionScheme,
+
+    /// The number of times to subdivide; defaults to 2. Each level has about four times as many faces as the last.
+    pub levels: Option<usize>,
+
+    /// Pairs of vertex indices, from zero, of edges to keep sharp.
+    #[serde(default)]
+    pub creases: Vec<[usize; 2]>,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonSubdivisionScheme {
+    CatmullClark,
+    Loop,
+}
+
+impl JsonSubdivision {
+    pub fn to_subdivision(&self) -> Result<Subdivision, ObjectError> {
+        let levels = self.levels.unwrap_or(2);
+        if levels == 0 {
+            return Err(ObjectError::NoSubdivisionLevels);
+        }
+
+        let scheme = match self.scheme {
+            JsonSubdivisionScheme::CatmullClark => SubdivisionScheme::CatmullClark,
+            JsonSubdivisionScheme::Loop => SubdivisionScheme::Loop,
+        };
+        return Ok(Subdivision::new(scheme, levels, self.creases.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let subdivision = serde_json::from_str::<JsonSubdivision>("{\"scheme\": \"catmull-clark\"}")
+            .unwrap()
+            .to_subdivision()
+            .unwrap();
+
+        assert_eq!(subdivision, Subdivision::new(SubdivisionScheme::CatmullClark, 2, vec![]));
+    }
+
+    #[test]
+    fn loop_with_creases() {
+        let json = "{\"scheme\": \"loop\", \"levels\": 3, \"creases\": [[0, 1], [1, 2]]}";
+
+        let subdivision = serde_json::from_str::<JsonSubdivision>(json).unwrap().to_subdivision().unwrap();
+
+        assert_eq!(subdivision, Subdivision::new(SubdivisionScheme::Loop, 3, vec![[0, 1], [1, 2]]));
+    }
+
+    #[test]
+    fn no_levels() {
+        let error = serde_json::from_str::<JsonSubdivision>("{\"scheme\": \"loop\", \"levels\": 0}")
+            .unwrap()
+            .to_subdivision()
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ObjectError::NoSubdivisionLevels));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::scene::io::json::{JsonMaterial, JsonScalar};
+use crate::scene::io::json::{JsonContext, JsonMaterial, JsonScalar};
 use crate::scene::Texture;
 use serde::Deserialize;
 
@@ -17,14 +17,14 @@ pub enum JsonTexture {
 }
 
 impl JsonTexture {
-    pub fn to_texture(&self) -> Texture {
+    pub fn to_texture(&self, context: &JsonContext) -> Texture {
         return match &self {
             JsonTexture::Solid { material } => Texture::Solid {
-                material: material.to_material(),
+                material: material.to_material(context),
             },
             JsonTexture::Chequerboard { material1, material2, size } => Texture::Chequerboard {
-                material1: material1.to_material(),
-                material2: material2.to_material(),
+                material1: material1.to_material(context),
+                material2: material2.to_material(context),
                 size: *size,
             },
         };
@@ -0,0 +1,93 @@
+use crate::maths::{Point, Rotation, Vector};
+use crate::scene::io::json::{JsonPoint, JsonScalar, JsonVector};
+use nalgebra::{Quaternion, Unit};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonRotation {
+    /// Rotations about the x, y and z axes, applied in that order.
+    EulerDegrees(JsonVector),
+    AxisAngle {
+        axis: JsonVector,
+        angle_degrees: JsonScalar,
+    },
+    /// As [w, x, y, z]; normalised before use.
+    Quaternion([JsonScalar; 4]),
+    /// Points the object's z axis at the target, with its y axis towards up, which defaults to the y axis.
+    LookAt {
+        target: JsonPoint,
+        up: Option<JsonVector>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum JsonScale {
+    Uniform(JsonScalar),
+    NonUniform(JsonVector),
+}
+
+impl JsonRotation {
+    /// Look at rotations need the object's position to find the direction of the target.
+    pub fn to_rotation(&self, position: &Point) -> Rotation {
+        return match self {
+            JsonRotation::EulerDegrees(angles) => {
+                let angles = angles.to_vector();
+                Rotation::from_euler_angles(angles.x.to_radians(), angles.y.to_radians(), angles.z.to_radians())
+            }
+            JsonRotation::AxisAngle { axis, angle_degrees } => Rotation::from_axis_angle(&Unit::new_normalize(axis.to_vector()), angle_degrees.to_radians()),
+            JsonRotation::Quaternion([w, x, y, z]) => Rotation::from_quaternion(Quaternion::new(*w, *x, *y, *z)),
+            JsonRotation::LookAt { target, up } => {
+                Rotation::face_towards(&(target.to_point() - position), &up.as_ref().map_or(Vector::y(), |up| up.to_vector()))
+            }
+        };
+    }
+}
+
+impl JsonScale {
+    pub fn to_scale(&self) -> Vector {
+        return match self {
+            JsonScale::Uniform(scale) => Vector::new(*scale, *scale, *scale),
+            JsonScale::NonUniform(scale) => scale.to_vector(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        json,
+        case("{\"euler_degrees\": [0, 0, 90]}"),
+        case("{\"axis_angle\": {\"axis\": [0, 0, 2], \"angle_degrees\": 90}}"),
+        case("{\"quaternion\": [1, 0, 0, 1]}"),
+        case("{\"look_at\": {\"target\": [1, 0, 1], \"up\": [-1, 0, 0]}}")
+    )]
+    fn rotation_turns_x_to_y(json: &str) {
+        let rotation = serde_json::from_str::<JsonRotation>(json).unwrap().to_rotation(&Point::new(1.0, 0.0, 0.0));
+
+        let x = rotation * Vector::x();
+        assert_abs_diff_eq!(x.x, 0.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(x.y, 1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(x.z, 0.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn look_at_points_z_at_target() {
+        let rotation = serde_json::from_str::<JsonRotation>("{\"look_at\": {\"target\": [0, 0, -5]}}")
+            .unwrap()
+            .to_rotation(&Point::new(0.0, 0.0, 5.0));
+
+        let z = rotation * Vector::z();
+        assert_abs_diff_eq!(z.z, -1.0, epsilon = 0.0001);
+    }
+
+    #[rstest(json, expected, case("2", Vector::new(2.0, 2.0, 2.0)), case("[1, 2, 3]", Vector::new(1.0, 2.0, 3.0)))]
+    fn scale(json: &str, expected: Vector) {
+        assert_eq!(serde_json::from_str::<JsonScale>(json).unwrap().to_scale(), expected);
+    }
+}
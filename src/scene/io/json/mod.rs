@@ -4,35 +4,78 @@ pub use self::json_camera::JsonCamera;
 mod json_colour;
 pub use self::json_colour::*;
 
+mod json_csg;
+pub use self::json_csg::*;
+
+mod json_displacement;
+pub use self::json_displacement::*;
+
+mod json_heightfield;
+pub use self::json_heightfield::*;
+
+mod json_instance;
+pub use self::json_instance::*;
+
 mod json_light;
 pub use self::json_light::*;
 
 mod json_material;
 pub use self::json_material::*;
 
+mod json_metaballs;
+pub use self::json_metaballs::*;
+
 mod json_object;
 pub use self::json_object::*;
 
+mod json_patches;
+pub use self::json_patches::*;
+
 mod json_point;
 pub use self::json_point::*;
 
 mod json_scene;
 pub use self::json_scene::*;
 
+mod json_sdf;
+pub use self::json_sdf::*;
+
+mod json_shading;
+pub use self::json_shading::*;
+
+mod json_spectrum;
+pub use self::json_spectrum::*;
+
+mod json_subdivision;
+pub use self::json_subdivision::*;
+
 mod json_texture;
 pub use self::json_texture::*;
 
+mod json_transformation;
+pub use self::json_transformation::*;
+
 mod json_vector;
 pub use self::json_vector::*;
 
+use crate::image::WorkingColourSpace;
+use crate::maths::Scalar;
 use crate::scene::Scene;
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+pub type JsonScalar = Scalar;
 
-pub type JsonScalar = f32;
+/// Scene wide settings needed when converting the JSON types into scene types.
+#[derive(Default)]
+pub struct JsonContext {
+    pub colour_space: WorkingColourSpace,
+    /// The directory containing the scene file, which other files are relative to.
+    pub directory: PathBuf,
+}
 
 pub fn load(path: &Path) -> Result<Scene, Box<dyn Error>> {
     let json_scene = JsonScene::load(path)?;
 
-    return Ok(json_scene.to_scene());
+    return json_scene.to_scene(path.parent().unwrap_or_else(|| Path::new("")));
 }
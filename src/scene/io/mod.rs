@@ -1 +1,18 @@
+pub mod gltf;
 pub mod json;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use crate::scene::Scene;
+use std::error::Error;
+use std::path::Path;
+
+/// Loads a scene from a glTF or GLB file, by its extension, or otherwise a JSON scene file.
+pub fn load(path: &Path) -> Result<Scene, Box<dyn Error>> {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    return match extension.as_deref() {
+        Some("gltf") | Some("glb") => gltf::load(path),
+        _ => json::load(path),
+    };
+}
@@ -0,0 +1,316 @@
+mod mtl_file;
+pub use self::mtl_file::MtlMaterial;
+
+mod obj_file;
+pub use self::obj_file::*;
+
+use crate::image::WorkingColourSpace;
+use crate::maths::Scalar;
+use crate::scene::Texture;
+use crate::shapes::{Mesh, Shading, Subdivision};
+use snafu::Snafu;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read {:?}; {}.", path, reason))]
+    CannotRead { path: PathBuf, reason: String },
+
+    #[snafu(display("Invalid line {} in {:?}; {}.", line, path, reason))]
+    InvalidLine { path: PathBuf, line: usize, reason: String },
+
+    #[snafu(display("Unable to subdivide {:?}; {}.", path, reason))]
+    CannotSubdivide { path: PathBuf, reason: String },
+
+    #[snafu(display("Unknown material {:?} used in {:?}.", name, path))]
+    UnknownMaterial { path: PathBuf, name: String },
+
+    #[snafu(display("No faces to load from {:?}.", path))]
+    NoFaces { path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error on a line of a file, before the file it came from is known.
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// A mesh loaded from an OBJ file, with a texture giving each face the material from its MTL file.
+pub struct ObjModel {
+    pub mesh: Mesh,
+    pub texture: Texture,
+}
+
+/// Loads an OBJ file and any MTL files it references, which are relative to the OBJ file. Only the faces in the given
+/// groups are loaded, or all faces if no groups are given. Faces without a material are given the default MTL one. If
+/// the faces are subdivided, their crease edges are pairs of indices of `v` lines, from zero, and the file's normals and
+/// texture coordinates are not used.
+pub fn load(path: &Path, groups: Option<&[String]>, shading: Shading, subdivision: Option<&Subdivision>, colour_space: WorkingColourSpace) -> Result<ObjModel> {
+    let file = ObjFile::parse(&read(path)?).map_err(|error| invalid_line(path, error))?;
+
+    let mut materials = vec![MtlMaterial::parse("newmtl default").unwrap().remove(0).to_material(colour_space)];
+    let mut material_indices = HashMap::new();
+    for library in &file.material_libraries {
+        let library_path = path.parent().unwrap_or_else(|| Path::new("")).join(library);
+        for material in MtlMaterial::parse(&read(&library_path)?).map_err(|error| invalid_line(&library_path, error))? {
+            material_indices.insert(material.name.clone(), materials.len());
+            materials.push(material.to_material(colour_space));
+        }
+    }
+
+    let faces: Vec<&ObjFace> = file
+        .faces
+        .iter()
+        .filter(|face| groups.is_none_or(|groups| face.groups.iter().any(|group| groups.contains(group))))
+        .collect();
+    if faces.is_empty() {
+        return Err(Error::NoFaces { path: path.to_path_buf() });
+    }
+
+    let face_materials = faces
+        .iter()
+        .map(|face| match &face.material {
+            Some(name) => material_indices.get(name).copied().ok_or_else(|| Error::UnknownMaterial {
+                path: path.to_path_buf(),
+                name: name.clone(),
+            }),
+            None => Ok(0),
+        })
+        .collect::<Result<Vec<usize>>>()?;
+
+    if let Some(subdivision) = subdivision {
+        let polygons: Vec<Vec<usize>> = faces.iter().map(|face| face.corners.iter().map(|corner| corner.position).collect()).collect();
+        let (mesh, origins) = subdivision
+            .to_mesh(&file.positions, &polygons, shading)
+            .map_err(|reason| Error::CannotSubdivide {
+                path: path.to_path_buf(),
+                reason,
+            })?;
+        return Ok(ObjModel {
+            mesh,
+            texture: Texture::PerFace {
+                materials,
+                faces: origins.iter().map(|&origin| face_materials[origin]).collect(),
+            },
+        });
+    }
+
+    // Mesh vertices have a single index, so there is one for each distinct combination of OBJ indices.
+    let mut vertices: HashMap<ObjCorner, usize> = HashMap::new();
+    let mut corners: Vec<ObjCorner> = vec![];
+    let mut indices = vec![];
+    let mut triangle_materials = vec![];
+
+    for (face, &material) in faces.iter().zip(&face_materials) {
+        let mut vertex = |corner: &ObjCorner| {
+            return *vertices.entry(*corner).or_insert_with(|| {
+                corners.push(*corner);
+                corners.len() - 1
+            });
+        };
+
+        // Polygons are split into a fan of triangles.
+        let first = vertex(&face.corners[0]);
+        for pair in face.corners[1..].windows(2) {
+            indices.push([first, vertex(&pair[0]), vertex(&pair[1])]);
+            triangle_materials.push(material);
+        }
+    }
+
+    let positions = corners.iter().map(|corner| file.positions[corner.position]).collect();
+    let normals = corners.iter().map(|corner| corner.normal.map(|normal| file.normals[normal])).collect();
+    let texture_coordinates = corners
+        .iter()
+        .map(|corner| corner.texture_coordinate.map(|coordinate| file.texture_coordinates[coordinate]))
+        .collect();
+
+    return Ok(ObjModel {
+        mesh: Mesh::new(positions, indices, normals, texture_coordinates, shading),
+        texture: Texture::PerFace {
+            materials,
+            faces: triangle_materials,
+        },
+    });
+}
+
+fn read(path: &Path) -> Result<String> {
+    return fs::read_to_string(path).map_err(|error| Error::CannotRead {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    });
+}
+
+fn invalid_line(path: &Path, error: LineError) -> Error {
+    return Error::InvalidLine {
+        path: path.to_path_buf(),
+        line: error.line,
+        reason: error.reason,
+    };
+}
+
+// Parses between minimum and maximum numbers.
+fn parse_scalars(arguments: &[&str], minimum: usize, maximum: usize) -> Result<Vec<Scalar>, String> {
+    if arguments.len() < minimum || arguments.len() > maximum {
+        return Err(match minimum == maximum {
+            true => format!("expected {} numbers", minimum),
+            false => format!("expected {} to {} numbers", minimum, maximum),
+        });
+    }
+
+    return arguments
+        .iter()
+        .map(|argument| argument.parse().map_err(|_| format!("invalid number {:?}", argument)))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Colour;
+    use crate::maths::consts::PI;
+    use crate::maths::{Isometry, Point, Ray, Vector};
+    use crate::shapes::SubdivisionScheme;
+    use ncollide3d::query::RayCast;
+
+    fn write_files(name: &str, obj: &str, mtl: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("rust-rendering-obj-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("model.obj"), obj).unwrap();
+        fs::write(directory.join("model.mtl"), mtl).unwrap();
+        return directory.join("model.obj");
+    }
+
+    const QUADS: &str = "
+mtllib model.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 2 0 0
+v 2 1 0
+g right
+f 2 5 6 3
+g left
+usemtl red
+f 1 2 3 4
+";
+
+    const MTL: &str = "
+newmtl red
+Kd 1 0 0
+";
+
+    fn smooth() -> Shading {
+        return Shading::Smooth { crease_angle: PI };
+    }
+
+    #[test]
+    fn load_quads() {
+        let path = write_files("quads", QUADS, MTL);
+
+        let model = load(&path, None, smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+        assert_eq!(model.mesh.triangles().faces().len(), 4);
+        assert_eq!(model.mesh.triangles().points().len(), 6);
+
+        match &model.texture {
+            Texture::PerFace { materials, faces } => {
+                assert_eq!(faces, &vec![0, 0, 1, 1]);
+                assert_eq!(materials[1].diffuse_colour, Colour::new(1.0, 0.0, 0.0));
+                assert_eq!(materials[0].diffuse_colour, Colour::new(0.8, 0.8, 0.8));
+            }
+            _ => panic!("Expected a per face texture."),
+        }
+
+        let ray = Ray::new(Point::new(1.5, 0.5, 1.0), -Vector::z());
+        let intersection = model.mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_eq!(
+            model
+                .texture
+                .material_at(&Point::origin(), &Point::origin(), intersection.feature)
+                .diffuse_colour,
+            Colour::new(0.8, 0.8, 0.8)
+        );
+    }
+
+    #[test]
+    fn load_subdivided() {
+        let path = write_files("subdivided", QUADS, MTL);
+        let subdivision = Subdivision::new(SubdivisionScheme::CatmullClark, 1, vec![]);
+
+        let model = load(&path, None, smooth(), Some(&subdivision), WorkingColourSpace::LinearSrgb).unwrap();
+
+        // Each quad becomes four, each split into two triangles.
+        assert_eq!(model.mesh.triangles().faces().len(), 16);
+        match &model.texture {
+            Texture::PerFace { faces, .. } => {
+                assert_eq!(faces.iter().filter(|&&material| material == 1).count(), 8);
+            }
+            _ => panic!("Expected a per face texture."),
+        }
+    }
+
+    #[test]
+    fn load_subdivided_degenerate() {
+        let path = write_files("subdivided_degenerate", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 1 2", MTL);
+        let subdivision = Subdivision::new(SubdivisionScheme::Loop, 1, vec![]);
+
+        let error = load(&path, None, smooth(), Some(&subdivision), WorkingColourSpace::LinearSrgb).err().unwrap();
+
+        assert!(matches!(error, Error::CannotSubdivide { .. }));
+    }
+
+    #[test]
+    fn load_groups() {
+        let path = write_files("groups", QUADS, MTL);
+
+        let model = load(&path, Some(&["right".to_string()]), smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+        assert_eq!(model.mesh.triangles().faces().len(), 2);
+        assert_eq!(model.mesh.triangles().points().len(), 4);
+    }
+
+    #[test]
+    fn load_no_faces() {
+        let path = write_files("no_faces", QUADS, MTL);
+
+        let error = load(&path, Some(&["missing".to_string()]), smooth(), None, WorkingColourSpace::LinearSrgb)
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, Error::NoFaces { .. }));
+    }
+
+    #[test]
+    fn load_unknown_material() {
+        let path = write_files("unknown_material", QUADS, "newmtl blue\nKd 0 0 1");
+
+        let error = load(&path, None, smooth(), None, WorkingColourSpace::LinearSrgb).err().unwrap();
+
+        assert_eq!(error.to_string(), format!("Unknown material \"red\" used in {:?}.", path));
+    }
+
+    #[test]
+    fn load_missing_library() {
+        let path = write_files("missing_library", "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3", MTL);
+
+        let error = load(&path, None, smooth(), None, WorkingColourSpace::LinearSrgb).err().unwrap();
+
+        assert!(matches!(error, Error::CannotRead { .. }));
+    }
+
+    #[test]
+    fn load_normals_and_texture_coordinates() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3/1";
+        let path = write_files("normals", obj, MTL);
+
+        let model = load(&path, None, smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+        assert!(model.mesh.triangles().uvs().is_some());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::image::{Colour, WorkingColourSpace};
+use crate::maths::Scalar;
+use crate::scene::io::obj::{parse_scalars, LineError};
+use crate::scene::{Material, RefractiveIndex};
+
+// The diffuse colour the MTL format specifies when none is given.
+const DEFAULT_DIFFUSE: Scalar = 0.8;
+
+/// A material from a Wavefront MTL file. Texture maps are not supported.
+pub struct MtlMaterial {
+    pub name: String,
+    pub ambient_colour: Option<Colour>,
+    pub diffuse_colour: Option<Colour>,
+    pub specular_colour: Option<Colour>,
+    pub specular_exponent: Option<Scalar>,
+    pub illumination_model: Option<u32>,
+    pub dissolve: Option<Scalar>,
+    pub optical_density: Option<Scalar>,
+}
+
+impl MtlMaterial {
+    fn new(name: String) -> MtlMaterial {
+        return MtlMaterial {
+            name,
+            ambient_colour: None,
+            diffuse_colour: None,
+            specular_colour: None,
+            specular_exponent: None,
+            illumination_model: None,
+            dissolve: None,
+            optical_density: None,
+        };
+    }
+
+    pub fn parse(text: &str) -> Result<Vec<MtlMaterial>, LineError> {
+        let mut materials: Vec<MtlMaterial> = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_error = |reason: String| LineError { line: index + 1, reason };
+
+            let mut tokens = line.split('#').next().unwrap().split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let arguments: Vec<&str> = tokens.collect();
+
+            if keyword == "newmtl" {
+                materials.push(MtlMaterial::new(arguments.join(" ")));
+                continue;
+            }
+
+            let material = match materials.last_mut() {
+                Some(material) => material,
+                None if is_material_statement(keyword) => return Err(line_error(format!("{} before newmtl", keyword))),
+                None => continue,
+            };
+
+            match keyword {
+                "Ka" => material.ambient_colour = Some(parse_colour(&arguments).map_err(line_error)?),
+                "Kd" => material.diffuse_colour = Some(parse_colour(&arguments).map_err(line_error)?),
+                "Ks" => material.specular_colour = Some(parse_colour(&arguments).map_err(line_error)?),
+                "Ns" => material.specular_exponent = Some(parse_scalars(&arguments, 1, 1).map_err(line_error)?[0]),
+                "Ni" => material.optical_density = Some(parse_scalars(&arguments, 1, 1).map_err(line_error)?[0]),
+                "d" => material.dissolve = Some(parse_scalars(&arguments, 1, 1).map_err(line_error)?[0]),
+                "Tr" => material.dissolve = Some(1.0 - parse_scalars(&arguments, 1, 1).map_err(line_error)?[0]),
+                "illum" => {
+                    let model = arguments
+                        .first()
+                        .and_then(|model| model.parse().ok())
+                        .ok_or_else(|| line_error("invalid illumination model".to_string()))?;
+                    material.illumination_model = Some(model);
+                }
+                _ => {}
+            }
+        }
+
+        return Ok(materials);
+    }
+
+    /// Converts to a material. Ka, Kd and Ks map to the ambient, diffuse and specular colours, Ns to the shininess,
+    /// and illumination models with ray traced reflection take their reflectivity from Ks. Colours are taken to be
+    /// linear sRGB.
+    pub fn to_material(&self, colour_space: WorkingColourSpace) -> Material {
+        let diffuse_colour = self
+            .diffuse_colour
+            .unwrap_or_else(|| Colour::new(DEFAULT_DIFFUSE, DEFAULT_DIFFUSE, DEFAULT_DIFFUSE));
+        let ambient_colour = self.ambient_colour.unwrap_or(diffuse_colour);
+        let specular_colour = self.specular_colour.unwrap_or_else(Colour::black);
+
+        let illumination_model = self.illumination_model.unwrap_or(2);
+        let reflectivity = match illumination_model {
+            3..=7 => (specular_colour.r + specular_colour.g + specular_colour.b) / 3.0,
+            _ => 0.0,
+        };
+        let shininess = match illumination_model {
+            0 | 1 => 0.0,
+            _ => self.specular_exponent.unwrap_or(0.0),
+        };
+
+        let transparency = 1.0 - self.dissolve.unwrap_or(1.0).clamp(0.0, 1.0);
+
+        return Material {
+            ambient_colour: colour_space.from_linear_srgb(&ambient_colour),
+            diffuse_colour: colour_space.from_linear_srgb(&diffuse_colour),
+            specular_colour: colour_space.from_linear_srgb(&specular_colour),
+            shininess,
+            reflectivity,
+            transparency,
+            refractive_index: self.optical_density.map_or(RefractiveIndex::default(), RefractiveIndex::Constant),
+        };
+    }
+}
+
+fn is_material_statement(keyword: &str) -> bool {
+    return matches!(keyword, "Ka" | "Kd" | "Ks" | "Ns" | "Ni" | "d" | "Tr" | "illum");
+}
+
+// Only RGB colours are supported; spectral (spectral file.rfl) and CIE XYZ (xyz x y z) colours are not.
+fn parse_colour(arguments: &[&str]) -> Result<Colour, String> {
+    let values = parse_scalars(arguments, 1, 3)?;
+
+    // A single value is used for all three channels.
+    return Ok(match values.len() {
+        1 => Colour::new(values[0], values[0], values[0]),
+        _ => Colour::new(values[0], *values.get(1).unwrap_or(&values[0]), *values.get(2).unwrap_or(&values[0])),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    const MATERIALS: &str = "
+# Two materials.
+newmtl shiny red
+Ka 0.1 0 0
+Kd 1 0 0
+Ks 0.5 0.5 0.5
+Ns 100
+illum 3
+
+newmtl glass
+Kd 0.2
+d 0.25
+Ni 1.5
+";
+
+    #[test]
+    fn parse() {
+        let materials = MtlMaterial::parse(MATERIALS).unwrap();
+
+        assert_eq!(materials.len(), 2);
+
+        let red = &materials[0];
+        assert_eq!(red.name, "shiny red");
+        assert_eq!(red.ambient_colour, Some(Colour::new(0.1, 0.0, 0.0)));
+        assert_eq!(red.diffuse_colour, Some(Colour::new(1.0, 0.0, 0.0)));
+        assert_eq!(red.specular_colour, Some(Colour::new(0.5, 0.5, 0.5)));
+        assert_eq!(red.specular_exponent, Some(100.0));
+        assert_eq!(red.illumination_model, Some(3));
+
+        let glass = &materials[1];
+        assert_eq!(glass.diffuse_colour, Some(Colour::new(0.2, 0.2, 0.2)));
+        assert_eq!(glass.dissolve, Some(0.25));
+        assert_eq!(glass.optical_density, Some(1.5));
+    }
+
+    #[test]
+    fn to_material() {
+        let materials = MtlMaterial::parse(MATERIALS).unwrap();
+
+        let red = materials[0].to_material(WorkingColourSpace::LinearSrgb);
+        assert_eq!(red.ambient_colour, Colour::new(0.1, 0.0, 0.0));
+        assert_eq!(red.diffuse_colour, Colour::new(1.0, 0.0, 0.0));
+        assert_eq!(red.specular_colour, Colour::new(0.5, 0.5, 0.5));
+        assert_abs_diff_eq!(red.shininess, 100.0);
+        assert_abs_diff_eq!(red.reflectivity, 0.5);
+        assert_abs_diff_eq!(red.transparency, 0.0);
+
+        let glass = materials[1].to_material(WorkingColourSpace::LinearSrgb);
+        assert_eq!(glass.ambient_colour, Colour::new(0.2, 0.2, 0.2));
+        assert_eq!(glass.specular_colour, Colour::black());
+        assert_abs_diff_eq!(glass.reflectivity, 0.0);
+        assert_abs_diff_eq!(glass.transparency, 0.75);
+        assert_abs_diff_eq!(glass.refractive_index.at(500.0), 1.5);
+    }
+
+    #[test]
+    fn statement_before_newmtl() {
+        let error = MtlMaterial::parse("Kd 1 1 1").err().unwrap();
+
+        assert_eq!(error.line, 1);
+        assert_eq!(error.reason, "Kd before newmtl");
+    }
+}
@@ -0,0 +1,209 @@
+use crate::maths::{Point, Scalar, Vector};
+use crate::scene::io::obj::{parse_scalars, LineError};
+use nalgebra::Point2;
+
+/// The contents of a Wavefront OBJ file. Only polygonal faces are supported; points, lines, curves and surfaces are
+/// ignored, as are smoothing groups since shading is chosen when loading.
+pub struct ObjFile {
+    pub positions: Vec<Point>,
+    pub texture_coordinates: Vec<Point2<Scalar>>,
+    pub normals: Vec<Vector>,
+    pub faces: Vec<ObjFace>,
+    pub material_libraries: Vec<String>,
+}
+
+pub struct ObjFace {
+    pub corners: Vec<ObjCorner>,
+    pub material: Option<String>,
+    pub groups: Vec<String>,
+}
+
+/// Zero based indices of the data for a corner of a face.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ObjCorner {
+    pub position: usize,
+    pub texture_coordinate: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+impl ObjFile {
+    pub fn parse(text: &str) -> Result<ObjFile, LineError> {
+        let mut file = ObjFile {
+            positions: vec![],
+            texture_coordinates: vec![],
+            normals: vec![],
+            faces: vec![],
+            material_libraries: vec![],
+        };
+
+        let mut material = None;
+        let mut groups = vec!["default".to_string()];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_error = |reason: String| LineError { line: index + 1, reason };
+
+            let mut tokens = line.split('#').next().unwrap().split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let arguments: Vec<&str> = tokens.collect();
+
+            match keyword {
+                "v" => {
+                    let values = parse_scalars(&arguments, 3, 4).map_err(line_error)?;
+                    file.positions.push(Point::new(values[0], values[1], values[2]));
+                }
+                "vt" => {
+                    let values = parse_scalars(&arguments, 1, 3).map_err(line_error)?;
+                    file.texture_coordinates.push(Point2::new(values[0], *values.get(1).unwrap_or(&0.0)));
+                }
+                "vn" => {
+                    let values = parse_scalars(&arguments, 3, 3).map_err(line_error)?;
+                    file.normals.push(Vector::new(values[0], values[1], values[2]));
+                }
+                "f" => {
+                    if arguments.len() < 3 {
+                        return Err(line_error("a face needs at least three vertices".to_string()));
+                    }
+                    let corners = arguments
+                        .iter()
+                        .map(|argument| file.parse_corner(argument))
+                        .collect::<Result<_, _>>()
+                        .map_err(line_error)?;
+                    file.faces.push(ObjFace {
+                        corners,
+                        material: material.clone(),
+                        groups: groups.clone(),
+                    });
+                }
+                "g" => {
+                    groups = match arguments.is_empty() {
+                        true => vec!["default".to_string()],
+                        false => arguments.iter().map(|group| group.to_string()).collect(),
+                    };
+                }
+                "usemtl" => material = Some(arguments.join(" ")),
+                "mtllib" => file.material_libraries.extend(arguments.iter().map(|library| library.to_string())),
+                _ => {}
+            }
+        }
+
+        return Ok(file);
+    }
+
+    // Parses v, v/vt, v//vn or v/vt/vn.
+    fn parse_corner(&self, text: &str) -> Result<ObjCorner, String> {
+        let mut parts = text.split('/');
+
+        let position = resolve_index(parts.next(), self.positions.len(), "vertex")?.ok_or_else(|| format!("{:?} has no vertex index", text))?;
+        let texture_coordinate = resolve_index(parts.next(), self.texture_coordinates.len(), "texture coordinate")?;
+        let normal = resolve_index(parts.next(), self.normals.len(), "normal")?;
+
+        return Ok(ObjCorner {
+            position,
+            texture_coordinate,
+            normal,
+        });
+    }
+}
+
+// Indices start at one, and negative indices count back from the most recent element.
+fn resolve_index(text: Option<&str>, count: usize, name: &str) -> Result<Option<usize>, String> {
+    let text = match text {
+        Some(text) if !text.is_empty() => text,
+        _ => return Ok(None),
+    };
+
+    let index: isize = text.parse().map_err(|_| format!("invalid {} index {:?}", name, text))?;
+    let resolved = match index {
+        index if index > 0 => index - 1,
+        index => count as isize + index,
+    };
+
+    if index == 0 || resolved < 0 || resolved >= count as isize {
+        return Err(format!("{} index {} is out of range", name, index));
+    }
+
+    return Ok(Some(resolved as usize));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_CORNER: &str = "
+# A comment.
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0 1.0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+
+g front
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/3/1
+g back other
+usemtl blue
+f -4//1 -2//1 -3//1
+";
+
+    #[test]
+    fn parse() {
+        let file = ObjFile::parse(CUBE_CORNER).unwrap();
+
+        assert_eq!(file.positions.len(), 4);
+        assert_eq!(file.positions[3], Point::new(0.0, 1.0, 0.0));
+        assert_eq!(file.texture_coordinates.len(), 3);
+        assert_eq!(file.normals, vec![Vector::z()]);
+        assert_eq!(file.material_libraries, vec!["materials.mtl"]);
+        assert_eq!(file.faces.len(), 2);
+
+        let quad = &file.faces[0];
+        assert_eq!(quad.corners.len(), 4);
+        assert_eq!(
+            quad.corners[1],
+            ObjCorner {
+                position: 1,
+                texture_coordinate: Some(1),
+                normal: Some(0)
+            }
+        );
+        assert_eq!(quad.material.as_deref(), Some("red"));
+        assert_eq!(quad.groups, vec!["front"]);
+
+        let triangle = &file.faces[1];
+        assert_eq!(triangle.corners.iter().map(|corner| corner.position).collect::<Vec<_>>(), vec![0, 2, 1]);
+        assert_eq!(triangle.corners[0].texture_coordinate, None);
+        assert_eq!(triangle.material.as_deref(), Some("blue"));
+        assert_eq!(triangle.groups, vec!["back", "other"]);
+    }
+
+    #[test]
+    fn faces_without_group_are_in_default() {
+        let file = ObjFile::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3").unwrap();
+
+        assert_eq!(file.faces[0].groups, vec!["default"]);
+        assert_eq!(file.faces[0].material, None);
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let error = ObjFile::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4").err().unwrap();
+
+        assert_eq!(error.line, 4);
+        assert_eq!(error.reason, "vertex index 4 is out of range");
+    }
+
+    #[test]
+    fn invalid_vertex() {
+        let error = ObjFile::parse("v 0 zero 0").err().unwrap();
+
+        assert_eq!(error.line, 1);
+        assert_eq!(error.reason, "invalid number \"zero\"");
+    }
+}
@@ -0,0 +1,577 @@
+use crate::image::{Colour, WorkingColourSpace};
+use crate::maths::{Point, Scalar, Vector};
+use crate::scene::Texture;
+use crate::shapes::{Mesh, Shading, Subdivision};
+use snafu::Snafu;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitAsciiWhitespace;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read {:?}; {}.", path, reason))]
+    CannotRead { path: PathBuf, reason: String },
+
+    #[snafu(display("Invalid PLY file {:?}; {}.", path, reason))]
+    Invalid { path: PathBuf, reason: String },
+
+    #[snafu(display("Unable to subdivide {:?}; {}.", path, reason))]
+    CannotSubdivide { path: PathBuf, reason: String },
+
+    #[snafu(display("No faces to load from {:?}.", path))]
+    NoFaces { path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A mesh loaded from a PLY file, with a texture from its vertex colours if it has them.
+pub struct PlyModel {
+    pub mesh: Mesh,
+    pub texture: Option<Texture>,
+}
+
+/// The contents of a PLY file, in either ASCII or binary format. Only vertices and faces are used; other elements are
+/// skipped.
+pub struct PlyFile {
+    pub positions: Vec<Point>,
+    pub normals: Option<Vec<Vector>>,
+    /// sRGB encoded colours from zero to one, as integer colours are scaled by their type's maximum.
+    pub colours: Option<Vec<[Scalar; 3]>>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Type {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+#[derive(Debug)]
+enum Property {
+    Value { name: String, value: Type },
+    List { name: String, count: Type, item: Type },
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// The values of the elements after the header, read one at a time.
+enum Data<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], little_endian: bool },
+}
+
+/// Loads a PLY file. Polygons are split into a fan of triangles, or subdivided, when the file's normals are not used.
+/// Vertex colours, if there are any, are interpolated across each polygon to give it a matte material.
+pub fn load(path: &Path, shading: Shading, subdivision: Option<&Subdivision>, colour_space: WorkingColourSpace) -> Result<PlyModel> {
+    let bytes = fs::read(path).map_err(|error| Error::CannotRead {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    })?;
+    let invalid = |reason: String| Error::Invalid {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let file = PlyFile::parse(&bytes).map_err(invalid)?;
+
+    let mut indices = vec![];
+    let mut polygons = vec![];
+    for (polygon, face) in file.faces.iter().enumerate() {
+        if let Some(index) = face.iter().find(|&&index| index >= file.positions.len()) {
+            return Err(invalid(format!("vertex {} of a face is out of range", index)));
+        }
+        for pair in face[1..].windows(2) {
+            indices.push([face[0], pair[0], pair[1]]);
+            polygons.push(polygon);
+        }
+    }
+    if indices.is_empty() {
+        return Err(Error::NoFaces { path: path.to_path_buf() });
+    }
+
+    if let Some(subdivision) = subdivision {
+        let (mesh, origins) = subdivision
+            .to_mesh(&file.positions, &file.faces, shading)
+            .map_err(|reason| Error::CannotSubdivide {
+                path: path.to_path_buf(),
+                reason,
+            })?;
+        let texture = vertex_colours(&file, origins, colour_space);
+        return Ok(PlyModel { mesh, texture });
+    }
+
+    let texture = vertex_colours(&file, polygons, colour_space);
+    return Ok(PlyModel {
+        mesh: Mesh::new(file.positions, indices, file.normals, None, shading),
+        texture,
+    });
+}
+
+// The file's vertex colours, if it has any, on its polygons, with the polygon each face of the mesh came from.
+fn vertex_colours(file: &PlyFile, faces: Vec<usize>, colour_space: WorkingColourSpace) -> Option<Texture> {
+    let colours = file.colours.as_ref()?;
+    return Some(Texture::PerVertex {
+        positions: file.positions.clone(),
+        colours: colours
+            .iter()
+            .map(|&[r, g, b]| colour_space.from_linear_srgb(&Colour::from_srgb(r, g, b)))
+            .collect(),
+        polygons: file.faces.clone(),
+        faces,
+    });
+}
+
+impl PlyFile {
+    pub fn parse(bytes: &[u8]) -> Result<PlyFile, String> {
+        let (format, elements, header_length) = parse_header(bytes)?;
+        let body = &bytes[header_length..];
+        let mut data = match format {
+            Format::Ascii => Data::Ascii(
+                std::str::from_utf8(body)
+                    .map_err(|_| "invalid ASCII data".to_string())?
+                    .split_ascii_whitespace(),
+            ),
+            Format::BinaryLittleEndian => Data::Binary {
+                bytes: body,
+                little_endian: true,
+            },
+            Format::BinaryBigEndian => Data::Binary {
+                bytes: body,
+                little_endian: false,
+            },
+        };
+
+        let mut file = PlyFile {
+            positions: vec![],
+            normals: None,
+            colours: None,
+            faces: vec![],
+        };
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => file.read_vertices(element, &mut data)?,
+                "face" => file.read_faces(element, &mut data)?,
+                _ => {
+                    for _ in 0..element.count {
+                        for property in &element.properties {
+                            data.skip(property)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        return Ok(file);
+    }
+
+    fn read_vertices(&mut self, element: &Element, data: &mut Data) -> Result<(), String> {
+        let find = |names: &[&str]| element.properties.iter().position(|property| names.contains(&property.name()));
+        let find_all = |names: [&[&str]; 3]| -> Option<[usize; 3]> {
+            let [x, y, z] = names.map(find);
+            return Some([x?, y?, z?]);
+        };
+        let position = find_all([&["x"], &["y"], &["z"]]).ok_or_else(|| "vertices need x, y and z".to_string())?;
+        let normal = find_all([&["nx"], &["ny"], &["nz"]]);
+        let colour = find_all([&["red", "diffuse_red"], &["green", "diffuse_green"], &["blue", "diffuse_blue"]]);
+
+        let mut normals = vec![];
+        let mut colours = vec![];
+        let mut values = vec![0.0; element.properties.len()];
+        for _ in 0..element.count {
+            for (value, property) in values.iter_mut().zip(&element.properties) {
+                *value = match property {
+                    Property::Value { value, .. } => data.read(*value)?,
+                    Property::List { .. } => {
+                        data.skip(property)?;
+                        0.0
+                    }
+                };
+            }
+
+            let [x, y, z] = position.map(|index| values[index] as Scalar);
+            self.positions.push(Point::new(x, y, z));
+            if let Some([x, y, z]) = normal.map(|normal| normal.map(|index| values[index] as Scalar)) {
+                normals.push(Vector::new(x, y, z));
+            }
+            if let Some(colour) = colour {
+                colours.push(colour.map(|index| {
+                    let value = values[index] as Scalar;
+                    return match &element.properties[index] {
+                        Property::Value { value: kind, .. } => value / kind.maximum(),
+                        Property::List { .. } => 0.0,
+                    };
+                }));
+            }
+        }
+
+        self.normals = normal.map(|_| normals);
+        self.colours = colour.map(|_| colours);
+        return Ok(());
+    }
+
+    fn read_faces(&mut self, element: &Element, data: &mut Data) -> Result<(), String> {
+        let indices = element
+            .properties
+            .iter()
+            .position(|property| matches!(property, Property::List { name, .. } if name == "vertex_indices" || name == "vertex_index"))
+            .ok_or_else(|| "faces need a list of vertex indices".to_string())?;
+
+        for _ in 0..element.count {
+            for (index, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::List { count, item, .. } if index == indices => {
+                        let length = data.read(*count)? as usize;
+                        if length < 3 {
+                            return Err(format!("a face has {} vertices", length));
+                        }
+                        let face = (0..length).map(|_| Ok(data.read(*item)? as usize)).collect::<Result<_, String>>()?;
+                        self.faces.push(face);
+                    }
+                    _ => data.skip(property)?,
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+// The format, the elements, and the length of the header in bytes.
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), String> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or_else(|| "no end_header".to_string())?;
+    let length = bytes[end..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |newline| end + newline + 1);
+    let header = std::str::from_utf8(&bytes[..end]).map_err(|_| "invalid header".to_string())?;
+
+    let mut lines = header.lines().map(|line| line.split_whitespace().collect::<Vec<&str>>());
+    if lines.next().as_deref() != Some(&["ply"]) {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for tokens in lines {
+        match tokens.as_slice() {
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(format!("unknown format {:?}", name)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| format!("invalid count {:?}", count))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or_else(|| "property before element".to_string())?;
+                element.properties.push(Property::List {
+                    name: name.to_string(),
+                    count: Type::parse(count)?,
+                    item: Type::parse(item)?,
+                });
+            }
+            ["property", value, name] => {
+                let element = elements.last_mut().ok_or_else(|| "property before element".to_string())?;
+                element.properties.push(Property::Value {
+                    name: name.to_string(),
+                    value: Type::parse(value)?,
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("invalid header line {:?}", tokens.join(" "))),
+        }
+    }
+
+    return Ok((format.ok_or_else(|| "no format".to_string())?, elements, length));
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        return match self {
+            Property::Value { name, .. } | Property::List { name, .. } => name,
+        };
+    }
+}
+
+impl Type {
+    fn parse(name: &str) -> Result<Type, String> {
+        return Ok(match name {
+            "char" | "int8" => Type::Int8,
+            "uchar" | "uint8" => Type::UInt8,
+            "short" | "int16" => Type::Int16,
+            "ushort" | "uint16" => Type::UInt16,
+            "int" | "int32" => Type::Int32,
+            "uint" | "uint32" => Type::UInt32,
+            "float" | "float32" => Type::Float32,
+            "double" | "float64" => Type::Float64,
+            _ => return Err(format!("unknown type {:?}", name)),
+        });
+    }
+
+    fn size(self) -> usize {
+        return match self {
+            Type::Int8 | Type::UInt8 => 1,
+            Type::Int16 | Type::UInt16 => 2,
+            Type::Int32 | Type::UInt32 | Type::Float32 => 4,
+            Type::Float64 => 8,
+        };
+    }
+
+    // The value colours of the type are scaled by: the largest value of integer types, or one for floating point.
+    fn maximum(self) -> Scalar {
+        return match self {
+            Type::Int8 => i8::MAX as Scalar,
+            Type::UInt8 => u8::MAX as Scalar,
+            Type::Int16 => i16::MAX as Scalar,
+            Type::UInt16 => u16::MAX as Scalar,
+            Type::Int32 => i32::MAX as Scalar,
+            Type::UInt32 => u32::MAX as Scalar,
+            Type::Float32 | Type::Float64 => 1.0,
+        };
+    }
+}
+
+impl Data<'_> {
+    fn read(&mut self, value: Type) -> Result<f64, String> {
+        return match self {
+            Data::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(|| "unexpected end of data".to_string())?;
+                token.parse().map_err(|_| format!("invalid number {:?}", token))
+            }
+            Data::Binary { bytes, little_endian } => {
+                if bytes.len() < value.size() {
+                    return Err("unexpected end of data".to_string());
+                }
+                let (value_bytes, rest) = bytes.split_at(value.size());
+                *bytes = rest;
+
+                let mut buffer = [0u8; 8];
+                buffer[..value.size()].copy_from_slice(value_bytes);
+                if !*little_endian {
+                    buffer[..value.size()].reverse();
+                }
+                let [a, b, c, d, ..] = buffer;
+                Ok(match value {
+                    Type::Int8 => a as i8 as f64,
+                    Type::UInt8 => a as f64,
+                    Type::Int16 => i16::from_le_bytes([a, b]) as f64,
+                    Type::UInt16 => u16::from_le_bytes([a, b]) as f64,
+                    Type::Int32 => i32::from_le_bytes([a, b, c, d]) as f64,
+                    Type::UInt32 => u32::from_le_bytes([a, b, c, d]) as f64,
+                    Type::Float32 => f32::from_le_bytes([a, b, c, d]) as f64,
+                    Type::Float64 => f64::from_le_bytes(buffer),
+                })
+            }
+        };
+    }
+
+    fn skip(&mut self, property: &Property) -> Result<(), String> {
+        match property {
+            Property::Value { value, .. } => {
+                self.read(*value)?;
+            }
+            Property::List { count, item, .. } => {
+                for _ in 0..self.read(*count)? as usize {
+                    self.read(*item)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts::PI;
+    use crate::maths::{Isometry, Ray};
+    use crate::shapes::SubdivisionScheme;
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+
+    fn write_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust-rendering-ply-{}-{}.ply", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        return path;
+    }
+
+    fn smooth() -> Shading {
+        return Shading::Smooth { crease_angle: PI };
+    }
+
+    // A unit square in the x–y plane from a quad, with a red and a blue side.
+    const ASCII: &str = "ply
+format ascii 1.0
+comment A square
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 0 0 1 255 0 0
+1 0 0 0 0 1 255 0 0
+1 1 0 0 0 1 0 0 255
+0 1 0 0 0 1 0 0 255
+4 0 1 2 3
+0 2
+";
+
+    // The same square, in binary, without normals and with an extra list property on each vertex.
+    fn binary(little_endian: bool) -> Vec<u8> {
+        let format = if little_endian { "binary_little_endian" } else { "binary_big_endian" };
+        let header = format!(
+            "ply\nformat {} 1.0\nelement vertex 4\nproperty double x\nproperty double y\nproperty double z\nproperty list uchar short tags\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty uchar flags\nproperty list uchar uint vertex_index\nend_header\n",
+            format
+        );
+        let mut bytes = header.into_bytes();
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        for (index, position) in positions.iter().enumerate() {
+            for &coordinate in position {
+                let coordinate: f64 = coordinate;
+                bytes.extend_from_slice(&if little_endian { coordinate.to_le_bytes() } else { coordinate.to_be_bytes() });
+            }
+            bytes.push(1);
+            bytes.extend_from_slice(&if little_endian { 7i16.to_le_bytes() } else { 7i16.to_be_bytes() });
+            bytes.extend_from_slice(if index < 2 { &[255, 0, 0] } else { &[0, 0, 255] });
+        }
+        bytes.extend_from_slice(&[0, 4]);
+        for index in 0..4u32 {
+            bytes.extend_from_slice(&if little_endian { index.to_le_bytes() } else { index.to_be_bytes() });
+        }
+        return bytes;
+    }
+
+    fn check_square(model: &PlyModel) {
+        assert_eq!(model.mesh.triangles().faces().len(), 2);
+        let ray = Ray::new(Point::new(0.9, 0.1, 1.0), -Vector::z());
+        let intersection = model.mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_abs_diff_eq!(intersection.toi, 1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal, Vector::z(), epsilon = 0.0001);
+
+        // The square is red along the bottom and blue along the top, decoded from sRGB then interpolated.
+        let texture = model.texture.as_ref().unwrap();
+        for (y, expected) in [
+            (0.0, Colour::new(1.0, 0.0, 0.0)),
+            (0.25, Colour::new(0.75, 0.0, 0.25)),
+            (1.0, Colour::new(0.0, 0.0, 1.0)),
+        ] {
+            let point = Point::new(0.9, y, 0.0);
+            let colour = texture.material_at(&point, &point, intersection.feature).diffuse_colour;
+            assert_abs_diff_eq!(colour.r, expected.r, epsilon = 0.0001);
+            assert_abs_diff_eq!(colour.g, 0.0, epsilon = 0.0001);
+            assert_abs_diff_eq!(colour.b, expected.b, epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn load_ascii() {
+        let path = write_file("ascii", ASCII.as_bytes());
+
+        let model = load(&path, smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+        check_square(&model);
+    }
+
+    #[test]
+    fn load_binary() {
+        for little_endian in [true, false] {
+            let path = write_file(&format!("binary-{}", little_endian), &binary(little_endian));
+
+            let model = load(&path, smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+            check_square(&model);
+        }
+    }
+
+    #[test]
+    fn subdivided_colours_are_interpolated() {
+        let path = write_file("subdivided", ASCII.as_bytes());
+        let subdivision = Subdivision::new(SubdivisionScheme::CatmullClark, 2, vec![]);
+
+        let model = load(&path, smooth(), Some(&subdivision), WorkingColourSpace::LinearSrgb).unwrap();
+
+        // The square's edges are boundaries so it stays flat, and its faces are smaller than the polygon they came from.
+        let ray = Ray::new(Point::new(0.5, 0.25, 1.0), -Vector::z());
+        let intersection = model.mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        let point = ray.point_at(intersection.toi);
+        let colour = model.texture.unwrap().material_at(&point, &point, intersection.feature).diffuse_colour;
+        assert_abs_diff_eq!(colour.r, 0.75, epsilon = 0.0001);
+        assert_abs_diff_eq!(colour.b, 0.25, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn parse_normals_and_colours() {
+        let file = PlyFile::parse(ASCII.as_bytes()).unwrap();
+
+        assert_eq!(file.positions.len(), 4);
+        assert_eq!(file.normals.unwrap()[2], Vector::z());
+        assert_eq!(file.colours.unwrap()[3], [0.0, 0.0, 1.0]);
+        assert_eq!(file.faces, vec![vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn without_colours() {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        let path = write_file("without_colours", ply.as_bytes());
+
+        let model = load(&path, smooth(), None, WorkingColourSpace::LinearSrgb).unwrap();
+
+        assert!(model.texture.is_none());
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n";
+        let path = write_file("index_out_of_range", ply.as_bytes());
+
+        let error = load(&path, smooth(), None, WorkingColourSpace::LinearSrgb).err().unwrap();
+
+        assert_eq!(error.to_string(), format!("Invalid PLY file {:?}; vertex 3 of a face is out of range.", path));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = binary(true);
+
+        let error = PlyFile::parse(&bytes[..bytes.len() - 2]).err().unwrap();
+
+        assert_eq!(error, "unexpected end of data");
+    }
+}
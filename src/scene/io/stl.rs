@@ -0,0 +1,237 @@
+use crate::maths::{Point, Scalar};
+use crate::shapes::{Mesh, Shading, Subdivision};
+use snafu::Snafu;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The header of a binary file, before the number of triangles.
+const HEADER_LENGTH: usize = 80;
+
+// Each triangle of a binary file is a normal and three vertices of three 32 bit floats, then a 16 bit attribute.
+const TRIANGLE_LENGTH: usize = 50;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read {:?}; {}.", path, reason))]
+    CannotRead { path: PathBuf, reason: String },
+
+    #[snafu(display("Invalid STL file {:?}; {}.", path, reason))]
+    Invalid { path: PathBuf, reason: String },
+
+    #[snafu(display("Unable to subdivide {:?}; {}.", path, reason))]
+    CannotSubdivide { path: PathBuf, reason: String },
+
+    #[snafu(display("No faces to load from {:?}.", path))]
+    NoFaces { path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Loads an STL file, in either ASCII or binary format. STL files list each triangle's corners separately, so corners
+/// at the same position are joined into one vertex, for smooth shading. The normals in the file are ignored, as they
+/// are often missing or wrong. Crease edges of a subdivision are pairs of indices of these joined vertices, in the
+/// order they first appear.
+pub fn load(path: &Path, shading: Shading, subdivision: Option<&Subdivision>) -> Result<Mesh> {
+    let bytes = fs::read(path).map_err(|error| Error::CannotRead {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    })?;
+    let triangles = parse(&bytes).map_err(|reason| Error::Invalid {
+        path: path.to_path_buf(),
+        reason,
+    })?;
+    if triangles.is_empty() {
+        return Err(Error::NoFaces { path: path.to_path_buf() });
+    }
+
+    let mut positions = vec![];
+    let mut vertices = HashMap::new();
+    let indices: Vec<[usize; 3]> = triangles
+        .iter()
+        .map(|triangle| {
+            return triangle.map(|corner| {
+                // Adding zero turns negative zeros positive, so they match.
+                let key = corner.map(|coordinate| (coordinate + 0.0).to_bits());
+                return *vertices.entry(key).or_insert_with(|| {
+                    positions.push(Point::from(corner));
+                    positions.len() - 1
+                });
+            });
+        })
+        .collect();
+
+    if let Some(subdivision) = subdivision {
+        let (mesh, _) = subdivision.to_mesh(&positions, &indices, shading).map_err(|reason| Error::CannotSubdivide {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+        return Ok(mesh);
+    }
+
+    return Ok(Mesh::new(positions, indices, None, None, shading));
+}
+
+/// The corners of each triangle of an STL file.
+pub fn parse(bytes: &[u8]) -> Result<Vec<[[Scalar; 3]; 3]>, String> {
+    // Binary files can start with "solid" too, so check whether the length matches the number of triangles first.
+    if bytes.len() >= HEADER_LENGTH + 4 {
+        let count = u32::from_le_bytes(bytes[HEADER_LENGTH..HEADER_LENGTH + 4].try_into().unwrap()) as usize;
+        if bytes.len() == HEADER_LENGTH + 4 + count * TRIANGLE_LENGTH {
+            return Ok(parse_binary(&bytes[HEADER_LENGTH + 4..]));
+        }
+    }
+
+    return match bytes.starts_with(b"solid") {
+        true => parse_ascii(std::str::from_utf8(bytes).map_err(|_| "invalid ASCII file".to_string())?),
+        false => Err("the length does not match the number of triangles".to_string()),
+    };
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<[[Scalar; 3]; 3]> {
+    let float = |offset: usize| f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as Scalar;
+
+    return (0..bytes.len() / TRIANGLE_LENGTH)
+        .map(|triangle| {
+            // Skipping the normal.
+            let start = triangle * TRIANGLE_LENGTH + 12;
+            return [0, 1, 2].map(|corner| [0, 1, 2].map(|axis| float(start + corner * 12 + axis * 4)));
+        })
+        .collect();
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<[[Scalar; 3]; 3]>, String> {
+    let mut triangles = vec![];
+    let mut corners = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let line_error = |reason: &str| format!("{} on line {}", reason, index + 1);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["vertex", x, y, z] => {
+                let corner = [x, y, z].map(|coordinate| coordinate.parse::<Scalar>());
+                match corner {
+                    [Ok(x), Ok(y), Ok(z)] => corners.push([x, y, z]),
+                    _ => return Err(line_error("invalid vertex")),
+                }
+            }
+            ["endloop"] => {
+                if corners.len() != 3 {
+                    return Err(line_error("expected three vertices"));
+                }
+                triangles.push([corners[0], corners[1], corners[2]]);
+                corners.clear();
+            }
+            ["vertex", ..] => return Err(line_error("expected three numbers")),
+            _ => {}
+        }
+    }
+
+    return Ok(triangles);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts::PI;
+    use crate::maths::{Isometry, Ray, Vector};
+    use crate::shapes::SubdivisionScheme;
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+
+    fn write_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust-rendering-stl-{}-{}.stl", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        return path;
+    }
+
+    // Two triangles of a unit square in the x–y plane, sharing an edge.
+    const ASCII: &str = "solid square
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    fn binary() -> Vec<u8> {
+        // Starting with "solid", as some exporters do.
+        let mut bytes = b"solid binary".to_vec();
+        bytes.resize(HEADER_LENGTH, 0);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        let triangles: [[f32; 9]; 2] = [[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0]];
+        for triangle in triangles {
+            bytes.extend_from_slice(&[0; 12]);
+            for coordinate in triangle {
+                bytes.extend_from_slice(&coordinate.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0; 2]);
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn load_ascii_and_binary() {
+        for (name, contents) in [("ascii", ASCII.as_bytes().to_vec()), ("binary", binary())] {
+            let path = write_file(name, &contents);
+
+            let mesh = load(&path, Shading::Smooth { crease_angle: PI }, None).unwrap();
+
+            assert_eq!(mesh.triangles().faces().len(), 2);
+            // The corners on the shared edge are joined.
+            assert_eq!(mesh.triangles().points().len(), 4);
+            let ray = Ray::new(Point::new(0.2, 0.7, 2.0), -Vector::z());
+            let intersection = mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+            assert_abs_diff_eq!(intersection.toi, 2.0, epsilon = 0.0001);
+            assert_abs_diff_eq!(intersection.normal, Vector::z(), epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn crease_out_of_range() {
+        let path = write_file("crease", ASCII.as_bytes());
+        let subdivision = Subdivision::new(SubdivisionScheme::Loop, 1, vec![[0, 4]]);
+
+        let error = load(&path, Shading::Flat, Some(&subdivision)).err().unwrap();
+
+        assert!(matches!(error, Error::CannotSubdivide { .. }), "{}", error);
+    }
+
+    #[test]
+    fn truncated_binary() {
+        let bytes = binary();
+
+        let error = parse(&bytes[5..bytes.len() - 10]).err().unwrap();
+
+        assert_eq!(error, "the length does not match the number of triangles");
+    }
+
+    #[test]
+    fn missing_vertex() {
+        let error = parse(b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\n")
+            .err()
+            .unwrap();
+
+        assert_eq!(error, "expected three vertices on line 6");
+    }
+
+    #[test]
+    fn empty() {
+        let path = write_file("empty", b"solid empty\nendsolid empty\n");
+
+        let error = load(&path, Shading::Flat, None).err().unwrap();
+
+        assert!(matches!(error, Error::NoFaces { .. }));
+    }
+}
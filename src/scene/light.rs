@@ -1,4 +1,4 @@
-use crate::image::Colour;
+use crate::image::{Colour, Spectrum, WorkingColourSpace};
 use crate::maths::ray;
 use crate::maths::{sphere, Isometry, Point, Ray, Scalar};
 use crate::scene::Attenuation;
@@ -9,6 +9,7 @@ use std::iter::once;
 pub struct Light {
     pub position: Point,
     pub colour: Colour,
+    pub spectrum: Option<Spectrum>,
     pub shape: LightShape,
     pub attenuation: Attenuation,
     pub sample_factor: Scalar,
@@ -64,6 +65,7 @@ impl Light {
         return Light {
             position,
             colour,
+            spectrum: None,
             shape,
             attenuation,
             sample_factor: 1.0 / sample_count as Scalar,
@@ -72,9 +74,17 @@ impl Light {
         };
     }
 
+    /// Gives the light a spectral power distribution. The colour is replaced by the spectrum's RGB equivalent in the
+    /// given colour space so non-spectral rendering still matches.
+    pub fn with_spectrum(mut self, spectrum: Spectrum, colour_space: WorkingColourSpace) -> Light {
+        self.colour = spectrum.to_colour(&colour_space.primaries());
+        self.spectrum = Some(spectrum);
+        return self;
+    }
+
     pub fn sample_rays_to<'a>(&'a self, point: &'a Point) -> Box<dyn Iterator<Item = Ray> + 'a> {
         return match &self.cached_samples {
-            Some(samples) => Box::new(samples.iter().map(move |sample| ray::between(&sample, point))),
+            Some(samples) => Box::new(samples.iter().map(move |sample| ray::between(sample, point))),
             None => Box::new(self.shape.surface_points(&self.transformation).map(move |sample| ray::between(&sample, point))),
         };
     }
@@ -1,12 +1,16 @@
 use crate::image::Colour;
 use crate::maths::Scalar;
+use crate::scene::RefractiveIndex;
 
+#[derive(Clone)]
 pub struct Material {
     pub ambient_colour: Colour,
     pub diffuse_colour: Colour,
     pub specular_colour: Colour,
     pub shininess: Scalar,
     pub reflectivity: Scalar,
+    pub transparency: Scalar,
+    pub refractive_index: RefractiveIndex,
 }
 
 impl Material {
@@ -17,6 +21,8 @@ impl Material {
             specular_colour: colour,
             shininess: 0.0,
             reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: RefractiveIndex::default(),
         };
     }
 }
@@ -1,6 +1,9 @@
 mod attenuation;
 pub use self::attenuation::Attenuation;
 
+mod bvh;
+pub use self::bvh::Bvh;
+
 mod camera;
 pub use self::camera::Camera;
 
@@ -13,6 +16,10 @@ pub use self::material::Material;
 mod object;
 pub use self::object::Object;
 
+mod refractive_index;
+pub use self::refractive_index::*;
+
+#[allow(clippy::module_inception)]
 mod scene;
 pub use self::scene::*;
 
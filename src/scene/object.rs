@@ -1,17 +1,21 @@
-use crate::maths::{Isometry, Plane, Point, Scalar, Sphere, Vector};
+use crate::maths::{Plane, Point, Scalar, Sphere, Transformation, Vector};
 use crate::scene::Texture;
+use crate::shapes::{Capsule, Cone, Cylinder, Disc, Mesh, Rectangle, Scaled, Shading, Torus};
 use nalgebra::Unit;
-use ncollide3d::shape::{Shape, ShapeHandle};
+use ncollide3d::shape::{Cuboid, Shape, ShapeHandle};
 use std::ptr;
+use std::sync::Arc;
 
+#[allow(clippy::manual_non_exhaustive)]
 pub struct Object {
     pub shape: ShapeHandle<Scalar>,
 
     pub position: Point,
 
-    pub texture: Texture,
+    /// Shared between instances of the same prototype.
+    pub texture: Arc<Texture>,
 
-    pub transformation: Isometry,
+    pub transformation: Transformation,
 
     _private: (),
 }
@@ -21,8 +25,8 @@ impl Object {
         Object {
             shape: ShapeHandle::new(shape),
             position,
-            texture,
-            transformation: Isometry::translation(position.x, position.y, position.z),
+            texture: Arc::new(texture),
+            transformation: Transformation::translation(position),
             _private: (),
         }
     }
@@ -34,6 +38,83 @@ impl Object {
     pub fn new_plane(centre: Point, normal: Vector, texture: Texture) -> Object {
         return Object::new(Plane::new(Unit::new_normalize(normal)), centre, texture);
     }
+
+    /// An axis aligned box; `size` is its width, height and depth.
+    pub fn new_cuboid(centre: Point, size: Vector, texture: Texture) -> Object {
+        return Object::new(Cuboid::new(size / 2.0), centre, texture);
+    }
+
+    /// A cylinder with flat caps along the y axis.
+    pub fn new_cylinder(centre: Point, height: Scalar, radius: Scalar, texture: Texture) -> Object {
+        return Object::new(Cylinder::new(height / 2.0, radius), centre, texture);
+    }
+
+    /// A cone along the y axis, pointing up, with `centre` half way between its base and apex.
+    pub fn new_cone(centre: Point, height: Scalar, radius: Scalar, texture: Texture) -> Object {
+        return Object::new(Cone::new(height / 2.0, radius), centre, texture);
+    }
+
+    /// A capsule along the y axis; `height` is the length of the cylinder between its hemispherical ends.
+    pub fn new_capsule(centre: Point, height: Scalar, radius: Scalar, texture: Texture) -> Object {
+        return Object::new(Capsule::new(height / 2.0, radius), centre, texture);
+    }
+
+    /// A disc facing up the y axis.
+    pub fn new_disc(centre: Point, radius: Scalar, texture: Texture) -> Object {
+        return Object::new(Disc::new(radius), centre, texture);
+    }
+
+    /// A rectangle facing up the y axis; `width` is along the x axis and `depth` along the z axis.
+    pub fn new_rectangle(centre: Point, width: Scalar, depth: Scalar, texture: Texture) -> Object {
+        return Object::new(Rectangle::new(width / 2.0, depth / 2.0), centre, texture);
+    }
+
+    /// A torus around the y axis; `major_radius` is from its centre to the middle of the tube.
+    pub fn new_torus(centre: Point, major_radius: Scalar, minor_radius: Scalar, texture: Texture) -> Object {
+        return Object::new(Torus::new(major_radius, minor_radius), centre, texture);
+    }
+
+    /// A triangle mesh; `positions` are relative to `position`, and each entry in `indices` gives the vertices of a
+    /// triangle. Normals, if given, are per vertex.
+    pub fn new_mesh(
+        position: Point,
+        positions: Vec<Point>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vector>>,
+        shading: Shading,
+        texture: Texture,
+    ) -> Object {
+        return Object::new(Mesh::new(positions, indices, normals, None, shading), position, texture);
+    }
+
+    /// A copy of the object sharing its shape, and its texture unless another is given, moved by `transformation` on
+    /// top of its own transformation. Returns `None` if they can't be combined; see `Transformation::then`.
+    pub fn instance(&self, transformation: &Transformation, texture: Option<Arc<Texture>>) -> Option<Object> {
+        let combined = self.transformation.then(transformation)?;
+        let object = Object {
+            shape: self.shape.clone(),
+            position: self.position,
+            texture: texture.unwrap_or_else(|| self.texture.clone()),
+            transformation: self.transformation,
+            _private: (),
+        };
+        return Some(object.with_transformation(combined));
+    }
+
+    /// Replaces the object's position with a transformation, which can also rotate and scale it.
+    pub fn with_transformation(mut self, transformation: Transformation) -> Object {
+        // Unwrap any previous scaling so it isn't applied on top of the new one.
+        if let Some(scaled) = self.shape.as_shape::<Scaled>() {
+            self.shape = scaled.shape().clone();
+        }
+        if transformation.is_scaled() {
+            self.shape = ShapeHandle::new(Scaled::new(self.shape, transformation.scale));
+        }
+
+        self.position = Point::from(transformation.translation);
+        self.transformation = transformation;
+        return self;
+    }
 }
 
 impl PartialEq for Object {
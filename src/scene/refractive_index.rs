@@ -0,0 +1,80 @@
+use crate::maths::Scalar;
+
+/// Wavelength in nanometres used when rendering without spectral sampling; the sodium D line that refractive indices
+/// are usually quoted at.
+pub const REFERENCE_WAVELENGTH: Scalar = 589.3;
+
+#[derive(Clone)]
+pub enum RefractiveIndex {
+    Constant(Scalar),
+    /// n = a + b / λ², with λ in micrometres.
+    Cauchy {
+        a: Scalar,
+        b: Scalar,
+    },
+    /// n² = 1 + Σ bᵢλ² / (λ² - cᵢ), with λ in micrometres.
+    Sellmeier {
+        b: [Scalar; 3],
+        c: [Scalar; 3],
+    },
+}
+
+impl RefractiveIndex {
+    pub fn at(&self, wavelength: Scalar) -> Scalar {
+        let micrometres = wavelength / 1000.0;
+        let squared = micrometres * micrometres;
+
+        return match self {
+            RefractiveIndex::Constant(n) => *n,
+            RefractiveIndex::Cauchy { a, b } => a + b / squared,
+            RefractiveIndex::Sellmeier { b, c } => {
+                let sum: Scalar = b.iter().zip(c.iter()).map(|(b, c)| b * squared / (squared - c)).sum();
+                (1.0 + sum).sqrt()
+            }
+        };
+    }
+
+    /// Whether the index varies with wavelength, i.e. whether refracted rays separate into colours.
+    pub fn is_dispersive(&self) -> bool {
+        return !matches!(self, RefractiveIndex::Constant(_));
+    }
+}
+
+impl Default for RefractiveIndex {
+    fn default() -> RefractiveIndex {
+        return RefractiveIndex::Constant(1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn bk7() -> RefractiveIndex {
+        return RefractiveIndex::Sellmeier {
+            b: [1.039_612, 0.231_792_3, 1.010_47],
+            c: [0.006_000_7, 0.020_017_9, 103.560_65],
+        };
+    }
+
+    #[rstest(wavelength, expected, case(REFERENCE_WAVELENGTH, 1.5168), case(486.1, 1.5224), case(656.3, 1.5143))]
+    fn sellmeier(wavelength: Scalar, expected: Scalar) {
+        assert_abs_diff_eq!(bk7().at(wavelength), expected, epsilon = 0.0005);
+    }
+
+    #[test]
+    fn cauchy() {
+        let index = RefractiveIndex::Cauchy { a: 1.5, b: 0.005 };
+
+        assert_abs_diff_eq!(index.at(500.0), 1.52, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn is_dispersive() {
+        assert!(!RefractiveIndex::Constant(1.5).is_dispersive());
+        assert!(RefractiveIndex::Cauchy { a: 1.5, b: 0.005 }.is_dispersive());
+        assert!(bk7().is_dispersive());
+    }
+}
@@ -1,18 +1,34 @@
-use crate::image::Colour;
-use crate::maths::{vector, Point, Ray, Scalar, Vector};
-use crate::scene::{Camera, Light, Object};
+use crate::image::{Colour, WorkingColourSpace};
+use crate::maths::packet::{Lanes, Mask, RayPacket, PACKET_SIZE};
+use crate::maths::{vector, Plane, Point, Ray, RayIntersection, Scalar, Sphere, Vector};
+use crate::scene::{Bvh, Camera, Light, Material, Object};
+use crate::shapes::{Heightfield, Mesh, Scaled};
 use nalgebra::Unit;
-use ncollide3d::pipeline::{CollisionGroups, GeometricQueryType};
-use ncollide3d::world::CollisionWorld;
+use ncollide3d::shape::{FeatureId, Shape};
+use std::array;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+// Rays leaving a surface start a little way off it, so floating point error in the point they leave from can't put them
+// behind it. The error grows with the size of the coordinates used to find the point, so the offset is this many units
+// in the last place of them.
+const SPAWN_OFFSET_ULPS: Scalar = 64.0;
+
+// Distance fields only find their surfaces to within a tolerance, so rays never start closer to a surface than this.
+const MINIMUM_SPAWN_OFFSET: Scalar = 0.0002;
+
+// Limit on bounces inside an object before the light is considered absorbed.
+const MAXIMUM_INTERNAL_REFLECTIONS: usize = 8;
 
 pub struct Scene {
     pub ambient_light: Colour,
     pub background_colour: Colour,
     pub camera: Camera,
     pub lights: Vec<Light>,
-    world: CollisionWorld<Scalar, Object>,
-    groups: CollisionGroups,
-    query: GeometricQueryType<Scalar>,
+    pub colour_space: WorkingColourSpace,
+    objects: Vec<Object>,
+    // Built on the first query after objects are added, so adding them one at a time doesn't rebuild it each time.
+    bvh: OnceLock<Bvh>,
 }
 
 impl Scene {
@@ -22,9 +38,9 @@ impl Scene {
             background_colour: Colour::black(),
             camera: Camera::default(),
             lights: vec![],
-            world: CollisionWorld::<Scalar, Object>::new(0.01),
-            groups: CollisionGroups::new(),
-            query: GeometricQueryType::Contacts(0.0, 0.0),
+            colour_space: WorkingColourSpace::default(),
+            objects: vec![],
+            bvh: OnceLock::new(),
         };
     }
 
@@ -39,77 +55,218 @@ impl Scene {
     }
 
     pub fn add_object(&mut self, object: Object) {
-        self.world.add(object.transformation, object.shape.clone(), self.groups, self.query, object);
-        self.world.update();
+        self.objects.push(object);
+        self.bvh = OnceLock::new();
     }
 
     pub fn add_objects(&mut self, objects: impl IntoIterator<Item = Object>) {
-        for object in objects {
-            self.world.add(object.transformation, object.shape.clone(), self.groups, self.query, object);
-        }
-        self.world.update();
+        self.objects.extend(objects);
+        self.bvh = OnceLock::new();
+    }
+
+    fn bvh(&self) -> &Bvh {
+        return self.bvh.get_or_init(|| {
+            let bounds: Vec<_> = self.objects.iter().map(|object| object.shape.aabb(&object.transformation.isometry())).collect();
+            return Bvh::new(&bounds);
+        });
     }
 
     pub fn first_collision_with_ray<'a>(&'a self, ray: &'a Ray) -> Option<RayCollision<'a>> {
-        return match self.world.first_interference_with_ray(&ray, std::f32::MAX, &self.groups) {
-            Some(interference) => Some(RayCollision::new(
-                ray,
-                ray.point_at(interference.inter.toi),
-                interference.inter.normal,
-                interference.co.data(),
-            )),
-            None => None,
-        };
+        let (index, intersection) = self.bvh().first_intersection(ray, Scalar::MAX, |index, max_toi| {
+            let object = &self.objects[index];
+            // Not solid, so rays that start just inside an object, e.g. leaving one where it touches another, hit its
+            // surface rather than stopping where they start.
+            return object
+                .shape
+                .as_ray_cast()?
+                .toi_and_normal_with_ray(&object.transformation.isometry(), ray, max_toi, false);
+        })?;
+
+        return Some(RayCollision::new(ray, &intersection, &self.objects[index]));
+    }
+
+    /// The first collisions of each ray of a packet, found together. Quicker than finding them one at a time when the
+    /// rays are coherent, e.g. primary rays through neighbouring pixels.
+    pub fn first_collisions_with_packet<'a>(&'a self, packet: &'a RayPacket) -> [Option<RayCollision<'a>>; PACKET_SIZE] {
+        let intersections = self.bvh().first_intersections(packet, Scalar::MAX, |index, max_tois, mask| {
+            return intersect_packet(&self.objects[index], packet, max_tois, mask);
+        });
+
+        return array::from_fn(|lane| {
+            let (index, intersection) = intersections[lane]?;
+            return Some(RayCollision::new(&packet.rays[lane], &intersection, &self.objects[index]));
+        });
+    }
+
+    /// Whether anything blocks the ray within `max_distance` of its origin, e.g. between a light and a point it might
+    /// light. Stops at the first object found rather than finding the closest, so is cheaper than looking for a collision.
+    pub fn is_occluded(&self, ray: &Ray, max_distance: Scalar) -> bool {
+        return self.bvh().any_intersection(ray, max_distance, |index, max_toi| {
+            let object = &self.objects[index];
+            // Not solid, the same as collisions, so a ray starting just inside an object is blocked by its surface
+            // rather than straight away.
+            return match object.shape.as_ray_cast() {
+                Some(shape) => shape.toi_with_ray(&object.transformation.isometry(), ray, max_toi, false).is_some(),
+                None => false,
+            };
+        });
     }
 
-    pub fn first_collision_with_ray_excluding<'a>(&'a self, ray: &'a Ray, excluding: &'a Object) -> Option<RayCollision<'a>> {
-        let closest = self
-            .world
-            .interferences_with_ray(&ray, std::f32::MAX, &self.groups)
-            .filter(|interference| interference.1.data() != excluding)
-            .min_by(|x, y| x.2.toi.partial_cmp(&y.2.toi).unwrap());
-
-        return match closest {
-            Some(interference) => Some(RayCollision::new(
-                ray,
-                ray.point_at(interference.2.toi),
-                interference.2.normal,
-                interference.1.data(),
-            )),
-            None => None,
+    /// Refracts the ray of a collision into the object it hit, follows it through the object including any total
+    /// internal reflections, and returns the ray that leaves the object. Other objects inside the object are ignored.
+    pub fn transmitted_ray(&self, collision: &RayCollision, refractive_index: Scalar) -> Option<Ray> {
+        let (normal, eta) = match collision.ray.dir.dot(&collision.normal) < 0.0 {
+            true => (collision.normal, 1.0 / refractive_index),
+            false => (-collision.normal, refractive_index),
         };
+
+        let direction = vector::refract(&collision.ray.dir, &normal, eta)?;
+        let mut ray = collision.spawn_ray(direction);
+
+        let object = collision.object;
+        let shape = object.shape.as_ray_cast()?;
+        for _ in 0..MAXIMUM_INTERNAL_REFLECTIONS {
+            // Not solid, otherwise rays starting inside the shape would hit it immediately.
+            let exit = match shape.toi_and_normal_with_ray(&object.transformation.isometry(), &ray, Scalar::MAX, false) {
+                Some(exit) => exit,
+                // Open shapes such as planes might never be left.
+                None => return Some(ray),
+            };
+
+            let point = ray.point_at(exit.toi);
+            let mut normal = Unit::new_normalize(exit.normal);
+            if ray.dir.dot(&normal) > 0.0 {
+                normal = -normal;
+            }
+
+            let geometric_normal = geometric_normal(object, &exit);
+            let direction = match vector::refract(&ray.dir, &normal, refractive_index) {
+                Some(refracted) => return Some(spawn_ray(&point, &geometric_normal, refracted, &ray.origin)),
+                None => -vector::reflect(&ray.dir, &normal),
+            };
+            ray = spawn_ray(&point, &geometric_normal, direction, &ray.origin);
+        }
+
+        return None;
     }
 }
 
+impl Default for Scene {
+    fn default() -> Scene {
+        return Scene::new();
+    }
+}
+
+// Spheres, planes and meshes are intersected with the whole packet at once; other shapes a ray at a time, for the rays in
+// the mask. Not solid, the same as single rays.
+fn intersect_packet(object: &Object, packet: &RayPacket, max_tois: Lanes, mask: Mask) -> [Option<RayIntersection>; PACKET_SIZE] {
+    let m = object.transformation.isometry();
+    if let Some(sphere) = object.shape.as_shape::<Sphere>() {
+        return packet.intersect_sphere(&Point::from(m.translation.vector), sphere.radius, max_tois);
+    }
+    if let Some(plane) = object.shape.as_shape::<Plane>() {
+        return packet.intersect_plane(&m, &plane.normal, max_tois);
+    }
+    if let Some(mesh) = object.shape.as_shape::<Mesh>() {
+        return mesh.intersect_packet(&m, packet, max_tois);
+    }
+
+    return array::from_fn(|lane| match mask.lane(lane) {
+        true => object
+            .shape
+            .as_ray_cast()?
+            .toi_and_normal_with_ray(&m, &packet.rays[lane], max_tois.lane(lane), false),
+        false => None,
+    });
+}
+
+// Meshes and heightfields interpolate the normals of their triangles for smooth shading; this is the normal of the
+// triangle that was actually hit.
+fn geometric_normal(object: &Object, intersection: &RayIntersection) -> Unit<Vector> {
+    return match local_geometric_normal(object.shape.as_ref(), intersection.feature) {
+        Some(normal) => Unit::new_normalize(object.transformation.isometry() * normal),
+        None => Unit::new_normalize(intersection.normal),
+    };
+}
+
+fn local_geometric_normal(shape: &dyn Shape<Scalar>, feature: FeatureId) -> Option<Vector> {
+    if let Some(scaled) = shape.as_shape::<Scaled>() {
+        // Normals transform by the inverse transpose of the scale.
+        return Some(local_geometric_normal(scaled.shape().as_ref(), feature)?.component_div(&scaled.scale()));
+    }
+    if let Some(mesh) = shape.as_shape::<Mesh>() {
+        return mesh.face_normal(feature);
+    }
+    if let Some(heightfield) = shape.as_shape::<Heightfield>() {
+        return heightfield.face_normal(feature);
+    }
+    return None;
+}
+
+// A ray from a point on a surface, hit by a ray from `from`, moved off the surface along its normal to the side the ray
+// is going.
+fn spawn_ray(point: &Point, geometric_normal: &Unit<Vector>, direction: Vector, from: &Point) -> Ray {
+    let magnitude = point.coords.amax() + (point - from).norm();
+    let offset = (magnitude * SPAWN_OFFSET_ULPS * Scalar::EPSILON).max(MINIMUM_SPAWN_OFFSET);
+    let normal = match direction.dot(geometric_normal) < 0.0 {
+        true => -geometric_normal.into_inner(),
+        false => geometric_normal.into_inner(),
+    };
+    return Ray::new(point + normal * offset, direction);
+}
+
 // TODO: Use laziness?
+#[allow(clippy::manual_non_exhaustive)]
 pub struct RayCollision<'a> {
     pub ray: &'a Ray,
     pub intersection: Point,
+    /// The normal for shading, which may be interpolated, e.g. across the faces of a smooth mesh.
     pub normal: Unit<Vector>,
+    /// The normal of the surface itself.
+    pub geometric_normal: Unit<Vector>,
+    /// The part of the object's shape that was hit, e.g. the face of a mesh.
+    pub feature: FeatureId,
     pub object: &'a Object,
     _private: (),
 }
 
 impl RayCollision<'_> {
-    fn new<'a>(ray: &'a Ray, intersection: Point, normal: Vector, object: &'a Object) -> RayCollision<'a> {
+    fn new<'a>(ray: &'a Ray, intersection: &RayIntersection, object: &'a Object) -> RayCollision<'a> {
         return RayCollision {
             ray,
-            intersection,
-            normal: Unit::new_normalize(normal),
+            intersection: ray.point_at(intersection.toi),
+            normal: Unit::new_normalize(intersection.normal),
+            geometric_normal: geometric_normal(object, intersection),
+            feature: intersection.feature,
             object,
             _private: (),
         };
     }
 
+    /// The material of the object at the point of collision.
+    pub fn material(&self) -> Cow<'_, Material> {
+        let local_point = self.object.transformation.inverse_transform_point(&self.intersection);
+        return self.object.texture.material_at(&self.intersection, &local_point, self.feature);
+    }
+
+    /// A ray leaving the point of collision, e.g. towards a light or reflected off the surface. It starts just off the
+    /// surface on the side it is going, so it doesn't hit the surface where it starts due to floating point error, but
+    /// can still hit other parts of the same object.
+    pub fn spawn_ray(&self, direction: Vector) -> Ray {
+        return spawn_ray(&self.intersection, &self.geometric_normal, direction, &self.ray.origin);
+    }
+
     pub fn reflection_ray(&self) -> Ray {
-        return Ray::new(self.intersection, -vector::reflect(&self.ray.dir, &self.normal));
+        return self.spawn_ray(-vector::reflect(&self.ray.dir, &self.normal));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::scene::{Material, Texture};
+    use crate::maths::{consts, Rotation, Transformation};
+    use crate::scene::Texture;
+    use crate::shapes::{Shading, Torus};
     use approx::assert_abs_diff_eq;
     use nalgebra::Matrix;
     use rstest::rstest;
@@ -119,11 +276,11 @@ mod tests {
         normal,
         expected_reflected_direction,
         case(Vector::x(), Matrix::normalize(&Vector::new(1.0, 1.0, 0.0)), -Vector::y()),
-        case(Vector::z(), Matrix::normalize(&Vector::new(0.0, (45.0f32 / 2.0).to_radians().tan(), 1.0)), -Matrix::normalize(&Vector::new(0.0, 1.0, 1.0)))
+        case(Vector::z(), Matrix::normalize(&Vector::new(0.0, Scalar::to_radians(45.0 / 2.0).tan(), 1.0)), -Matrix::normalize(&Vector::new(0.0, 1.0, 1.0)))
     )]
     fn reflection_ray(direction: Vector, normal: Vector, expected_reflected_direction: Vector) {
-        let ray = Ray::new(Point::origin(), direction);
         let intersection = Point::new(-1.0, 0.0, 0.0);
+        let ray = Ray::new(intersection - direction, direction);
         let object = Object::new_sphere(
             Point::origin(),
             1.0,
@@ -132,7 +289,7 @@ mod tests {
             },
         );
 
-        let collision = RayCollision::new(&ray, intersection, normal, &object);
+        let collision = RayCollision::new(&ray, &RayIntersection::new(1.0, normal, FeatureId::Face(0)), &object);
 
         let reflection = collision.reflection_ray();
 
@@ -146,4 +303,229 @@ mod tests {
         assert_abs_diff_eq!(reflection.dir.y, expected_dir.y, epsilon = 0.01);
         assert_abs_diff_eq!(reflection.dir.z, expected_dir.z, epsilon = 0.01);
     }
+
+    #[test]
+    fn rotated_and_scaled_object() {
+        let texture = Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+        // An ellipsoid with its long axis along z, after rotating the x axis onto z.
+        let transformation = Transformation::new(
+            Point::new(0.0, 0.0, -10.0),
+            Rotation::from_axis_angle(&Vector::y_axis(), -consts::FRAC_PI_2),
+            Vector::new(3.0, 1.0, 1.0),
+        );
+        let mut scene = Scene::new();
+        scene.add_object(Object::new_sphere(Point::origin(), 1.0, texture).with_transformation(transformation));
+
+        let ray = Ray::new(Point::origin(), -Vector::z());
+        let collision = scene.first_collision_with_ray(&ray).unwrap();
+
+        assert_abs_diff_eq!(collision.intersection.z, -7.0, epsilon = 0.001);
+        assert_abs_diff_eq!(collision.normal.z, 1.0, epsilon = 0.001);
+
+        let ray = Ray::new(Point::new(0.0, 0.0, -10.0 + 1.5 * Scalar::sqrt(2.0)), Vector::x());
+        assert!(scene.first_collision_with_ray(&ray).is_some());
+        let ray = Ray::new(Point::new(0.0, 0.0, -10.0 + 1.5 * Scalar::sqrt(2.0)), Vector::y());
+        assert!(scene.first_collision_with_ray(&ray).is_some());
+        let ray = Ray::new(Point::new(0.0, 0.0, -6.9), Vector::x());
+        assert!(scene.first_collision_with_ray(&ray).is_none());
+    }
+
+    // A V shaped valley along the z axis, either flat or with normals interpolated as if it were a smooth curve.
+    fn valley(smooth: bool) -> Object {
+        let positions = vec![
+            Point::new(-1.0, 1.0, -1.0),
+            Point::new(-1.0, 1.0, 1.0),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(1.0, 1.0, -1.0),
+            Point::new(1.0, 1.0, 1.0),
+        ];
+        let indices = vec![[0, 1, 3], [0, 3, 2], [2, 3, 5], [2, 5, 4]];
+        let (normals, shading) = match smooth {
+            true => (Some(vec![Vector::y(); 6]), Shading::Smooth { crease_angle: 0.0 }),
+            false => (None, Shading::Flat),
+        };
+        let texture = Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+        return Object::new_mesh(Point::origin(), positions, indices, normals, shading, texture);
+    }
+
+    #[test]
+    fn spawn_ray_leaves_the_face_that_was_hit() {
+        let mut scene = Scene::new();
+        scene.add_object(valley(true));
+        let ray = Ray::new(Point::new(-0.5, 5.0, 0.0), -Vector::y());
+        let collision = scene.first_collision_with_ray(&ray).unwrap();
+        assert_abs_diff_eq!(collision.normal.into_inner(), Vector::y(), epsilon = 0.0001);
+
+        // Above the interpolated normal but below the face, so it leaves from the far side of the face.
+        let direction = Matrix::normalize(&Vector::new(-1.0, -0.5, 0.0));
+        let spawned = collision.spawn_ray(direction);
+
+        assert!((spawned.origin - collision.intersection).dot(&collision.geometric_normal) < 0.0);
+        assert!(scene.first_collision_with_ray(&spawned).is_none());
+    }
+
+    #[test]
+    fn objects_shadow_and_reflect_themselves() {
+        let mut scene = Scene::new();
+        scene.add_object(valley(false));
+        let ray = Ray::new(Point::new(-0.5, 5.0, 0.0), -Vector::y());
+        let collision = scene.first_collision_with_ray(&ray).unwrap();
+
+        // The other side of the valley is between the point and anything to its right.
+        let shadow_ray = collision.spawn_ray(Matrix::normalize(&Vector::new(1.0, 0.2, 0.0)));
+        assert!(scene.is_occluded(&shadow_ray, 10.0));
+        let shadow_ray = collision.spawn_ray(Vector::y());
+        assert!(!scene.is_occluded(&shadow_ray, 10.0));
+
+        let reflection_ray = collision.reflection_ray();
+        let reflection = scene.first_collision_with_ray(&reflection_ray).unwrap();
+        assert!(reflection.object == collision.object);
+        assert!(reflection.intersection.x > 0.0);
+    }
+
+    #[rstest(position, case(Point::new(0.0, 0.0, 5.0)), case(Point::new(10_000.0, 0.0, 5.0)))]
+    fn spawn_offset_grows_with_coordinates(position: Point) {
+        let object = Object::new_sphere(
+            position,
+            1.0,
+            Texture::Solid {
+                material: Material::matte(Colour::black()),
+            },
+        );
+        let ray = Ray::new(position - Vector::z() * 5.0, Vector::z());
+        let intersection = object
+            .shape
+            .as_ray_cast()
+            .unwrap()
+            .toi_and_normal_with_ray(&object.transformation.isometry(), &ray, Scalar::MAX, true)
+            .unwrap();
+        let collision = RayCollision::new(&ray, &intersection, &object);
+
+        let offset = (collision.spawn_ray(-Vector::z()).origin - collision.intersection).norm();
+
+        let expected = (position.coords.amax() * SPAWN_OFFSET_ULPS * Scalar::EPSILON).max(MINIMUM_SPAWN_OFFSET);
+        assert!(offset >= MINIMUM_SPAWN_OFFSET * 0.99);
+        assert!(offset >= expected * 0.99);
+        // Moving off the surface doesn't hit it again.
+        assert!(!object
+            .shape
+            .as_ray_cast()
+            .unwrap()
+            .intersects_ray(&object.transformation.isometry(), &collision.spawn_ray(-Vector::z()), Scalar::MAX));
+    }
+
+    #[rstest(max_distance, expected, case(4.0, false), case(5.5, true), case(100.0, true))]
+    fn is_occluded(max_distance: Scalar, expected: bool) {
+        let texture = || Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+        // One sphere in front of the ray and one behind it.
+        let mut scene = Scene::new();
+        scene.add_object(Object::new_sphere(Point::new(0.0, 0.0, 6.0), 1.0, texture()));
+        scene.add_object(Object::new_sphere(Point::new(0.0, 0.0, -6.0), 1.0, texture()));
+
+        let ray = Ray::new(Point::origin(), Vector::z());
+
+        assert_eq!(scene.is_occluded(&ray, max_distance), expected);
+    }
+
+    #[test]
+    fn objects_added_after_a_query_are_found() {
+        let texture = || Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+        let mut scene = Scene::new();
+        scene.add_object(Object::new_sphere(Point::new(0.0, 0.0, 6.0), 1.0, texture()));
+        let ray = Ray::new(Point::origin(), Vector::z());
+        assert_abs_diff_eq!(
+            scene.first_collision_with_ray(&ray).unwrap().intersection,
+            Point::new(0.0, 0.0, 5.0),
+            epsilon = 1e-5
+        );
+
+        scene.add_object(Object::new_sphere(Point::new(0.0, 0.0, 3.0), 1.0, texture()));
+
+        assert_abs_diff_eq!(
+            scene.first_collision_with_ray(&ray).unwrap().intersection,
+            Point::new(0.0, 0.0, 2.0),
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn first_collisions_with_packet() {
+        let texture = || Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+        let mut scene = Scene::new();
+        scene.add_object(Object::new_sphere(Point::new(0.0, 0.0, 5.0), 1.0, texture()));
+        scene.add_object(Object::new_plane(Point::new(0.0, -1.0, 0.0), Vector::y(), texture()));
+        scene.add_object(Object::new(Torus::new(2.0, 0.5), Point::new(3.0, 0.0, 8.0), texture()));
+
+        let rays = [
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::z()),
+            Ray::new(Point::new(0.0, 0.0, -5.0), Matrix::normalize(&Vector::new(0.0, -0.2, 1.0))),
+            Ray::new(Point::new(0.0, 0.0, -5.0), Matrix::normalize(&Vector::new(0.3, 0.0, 1.0))),
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::y()),
+        ];
+        let packet = RayPacket::new(rays);
+
+        let collisions = scene.first_collisions_with_packet(&packet);
+
+        assert_same_as_single_rays(&scene, &packet, &collisions);
+        assert!(collisions.iter().take(3).all(|collision| collision.is_some()));
+        assert!(collisions[3].is_none());
+    }
+
+    #[rstest(
+        object,
+        case(Object::new_sphere(Point::origin(), 2.0, texture())),
+        case(Object::new_plane(Point::new(0.0, 1.0, 0.0), -Vector::y(), texture())),
+        case(Object::new_cylinder(Point::origin(), 4.0, 2.0, texture())),
+        case(Object::new_torus(Point::new(2.0, 0.0, 0.0), 2.0, 0.5, texture()))
+    )]
+    fn first_collisions_with_packet_from_inside(object: Object) {
+        let mut scene = Scene::new();
+        scene.add_object(object);
+
+        let rays = [
+            Ray::new(Point::origin(), Vector::z()),
+            Ray::new(Point::origin(), Vector::y()),
+            Ray::new(Point::origin(), Matrix::normalize(&Vector::new(0.3, -0.2, 1.0))),
+            Ray::new(Point::origin(), -Vector::y()),
+        ];
+        let packet = RayPacket::new(rays);
+
+        let collisions = scene.first_collisions_with_packet(&packet);
+
+        assert_same_as_single_rays(&scene, &packet, &collisions);
+        // Rays starting inside hit the surface on the way out rather than where they start.
+        assert!(collisions
+            .iter()
+            .flatten()
+            .all(|collision| (collision.intersection - collision.ray.origin).norm() > 0.1));
+    }
+
+    fn texture() -> Texture {
+        return Texture::Solid {
+            material: Material::matte(Colour::black()),
+        };
+    }
+
+    fn assert_same_as_single_rays(scene: &Scene, packet: &RayPacket, collisions: &[Option<RayCollision>; PACKET_SIZE]) {
+        for (ray, collision) in packet.rays.iter().zip(collisions.iter()) {
+            let expected = scene.first_collision_with_ray(ray);
+            assert_eq!(collision.is_some(), expected.is_some());
+            if let (Some(collision), Some(expected)) = (collision, expected) {
+                assert!(collision.object == expected.object);
+                assert_abs_diff_eq!(collision.intersection, expected.intersection, epsilon = 0.0001);
+                assert_abs_diff_eq!(collision.normal.into_inner(), expected.normal.into_inner(), epsilon = 0.0001);
+            }
+        }
+    }
 }
@@ -1,25 +1,183 @@
+use crate::image::Colour;
 use crate::maths::{Point, Scalar};
 use crate::scene::Material;
+use ncollide3d::shape::FeatureId;
+use std::borrow::Cow;
 
 pub enum Texture {
-    Solid { material: Material },
-    Chequerboard { material1: Material, material2: Material, size: Scalar },
+    Solid {
+        material: Material,
+    },
+    Chequerboard {
+        material1: Material,
+        material2: Material,
+        size: Scalar,
+    },
+    /// A material for each face of a mesh; `faces` gives the index into `materials` for each face.
+    PerFace {
+        materials: Vec<Material>,
+        faces: Vec<usize>,
+    },
+    /// Matte colours at the vertices of polygons, interpolated across them. `faces` gives the index into `polygons`
+    /// for each face of a mesh, which can be a smaller triangle of the polygon, e.g. after subdivision.
+    PerVertex {
+        positions: Vec<Point>,
+        colours: Vec<Colour>,
+        polygons: Vec<Vec<usize>>,
+        faces: Vec<usize>,
+    },
 }
 
 impl Texture {
-    pub fn material_at_point(&self, point: &Point) -> &Material {
+    /// The material at a point, given in the scene and in the object's own coordinates, on a feature of the object's
+    /// shape.
+    pub fn material_at(&self, point: &Point, local_point: &Point, feature: FeatureId) -> Cow<'_, Material> {
         return match self {
-            Texture::Solid { material } => material,
+            Texture::Solid { material } => Cow::Borrowed(material),
             Texture::Chequerboard { material1, material2, size } => {
                 let x = (point.x / size).round() as isize;
                 let y = (point.y / size).round() as isize;
                 let z = (point.z / size).round() as isize;
 
                 if ((x + y + z) & 1) == 0 {
-                    return material1;
+                    return Cow::Borrowed(material1);
                 }
-                return material2;
+                return Cow::Borrowed(material2);
+            }
+            Texture::PerFace { materials, faces } => {
+                // Meshes number the back of each face after all the fronts.
+                let face = match feature {
+                    FeatureId::Face(face) => face % faces.len(),
+                    _ => 0,
+                };
+                Cow::Borrowed(&materials[faces[face]])
+            }
+            Texture::PerVertex {
+                positions,
+                colours,
+                polygons,
+                faces,
+            } => {
+                let face = match feature {
+                    FeatureId::Face(face) => face % faces.len(),
+                    _ => 0,
+                };
+                let colour = interpolate(positions, colours, &polygons[faces[face]], local_point);
+                Cow::Owned(Material::matte(colour))
             }
         };
     }
 }
+
+// The colour at a point on a polygon, from the triangle of its fan the point is most inside of. Points of faces that
+// were subdivided or displaced can be a little off the polygon, so negative weights are dropped.
+fn interpolate(positions: &[Point], colours: &[Colour], polygon: &[usize], point: &Point) -> Colour {
+    let (triangle, weights) = polygon[1..]
+        .windows(2)
+        .map(|pair| {
+            let triangle = [polygon[0], pair[0], pair[1]];
+            let weights = barycentric(point, &triangle.map(|index| positions[index]));
+            return (triangle, weights);
+        })
+        .max_by(|(_, a), (_, b)| minimum(a).total_cmp(&minimum(b)))
+        .unwrap_or(([polygon[0]; 3], [1.0, 0.0, 0.0]));
+
+    let weights = weights.map(|weight| weight.max(0.0));
+    let total: Scalar = weights.iter().sum();
+    return (0..3).map(|corner| colours[triangle[corner]] * (weights[corner] / total)).sum();
+}
+
+fn minimum(weights: &[Scalar; 3]) -> Scalar {
+    return weights.iter().copied().fold(Scalar::INFINITY, Scalar::min);
+}
+
+// Barycentric coordinates of a point projected onto the plane of a triangle, for each of its corners. Degenerate
+// triangles take the colour of their first corner.
+fn barycentric(point: &Point, [a, b, c]: &[Point; 3]) -> [Scalar; 3] {
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+
+    let d00 = ab.dot(&ab);
+    let d01 = ab.dot(&ac);
+    let d11 = ac.dot(&ac);
+    let d20 = ap.dot(&ab);
+    let d21 = ap.dot(&ac);
+
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator == 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    return [1.0 - v - w, v, w];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        feature,
+        expected,
+        case(FeatureId::Face(0), Colour::new(1.0, 0.0, 0.0)),
+        case(FeatureId::Face(1), Colour::new(0.0, 1.0, 0.0)),
+        case(FeatureId::Face(2), Colour::new(1.0, 0.0, 0.0)),
+        case(FeatureId::Face(3), Colour::new(1.0, 0.0, 0.0)),
+        case(FeatureId::Face(4), Colour::new(0.0, 1.0, 0.0)),
+        case(FeatureId::Unknown, Colour::new(1.0, 0.0, 0.0))
+    )]
+    fn per_face(feature: FeatureId, expected: Colour) {
+        let texture = Texture::PerFace {
+            materials: vec![Material::matte(Colour::new(1.0, 0.0, 0.0)), Material::matte(Colour::new(0.0, 1.0, 0.0))],
+            faces: vec![0, 1, 0],
+        };
+
+        assert_eq!(texture.material_at(&Point::origin(), &Point::origin(), feature).diffuse_colour, expected);
+    }
+
+    fn assert_colour(actual: Colour, expected: Colour) {
+        assert_abs_diff_eq!(actual.r, expected.r, epsilon = 0.0001);
+        assert_abs_diff_eq!(actual.g, expected.g, epsilon = 0.0001);
+        assert_abs_diff_eq!(actual.b, expected.b, epsilon = 0.0001);
+    }
+
+    // A unit square in the xy plane, red along the bottom and blue along the top.
+    fn square() -> Texture {
+        return Texture::PerVertex {
+            positions: vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+            colours: vec![
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(0.0, 0.0, 1.0),
+                Colour::new(0.0, 0.0, 1.0),
+            ],
+            polygons: vec![vec![0, 1, 2, 3]],
+            faces: vec![0, 0],
+        };
+    }
+
+    #[rstest(
+        point,
+        expected,
+        case(Point::new(0.5, 0.0, 0.0), Colour::new(1.0, 0.0, 0.0)),
+        case(Point::new(0.9, 0.25, 0.0), Colour::new(0.75, 0.0, 0.25)),
+        case(Point::new(0.1, 0.75, 0.0), Colour::new(0.25, 0.0, 0.75)),
+        case(Point::new(0.5, 0.5, 0.3), Colour::new(0.5, 0.0, 0.5)),
+        case(Point::new(0.5, 2.0, 0.0), Colour::new(0.0, 0.0, 1.0))
+    )]
+    fn per_vertex(point: Point, expected: Colour) {
+        let texture = square();
+
+        for face in 0..4 {
+            let colour = texture.material_at(&Point::origin(), &point, FeatureId::Face(face)).diffuse_colour;
+            assert_colour(colour, expected);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::{circle_interval, quadratic_interval, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A cylinder along the y axis with hemispherical ends, centred on the origin. `half_height` is half the length of the
+/// cylindrical part. ncollide has a capsule, but it finds intersections iteratively and its normals are too inaccurate
+/// for smooth shading. The side is face 0, the top end face 1 and the bottom end face 2.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capsule {
+    pub half_height: Scalar,
+    pub radius: Scalar,
+}
+
+impl Capsule {
+    pub fn new(half_height: Scalar, radius: Scalar) -> Capsule {
+        assert!(
+            half_height >= 0.0 && radius > 0.0,
+            "Capsules must have a positive radius and a non-negative height."
+        );
+        return Capsule { half_height, radius };
+    }
+
+    pub fn interval(&self, ray: &Ray) -> Option<Interval> {
+        // The capsule is convex, so its interval is the union of the intervals of its parts.
+        let side = circle_interval(ray, self.radius, |_| true, FeatureId::Face(0))
+            .and_then(|side| side.intersection(&Interval::slab(ray, &Vector::y(), self.half_height, [FeatureId::Unknown; 2])?));
+        let top = self.end_interval(ray, self.half_height, FeatureId::Face(1));
+        let bottom = self.end_interval(ray, -self.half_height, FeatureId::Face(2));
+
+        return [side, top, bottom].iter().flatten().copied().reduce(|union, interval| union.union(&interval));
+    }
+
+    fn end_interval(&self, ray: &Ray, y: Scalar, feature: FeatureId) -> Option<Interval> {
+        let centre = Point::new(0.0, y, 0.0);
+        let offset = ray.origin - centre;
+        let coefficients = (
+            ray.dir.norm_squared(),
+            2.0 * offset.dot(&ray.dir),
+            offset.norm_squared() - self.radius * self.radius,
+        );
+
+        return quadratic_interval(coefficients, |_| true, |toi| (ray.point_at(toi) - centre) / self.radius, feature);
+    }
+}
+
+impl Solid for Capsule {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        return self.interval(ray).into_iter().collect();
+    }
+}
+
+impl RayCast<Scalar> for Capsule {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return self.interval(&ray.inverse_transform_by(m))?.to_ray_intersection(m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Capsule {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let half_extents = Vector::new(self.radius, self.half_height + self.radius, self.radius);
+        return AABB::new(Point::from(-half_extents), Point::from(half_extents));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.half_height + self.radius);
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use nalgebra::Matrix;
+    use rstest::rstest;
+
+    #[rstest(
+        origin,
+        direction,
+        toi,
+        normal,
+        feature,
+        case(Point::new(-5.0, 0.5, 0.0), Vector::x(), 4.5, -Vector::x(), 0),
+        case(Point::new(0.0, 5.0, 0.0), -Vector::y(), 2.5, Vector::y(), 1),
+        case(Point::new(0.0, -5.0, 0.0), Vector::y(), 2.5, -Vector::y(), 2),
+        case(Point::new(-5.0, 2.0 + Scalar::sqrt(0.5) * 0.5, 0.0), Vector::x(), 5.0 - Scalar::sqrt(0.5) * 0.5, Matrix::normalize(&Vector::new(-1.0, 1.0, 0.0)), 1)
+    )]
+    fn hit(origin: Point, direction: Vector, toi: Scalar, normal: Vector, feature: usize) {
+        let capsule = Capsule::new(2.0, 0.5);
+
+        let intersection = capsule
+            .toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, toi, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, normal.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, normal.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.z, normal.z, epsilon = 0.0001);
+        assert_eq!(intersection.feature, FeatureId::Face(feature));
+    }
+
+    #[test]
+    fn miss() {
+        let capsule = Capsule::new(2.0, 0.5);
+        let ray = Ray::new(Point::new(-5.0, 2.45, 0.45), Vector::x());
+
+        assert!(capsule.toi_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).is_none());
+    }
+}
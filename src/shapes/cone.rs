@@ -0,0 +1,134 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::{quadratic_interval, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A cone along the y axis, centred on the origin, with its apex at the top and a flat base at the bottom. The side is
+/// face 0 and the base face 1.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cone {
+    pub half_height: Scalar,
+    pub radius: Scalar,
+}
+
+impl Cone {
+    pub fn new(half_height: Scalar, radius: Scalar) -> Cone {
+        assert!(half_height > 0.0 && radius > 0.0, "Cones must have a positive height and radius.");
+        return Cone { half_height, radius };
+    }
+
+    pub fn interval(&self, ray: &Ray) -> Option<Interval> {
+        let caps = Interval::slab(ray, &Vector::y(), self.half_height, [FeatureId::Face(0), FeatureId::Face(1)])?;
+
+        // Points on the side satisfy x² + z² = k²(h - y)², where k is the radius' change with height. This is a double
+        // cone, so rays can be inside it twice; only the lower part is between the caps.
+        let (o, d) = (ray.origin, ray.dir);
+        let k2 = (self.radius / (2.0 * self.half_height)).powi(2);
+        let w = self.half_height - o.y;
+        let coefficients = (
+            d.x * d.x + d.z * d.z - k2 * d.y * d.y,
+            2.0 * (o.x * d.x + o.z * d.z + k2 * w * d.y),
+            o.x * o.x + o.z * o.z - k2 * w * w,
+        );
+
+        let side = quadratic_interval(
+            coefficients,
+            |toi| ray.point_at(toi).y <= self.half_height,
+            |toi| {
+                let point = ray.point_at(toi);
+                let normal = Vector::new(point.x, k2 * (self.half_height - point.y), point.z);
+                // The apex has no normal, so point it straight up.
+                return normal.try_normalize(0.0).unwrap_or_else(Vector::y);
+            },
+            FeatureId::Face(0),
+        )?;
+
+        return side.intersection(&caps);
+    }
+}
+
+impl Solid for Cone {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        return self.interval(ray).into_iter().collect();
+    }
+}
+
+impl RayCast<Scalar> for Cone {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return self.interval(&ray.inverse_transform_by(m))?.to_ray_intersection(m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Cone {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let half_extents = Vector::new(self.radius, self.half_height, self.radius);
+        return AABB::new(Point::from(-half_extents), Point::from(half_extents));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.half_height.hypot(self.radius));
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use nalgebra::Matrix;
+    use rstest::rstest;
+
+    // Twice as tall as it is wide, so the side's normal is (2, 1) in the plane through the axis.
+    #[rstest(
+        origin,
+        direction,
+        toi,
+        normal,
+        feature,
+        case(Point::new(-5.0, 0.0, 0.0), Vector::x(), 4.5, Matrix::normalize(&Vector::new(-2.0, 1.0, 0.0)), 0),
+        case(Point::new(0.0, 5.0, 0.0), -Vector::y(), 4.0, Vector::y(), 0),
+        case(Point::new(0.5, -5.0, 0.0), Vector::y(), 4.0, -Vector::y(), 1),
+        case(Point::new(0.0, 0.5, 5.0), -Vector::z(), 4.75, Matrix::normalize(&Vector::new(0.0, 1.0, 2.0)), 0)
+    )]
+    fn hit(origin: Point, direction: Vector, toi: Scalar, normal: Vector, feature: usize) {
+        let cone = Cone::new(1.0, 1.0);
+
+        let intersection = cone
+            .toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, toi, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, normal.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, normal.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.z, normal.z, epsilon = 0.0001);
+        assert_eq!(intersection.feature, FeatureId::Face(feature));
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        case(Point::new(-5.0, 0.9, 0.5), Vector::x()),
+        case(Point::new(-5.0, -1.5, 0.0), Vector::x()),
+        // Passes through the upper half of the double cone, above the apex.
+        case(Point::new(0.0, 3.0, -5.0), Vector::z())
+    )]
+    fn miss(origin: Point, direction: Vector) {
+        let cone = Cone::new(1.0, 1.0);
+
+        assert!(cone
+            .toi_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .is_none());
+    }
+}
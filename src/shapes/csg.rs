@@ -0,0 +1,264 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Transformation, Unit, Vector};
+use crate::shapes::{Crossing, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, BoundingVolume, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// The left shape with the right shape cut away.
+    Difference,
+}
+
+/// One side of a CSG operation; a solid positioned relative to the CSG shape.
+#[derive(Clone)]
+pub struct CsgChild {
+    solid: Arc<dyn Solid>,
+    transformation: Transformation,
+}
+
+/// A shape built by combining two solids, either of which can be another CSG shape.
+#[derive(Clone)]
+pub struct Csg {
+    operation: CsgOperation,
+    left: CsgChild,
+    right: CsgChild,
+}
+
+impl CsgOperation {
+    fn contains(&self, left: bool, right: bool) -> bool {
+        return match self {
+            CsgOperation::Union => left || right,
+            CsgOperation::Intersection => left && right,
+            CsgOperation::Difference => left && !right,
+        };
+    }
+
+    /// Combines the intervals of the left and right shapes along the same ray.
+    pub fn combine(&self, left: &[Interval], right: &[Interval]) -> Vec<Interval> {
+        let mut crossings: Vec<(Crossing, bool, bool)> = vec![];
+        for (intervals, is_left) in [(left, true), (right, false)] {
+            for interval in intervals {
+                crossings.push((interval.entry, is_left, true));
+                crossings.push((interval.exit, is_left, false));
+            }
+        }
+        // Entries before exits at the same time, so touching intervals join up.
+        crossings.sort_by(|x, y| x.0.toi.partial_cmp(&y.0.toi).unwrap().then(y.2.cmp(&x.2)));
+
+        let (mut inside_left, mut inside_right) = (false, false);
+        let mut entry: Option<Crossing> = None;
+        let mut intervals = vec![];
+        for (crossing, is_left, entering) in crossings {
+            match is_left {
+                true => inside_left = entering,
+                false => inside_right = entering,
+            }
+
+            let inside = self.contains(inside_left, inside_right);
+            if inside == entry.is_some() {
+                continue;
+            }
+
+            // Leaving the right shape of a difference enters the result, so the normal must be flipped to point out of it.
+            let normal = match inside == entering {
+                true => crossing.normal,
+                false => -crossing.normal,
+            };
+            let crossing = Crossing::new(crossing.toi, normal, crossing.feature);
+
+            match entry.take() {
+                None => entry = Some(crossing),
+                Some(entry) if entry.toi < crossing.toi => intervals.push(Interval::new(entry, crossing)),
+                Some(_) => {}
+            }
+        }
+
+        return intervals;
+    }
+}
+
+impl CsgChild {
+    pub fn new(solid: Arc<dyn Solid>, transformation: Transformation) -> CsgChild {
+        return CsgChild { solid, transformation };
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let local = ray.inverse_transform_by(&self.transformation.isometry());
+        let scale = &self.transformation.scale;
+        let local = Ray::new(Point::from(local.origin.coords.component_div(scale)), local.dir.component_div(scale));
+
+        let mut intervals = self.solid.intervals(&local);
+        for interval in intervals.iter_mut() {
+            for crossing in [&mut interval.entry, &mut interval.exit] {
+                let normal = self.transformation.transform_normal(&crossing.normal);
+                crossing.normal = normal.try_normalize(0.0).unwrap_or(normal);
+            }
+        }
+        return intervals;
+    }
+
+    fn aabb(&self) -> AABB<Scalar> {
+        let aabb = self.solid.local_aabb();
+        let a = aabb.mins.coords.component_mul(&self.transformation.scale);
+        let b = aabb.maxs.coords.component_mul(&self.transformation.scale);
+        return AABB::new(Point::from(a.inf(&b)), Point::from(a.sup(&b))).transform_by(&self.transformation.isometry());
+    }
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: CsgChild, right: CsgChild) -> Csg {
+        return Csg { operation, left, right };
+    }
+}
+
+impl Solid for Csg {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let left = self.left.intervals(ray);
+        if left.is_empty() && self.operation != CsgOperation::Union {
+            return left;
+        }
+
+        return self.operation.combine(&left, &self.right.intervals(ray));
+    }
+}
+
+impl RayCast<Scalar> for Csg {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return Interval::first_ray_intersection(&self.intervals(&ray.inverse_transform_by(m)), m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Csg {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let left = self.left.aabb();
+        return match self.operation {
+            CsgOperation::Union => left.merged(&self.right.aabb()),
+            // An empty intersection still needs a valid box.
+            CsgOperation::Intersection => {
+                let right = self.right.aabb();
+                let mins = left.mins.sup(&right.mins);
+                AABB::new(mins, left.maxs.inf(&right.maxs).sup(&mins))
+            }
+            CsgOperation::Difference => left,
+        };
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::Sphere;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn interval(entry: Scalar, exit: Scalar) -> Interval {
+        return Interval::new(
+            Crossing::new(entry, -Vector::x(), FeatureId::Face(0)),
+            Crossing::new(exit, Vector::x(), FeatureId::Face(0)),
+        );
+    }
+
+    fn tois(intervals: &[Interval]) -> Vec<(Scalar, Scalar)> {
+        return intervals.iter().map(|interval| (interval.entry.toi, interval.exit.toi)).collect();
+    }
+
+    #[rstest(
+        operation,
+        expected,
+        case(CsgOperation::Union, vec![(1.0, 4.0), (5.0, 8.0)]),
+        case(CsgOperation::Intersection, vec![(2.0, 3.0), (6.0, 7.0)]),
+        case(CsgOperation::Difference, vec![(1.0, 2.0), (7.0, 8.0)])
+    )]
+    fn combine(operation: CsgOperation, expected: Vec<(Scalar, Scalar)>) {
+        let left = [interval(1.0, 3.0), interval(6.0, 8.0)];
+        let right = [interval(2.0, 4.0), interval(5.0, 7.0)];
+
+        assert_eq!(tois(&operation.combine(&left, &right)), expected);
+    }
+
+    #[test]
+    fn difference_flips_normals_of_right() {
+        let intervals = CsgOperation::Difference.combine(&[interval(1.0, 4.0)], &[interval(2.0, 3.0)]);
+
+        assert_eq!(tois(&intervals), vec![(1.0, 2.0), (3.0, 4.0)]);
+        assert_eq!(intervals[0].exit.normal, Vector::x());
+        assert_eq!(intervals[1].entry.normal, -Vector::x());
+    }
+
+    #[test]
+    fn touching_intervals_join() {
+        let intervals = CsgOperation::Union.combine(&[interval(1.0, 2.0)], &[interval(2.0, 3.0)]);
+
+        assert_eq!(tois(&intervals), vec![(1.0, 3.0)]);
+    }
+
+    fn sphere(x: Scalar, radius: Scalar) -> CsgChild {
+        return CsgChild::new(Arc::new(Sphere::new(radius)), Transformation::translation(Point::new(x, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn lens() {
+        // The intersection of two overlapping spheres.
+        let lens = Csg::new(CsgOperation::Intersection, sphere(-1.5, 2.0), sphere(1.5, 2.0));
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::x());
+
+        let intersection = lens.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 4.5);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0);
+
+        let aabb = lens.local_aabb();
+        assert_abs_diff_eq!(aabb.mins.x, -0.5);
+        assert_abs_diff_eq!(aabb.maxs.x, 0.5);
+    }
+
+    #[test]
+    fn nested_bowl() {
+        // A hollow sphere cut in half, opening upwards.
+        let hollow = Csg::new(CsgOperation::Difference, sphere(0.0, 2.0), sphere(0.0, 1.5));
+        let lid = CsgChild::new(
+            Arc::new(ncollide3d::shape::Cuboid::new(Vector::new(3.0, 1.5, 3.0))),
+            Transformation::translation(Point::new(0.0, 1.5, 0.0)),
+        );
+        let bowl = Csg::new(
+            CsgOperation::Difference,
+            CsgChild::new(Arc::new(hollow), Transformation::translation(Point::origin())),
+            lid,
+        );
+
+        // Straight down into the bowl, hitting its inside surface.
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), -Vector::y());
+        let intersection = bowl.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_abs_diff_eq!(intersection.toi, 6.5);
+        assert_abs_diff_eq!(intersection.normal.y, 1.0);
+
+        // Across the top of the bowl, hitting its rim.
+        let ray = Ray::new(Point::new(-5.0, -0.1, 0.0), Vector::x());
+        let intervals = bowl.intervals(&ray);
+        assert_eq!(intervals.len(), 2);
+        assert_abs_diff_eq!(intervals[0].exit.normal.x, 1.0, epsilon = 0.01);
+
+        let ray = Ray::new(Point::new(-5.0, 0.1, 0.0), Vector::x());
+        assert!(bowl.intervals(&ray).is_empty());
+    }
+}
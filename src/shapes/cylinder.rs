@@ -0,0 +1,130 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::{quadratic_interval, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A cylinder along the y axis, centred on the origin, with flat caps. The side is face 0, the top cap face 1 and the
+/// bottom cap face 2.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cylinder {
+    pub half_height: Scalar,
+    pub radius: Scalar,
+}
+
+impl Cylinder {
+    pub fn new(half_height: Scalar, radius: Scalar) -> Cylinder {
+        assert!(half_height > 0.0 && radius > 0.0, "Cylinders must have a positive height and radius.");
+        return Cylinder { half_height, radius };
+    }
+
+    pub fn interval(&self, ray: &Ray) -> Option<Interval> {
+        let side = circle_interval(ray, self.radius, |_| true, FeatureId::Face(0))?;
+        let caps = Interval::slab(ray, &Vector::y(), self.half_height, [FeatureId::Face(1), FeatureId::Face(2)])?;
+        return side.intersection(&caps);
+    }
+}
+
+/// Where the ray is inside the infinite cylinder of the given radius around the y axis.
+pub fn circle_interval(ray: &Ray, radius: Scalar, keep: impl Fn(Scalar) -> bool, feature: FeatureId) -> Option<Interval> {
+    let (o, d) = (ray.origin, ray.dir);
+    let coefficients = (d.x * d.x + d.z * d.z, 2.0 * (o.x * d.x + o.z * d.z), o.x * o.x + o.z * o.z - radius * radius);
+
+    return quadratic_interval(
+        coefficients,
+        keep,
+        |toi| {
+            let point = ray.point_at(toi);
+            return Vector::new(point.x, 0.0, point.z) / radius;
+        },
+        feature,
+    );
+}
+
+impl Solid for Cylinder {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        return self.interval(ray).into_iter().collect();
+    }
+}
+
+impl RayCast<Scalar> for Cylinder {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return self.interval(&ray.inverse_transform_by(m))?.to_ray_intersection(m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Cylinder {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let half_extents = Vector::new(self.radius, self.half_height, self.radius);
+        return AABB::new(Point::from(-half_extents), Point::from(half_extents));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.half_height.hypot(self.radius));
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use nalgebra::Matrix;
+    use rstest::rstest;
+
+    #[rstest(
+        origin,
+        direction,
+        toi,
+        normal,
+        feature,
+        case(Point::new(-5.0, 0.5, 0.0), Vector::x(), 4.0, -Vector::x(), 0),
+        case(Point::new(0.6, 5.0, 0.0), -Vector::y(), 3.0, Vector::y(), 1),
+        case(Point::new(0.0, -5.0, 0.0), Vector::new(0.0, 2.0, 0.0), 1.5, -Vector::y(), 2),
+        case(Point::new(-4.0, 0.0, 4.0), Matrix::normalize(&Vector::new(1.0, 0.0, -1.0)), 4.0 * Scalar::sqrt(2.0) - 1.0, Matrix::normalize(&Vector::new(-1.0, 0.0, 1.0)), 0)
+    )]
+    fn hit(origin: Point, direction: Vector, toi: Scalar, normal: Vector, feature: usize) {
+        let cylinder = Cylinder::new(2.0, 1.0);
+
+        let intersection = cylinder
+            .toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, toi, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, normal.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, normal.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.z, normal.z, epsilon = 0.0001);
+        assert_eq!(intersection.feature, FeatureId::Face(feature));
+    }
+
+    #[rstest(origin, direction, case(Point::new(-5.0, 2.5, 0.0), Vector::x()), case(Point::new(1.5, 5.0, 0.0), -Vector::y()))]
+    fn miss(origin: Point, direction: Vector) {
+        let cylinder = Cylinder::new(2.0, 1.0);
+
+        assert!(cylinder
+            .toi_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .is_none());
+    }
+
+    #[test]
+    fn exit_from_inside() {
+        let cylinder = Cylinder::new(2.0, 1.0);
+        let ray = Ray::new(Point::origin(), Vector::x());
+
+        let intersection = cylinder.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, false).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0, epsilon = 0.0001);
+    }
+}
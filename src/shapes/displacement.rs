@@ -0,0 +1,376 @@
+use crate::maths::noise::Noise;
+use crate::maths::{Coordinates, Point, Scalar, Vector};
+use crate::shapes::{Mesh, Shading};
+use std::collections::HashMap;
+
+/// Heights between zero and one over a plane.
+#[derive(Debug, Clone)]
+pub enum HeightMap {
+    /// Rows of heights from the bottom of an image up, `width` to a row, which repeat every unit along each axis.
+    /// Heights between the centres of pixels are interpolated.
+    Image { heights: Vec<Scalar>, width: usize },
+    /// Fractal noise with bumps about a unit across; see `Noise::fractal`.
+    Noise { noise: Noise, octaves: usize, persistence: Scalar },
+}
+
+impl HeightMap {
+    pub fn height_at(&self, coordinates: &Coordinates) -> Scalar {
+        return match self {
+            HeightMap::Image { heights, width } => {
+                let rows = heights.len() / width;
+                let x = coordinates.x * *width as Scalar - 0.5;
+                let y = coordinates.y * rows as Scalar - 0.5;
+                let (fx, fy) = (x - x.floor(), y - y.floor());
+
+                let height = |dx: i64, dy: i64| {
+                    let column = (x.floor() as i64 + dx).rem_euclid(*width as i64) as usize;
+                    let row = (y.floor() as i64 + dy).rem_euclid(rows as i64) as usize;
+                    return heights[row * width + column];
+                };
+
+                let bottom = height(0, 0) * (1.0 - fx) + height(1, 0) * fx;
+                let top = height(0, 1) * (1.0 - fx) + height(1, 1) * fx;
+                bottom * (1.0 - fy) + top * fy
+            }
+            HeightMap::Noise { noise, octaves, persistence } => (noise.fractal(coordinates, *octaves, *persistence) + 1.0) / 2.0,
+        };
+    }
+}
+
+/// Moves the surface of a mesh along its normals by the heights of a height map, for detail that shows in silhouettes
+/// and shadows. The mesh is first split into triangles with edges no longer than `edge_length`, so there are enough
+/// vertices to move. The height map is laid over the mesh's texture coordinates, or where it has none, projected onto
+/// it along each axis and blended by the normals. Either way, `size` is the size of a unit of the height map, in
+/// texture coordinates or the mesh's own units respectively.
+#[derive(Debug, Clone)]
+#[allow(clippy::manual_non_exhaustive)]
+pub struct Displacement {
+    pub heights: HeightMap,
+    /// The distance a height of one moves the surface; negative to move it inwards.
+    pub scale: Scalar,
+    pub edge_length: Scalar,
+    pub size: Scalar,
+    _private: (),
+}
+
+/// A corner of a triangle while it is being split: its vertex, with the mesh's normal and texture coordinates there.
+#[derive(Copy, Clone)]
+struct Corner {
+    vertex: usize,
+    normal: Vector,
+    texture_coordinates: Option<Coordinates>,
+}
+
+impl Displacement {
+    /// # Panics
+    ///
+    /// Panics if `edge_length` or `size` is not positive, or an image height map has no heights.
+    pub fn new(heights: HeightMap, scale: Scalar, edge_length: Scalar, size: Scalar) -> Displacement {
+        assert!(edge_length > 0.0, "Displacement needs a positive edge length.");
+        assert!(size > 0.0, "Displacement needs a positive size.");
+        if let HeightMap::Image { heights, width } = &heights {
+            assert!(*width > 0 && heights.len() >= *width, "Displacement needs at least one height.");
+        }
+
+        return Displacement {
+            heights,
+            scale,
+            edge_length,
+            size,
+            _private: (),
+        };
+    }
+
+    /// Displaces a mesh, giving a new mesh with normals calculated from its displaced faces. Also returns the index of
+    /// the face of the original mesh each face came from, e.g. to give it the original face's material. Vertices at the
+    /// same position move together, so the surface doesn't split where the mesh's normals or texture coordinates do.
+    pub fn displace(&self, mesh: &Mesh, shading: Shading) -> (Mesh, Vec<usize>) {
+        let triangles = mesh.triangles();
+
+        // Vertices at the same position are joined, so edges shared by faces are split at the same points.
+        let mut positions = vec![];
+        let mut vertices = HashMap::new();
+        let joined: Vec<usize> = triangles
+            .points()
+            .iter()
+            .map(|point| {
+                // Adding zero turns negative zeros positive, so they match.
+                let key = point.coords.map(|coordinate| (coordinate + 0.0).to_bits());
+                return *vertices.entry(key).or_insert_with(|| {
+                    positions.push(*point);
+                    positions.len() - 1
+                });
+            })
+            .collect();
+
+        let mut faces: Vec<([Corner; 3], usize)> = triangles
+            .faces()
+            .iter()
+            .zip(mesh.normals())
+            .enumerate()
+            .map(|(origin, (face, normals))| {
+                let corners = [0, 1, 2].map(|corner| Corner {
+                    vertex: joined[face.indices[corner]],
+                    normal: normals[corner],
+                    texture_coordinates: triangles.uvs().map(|uvs| uvs[face.indices[corner]]),
+                });
+                return (corners, origin);
+            })
+            .collect();
+
+        loop {
+            let mut midpoints = HashMap::new();
+            for (corners, _) in &faces {
+                for index in 0..3 {
+                    let (a, b) = (corners[index].vertex, corners[(index + 1) % 3].vertex);
+                    if (positions[a] - positions[b]).norm() > self.edge_length {
+                        midpoints.entry(key(a, b)).or_insert_with(|| {
+                            positions.push(positions[a] + (positions[b] - positions[a]) / 2.0);
+                            positions.len() - 1
+                        });
+                    }
+                }
+            }
+            if midpoints.is_empty() {
+                break;
+            }
+
+            faces = faces
+                .into_iter()
+                .flat_map(|(corners, origin)| split(&positions, &corners, &midpoints).into_iter().map(move |corners| (corners, origin)))
+                .collect();
+        }
+
+        // Each vertex moves along the average of the normals of the corners at it, by the average of their heights.
+        let mut normals = vec![Vector::zeros(); positions.len()];
+        let mut heights = vec![(0.0, 0); positions.len()];
+        for corner in faces.iter().flat_map(|(corners, _)| corners) {
+            normals[corner.vertex] += corner.normal;
+            if let Some(texture_coordinates) = corner.texture_coordinates {
+                let (total, count) = &mut heights[corner.vertex];
+                *total += self.heights.height_at(&(texture_coordinates / self.size));
+                *count += 1;
+            }
+        }
+
+        let displaced = positions
+            .iter()
+            .zip(normals)
+            .zip(heights)
+            .map(|((position, normal), (total, count))| {
+                let normal = normal.try_normalize(0.0).unwrap_or_else(Vector::zeros);
+                let height = match count {
+                    0 => self.projected_height(position, &normal),
+                    _ => total / count as Scalar,
+                };
+                return position + normal * height * self.scale;
+            })
+            .collect();
+
+        let indices = faces.iter().map(|(corners, _)| corners.map(|corner| corner.vertex)).collect();
+        let origins = faces.iter().map(|(_, origin)| *origin).collect();
+        return (Mesh::new(displaced, indices, None, None, shading), origins);
+    }
+
+    // The height map projected along each axis, blended so faces mostly facing along an axis use its projection.
+    fn projected_height(&self, position: &Point, normal: &Vector) -> Scalar {
+        let weights = normal.map(|component| component.powi(4));
+        let total = weights.sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        let point = position.coords / self.size;
+        let heights = Vector::new(
+            self.heights.height_at(&Coordinates::new(point.y, point.z)),
+            self.heights.height_at(&Coordinates::new(point.z, point.x)),
+            self.heights.height_at(&Coordinates::new(point.x, point.y)),
+        );
+        return weights.dot(&heights) / total;
+    }
+}
+
+// Splits a triangle at the midpoints of any of its edges that have them, keeping its winding.
+fn split(positions: &[Point], corners: &[Corner; 3], midpoints: &HashMap<[usize; 2], usize>) -> Vec<[Corner; 3]> {
+    let midpoint = |index: usize| {
+        let (a, b) = (&corners[index], &corners[(index + 1) % 3]);
+        return midpoints.get(&key(a.vertex, b.vertex)).map(|&vertex| Corner {
+            vertex,
+            normal: (a.normal + b.normal) / 2.0,
+            texture_coordinates: a.texture_coordinates.zip(b.texture_coordinates).map(|(a, b)| a + (b - a) / 2.0),
+        });
+    };
+    let split = [midpoint(0), midpoint(1), midpoint(2)];
+
+    // Rotate the corners so any single split edge comes first, or any single unsplit edge last.
+    let count = split.iter().flatten().count();
+    let rotation = match count {
+        1 => (0..3).find(|&index| split[index].is_some()).unwrap(),
+        2 => (0..3).find(|&index| split[(index + 2) % 3].is_none()).unwrap(),
+        _ => 0,
+    };
+    let [c0, c1, c2] = [0, 1, 2].map(|index| corners[(index + rotation) % 3]);
+    let [m01, m12, m20] = [0, 1, 2].map(|index| split[(index + rotation) % 3]);
+
+    return match (m01, m12, m20) {
+        (Some(m01), None, None) => vec![[m01, c1, c2], [m01, c2, c0]],
+        (Some(m01), Some(m12), None) => {
+            let distance = |a: &Corner, b: &Corner| (positions[a.vertex] - positions[b.vertex]).norm();
+            // The remaining quadrilateral is split along its shorter diagonal.
+            match distance(&c0, &m12) < distance(&m01, &c2) {
+                true => vec![[m01, c1, m12], [c0, m01, m12], [c0, m12, c2]],
+                false => vec![[m01, c1, m12], [m01, m12, c2], [m01, c2, c0]],
+            }
+        }
+        (Some(m01), Some(m12), Some(m20)) => vec![[c0, m01, m20], [c1, m12, m01], [c2, m20, m12], [m01, m12, m20]],
+        _ => vec![*corners],
+    };
+}
+
+fn key(a: usize, b: usize) -> [usize; 2] {
+    return [a.min(b), a.max(b)];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts;
+    use approx::assert_abs_diff_eq;
+    use nalgebra::Point2;
+
+    fn flat(height: Scalar) -> HeightMap {
+        return HeightMap::Image {
+            heights: vec![height],
+            width: 1,
+        };
+    }
+
+    fn square(texture_coordinates: bool) -> Mesh {
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let texture_coordinates = match texture_coordinates {
+            true => Some(positions.iter().map(|position| Point2::new(position.x, position.y)).collect()),
+            false => None,
+        };
+        return Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], None, texture_coordinates, Shading::Flat);
+    }
+
+    // A cube with separate vertices for each face, as it would be for flat shading.
+    fn cube() -> Mesh {
+        let mut positions = vec![];
+        let mut indices = vec![];
+        for axis in 0..3 {
+            for side in [-0.5, 0.5] {
+                let corner = |u: Scalar, v: Scalar| {
+                    let mut point = Point::origin();
+                    point[axis] = side;
+                    point[(axis + 1) % 3] = u;
+                    point[(axis + 2) % 3] = v;
+                    return point;
+                };
+                let first = positions.len();
+                positions.extend([corner(-0.5, -0.5), corner(0.5, -0.5), corner(0.5, 0.5), corner(-0.5, 0.5)]);
+                match side > 0.0 {
+                    true => indices.extend([[first, first + 1, first + 2], [first, first + 2, first + 3]]),
+                    false => indices.extend([[first, first + 2, first + 1], [first, first + 3, first + 2]]),
+                }
+            }
+        }
+        return Mesh::new(positions, indices, None, None, Shading::Flat);
+    }
+
+    fn edge_lengths(mesh: &Mesh) -> impl Iterator<Item = Scalar> + '_ {
+        let points = mesh.triangles().points();
+        return mesh
+            .triangles()
+            .faces()
+            .iter()
+            .flat_map(move |face| (0..3).map(move |index| (points[face.indices[index]] - points[face.indices[(index + 1) % 3]]).norm()));
+    }
+
+    #[test]
+    fn image_heights_are_interpolated_and_repeat() {
+        let heights = HeightMap::Image {
+            heights: vec![0.0, 1.0],
+            width: 2,
+        };
+
+        assert_abs_diff_eq!(heights.height_at(&Coordinates::new(0.25, 0.5)), 0.0);
+        assert_abs_diff_eq!(heights.height_at(&Coordinates::new(0.5, 0.5)), 0.5);
+        assert_abs_diff_eq!(heights.height_at(&Coordinates::new(1.0, 0.5)), 0.5);
+        assert_abs_diff_eq!(heights.height_at(&Coordinates::new(1.75, 3.0)), 1.0);
+    }
+
+    #[test]
+    fn faces_are_split_to_the_edge_length() {
+        let displacement = Displacement::new(flat(0.0), 1.0, 0.1, 1.0);
+
+        let (mesh, origins) = displacement.displace(&square(false), Shading::Flat);
+
+        assert!(edge_lengths(&mesh).all(|length| length <= 0.1));
+        assert_eq!(origins.len(), mesh.triangles().faces().len());
+        assert!(origins.iter().all(|&origin| origin < 2));
+    }
+
+    #[test]
+    fn moves_along_the_normals() {
+        let displacement = Displacement::new(flat(0.5), 0.2, 0.5, 1.0);
+
+        let (mesh, _) = displacement.displace(&square(false), Shading::Flat);
+
+        for point in mesh.triangles().points() {
+            assert_abs_diff_eq!(point.z, 0.1, epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn uses_texture_coordinates() {
+        // Low along the bottom of the image and high along the top.
+        let heights = HeightMap::Image {
+            heights: vec![0.0, 0.0, 1.0, 1.0],
+            width: 2,
+        };
+        let displacement = Displacement::new(heights.clone(), 1.0, 0.25, 2.0);
+
+        let (mesh, _) = displacement.displace(&square(true), Shading::Flat);
+
+        // With a size of two, the square covers the bottom left quarter of the image.
+        for point in mesh.triangles().points() {
+            let expected = heights.height_at(&Coordinates::new(point.x / 2.0, point.y / 2.0));
+            assert_abs_diff_eq!(point.z, expected, epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn split_vertices_stay_joined() {
+        let heights = HeightMap::Noise {
+            noise: Noise::new(3),
+            octaves: 3,
+            persistence: 0.5,
+        };
+        let displacement = Displacement::new(heights, 0.1, 0.2, 0.5);
+
+        let (mesh, _) = displacement.displace(&cube(), Shading::Smooth { crease_angle: consts::PI });
+
+        // Each edge, by the positions at its ends, is shared by exactly two faces, so the surface is closed.
+        let points = mesh.triangles().points();
+        let position = |index: usize| points[index].coords.map(|coordinate| coordinate.to_bits());
+        let mut edges = HashMap::new();
+        for face in mesh.triangles().faces() {
+            for index in 0..3 {
+                let (a, b) = (position(face.indices[index]), position(face.indices[(index + 1) % 3]));
+                let edge = match a.as_slice() < b.as_slice() {
+                    true => (a, b),
+                    false => (b, a),
+                };
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+        assert!(edges.values().all(|&count| count == 2));
+        assert!(edge_lengths(&mesh).all(|length| length < 0.3));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::Sdf;
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+// Distance from the surface at which a ray is considered to have hit it.
+const SURFACE_DISTANCE: Scalar = 0.0001;
+
+// Offset used to find the gradient of the field for normals.
+const GRADIENT_OFFSET: Scalar = 0.0001;
+
+/// A surface defined by a signed distance field, found by sphere tracing; stepping along the ray by the distance to the
+/// surface until it is close enough. Tracing is limited to a bounding box.
+#[derive(Clone)]
+pub struct DistanceField {
+    field: Sdf,
+    bounds: AABB<Scalar>,
+    max_steps: usize,
+    step_scale: Scalar,
+}
+
+impl DistanceField {
+    /// Rays that haven't hit the surface after `max_steps` are taken to have missed it. Each step is scaled by
+    /// `step_scale`, which must be below one if the field overestimates distances, e.g. when twisted.
+    pub fn new(field: Sdf, bounds: AABB<Scalar>, max_steps: usize, step_scale: Scalar) -> DistanceField {
+        assert!(step_scale > 0.0, "The step scale must be positive.");
+        return DistanceField {
+            field,
+            bounds,
+            max_steps,
+            step_scale,
+        };
+    }
+
+    pub fn field(&self) -> &Sdf {
+        return &self.field;
+    }
+
+    /// The gradient of the field, which is the normal of the surface, from four samples on a tetrahedron.
+    pub fn normal(&self, point: &Point) -> Vector {
+        let normal = [
+            Vector::new(1.0, -1.0, -1.0),
+            Vector::new(-1.0, -1.0, 1.0),
+            Vector::new(-1.0, 1.0, -1.0),
+            Vector::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .map(|offset| offset * self.field.distance(&(point + offset * GRADIENT_OFFSET)))
+        .sum::<Vector>();
+        return normal.try_normalize(0.0).unwrap_or_else(Vector::y);
+    }
+
+    // The part of the ray inside the bounds, in distance along the ray rather than time.
+    fn clip(&self, origin: &Point, direction: &Vector) -> Option<(Scalar, Scalar)> {
+        let (mut near, mut far) = (0.0 as Scalar, Scalar::INFINITY);
+        for axis in 0..3 {
+            let (t0, t1) = (
+                (self.bounds.mins[axis] - origin[axis]) / direction[axis],
+                (self.bounds.maxs[axis] - origin[axis]) / direction[axis],
+            );
+            // Rays parallel to the axis give infinities, or NaN when exactly on a boundary, which max and min ignore.
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        return match near <= far {
+            true => Some((near, far)),
+            false => None,
+        };
+    }
+}
+
+impl RayCast<Scalar> for DistanceField {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        let ray = ray.inverse_transform_by(m);
+        // Rays of scaled objects aren't normalised, and distances must be.
+        let speed = ray.dir.norm();
+        let direction = ray.dir / speed;
+
+        let (near, far) = self.clip(&ray.origin, &direction)?;
+        let far = far.min(max_toi * speed);
+        if near > far {
+            return None;
+        }
+
+        // Rays starting inside trace the negated field to find their way out, unless the shape is solid. Only the field
+        // within the bounds is the shape, so that's where rays from outside them start.
+        let entry = ray.origin + direction * near;
+        let inside = self.field.distance(&entry) < 0.0;
+        if inside && solid {
+            return Some(RayIntersection::new(near / speed, m * self.normal(&entry), FeatureId::Face(0)));
+        }
+        let sign = match inside {
+            true => -1.0,
+            false => 1.0,
+        };
+
+        let mut distance = near;
+        for _ in 0..self.max_steps {
+            let point = ray.origin + direction * distance;
+            let step = sign * self.field.distance(&point);
+
+            if step < SURFACE_DISTANCE {
+                return Some(RayIntersection::new(distance / speed, m * (sign * self.normal(&point)), FeatureId::Face(0)));
+            }
+
+            distance += step * self.step_scale;
+            if distance > far {
+                return None;
+            }
+        }
+
+        return None;
+    }
+}
+
+impl Shape<Scalar> for DistanceField {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.bounds.transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return self.bounds;
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn sphere() -> DistanceField {
+        let field = Sdf::Sphere { radius: 1.0 };
+        let bounds = field.bounds().unwrap();
+        return DistanceField::new(field, bounds, 100, 1.0);
+    }
+
+    #[test]
+    fn hit() {
+        let ray = Ray::new(Point::new(0.5, 0.0, -5.0), Vector::z());
+
+        let intersection = sphere().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        let expected_z = -Scalar::sqrt(0.75);
+        assert_abs_diff_eq!(intersection.toi, 5.0 + expected_z, epsilon = 0.001);
+        assert_abs_diff_eq!(intersection.normal.x, 0.5, epsilon = 0.001);
+        assert_abs_diff_eq!(intersection.normal.z, expected_z, epsilon = 0.001);
+    }
+
+    #[test]
+    fn unnormalised_ray() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 2.0));
+
+        let intersection = sphere().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 2.0, epsilon = 0.001);
+    }
+
+    #[test]
+    fn miss() {
+        let ray = Ray::new(Point::new(1.1, 0.0, -5.0), Vector::z());
+
+        assert!(sphere().toi_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).is_none());
+        assert!(sphere()
+            .toi_with_ray(&Isometry::identity(), &Ray::new(Point::new(0.0, 0.0, -5.0), -Vector::z()), Scalar::MAX, true)
+            .is_none());
+    }
+
+    #[test]
+    fn max_toi() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::z());
+
+        assert!(sphere().toi_with_ray(&Isometry::identity(), &ray, 3.0, true).is_none());
+    }
+
+    #[test]
+    fn exit_from_inside() {
+        let ray = Ray::new(Point::origin(), Vector::x());
+
+        let intersection = sphere().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, false).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 1.0, epsilon = 0.001);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0, epsilon = 0.001);
+    }
+
+    #[test]
+    fn origin_inside_the_field_outside_the_bounds() {
+        // Two spheres, bounded to just the second, with the ray starting inside the first.
+        let field = Sdf::Union {
+            left: Box::new(Sdf::Sphere { radius: 1.0 }),
+            right: Box::new(Sdf::Translate {
+                offset: Vector::new(0.0, 0.0, 5.0),
+                field: Box::new(Sdf::Sphere { radius: 1.0 }),
+            }),
+            smoothness: 0.0,
+        };
+        let bounds = AABB::new(Point::new(-1.5, -1.5, 3.5), Point::new(1.5, 1.5, 6.5));
+        let shape = DistanceField::new(field, bounds, 100, 1.0);
+        let ray = Ray::new(Point::origin(), Vector::z());
+
+        for solid in [true, false] {
+            let intersection = shape.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, solid).unwrap();
+
+            assert_abs_diff_eq!(intersection.toi, 4.0, epsilon = 0.001);
+            assert_abs_diff_eq!(intersection.normal.z, -1.0, epsilon = 0.001);
+        }
+    }
+
+    #[test]
+    fn transformed() {
+        let m = Isometry::translation(0.0, 0.0, 10.0);
+        let ray = Ray::new(Point::origin(), Vector::z());
+
+        let intersection = sphere().toi_and_normal_with_ray(&m, &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 9.0, epsilon = 0.001);
+        assert_abs_diff_eq!(intersection.normal.z, -1.0, epsilon = 0.001);
+    }
+}
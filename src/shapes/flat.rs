@@ -0,0 +1,148 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A flat circle in the x-z plane, centred on the origin. Both sides can be hit, and the normal faces the ray.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Disc {
+    pub radius: Scalar,
+}
+
+/// A flat rectangle in the x-z plane, centred on the origin. Both sides can be hit, and the normal faces the ray.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rectangle {
+    pub half_width: Scalar,
+    pub half_depth: Scalar,
+}
+
+impl Disc {
+    pub fn new(radius: Scalar) -> Disc {
+        assert!(radius > 0.0, "Discs must have a positive radius.");
+        return Disc { radius };
+    }
+}
+
+impl Rectangle {
+    pub fn new(half_width: Scalar, half_depth: Scalar) -> Rectangle {
+        assert!(half_width > 0.0 && half_depth > 0.0, "Rectangles must have a positive width and depth.");
+        return Rectangle { half_width, half_depth };
+    }
+}
+
+/// Intersects a ray with the x-z plane, only keeping points that `contains` accepts.
+fn toi_and_normal_with_plane(m: &Isometry, ray: &Ray, max_toi: Scalar, contains: impl Fn(&Point) -> bool) -> Option<RayIntersection> {
+    let ray = ray.inverse_transform_by(m);
+    if ray.dir.y == 0.0 {
+        return None;
+    }
+
+    let toi = -ray.origin.y / ray.dir.y;
+    if toi < 0.0 || toi > max_toi || !contains(&ray.point_at(toi)) {
+        return None;
+    }
+
+    let normal = match ray.dir.y < 0.0 {
+        true => Vector::y(),
+        false => -Vector::y(),
+    };
+    return Some(RayIntersection::new(toi, m * normal, FeatureId::Face(0)));
+}
+
+impl RayCast<Scalar> for Disc {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, _: bool) -> Option<RayIntersection> {
+        return toi_and_normal_with_plane(m, ray, max_toi, |point| point.x * point.x + point.z * point.z <= self.radius * self.radius);
+    }
+}
+
+impl RayCast<Scalar> for Rectangle {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, _: bool) -> Option<RayIntersection> {
+        return toi_and_normal_with_plane(m, ray, max_toi, |point| point.x.abs() <= self.half_width && point.z.abs() <= self.half_depth);
+    }
+}
+
+impl Shape<Scalar> for Disc {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return AABB::new(Point::new(-self.radius, 0.0, -self.radius), Point::new(self.radius, 0.0, self.radius));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.radius);
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+impl Shape<Scalar> for Rectangle {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return AABB::new(
+            Point::new(-self.half_width, 0.0, -self.half_depth),
+            Point::new(self.half_width, 0.0, self.half_depth),
+        );
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.half_width.hypot(self.half_depth));
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        origin,
+        direction,
+        expected_normal,
+        case(Point::new(0.5, 5.0, 0.5), -Vector::y(), Some(Vector::y())),
+        case(Point::new(0.5, -5.0, 0.5), Vector::y(), Some(-Vector::y())),
+        case(Point::new(0.9, 5.0, 0.0), -Vector::y(), None),
+        case(Point::new(0.5, 5.0, 0.5), Vector::y(), None),
+        case(Point::new(0.0, 0.0, -5.0), Vector::z(), None)
+    )]
+    fn rectangle(origin: Point, direction: Vector, expected_normal: Option<Vector>) {
+        let rectangle = Rectangle::new(0.75, 1.0);
+
+        let intersection = rectangle.toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true);
+
+        assert_eq!(intersection.map(|intersection| intersection.normal), expected_normal);
+        if let Some(intersection) = intersection {
+            assert_abs_diff_eq!(intersection.toi, 5.0);
+        }
+    }
+
+    #[rstest(origin, hit, case(Point::new(0.5, 5.0, 0.5), true), case(Point::new(0.75, 5.0, 0.75), false))]
+    fn disc(origin: Point, hit: bool) {
+        let disc = Disc::new(1.0);
+
+        assert_eq!(
+            disc.toi_with_ray(&Isometry::identity(), &Ray::new(origin, -Vector::y()), Scalar::MAX, true)
+                .is_some(),
+            hit
+        );
+    }
+}
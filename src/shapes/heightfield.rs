@@ -0,0 +1,404 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A surface of heights on a regular grid over the x–z plane, centred on the origin, such as a landscape. `size` gives
+/// its width along the x axis, its depth along the z axis, and the height of a height of one, so heights are usually
+/// between zero and one. Each cell of the grid is split into two triangles, with normals interpolated across them.
+#[derive(Clone)]
+pub struct Heightfield {
+    // Row by row along the z axis, each row running along the x axis, already scaled by the height.
+    heights: Vec<Scalar>,
+    normals: Vec<Vector>,
+    columns: usize,
+    rows: usize,
+    cell_size: (Scalar, Scalar),
+    bounds: AABB<Scalar>,
+}
+
+impl Heightfield {
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two columns or rows, or the number of heights isn't a whole number of rows.
+    pub fn new(heights: Vec<Scalar>, columns: usize, size: Vector) -> Heightfield {
+        assert!(
+            columns >= 2 && heights.len().is_multiple_of(columns) && heights.len() / columns >= 2,
+            "A heightfield must have at least two rows and columns of heights."
+        );
+        let rows = heights.len() / columns;
+
+        let heights: Vec<Scalar> = heights.iter().map(|height| height * size.y).collect();
+        let cell_size = (size.x / (columns - 1) as Scalar, size.z / (rows - 1) as Scalar);
+
+        let (lowest, highest) = heights.iter().fold((Scalar::MAX, Scalar::MIN), |(lowest, highest), &height| {
+            return (lowest.min(height), highest.max(height));
+        });
+        let bounds = AABB::new(
+            Point::new(-size.x / 2.0, lowest, -size.z / 2.0),
+            Point::new(size.x / 2.0, highest, size.z / 2.0),
+        );
+
+        let mut heightfield = Heightfield {
+            heights,
+            normals: vec![],
+            columns,
+            rows,
+            cell_size,
+            bounds,
+        };
+        heightfield.normals = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| heightfield.vertex_normal(column, row))
+            .collect();
+        return heightfield;
+    }
+
+    /// Samples `height` on a grid with the given number of columns and rows, passing it coordinates from zero to one
+    /// across the width and depth.
+    pub fn from_function<F: Fn(Scalar, Scalar) -> Scalar>(columns: usize, rows: usize, size: Vector, height: F) -> Heightfield {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| height(column as Scalar / (columns - 1) as Scalar, row as Scalar / (rows - 1) as Scalar))
+            .collect();
+        return Heightfield::new(heights, columns, size);
+    }
+
+    pub fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    pub fn rows(&self) -> usize {
+        return self.rows;
+    }
+
+    fn height(&self, column: usize, row: usize) -> Scalar {
+        return self.heights[column + row * self.columns];
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> Point {
+        return Point::new(
+            self.bounds.mins.x + column as Scalar * self.cell_size.0,
+            self.height(column, row),
+            self.bounds.mins.z + row as Scalar * self.cell_size.1,
+        );
+    }
+
+    // From the slopes to the neighbouring vertices, or to the vertex itself at the edges.
+    fn vertex_normal(&self, column: usize, row: usize) -> Vector {
+        let (left, right) = (column.saturating_sub(1), (column + 1).min(self.columns - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.rows - 1));
+
+        let slope_x = (self.height(right, row) - self.height(left, row)) / ((right - left) as Scalar * self.cell_size.0);
+        let slope_z = (self.height(column, front) - self.height(column, back)) / ((front - back) as Scalar * self.cell_size.1);
+        return Vector::new(-slope_x, 1.0, -slope_z).normalize();
+    }
+
+    /// The normal of the triangle a ray intersection hit, as it is rather than interpolated across it.
+    pub fn face_normal(&self, feature: FeatureId) -> Option<Vector> {
+        let face = match feature {
+            FeatureId::Face(face) if face < 2 * (self.columns - 1) * (self.rows - 1) => face,
+            _ => return None,
+        };
+
+        let cell = face / 2;
+        let triangle = cell_triangles(cell % (self.columns - 1), cell / (self.columns - 1))[face % 2];
+        let [a, b, c] = triangle.map(|(column, row)| self.vertex(column, row));
+        return (b - a).cross(&(c - a)).try_normalize(0.0);
+    }
+
+    // The nearest intersection with the two triangles of a cell, with the normal interpolated from its corners.
+    fn cell_intersection(&self, column: usize, row: usize, ray: &Ray) -> Option<(Scalar, Vector, FeatureId)> {
+        let triangles = cell_triangles(column, row);
+
+        let cell = column + row * (self.columns - 1);
+        return triangles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, triangle)| {
+                let points = triangle.map(|(column, row)| self.vertex(column, row));
+                let (toi, u, v) = triangle_intersection(ray, &points)?;
+
+                let normals = triangle.map(|(column, row)| self.normals[column + row * self.columns]);
+                let normal = (normals[0] * (1.0 - u - v) + normals[1] * u + normals[2] * v).normalize();
+                return Some((toi, normal, FeatureId::Face(2 * cell + index)));
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    }
+
+    // The range of times the ray is within the bounds.
+    fn clip(&self, ray: &Ray, max_toi: Scalar) -> Option<(Scalar, Scalar)> {
+        let (mut near, mut far) = (0.0 as Scalar, max_toi);
+        for axis in 0..3 {
+            // Rays parallel to the axis are either always or never between its bounds.
+            if ray.dir[axis] == 0.0 {
+                if ray.origin[axis] < self.bounds.mins[axis] || ray.origin[axis] > self.bounds.maxs[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let (t0, t1) = (
+                (self.bounds.mins[axis] - ray.origin[axis]) / ray.dir[axis],
+                (self.bounds.maxs[axis] - ray.origin[axis]) / ray.dir[axis],
+            );
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        return match near <= far {
+            true => Some((near, far)),
+            false => None,
+        };
+    }
+}
+
+// The columns and rows of the corners of the two triangles a cell is split into.
+fn cell_triangles(column: usize, row: usize) -> [[(usize, usize); 3]; 2] {
+    let corners = [(column, row), (column + 1, row + 1), (column + 1, row), (column, row + 1)];
+    return [[corners[0], corners[1], corners[2]], [corners[0], corners[3], corners[1]]];
+}
+
+// How far outside a triangle rays can be and still hit it, so rays along the edges between cells don't slip through.
+const BARYCENTRIC_TOLERANCE: Scalar = 0.00001;
+
+// Möller–Trumbore intersection with either side of a triangle, returning the time and the barycentric coordinates of
+// the second and third points.
+fn triangle_intersection(ray: &Ray, points: &[Point; 3]) -> Option<(Scalar, Scalar, Scalar)> {
+    let (edge1, edge2) = (points[1] - points[0], points[2] - points[0]);
+    let p = ray.dir.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < Scalar::EPSILON {
+        return None;
+    }
+
+    let to_origin = ray.origin - points[0];
+    let u = to_origin.dot(&p) / determinant;
+    if !(-BARYCENTRIC_TOLERANCE..=1.0 + BARYCENTRIC_TOLERANCE).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(&edge1);
+    let v = ray.dir.dot(&q) / determinant;
+    if v < -BARYCENTRIC_TOLERANCE || u + v > 1.0 + BARYCENTRIC_TOLERANCE {
+        return None;
+    }
+
+    let toi = edge2.dot(&q) / determinant;
+    return match toi >= 0.0 {
+        true => Some((toi, u, v)),
+        false => None,
+    };
+}
+
+impl RayCast<Scalar> for Heightfield {
+    /// Steps through the cells under the ray, from grid line to grid line, testing only the cells it passes over.
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, _: bool) -> Option<RayIntersection> {
+        let ray = ray.inverse_transform_by(m);
+        let (near, far) = self.clip(&ray, max_toi)?;
+
+        let (cells_x, cells_z) = (self.columns - 1, self.rows - 1);
+        let start = ray.point_at(near);
+        let cell_of = |position: Scalar, min: Scalar, size: Scalar, cells: usize| {
+            return (((position - min) / size).floor().max(0.0) as usize).min(cells - 1);
+        };
+        let mut column = cell_of(start.x, self.bounds.mins.x, self.cell_size.0, cells_x);
+        let mut row = cell_of(start.z, self.bounds.mins.z, self.cell_size.1, cells_z);
+
+        // The time the ray crosses the next grid line along an axis, and the time between grid lines.
+        let next_crossing = |cell: usize, origin: Scalar, direction: Scalar, min: Scalar, size: Scalar| {
+            return match direction {
+                d if d > 0.0 => (min + (cell + 1) as Scalar * size - origin) / d,
+                d if d < 0.0 => (min + cell as Scalar * size - origin) / d,
+                _ => Scalar::INFINITY,
+            };
+        };
+        let mut next_x = next_crossing(column, ray.origin.x, ray.dir.x, self.bounds.mins.x, self.cell_size.0);
+        let mut next_z = next_crossing(row, ray.origin.z, ray.dir.z, self.bounds.mins.z, self.cell_size.1);
+        let (delta_x, delta_z) = (self.cell_size.0 / ray.dir.x.abs(), self.cell_size.1 / ray.dir.z.abs());
+
+        let mut entry = near;
+        while entry <= far {
+            let exit = next_x.min(next_z).min(far);
+
+            // Skip cells the ray passes entirely above or below.
+            let (entry_y, exit_y) = (ray.point_at(entry).y, ray.point_at(exit).y);
+            let corners = [
+                self.height(column, row),
+                self.height(column + 1, row),
+                self.height(column, row + 1),
+                self.height(column + 1, row + 1),
+            ];
+            let (lowest, highest) = corners.iter().fold((Scalar::MAX, Scalar::MIN), |(lowest, highest), &height| {
+                return (lowest.min(height), highest.max(height));
+            });
+            if entry_y.min(exit_y) <= highest && entry_y.max(exit_y) >= lowest {
+                if let Some((toi, normal, feature)) = self.cell_intersection(column, row, &ray).filter(|(toi, ..)| *toi <= far) {
+                    // Normals face against the ray, whichever side the surface is hit from.
+                    let normal = match normal.dot(&ray.dir) > 0.0 {
+                        true => -normal,
+                        false => normal,
+                    };
+                    return Some(RayIntersection::new(toi, m * normal, feature));
+                }
+            }
+
+            if next_x < next_z {
+                if ray.dir.x > 0.0 && column + 1 < cells_x {
+                    column += 1;
+                } else if ray.dir.x < 0.0 && column > 0 {
+                    column -= 1;
+                } else {
+                    return None;
+                }
+                entry = next_x;
+                next_x += delta_x;
+            } else {
+                if ray.dir.z > 0.0 && row + 1 < cells_z {
+                    row += 1;
+                } else if ray.dir.z < 0.0 && row > 0 {
+                    row -= 1;
+                } else {
+                    return None;
+                }
+                entry = next_z;
+                next_z += delta_z;
+            }
+        }
+
+        return None;
+    }
+}
+
+impl Shape<Scalar> for Heightfield {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.bounds.transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return self.bounds;
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    // A 4x4 field sloping up along the x axis, from zero to three.
+    fn slope() -> Heightfield {
+        return Heightfield::from_function(4, 4, Vector::new(3.0, 3.0, 3.0), |x, _| x);
+    }
+
+    fn cast(heightfield: &Heightfield, origin: Point, direction: Vector) -> Option<RayIntersection> {
+        return heightfield.toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true);
+    }
+
+    #[rstest(
+        x,
+        z,
+        case(-1.5, -1.5),
+        case(-0.3, 0.7),
+        case(0.0, 0.0),
+        case(1.2, -1.1),
+        case(1.49, 1.49)
+    )]
+    fn hit_from_above(x: Scalar, z: Scalar) {
+        let intersection = cast(&slope(), Point::new(x, 10.0, z), -Vector::y()).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 10.0 - (x + 1.5), epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, -(0.5 as Scalar).sqrt(), epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, (0.5 as Scalar).sqrt(), epsilon = 0.0001);
+    }
+
+    #[rstest(direction, case(Vector::new(1.0, 0.0, 0.0)), case(Vector::new(1.0, 0.0, 0.1)), case(Vector::new(2.0, 0.0, -0.1)))]
+    fn hit_from_the_side(direction: Vector) {
+        // Level with the slope at x = 0, so it is hit there.
+        let ray = Ray::new(Point::new(-10.0, 1.5, 0.0), direction);
+
+        let intersection = slope().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(ray.point_at(intersection.toi).x, 0.0, epsilon = 0.0001);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        case(Point::new(-10.0, 3.5, 0.0), Vector::x()),
+        case(Point::new(2.0, 10.0, 0.0), -Vector::y()),
+        case(Point::new(0.0, 10.0, 0.0), Vector::y()),
+        case(Point::new(0.0, 1.0, -10.0), Vector::new(1.0, 0.0, 0.0))
+    )]
+    fn miss(origin: Point, direction: Vector) {
+        assert!(cast(&slope(), origin, direction).is_none());
+    }
+
+    #[test]
+    fn hit_from_below_faces_down() {
+        let intersection = cast(&slope(), Point::new(0.0, -10.0, 0.0), Vector::y()).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 11.5, epsilon = 0.0001);
+        assert!(intersection.normal.y < 0.0);
+    }
+
+    #[test]
+    fn max_toi() {
+        let ray = Ray::new(Point::new(0.0, 10.0, 0.0), -Vector::y());
+
+        assert!(slope().toi_with_ray(&Isometry::identity(), &ray, 8.0, true).is_none());
+    }
+
+    #[test]
+    fn smooth_normals() {
+        // A ridge along the z axis; the normal along it points straight up, and halfway down is between that and the
+        // slope's.
+        let ridge = Heightfield::new(vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0], 3, Vector::new(2.0, 1.0, 1.0));
+
+        let top = cast(&ridge, Point::new(0.0, 10.0, 0.0), -Vector::y()).unwrap();
+        let side = cast(&ridge, Point::new(0.5, 10.0, 0.0), -Vector::y()).unwrap();
+
+        assert_abs_diff_eq!(top.normal.x, 0.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(top.normal.y, 1.0, epsilon = 0.0001);
+        let expected = (Vector::y() + Vector::new(1.0, 1.0, 0.0).normalize()).normalize();
+        assert_abs_diff_eq!(side.normal.x, expected.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(side.normal.y, expected.y, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn transformed() {
+        let m = Isometry::translation(0.0, -5.0, 0.0);
+
+        let intersection = slope()
+            .toi_and_normal_with_ray(&m, &Ray::new(Point::new(-1.5, 10.0, 0.0), -Vector::y()), Scalar::MAX, true)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 15.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn bounds() {
+        let aabb = slope().local_aabb();
+
+        assert_eq!(aabb.mins, Point::new(-1.5, 0.0, -1.5));
+        assert_eq!(aabb.maxs, Point::new(1.5, 3.0, 1.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "A heightfield must have at least two rows and columns of heights.")]
+    fn too_few_heights() {
+        Heightfield::new(vec![0.0, 0.0, 0.0], 3, Vector::new(1.0, 1.0, 1.0));
+    }
+}
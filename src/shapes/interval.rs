@@ -0,0 +1,240 @@
+use crate::maths::{Isometry, Ray, RayIntersection, Scalar, Vector};
+use ncollide3d::shape::FeatureId;
+
+/// Where a ray crosses the surface of a shape. The normal points out of the shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Crossing {
+    pub toi: Scalar,
+    pub normal: Vector,
+    pub feature: FeatureId,
+}
+
+/// The part of a ray that is inside a convex shape, which can extend infinitely in either direction if the shape is
+/// unbounded along the ray.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    pub entry: Crossing,
+    pub exit: Crossing,
+}
+
+impl Crossing {
+    pub fn new(toi: Scalar, normal: Vector, feature: FeatureId) -> Crossing {
+        return Crossing { toi, normal, feature };
+    }
+
+    fn at_infinity(toi: Scalar) -> Crossing {
+        return Crossing::new(toi, Vector::zeros(), FeatureId::Unknown);
+    }
+}
+
+impl Interval {
+    pub fn new(entry: Crossing, exit: Crossing) -> Interval {
+        return Interval { entry, exit };
+    }
+
+    /// The whole ray, for shapes that don't limit it.
+    pub fn unbounded() -> Interval {
+        return Interval::new(Crossing::at_infinity(Scalar::NEG_INFINITY), Crossing::at_infinity(Scalar::INFINITY));
+    }
+
+    /// The part of the ray between two parallel planes, each `distance` from the origin along `normal`.
+    pub fn slab(ray: &Ray, normal: &Vector, distance: Scalar, features: [FeatureId; 2]) -> Option<Interval> {
+        let origin = ray.origin.coords.dot(normal);
+        let direction = ray.dir.dot(normal);
+
+        if direction == 0.0 {
+            return match origin.abs() <= distance {
+                true => Some(Interval::unbounded()),
+                false => None,
+            };
+        }
+
+        let positive = Crossing::new((distance - origin) / direction, *normal, features[0]);
+        let negative = Crossing::new((-distance - origin) / direction, -normal, features[1]);
+        return Some(match direction > 0.0 {
+            true => Interval::new(negative, positive),
+            false => Interval::new(positive, negative),
+        });
+    }
+
+    /// The part of the ray inside both intervals.
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        let entry = match self.entry.toi >= other.entry.toi {
+            true => self.entry,
+            false => other.entry,
+        };
+        let exit = match self.exit.toi <= other.exit.toi {
+            true => self.exit,
+            false => other.exit,
+        };
+
+        return match entry.toi <= exit.toi {
+            true => Some(Interval::new(entry, exit)),
+            false => None,
+        };
+    }
+
+    /// The part of the ray inside either interval, which must overlap as they come from parts of a convex shape.
+    pub fn union(&self, other: &Interval) -> Interval {
+        let entry = match self.entry.toi <= other.entry.toi {
+            true => self.entry,
+            false => other.entry,
+        };
+        let exit = match self.exit.toi >= other.exit.toi {
+            true => self.exit,
+            false => other.exit,
+        };
+
+        return Interval::new(entry, exit);
+    }
+
+    /// The first crossing in front of the ray's origin. Rays starting inside a solid shape hit it immediately, as with
+    /// ncollide's shapes; otherwise they hit it on the way out, with the normal facing back along the ray.
+    pub fn to_ray_intersection(&self, m: &Isometry, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        let (toi, normal, feature) = match (self.entry.toi >= 0.0, solid) {
+            _ if self.exit.toi < 0.0 => return None,
+            (true, _) => (self.entry.toi, self.entry.normal, self.entry.feature),
+            (false, true) => (0.0, self.entry.normal, self.entry.feature),
+            (false, false) => (self.exit.toi, -self.exit.normal, self.exit.feature),
+        };
+
+        if toi > max_toi || !toi.is_finite() {
+            return None;
+        }
+
+        return Some(RayIntersection::new(toi, m * normal, feature));
+    }
+
+    /// As `to_ray_intersection`, for the first interval that isn't behind the ray's origin.
+    pub fn first_ray_intersection(intervals: &[Interval], m: &Isometry, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return intervals
+            .iter()
+            .find(|interval| interval.exit.toi >= 0.0)?
+            .to_ray_intersection(m, max_toi, solid);
+    }
+}
+
+/// The interval where a quadratic in the ray's time, a t² + b t + c, is not positive. A quadratic that opens downwards
+/// is not positive on two infinite intervals, of which only the one `keep` accepts a time inside of is returned. The
+/// normal at each crossing is found from its time.
+pub fn quadratic_interval(
+    (a, b, c): (Scalar, Scalar, Scalar),
+    keep: impl Fn(Scalar) -> bool,
+    normal: impl Fn(Scalar) -> Vector,
+    feature: FeatureId,
+) -> Option<Interval> {
+    let crossing = |toi: Scalar| Crossing::new(toi, normal(toi), feature);
+
+    if a == 0.0 {
+        if b == 0.0 {
+            return match c <= 0.0 {
+                true => Some(Interval::unbounded()),
+                false => None,
+            };
+        }
+
+        let root = -c / b;
+        return Some(match b > 0.0 {
+            true => Interval::new(Crossing::at_infinity(Scalar::NEG_INFINITY), crossing(root)),
+            false => Interval::new(crossing(root), Crossing::at_infinity(Scalar::INFINITY)),
+        });
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return match a < 0.0 {
+            true => Some(Interval::unbounded()),
+            false => None,
+        };
+    }
+
+    // Avoids cancellation when b is much larger than a and c.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (mut near, mut far) = (q / a, c / q);
+    if q == 0.0 {
+        (near, far) = (0.0, 0.0);
+    }
+    if near > far {
+        (near, far) = (far, near);
+    }
+
+    if a > 0.0 {
+        return Some(Interval::new(crossing(near), crossing(far)));
+    }
+
+    if keep(near - 1.0) {
+        return Some(Interval::new(Crossing::at_infinity(Scalar::NEG_INFINITY), crossing(near)));
+    }
+    if keep(far + 1.0) {
+        return Some(Interval::new(crossing(far), Crossing::at_infinity(Scalar::INFINITY)));
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::Point;
+    use approx::assert_abs_diff_eq;
+
+    fn interval(entry: Scalar, exit: Scalar) -> Interval {
+        return Interval::new(
+            Crossing::new(entry, -Vector::x(), FeatureId::Face(0)),
+            Crossing::new(exit, Vector::x(), FeatureId::Face(1)),
+        );
+    }
+
+    #[test]
+    fn slab() {
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), -Vector::y());
+
+        let slab = Interval::slab(&ray, &Vector::y(), 1.0, [FeatureId::Face(1), FeatureId::Face(2)]).unwrap();
+
+        assert_eq!(slab.entry, Crossing::new(4.0, Vector::y(), FeatureId::Face(1)));
+        assert_eq!(slab.exit, Crossing::new(6.0, -Vector::y(), FeatureId::Face(2)));
+    }
+
+    #[test]
+    fn slab_parallel() {
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::x());
+
+        assert_eq!(Interval::slab(&ray, &Vector::y(), 1.0, [FeatureId::Unknown; 2]), None);
+        assert_eq!(Interval::slab(&ray, &Vector::y(), 6.0, [FeatureId::Unknown; 2]), Some(Interval::unbounded()));
+    }
+
+    #[test]
+    fn intersection_and_union() {
+        assert_eq!(interval(1.0, 3.0).intersection(&interval(2.0, 4.0)), Some(interval(2.0, 3.0)));
+        assert_eq!(interval(1.0, 2.0).intersection(&interval(3.0, 4.0)), None);
+        assert_eq!(interval(1.0, 3.0).union(&interval(2.0, 4.0)), interval(1.0, 4.0));
+    }
+
+    #[test]
+    fn ray_intersection() {
+        let m = Isometry::identity();
+
+        assert_eq!(interval(1.0, 3.0).to_ray_intersection(&m, 10.0, true).unwrap().toi, 1.0);
+        assert!(interval(1.0, 3.0).to_ray_intersection(&m, 0.5, true).is_none());
+        assert!(interval(-3.0, -1.0).to_ray_intersection(&m, 10.0, true).is_none());
+        assert_eq!(interval(-1.0, 3.0).to_ray_intersection(&m, 10.0, true).unwrap().toi, 0.0);
+
+        let exit = interval(-1.0, 3.0).to_ray_intersection(&m, 10.0, false).unwrap();
+        assert_eq!(exit.toi, 3.0);
+        assert_eq!(exit.normal, -Vector::x());
+    }
+
+    #[test]
+    fn quadratic() {
+        // (t - 1)(t - 3)
+        let interval = quadratic_interval((1.0, -4.0, 3.0), |_| true, |_| Vector::x(), FeatureId::Face(0)).unwrap();
+        assert_abs_diff_eq!(interval.entry.toi, 1.0);
+        assert_abs_diff_eq!(interval.exit.toi, 3.0);
+
+        // -(t - 1)(t - 3), keeping the part after the roots.
+        let interval = quadratic_interval((-1.0, 4.0, -3.0), |toi| toi > 0.0, |_| Vector::x(), FeatureId::Face(0));
+        assert_abs_diff_eq!(interval.unwrap().entry.toi, 3.0);
+        assert_eq!(interval.unwrap().exit.toi, Scalar::INFINITY);
+
+        assert_eq!(quadratic_interval((1.0, 0.0, 1.0), |_| true, |_| Vector::x(), FeatureId::Face(0)), None);
+    }
+}
@@ -0,0 +1,389 @@
+use crate::maths::packet::{Lanes, RayPacket, PACKET_SIZE};
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Vector};
+use nalgebra::{Point2, Point3, Unit};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::partitioning::BVH;
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape, TriMesh};
+use std::collections::HashMap;
+
+/// How normals are calculated across the faces of a mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Shading {
+    /// Each face uses its own normal, so the facets are visible.
+    Flat,
+    /// Normals are interpolated across faces from the vertex normals. Where the mesh gives no normals they are
+    /// averaged from the faces around each vertex, except across edges where the faces meet at more than the crease
+    /// angle, in radians, which stay sharp.
+    Smooth { crease_angle: Scalar },
+}
+
+/// A triangle mesh. Ray casts return a normal interpolated across the face that was hit.
+#[derive(Clone)]
+pub struct Mesh {
+    triangles: TriMesh<Scalar>,
+    // The normal at each corner of each face.
+    normals: Vec<[Vector; 3]>,
+}
+
+impl Mesh {
+    /// Creates a mesh from vertex positions, the indices of the vertices of each triangle, and optionally a normal per
+    /// vertex. Texture coordinates, if given, are per vertex and returned with ray intersections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no triangles, an index is out of range, or the number of normals or texture coordinates
+    /// does not match the number of positions.
+    pub fn new(
+        positions: Vec<Point>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vector>>,
+        texture_coordinates: Option<Vec<Point2<Scalar>>>,
+        shading: Shading,
+    ) -> Mesh {
+        assert!(!indices.is_empty(), "A mesh must have at least one triangle.");
+        assert!(
+            indices.iter().flatten().all(|&index| index < positions.len()),
+            "Mesh indices must be less than the number of positions, {}.",
+            positions.len()
+        );
+        if let Some(normals) = &normals {
+            assert_eq!(normals.len(), positions.len(), "A mesh must have one normal per position.");
+        }
+        if let Some(texture_coordinates) = &texture_coordinates {
+            assert_eq!(
+                texture_coordinates.len(),
+                positions.len(),
+                "A mesh must have one texture coordinate per position."
+            );
+        }
+
+        let face_normals: Vec<Vector> = indices.iter().map(|face| face_normal(&positions, face)).collect();
+
+        let corner_normals = match (shading, normals) {
+            (Shading::Flat, _) => face_normals.iter().map(|normal| [*normal; 3]).collect(),
+            (Shading::Smooth { .. }, Some(normals)) => indices.iter().map(|face| face.map(|index| normals[index].normalize())).collect(),
+            (Shading::Smooth { crease_angle }, None) => smooth_normals(&positions, &indices, &face_normals, crease_angle),
+        };
+
+        let faces = indices.iter().map(|face| Point3::new(face[0], face[1], face[2])).collect();
+
+        return Mesh {
+            triangles: TriMesh::new(positions, faces, texture_coordinates),
+            normals: corner_normals,
+        };
+    }
+
+    pub fn triangles(&self) -> &TriMesh<Scalar> {
+        return &self.triangles;
+    }
+
+    /// The normal at each corner of each face.
+    pub fn normals(&self) -> &[[Vector; 3]] {
+        return &self.normals;
+    }
+
+    /// The face a ray intersection hit, whichever side it was hit from.
+    pub fn face(&self, feature: FeatureId) -> Option<usize> {
+        return match feature {
+            FeatureId::Face(face) => Some(face % self.triangles.faces().len()),
+            _ => None,
+        };
+    }
+
+    /// The normal of the face a ray intersection hit, as it is rather than interpolated across it.
+    pub fn face_normal(&self, feature: FeatureId) -> Option<Vector> {
+        let face = self.face(feature)?;
+        return self.triangles.faces()[face].normal.map(|normal| normal.into_inner());
+    }
+
+    /// The intersections of a packet of rays with the mesh, if before the times given. The rays are traced through
+    /// the mesh's bounding volume tree together.
+    pub fn intersect_packet(&self, m: &Isometry, packet: &RayPacket, max_tois: Lanes) -> [Option<RayIntersection>; PACKET_SIZE] {
+        let local = packet.inverse_transform_by(m);
+        let bvt = self.triangles.bvt();
+
+        let mut max_tois = max_tois;
+        let mut faces = [None; PACKET_SIZE];
+        let mut stack: Vec<_> = bvt.root().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            let (bounds, face) = bvt.content(node);
+            if !local.hits_bounds(bounds, max_tois).any() {
+                continue;
+            }
+
+            match face {
+                Some(&face) => {
+                    let points = self.triangles.points();
+                    let indices = self.triangles.faces()[face].indices;
+                    let tois = local.intersect_triangle(&points[indices.x], &points[indices.y], &points[indices.z]);
+                    let closer = tois.lt(max_tois);
+                    max_tois = Lanes::select(closer, tois, max_tois);
+                    for (lane, closest) in faces.iter_mut().enumerate() {
+                        if closer.lane(lane) {
+                            *closest = Some(face);
+                        }
+                    }
+                }
+                None => stack.extend((0..bvt.num_children(node)).map(|child| bvt.child(child, node))),
+            }
+        }
+
+        // Work out the details of the closest hits the same way as for single rays.
+        return std::array::from_fn(|lane| {
+            let face = faces[lane]?;
+            let ray = &local.rays[lane];
+            let mut intersection = self
+                .triangles
+                .triangle_at(face)
+                .toi_and_normal_with_ray(&Isometry::identity(), ray, Scalar::MAX, false)?;
+            // Like the whole mesh, number the backs of the faces after the fronts.
+            intersection.feature = match intersection.feature {
+                FeatureId::Face(1) => FeatureId::Face(face + self.triangles.faces().len()),
+                _ => FeatureId::Face(face),
+            };
+            intersection.normal = m * intersection.normal;
+            return self.with_interpolated_normal(m, ray, intersection);
+        });
+    }
+
+    // Replaces the normal of an intersection with the face's normal, with the interpolated normal.
+    fn with_interpolated_normal(&self, m: &Isometry, local_ray: &Ray, mut intersection: RayIntersection) -> Option<RayIntersection> {
+        let face = self.face(intersection.feature)?;
+
+        let local_point = local_ray.point_at(intersection.toi);
+        let normal = (m * self.interpolated_normal(face, &local_point)).normalize();
+
+        // The triangle's normal faces the ray, so keep the interpolated normal on the same side.
+        intersection.normal = match normal.dot(&intersection.normal) < 0.0 {
+            true => -normal,
+            false => normal,
+        };
+
+        return Some(intersection);
+    }
+
+    fn interpolated_normal(&self, face: usize, point: &Point) -> Vector {
+        let indices = self.triangles.faces()[face].indices;
+        let points = self.triangles.points();
+        let (u, v, w) = barycentric(point, &points[indices.x], &points[indices.y], &points[indices.z]);
+
+        let normals = &self.normals[face];
+        return normals[0] * u + normals[1] * v + normals[2] * w;
+    }
+}
+
+// The unit normal of a face, following the winding of its vertices. Degenerate faces get an arbitrary normal.
+fn face_normal(positions: &[Point], face: &[usize; 3]) -> Vector {
+    return area_normal(positions, face).try_normalize(0.0).unwrap_or_else(Vector::z);
+}
+
+// A normal whose length is twice the area of the face, so faces can be weighted by area when averaging.
+fn area_normal(positions: &[Point], face: &[usize; 3]) -> Vector {
+    let (a, b, c) = (positions[face[0]], positions[face[1]], positions[face[2]]);
+    return (b - a).cross(&(c - a));
+}
+
+fn smooth_normals(positions: &[Point], indices: &[[usize; 3]], face_normals: &[Vector], crease_angle: Scalar) -> Vec<[Vector; 3]> {
+    // Vertices are matched by position rather than index, so faces still join smoothly where vertices have been
+    // duplicated, e.g. to give them different texture coordinates.
+    let key = |point: &Point| [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()];
+
+    let mut faces_at_position: HashMap<_, Vec<usize>> = HashMap::new();
+    for (face, face_indices) in indices.iter().enumerate() {
+        for &index in face_indices {
+            faces_at_position.entry(key(&positions[index])).or_default().push(face);
+        }
+    }
+
+    let minimum_cos = crease_angle.cos();
+
+    return indices
+        .iter()
+        .enumerate()
+        .map(|(face, face_indices)| {
+            face_indices.map(|index| {
+                let normal: Vector = faces_at_position[&key(&positions[index])]
+                    .iter()
+                    .filter(|&&other| face_normals[face].dot(&face_normals[other]) >= minimum_cos)
+                    .map(|&other| area_normal(positions, &indices[other]))
+                    .sum();
+
+                return normal.try_normalize(0.0).unwrap_or(face_normals[face]);
+            })
+        })
+        .collect();
+}
+
+// Barycentric coordinates of a point, assumed to be on the triangle abc, for each of a, b and c.
+fn barycentric(point: &Point, a: &Point, b: &Point, c: &Point) -> (Scalar, Scalar, Scalar) {
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+
+    let d00 = ab.dot(&ab);
+    let d01 = ab.dot(&ac);
+    let d11 = ac.dot(&ac);
+    let d20 = ap.dot(&ab);
+    let d21 = ap.dot(&ac);
+
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator == 0.0 {
+        return (1.0, 0.0, 0.0);
+    }
+
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    return (1.0 - v - w, v, w);
+}
+
+impl RayCast<Scalar> for Mesh {
+    fn toi_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<Scalar> {
+        return self.triangles.toi_with_ray(m, ray, max_toi, solid);
+    }
+
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        let intersection = self.triangles.toi_and_normal_and_uv_with_ray(m, ray, max_toi, solid)?;
+        return self.with_interpolated_normal(m, &ray.inverse_transform_by(m), intersection);
+    }
+}
+
+impl Shape<Scalar> for Mesh {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return Shape::aabb(&self.triangles, m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return self.triangles.local_aabb();
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.triangles.bounding_sphere(m);
+    }
+
+    fn tangent_cone_contains_dir(&self, feature: FeatureId, m: &Isometry, deformations: Option<&[Scalar]>, dir: &Unit<Vector>) -> bool {
+        return self.triangles.tangent_cone_contains_dir(feature, m, deformations, dir);
+    }
+
+    fn subshape_containing_feature(&self, feature: FeatureId) -> usize {
+        return self.triangles.subshape_containing_feature(feature);
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts::PI;
+    use approx::assert_abs_diff_eq;
+
+    // A roof shape; two faces meeting along the x axis at a right angle.
+    fn roof(shading: Shading) -> Mesh {
+        let positions = vec![
+            Point::new(-1.0, 0.0, 1.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(1.0, -1.0, 0.0),
+            Point::new(-1.0, -1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(-1.0, 1.0, 0.0),
+        ];
+        let indices = vec![[0, 3, 2], [0, 2, 1], [0, 1, 4], [0, 4, 5]];
+        return Mesh::new(positions, indices, None, None, shading);
+    }
+
+    fn normal_at(mesh: &Mesh, x: Scalar, y: Scalar) -> Vector {
+        let ray = Ray::new(Point::new(x, y, 5.0), -Vector::z());
+        return mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap().normal;
+    }
+
+    fn assert_vector(actual: Vector, expected: Vector) {
+        assert_abs_diff_eq!(actual.x, expected.x, epsilon = 0.001);
+        assert_abs_diff_eq!(actual.y, expected.y, epsilon = 0.001);
+        assert_abs_diff_eq!(actual.z, expected.z, epsilon = 0.001);
+    }
+
+    #[test]
+    fn flat_shading() {
+        let mesh = roof(Shading::Flat);
+
+        assert_vector(normal_at(&mesh, 0.0, -0.1), Vector::new(0.0, -1.0, 1.0).normalize());
+        assert_vector(normal_at(&mesh, 0.0, -0.9), Vector::new(0.0, -1.0, 1.0).normalize());
+    }
+
+    #[test]
+    fn smooth_shading_interpolates_normals() {
+        let mesh = roof(Shading::Smooth { crease_angle: PI });
+
+        // Along the ridge the normal is the average of the two faces.
+        assert_vector(normal_at(&mesh, 0.0, -0.001), Vector::z());
+        // Halfway down, halfway between the ridge normal and the face normal at the bottom edge.
+        assert_vector(normal_at(&mesh, 0.0, -0.5), (Vector::z() + Vector::new(0.0, -1.0, 1.0).normalize()).normalize());
+    }
+
+    #[test]
+    fn crease_angle_keeps_sharp_edges() {
+        let mesh = roof(Shading::Smooth { crease_angle: PI / 4.0 });
+
+        assert_vector(normal_at(&mesh, 0.0, -0.001), Vector::new(0.0, -1.0, 1.0).normalize());
+    }
+
+    #[test]
+    fn given_normals() {
+        let positions = vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(0.0, 1.0, 0.0)];
+        let normals = vec![Vector::new(-1.0, 0.0, 1.0), Vector::new(1.0, 0.0, 1.0), Vector::z()];
+        let mesh = Mesh::new(positions, vec![[0, 1, 2]], Some(normals), None, Shading::Smooth { crease_angle: PI });
+
+        assert_vector(normal_at(&mesh, 0.5, 0.0), Vector::z());
+    }
+
+    #[test]
+    fn normal_faces_ray_from_behind() {
+        let mesh = roof(Shading::Smooth { crease_angle: PI });
+        let ray = Ray::new(Point::new(0.0, -0.5, -5.0), Vector::z());
+
+        let normal = mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap().normal;
+
+        assert!(normal.z < 0.0);
+    }
+
+    #[test]
+    fn intersect_packet() {
+        let mesh = roof(Shading::Smooth { crease_angle: PI });
+        let m = Isometry::new(Vector::new(1.0, 2.0, 3.0), Vector::new(0.1, 0.2, 0.3));
+        let packet = RayPacket::new([
+            Ray::new(Point::new(1.0, 1.5, 10.0), -Vector::z()),
+            Ray::new(Point::new(1.0, 2.5, 10.0), -Vector::z()),
+            Ray::new(Point::new(10.0, 1.5, 10.0), -Vector::z()),
+            Ray::new(Point::new(1.0, 1.5, -10.0), Vector::z()),
+        ]);
+
+        let actual = mesh.intersect_packet(&m, &packet, Lanes::splat(100.0));
+
+        for (lane, actual) in actual.iter().enumerate() {
+            let expected = mesh.toi_and_normal_with_ray(&m, &packet.rays[lane], 100.0, false);
+            assert_eq!(actual.is_some(), expected.is_some(), "lane {}", lane);
+            if let (Some(actual), Some(expected)) = (actual, expected) {
+                assert_abs_diff_eq!(actual.toi, expected.toi, epsilon = 0.0001);
+                assert_vector(actual.normal, expected.normal);
+                assert_eq!(actual.feature, expected.feature);
+            }
+        }
+        assert!(actual[0].is_some() && actual[3].is_some());
+    }
+
+    #[test]
+    fn face() {
+        let mesh = roof(Shading::Flat);
+
+        assert_eq!(mesh.face(FeatureId::Face(1)), Some(1));
+        assert_eq!(mesh.face(FeatureId::Face(5)), Some(1));
+        assert_eq!(mesh.face(FeatureId::Unknown), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mesh indices must be less than the number of positions")]
+    fn index_out_of_range() {
+        Mesh::new(vec![Point::origin(); 3], vec![[0, 1, 3]], None, None, Shading::Flat);
+    }
+}
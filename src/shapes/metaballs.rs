@@ -0,0 +1,464 @@
+use crate::maths::polynomial;
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::{Crossing, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, BoundingVolume, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A source of the field of a metaball surface. Its field at a distance d is strength × (1 - d²/radius²)², falling
+/// smoothly to zero at `radius`. Negative strengths carve dents and holes in the surface around them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MetaballSource {
+    /// A ball around a point.
+    Point { centre: Point, radius: Scalar, strength: Scalar },
+    /// A capsule around a line segment, such as the bond between two atoms.
+    Segment {
+        start: Point,
+        end: Point,
+        radius: Scalar,
+        strength: Scalar,
+    },
+}
+
+/// A blobby surface where the summed fields of its sources reach a threshold. Sources blend into each other when
+/// close, like drops of liquid. A lone source of strength one has a surface at radius × √(1 - √threshold).
+#[derive(Clone)]
+pub struct Metaballs {
+    sources: Vec<MetaballSource>,
+    threshold: Scalar,
+}
+
+// The part of a ray within a source's radius where the squared distance to the source is a single quadratic,
+// a t² + b t + c.
+struct Piece {
+    start: f64,
+    end: f64,
+    strength: f64,
+    radius_squared: f64,
+    distance_squared: [f64; 3],
+}
+
+impl MetaballSource {
+    pub fn radius(&self) -> Scalar {
+        return match self {
+            MetaballSource::Point { radius, .. } | MetaballSource::Segment { radius, .. } => *radius,
+        };
+    }
+
+    pub fn strength(&self) -> Scalar {
+        return match self {
+            MetaballSource::Point { strength, .. } | MetaballSource::Segment { strength, .. } => *strength,
+        };
+    }
+
+    fn closest_point(&self, point: &Point) -> Point {
+        return match self {
+            MetaballSource::Point { centre, .. } => *centre,
+            MetaballSource::Segment { start, end, .. } => {
+                let axis = end - start;
+                let along = match axis.norm_squared() > 0.0 {
+                    true => ((point - start).dot(&axis) / axis.norm_squared()).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                start + axis * along
+            }
+        };
+    }
+
+    fn field(&self, point: &Point) -> Scalar {
+        let falloff = 1.0 - (point - self.closest_point(point)).norm_squared() / self.radius().powi(2);
+        return match falloff > 0.0 {
+            true => self.strength() * falloff * falloff,
+            false => 0.0,
+        };
+    }
+
+    fn gradient(&self, point: &Point) -> Vector {
+        let offset = point - self.closest_point(point);
+        let radius_squared = self.radius().powi(2);
+        let falloff = 1.0 - offset.norm_squared() / radius_squared;
+        return match falloff > 0.0 {
+            true => offset * (-4.0 * self.strength() * falloff / radius_squared),
+            false => Vector::zeros(),
+        };
+    }
+
+    fn aabb(&self) -> AABB<Scalar> {
+        let sphere = |centre: &Point| AABB::new(*centre, *centre).loosened(self.radius());
+        return match self {
+            MetaballSource::Point { centre, .. } => sphere(centre),
+            MetaballSource::Segment { start, end, .. } => sphere(start).merged(&sphere(end)),
+        };
+    }
+
+    // The pieces of the ray within the radius of the source.
+    #[cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+    fn pieces(&self, ray: &Ray) -> Vec<Piece> {
+        let (origin, direction) = (ray.origin.coords.map(|c| c as f64), ray.dir.map(|c| c as f64));
+        let radius_squared = (self.radius() as f64).powi(2);
+        let piece = |region: (f64, f64), distance_squared: [f64; 3]| {
+            let (start, end) = within_radius(distance_squared, radius_squared)?;
+            let (start, end) = (start.max(region.0), end.min(region.1));
+            return match start < end {
+                true => Some(Piece {
+                    start,
+                    end,
+                    strength: self.strength() as f64,
+                    radius_squared,
+                    distance_squared,
+                }),
+                false => None,
+            };
+        };
+        let to_point = |point: &Point| {
+            let offset = origin - point.coords.map(|c| c as f64);
+            return [direction.norm_squared(), 2.0 * offset.dot(&direction), offset.norm_squared()];
+        };
+
+        let everywhere = (f64::NEG_INFINITY, f64::INFINITY);
+        let (start, end) = match self {
+            MetaballSource::Point { centre, .. } => return piece(everywhere, to_point(centre)).into_iter().collect(),
+            MetaballSource::Segment { start, end, .. } => (start, end),
+        };
+
+        // Cull rays that miss the sphere around the whole segment before splitting the ray up.
+        let bounds = BoundingSphere::new(nalgebra::center(start, end), (end - start).norm() / 2.0 + self.radius());
+        let offset = ray.origin - bounds.center();
+        let (a, b, c) = (
+            ray.dir.norm_squared(),
+            2.0 * offset.dot(&ray.dir),
+            offset.norm_squared() - bounds.radius().powi(2),
+        );
+        if b * b - 4.0 * a * c < 0.0 {
+            return vec![];
+        }
+
+        // Where the nearest point is along the segment, rather than one of its ends, the squared distance is that to
+        // the infinite line through it.
+        let axis = (end - start).map(|c| c as f64);
+        let length_squared = axis.norm_squared();
+        if length_squared == 0.0 {
+            return piece(everywhere, to_point(start)).into_iter().collect();
+        }
+        let offset = origin - start.coords.map(|c| c as f64);
+        let (offset_along, direction_along) = (offset.dot(&axis), direction.dot(&axis));
+        let to_line = [
+            direction.norm_squared() - direction_along * direction_along / length_squared,
+            2.0 * (offset.dot(&direction) - offset_along * direction_along / length_squared),
+            offset.norm_squared() - offset_along * offset_along / length_squared,
+        ];
+
+        if direction_along == 0.0 {
+            let distance_squared = match offset_along / length_squared {
+                along if along < 0.0 => to_point(start),
+                along if along > 1.0 => to_point(end),
+                _ => to_line,
+            };
+            return piece(everywhere, distance_squared).into_iter().collect();
+        }
+
+        // The times the nearest point reaches the start and end of the segment.
+        let (at_start, at_end) = (-offset_along / direction_along, (length_squared - offset_along) / direction_along);
+        let (before, after) = match at_start < at_end {
+            true => ((everywhere.0, at_start), (at_end, everywhere.1)),
+            false => ((at_start, everywhere.1), (everywhere.0, at_end)),
+        };
+        return vec![
+            piece(before, to_point(start)),
+            piece((at_start.min(at_end), at_start.max(at_end)), to_line),
+            piece(after, to_point(end)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+    }
+}
+
+// The range of times a squared distance, as a quadratic in time, is within the radius.
+fn within_radius(distance_squared: [f64; 3], radius_squared: f64) -> Option<(f64, f64)> {
+    let [a, b, c] = distance_squared;
+    // Rays parallel to a segment stay the same distance from it.
+    if a.abs() < 1e-12 {
+        return match c < radius_squared {
+            true => Some((f64::NEG_INFINITY, f64::INFINITY)),
+            false => None,
+        };
+    }
+
+    let roots = polynomial::solve_quadratic(a, b, c - radius_squared);
+    return match roots.len() {
+        2 => Some((roots[0], roots[1])),
+        _ => None,
+    };
+}
+
+impl Piece {
+    // Adds the field of the piece, as a quartic in the time since `origin`, to `coefficients`.
+    fn add_field(&self, origin: f64, coefficients: &mut [f64; 5]) {
+        let [a, b, c] = self.distance_squared;
+        // The falloff, 1 - d²/r², as a quadratic in the time since the origin.
+        let falloff = [
+            -a / self.radius_squared,
+            -(2.0 * a * origin + b) / self.radius_squared,
+            1.0 - (a * origin * origin + b * origin + c) / self.radius_squared,
+        ];
+
+        coefficients[0] += self.strength * falloff[0] * falloff[0];
+        coefficients[1] += self.strength * 2.0 * falloff[0] * falloff[1];
+        coefficients[2] += self.strength * (falloff[1] * falloff[1] + 2.0 * falloff[0] * falloff[2]);
+        coefficients[3] += self.strength * 2.0 * falloff[1] * falloff[2];
+        coefficients[4] += self.strength * falloff[2] * falloff[2];
+    }
+}
+
+impl Metaballs {
+    /// # Panics
+    ///
+    /// Panics if there are no sources, a radius isn't positive, or the threshold isn't positive.
+    pub fn new(sources: Vec<MetaballSource>, threshold: Scalar) -> Metaballs {
+        assert!(!sources.is_empty(), "Metaballs must have at least one source.");
+        assert!(sources.iter().all(|source| source.radius() > 0.0), "Metaball sources must have positive radii.");
+        assert!(threshold > 0.0, "The metaball threshold must be positive.");
+        return Metaballs { sources, threshold };
+    }
+
+    pub fn sources(&self) -> &[MetaballSource] {
+        return &self.sources;
+    }
+
+    /// The summed field of all the sources; the surface is where this equals the threshold.
+    pub fn field(&self, point: &Point) -> Scalar {
+        return self.sources.iter().map(|source| source.field(point)).sum();
+    }
+
+    /// The outward normal, against the gradient of the field.
+    pub fn normal(&self, point: &Point) -> Vector {
+        let gradient: Vector = self.sources.iter().map(|source| source.gradient(point)).sum();
+        return (-gradient).try_normalize(0.0).unwrap_or_else(Vector::y);
+    }
+}
+
+impl Solid for Metaballs {
+    /// The field along the ray is a quartic between the times it enters or leaves the range of a source, or moves
+    /// between the end and middle of a segment, so each of those spans is solved exactly.
+    #[cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let pieces: Vec<Piece> = self.sources.iter().flat_map(|source| source.pieces(ray)).collect();
+
+        let mut times: Vec<f64> = pieces.iter().flat_map(|piece| [piece.start, piece.end]).collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        times.dedup();
+
+        let crossing = |time: f64| {
+            let toi = time as Scalar;
+            return Crossing::new(toi, self.normal(&ray.point_at(toi)), FeatureId::Face(0));
+        };
+
+        let mut intervals: Vec<Interval> = vec![];
+        let mut entry: Option<f64> = None;
+        for span in times.windows(2) {
+            let (start, end) = (span[0], span[1]);
+            let middle = (start + end) / 2.0;
+
+            let mut coefficients = [0.0; 5];
+            for piece in pieces.iter().filter(|piece| piece.start <= middle && middle <= piece.end) {
+                piece.add_field(start, &mut coefficients);
+            }
+            coefficients[4] -= self.threshold as f64;
+
+            let length = end - start;
+            let mut splits = vec![0.0];
+            splits.extend(
+                polynomial::solve_quartic(coefficients[0], coefficients[1], coefficients[2], coefficients[3], coefficients[4])
+                    .into_iter()
+                    .filter(|&root| 0.0 < root && root < length),
+            );
+            splits.push(length);
+
+            // Check whether the ray is inside between each root, as close or repeated roots can be merged or lost.
+            for split in splits.windows(2) {
+                let inside = polynomial::evaluate((split[0] + split[1]) / 2.0, &coefficients).0 > 0.0;
+                match (inside, entry) {
+                    (true, None) => entry = Some(start + split[0]),
+                    (false, Some(time)) => {
+                        intervals.push(Interval::new(crossing(time), crossing(start + split[0])));
+                        entry = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // The field beyond the last source is zero, so the ray is always outside by then.
+        if let (Some(time), Some(&last)) = (entry, times.last()) {
+            intervals.push(Interval::new(crossing(time), crossing(last)));
+        }
+        return intervals;
+    }
+}
+
+impl RayCast<Scalar> for Metaballs {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return Interval::first_ray_intersection(&self.intervals(&ray.inverse_transform_by(m)), m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Metaballs {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return self
+            .sources
+            .iter()
+            .map(|source| source.aabb())
+            .fold(self.sources[0].aabb(), |bounds, source| bounds.merged(&source));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn ball(x: Scalar) -> MetaballSource {
+        return MetaballSource::Point {
+            centre: Point::new(x, 0.0, 0.0),
+            radius: 2.0,
+            strength: 1.0,
+        };
+    }
+
+    fn cast(metaballs: &Metaballs, origin: Point, direction: Vector) -> Option<RayIntersection> {
+        return metaballs.toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true);
+    }
+
+    #[test]
+    fn lone_ball() {
+        // The surface is at 2 × √(1 - √0.25) = √2.
+        let metaballs = Metaballs::new(vec![ball(0.0)], 0.25);
+
+        let intersection = cast(&metaballs, Point::new(-10.0, 0.0, 0.0), Vector::x()).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 10.0 - (2.0 as Scalar).sqrt(), epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn balls_blend() {
+        // Apart, each surface would only reach √(2 - 1.2²) above the midpoint between them.
+        let metaballs = Metaballs::new(vec![ball(-1.2), ball(1.2)], 0.25);
+        let ray_origin = Point::new(0.0, 10.0, 0.0);
+
+        let intersection = cast(&metaballs, ray_origin, -Vector::y()).unwrap();
+
+        let point = Ray::new(ray_origin, -Vector::y()).point_at(intersection.toi);
+        assert!(point.y > (2.0 as Scalar - 1.44).sqrt());
+        assert_abs_diff_eq!(metaballs.field(&point), 0.25, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, 1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn intervals_through_separate_balls() {
+        let metaballs = Metaballs::new(vec![ball(-5.0), ball(5.0)], 0.25);
+
+        let intervals = metaballs.intervals(&Ray::new(Point::new(-10.0, 0.0, 0.0), Vector::x()));
+
+        let root_2 = (2.0 as Scalar).sqrt();
+        assert_eq!(intervals.len(), 2);
+        assert_abs_diff_eq!(intervals[0].entry.toi, 5.0 - root_2, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[0].exit.toi, 5.0 + root_2, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[1].entry.toi, 15.0 - root_2, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[0].exit.normal.x, 1.0, epsilon = 0.0001);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        case(Point::new(-10.0, 1.5, 0.0), Vector::x()),
+        case(Point::new(-10.0, 0.0, 0.0), -Vector::x()),
+        case(Point::new(0.0, 3.0, 0.0), Vector::z())
+    )]
+    fn miss(origin: Point, direction: Vector) {
+        assert!(cast(&Metaballs::new(vec![ball(0.0)], 0.25), origin, direction).is_none());
+    }
+
+    #[test]
+    fn negative_strength_makes_a_hole() {
+        let hole = MetaballSource::Point {
+            centre: Point::new(1.5, 0.0, 0.0),
+            radius: 1.0,
+            strength: -1.0,
+        };
+        let metaballs = Metaballs::new(vec![ball(0.0), hole], 0.25);
+
+        let intersection = cast(&metaballs, Point::new(10.0, 0.0, 0.0), -Vector::x()).unwrap();
+
+        assert!(intersection.toi > 10.0 - (2.0 as Scalar).sqrt() + 0.1);
+        assert_abs_diff_eq!(intersection.normal.x, 1.0, epsilon = 0.0001);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        toi,
+        case(Point::new(0.0, 10.0, 0.0), -Vector::y(), 10.0 - (2.0 as Scalar).sqrt()),
+        case(Point::new(-10.0, 0.0, 0.0), Vector::x(), 9.0 - (2.0 as Scalar).sqrt()),
+        case(Point::new(10.0, 0.0, 0.0), -Vector::x(), 9.0 - (2.0 as Scalar).sqrt()),
+        // Along the segment from off its end.
+        case(Point::new(-10.0, 0.5, 0.0), Vector::x(), 9.0 - (1.75 as Scalar).sqrt()),
+        // Diagonally towards the end.
+        case(Point::new(-3.0, 3.0, 0.0), Vector::new(1.0, -1.0, 0.0), (10.0 - (12.0 as Scalar).sqrt()) / 4.0 * (2.0 as Scalar).sqrt())
+    )]
+    fn segment(origin: Point, direction: Vector, toi: Scalar) {
+        let segment = MetaballSource::Segment {
+            start: Point::new(-1.0, 0.0, 0.0),
+            end: Point::new(1.0, 0.0, 0.0),
+            radius: 2.0,
+            strength: 1.0,
+        };
+        let metaballs = Metaballs::new(vec![segment], 0.25);
+
+        let intersection = cast(&metaballs, origin, direction.normalize()).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, toi, epsilon = 0.0001);
+        let point = Ray::new(origin, direction.normalize()).point_at(intersection.toi);
+        assert_abs_diff_eq!(metaballs.field(&point), 0.25, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn exit_from_inside() {
+        let metaballs = Metaballs::new(vec![ball(0.0)], 0.25);
+
+        let intersection = metaballs
+            .toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(Point::origin(), Vector::z()), Scalar::MAX, false)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, (2.0 as Scalar).sqrt(), epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.z, -1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn bounds() {
+        let metaballs = Metaballs::new(vec![ball(-1.0), ball(3.0)], 0.25);
+
+        let aabb = metaballs.local_aabb();
+
+        assert_eq!(aabb.mins, Point::new(-3.0, -2.0, -2.0));
+        assert_eq!(aabb.maxs, Point::new(5.0, 2.0, 2.0));
+    }
+}
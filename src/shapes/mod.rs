@@ -0,0 +1,56 @@
+mod capsule;
+pub use self::capsule::*;
+
+mod cone;
+pub use self::cone::*;
+
+mod csg;
+pub use self::csg::*;
+
+mod cylinder;
+pub use self::cylinder::*;
+
+mod displacement;
+pub use self::displacement::*;
+
+mod distance_field;
+pub use self::distance_field::*;
+
+mod flat;
+pub use self::flat::*;
+
+mod heightfield;
+pub use self::heightfield::*;
+
+mod interval;
+pub use self::interval::*;
+
+mod mesh;
+pub use self::mesh::*;
+
+mod metaballs;
+pub use self::metaballs::*;
+
+mod nurbs;
+pub use self::nurbs::*;
+
+mod patches;
+pub use self::patches::*;
+
+mod scaled;
+pub use self::scaled::*;
+
+mod sdf;
+pub use self::sdf::*;
+
+mod solid;
+pub use self::solid::*;
+
+mod subdivision;
+pub use self::subdivision::*;
+
+mod teapot;
+pub use self::teapot::*;
+
+mod torus;
+pub use self::torus::*;
@@ -0,0 +1,330 @@
+use crate::maths::{Point, Scalar, Vector};
+
+/// A non-uniform rational B-spline surface. Control points are in rows along the u direction, each row running along
+/// the v direction. Bicubic Bezier patches are the special case of degree three with no inner knots and unit weights.
+#[derive(Debug, Clone)]
+pub struct NurbsSurface {
+    degrees: [usize; 2],
+    knots: [Vec<Scalar>; 2],
+    counts: [usize; 2],
+    // Row by row, each point multiplied by its weight.
+    weighted_points: Vec<Vector>,
+    weights: Vec<Scalar>,
+}
+
+/// A point on a surface with its partial derivatives along u and v.
+#[derive(Debug, Copy, Clone)]
+pub struct SurfacePoint {
+    pub point: Point,
+    pub du: Vector,
+    pub dv: Vector,
+}
+
+impl NurbsSurface {
+    /// Creates a surface of the given degrees along u and v. Each direction needs as many knots as control points plus
+    /// the degree plus one. Weights default to one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rows of control points or weights are different lengths, the knots are decreasing or the wrong
+    /// number, or a weight isn't positive.
+    pub fn new(degrees: [usize; 2], knots: [Vec<Scalar>; 2], control_points: Vec<Vec<Point>>, weights: Option<Vec<Vec<Scalar>>>) -> NurbsSurface {
+        let counts = [control_points.len(), control_points.first().map_or(0, |row| row.len())];
+        assert!(
+            counts[0] > degrees[0] && counts[1] > degrees[1] && control_points.iter().all(|row| row.len() == counts[1]),
+            "A surface needs rows of the same number of control points, more than its degree in each direction."
+        );
+        for direction in 0..2 {
+            assert_eq!(
+                knots[direction].len(),
+                counts[direction] + degrees[direction] + 1,
+                "A surface needs as many knots as control points plus its degree plus one in each direction."
+            );
+            assert!(knots[direction].windows(2).all(|pair| pair[0] <= pair[1]), "Knots cannot decrease.");
+        }
+
+        let weights: Vec<Scalar> = match weights {
+            Some(weights) => {
+                assert!(
+                    weights.len() == counts[0] && weights.iter().all(|row| row.len() == counts[1]),
+                    "A surface needs one weight per control point."
+                );
+                weights.into_iter().flatten().collect()
+            }
+            None => vec![1.0; counts[0] * counts[1]],
+        };
+        assert!(weights.iter().all(|&weight| weight > 0.0), "Weights must be positive.");
+
+        let weighted_points = control_points
+            .iter()
+            .flatten()
+            .zip(weights.iter())
+            .map(|(point, weight)| point.coords * *weight)
+            .collect();
+
+        return NurbsSurface {
+            degrees,
+            knots,
+            counts,
+            weighted_points,
+            weights,
+        };
+    }
+
+    /// A bicubic Bezier patch from four rows of four control points, over the unit square.
+    pub fn bezier(control_points: &[[Point; 4]; 4]) -> NurbsSurface {
+        let knots = vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        let rows = control_points.iter().map(|row| row.to_vec()).collect();
+        return NurbsSurface::new([3, 3], [knots.clone(), knots], rows, None);
+    }
+
+    /// The range of parameters of the surface along u, for a `direction` of 0, or v, for 1.
+    pub fn domain(&self, direction: usize) -> (Scalar, Scalar) {
+        let (knots, degree) = (&self.knots[direction], self.degrees[direction]);
+        return (knots[degree], knots[self.counts[direction]]);
+    }
+
+    /// The distinct knots across the domain along u or v, between which the surface is a smooth polynomial.
+    pub fn breaks(&self, direction: usize) -> Vec<Scalar> {
+        let (start, end) = self.domain(direction);
+        let mut breaks: Vec<Scalar> = self.knots[direction].iter().copied().filter(|&knot| start <= knot && knot <= end).collect();
+        breaks.dedup();
+        return breaks;
+    }
+
+    /// The control points, row by row.
+    pub fn control_points(&self) -> impl Iterator<Item = Point> + '_ {
+        return self
+            .weighted_points
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(point, weight)| Point::from(point / *weight));
+    }
+
+    /// The point at the given parameters, which are clamped to the domain, and the derivatives there.
+    pub fn evaluate(&self, u: Scalar, v: Scalar) -> SurfacePoint {
+        let (u_span, u_basis, u_derivatives) = self.basis(0, u);
+        let (v_span, v_basis, v_derivatives) = self.basis(1, v);
+        let (u_first, v_first) = (u_span - self.degrees[0], v_span - self.degrees[1]);
+
+        // Sums in homogeneous coordinates, then the quotient rule for the derivatives.
+        let (mut point, mut du, mut dv) = (Vector::zeros(), Vector::zeros(), Vector::zeros());
+        let (mut weight, mut weight_du, mut weight_dv) = (0.0, 0.0, 0.0);
+        for (a, (&basis_u, &derivative_u)) in u_basis.iter().zip(u_derivatives.iter()).enumerate() {
+            for (b, (&basis_v, &derivative_v)) in v_basis.iter().zip(v_derivatives.iter()).enumerate() {
+                let index = (u_first + a) * self.counts[1] + v_first + b;
+                let (control, control_weight) = (self.weighted_points[index], self.weights[index]);
+
+                point += control * (basis_u * basis_v);
+                du += control * (derivative_u * basis_v);
+                dv += control * (basis_u * derivative_v);
+                weight += control_weight * basis_u * basis_v;
+                weight_du += control_weight * derivative_u * basis_v;
+                weight_dv += control_weight * basis_u * derivative_v;
+            }
+        }
+
+        let point = point / weight;
+        return SurfacePoint {
+            point: Point::from(point),
+            du: (du - point * weight_du) / weight,
+            dv: (dv - point * weight_dv) / weight,
+        };
+    }
+
+    /// The unit normal, following the cross product of the derivatives along u and v. Where that vanishes, e.g. where
+    /// a row of control points meets at a pole, it is taken from just inside the domain instead.
+    pub fn normal(&self, u: Scalar, v: Scalar) -> Vector {
+        return self.normal_at(u, v, &self.evaluate(u, v));
+    }
+
+    /// The same as `normal`, reusing the surface point at the parameters if it has already been evaluated.
+    pub fn normal_at(&self, u: Scalar, v: Scalar, surface_point: &SurfacePoint) -> Vector {
+        // Derivatives that vanish are left with rounding errors, so the cross product is compared to their size.
+        let scale = surface_point.du.norm_squared() + surface_point.dv.norm_squared();
+        if let Some(normal) = surface_point.du.cross(&surface_point.dv).try_normalize(scale * 1.0e-6) {
+            return normal;
+        }
+
+        let nudge = |direction: usize, parameter: Scalar| {
+            let (start, end) = self.domain(direction);
+            return parameter + (start + end - 2.0 * parameter).signum() * (end - start) * 1.0e-3;
+        };
+        let surface_point = self.evaluate(nudge(0, u), nudge(1, v));
+        return surface_point.du.cross(&surface_point.dv).try_normalize(0.0).unwrap_or_else(Vector::y);
+    }
+
+    // The index of the knot span containing the parameter, and the non-zero basis functions there and their
+    // derivatives, which are for the control points from the span minus the degree.
+    fn basis(&self, direction: usize, parameter: Scalar) -> (usize, Vec<Scalar>, Vec<Scalar>) {
+        let (knots, degree) = (&self.knots[direction], self.degrees[direction]);
+        let (start, end) = self.domain(direction);
+        let parameter = parameter.max(start).min(end);
+
+        // The last span with a knot at or before the parameter, but not the empty spans at the end of the domain.
+        let last = self.counts[direction] - 1;
+        let span = match parameter >= end {
+            true => (degree..=last).rev().find(|&span| knots[span] < knots[span + 1]).unwrap_or(last),
+            false => degree + knots[degree..=last + 1].partition_point(|&knot| knot <= parameter) - 1,
+        };
+
+        let basis = basis_functions(knots, degree, span, parameter);
+        if degree == 0 {
+            return (span, basis, vec![0.0]);
+        }
+
+        // Derivatives come from the basis functions of one degree lower.
+        let lower = basis_functions(knots, degree - 1, span, parameter);
+        let degree_scalar = degree as Scalar;
+        let term = |value: Scalar, width: Scalar| match width > 0.0 {
+            true => degree_scalar * value / width,
+            false => 0.0,
+        };
+        let derivatives = (0..=degree)
+            .map(|r| {
+                let index = span - degree + r;
+                let left = match r {
+                    0 => 0.0,
+                    _ => term(lower[r - 1], knots[index + degree] - knots[index]),
+                };
+                let right = match r == degree {
+                    true => 0.0,
+                    false => term(lower[r], knots[index + degree + 1] - knots[index + 1]),
+                };
+                return left - right;
+            })
+            .collect();
+
+        return (span, basis, derivatives);
+    }
+}
+
+// The basis functions of the given degree that are non-zero in the span, by the Cox–de Boor recurrence.
+fn basis_functions(knots: &[Scalar], degree: usize, span: usize, parameter: Scalar) -> Vec<Scalar> {
+    let mut basis = vec![0.0; degree + 1];
+    let (mut left, mut right) = (vec![0.0; degree + 1], vec![0.0; degree + 1]);
+    basis[0] = 1.0;
+    for j in 1..=degree {
+        left[j] = parameter - knots[span + 1 - j];
+        right[j] = knots[span + j] - parameter;
+        let mut saved = 0.0;
+        for r in 0..j {
+            let denominator = right[r + 1] + left[j - r];
+            let temporary = match denominator == 0.0 {
+                true => 0.0,
+                false => basis[r] / denominator,
+            };
+            basis[r] = saved + right[r + 1] * temporary;
+            saved = left[j - r] * temporary;
+        }
+        basis[j] = saved;
+    }
+    return basis;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    // A patch over the unit square in the x–z plane, bulging up in the middle.
+    fn bump() -> NurbsSurface {
+        let control_points = [0, 1, 2, 3].map(|i| {
+            return [0, 1, 2, 3].map(|j| {
+                let height = if (1..=2).contains(&i) && (1..=2).contains(&j) { 1.0 } else { 0.0 };
+                return Point::new(i as Scalar / 3.0, height, j as Scalar / 3.0);
+            });
+        });
+        return NurbsSurface::bezier(&control_points);
+    }
+
+    // A quarter of a unit cylinder of height one around the y axis, from the x axis to the z axis, which is exactly
+    // representable with weights.
+    fn quarter_cylinder() -> NurbsSurface {
+        let weight = Scalar::sqrt(0.5);
+        let arc = [Point::new(1.0, 0.0, 0.0), Point::new(1.0, 0.0, 1.0), Point::new(0.0, 0.0, 1.0)];
+        let rows = arc.iter().map(|point| vec![*point, point + Vector::y()]).collect();
+        let weights = vec![vec![1.0, 1.0], vec![weight, weight], vec![1.0, 1.0]];
+        return NurbsSurface::new([2, 1], [vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0], vec![0.0, 0.0, 1.0, 1.0]], rows, Some(weights));
+    }
+
+    #[rstest(u, v, case(0.0, 0.0), case(0.5, 0.5), case(0.25, 0.8), case(1.0, 1.0), case(1.0, 0.3))]
+    fn derivatives_match_differences(u: Scalar, v: Scalar) {
+        for surface in [bump(), quarter_cylinder()] {
+            let step = 0.001;
+            let point = surface.evaluate(u, v);
+            let (u0, u1) = ((u - step).max(0.0), (u + step).min(1.0));
+            let (v0, v1) = ((v - step).max(0.0), (v + step).min(1.0));
+
+            let du = (surface.evaluate(u1, v).point - surface.evaluate(u0, v).point) / (u1 - u0);
+            let dv = (surface.evaluate(u, v1).point - surface.evaluate(u, v0).point) / (v1 - v0);
+
+            assert_abs_diff_eq!(point.du, du, epsilon = 0.01);
+            assert_abs_diff_eq!(point.dv, dv, epsilon = 0.01);
+        }
+    }
+
+    #[test]
+    fn bezier_corners_and_centre() {
+        let surface = bump();
+
+        assert_abs_diff_eq!(surface.evaluate(0.0, 0.0).point, Point::new(0.0, 0.0, 0.0), epsilon = 0.0001);
+        assert_abs_diff_eq!(surface.evaluate(1.0, 1.0).point, Point::new(1.0, 0.0, 1.0), epsilon = 0.0001);
+        // Each direction's cubic has weights 1/8, 3/8, 3/8, 1/8 in the middle, so the height is (3/4)².
+        assert_abs_diff_eq!(surface.evaluate(0.5, 0.5).point, Point::new(0.5, 0.5625, 0.5), epsilon = 0.0001);
+    }
+
+    #[rstest(u, case(0.0), case(0.3), case(0.5), case(1.0))]
+    fn rational_surface_is_exact(u: Scalar) {
+        let surface = quarter_cylinder();
+
+        let point = surface.evaluate(u, 0.5).point;
+
+        assert_abs_diff_eq!(Vector::new(point.x, 0.0, point.z).norm(), 1.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(point.y, 0.5, epsilon = 0.0001);
+        let normal = surface.normal(u, 0.5);
+        assert_abs_diff_eq!(
+            normal.dot(&point.coords.component_mul(&Vector::new(1.0, 0.0, 1.0))).abs(),
+            1.0,
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn inner_knots() {
+        // A degree one surface with an inner knot is the bilinear patches between its control points.
+        let rows = vec![
+            vec![Point::new(0.0, 0.0, 0.0), Point::new(0.0, 0.0, 1.0)],
+            vec![Point::new(1.0, 1.0, 0.0), Point::new(1.0, 1.0, 1.0)],
+            vec![Point::new(2.0, 0.0, 0.0), Point::new(2.0, 0.0, 1.0)],
+        ];
+        let surface = NurbsSurface::new([1, 1], [vec![0.0, 0.0, 0.5, 1.0, 1.0], vec![0.0, 0.0, 1.0, 1.0]], rows, None);
+
+        assert_eq!(surface.breaks(0), vec![0.0, 0.5, 1.0]);
+        assert_abs_diff_eq!(surface.evaluate(0.25, 0.5).point, Point::new(0.5, 0.5, 0.5), epsilon = 0.0001);
+        assert_abs_diff_eq!(surface.evaluate(0.75, 0.5).du, Vector::new(2.0, -2.0, 0.0), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn normal_at_pole() {
+        // A patch whose first row of control points all meet at one point.
+        let control_points = [0, 1, 2, 3].map(|i| {
+            return [0, 1, 2, 3].map(|j| match i {
+                0 => Point::new(0.0, 1.0, 0.0),
+                _ => Point::new(i as Scalar, 1.0 - i as Scalar / 3.0, j as Scalar - 1.5),
+            });
+        });
+        let surface = NurbsSurface::bezier(&control_points);
+
+        let normal = surface.normal(0.0, 0.5);
+
+        assert_abs_diff_eq!(normal.norm(), 1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    #[should_panic(expected = "A surface needs as many knots as control points plus its degree plus one in each direction.")]
+    fn wrong_number_of_knots() {
+        NurbsSurface::new([1, 1], [vec![0.0, 1.0], vec![0.0, 0.0, 1.0, 1.0]], vec![vec![Point::origin(); 2]; 2], None);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::scene::Bvh;
+use crate::shapes::NurbsSurface;
+use nalgebra::Matrix3;
+use ncollide3d::bounding_volume::{BoundingSphere, BoundingVolume, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+// Pieces are split until the normals across them are within about 25° of the normal at their centre...
+const FLAT_COSINE: Scalar = 0.9;
+
+// ...and they are within this fraction of their size of the bilinear surface between their corners.
+const FLATNESS: Scalar = 0.05;
+
+// Pieces this many splits from a knot span are never split further, e.g. around poles, where normals vary however
+// small the piece.
+const MAXIMUM_DEPTH: usize = 8;
+
+// The number of samples along each side of a piece when checking how flat it is.
+const SAMPLES: usize = 5;
+
+const NEWTON_ITERATIONS: usize = 10;
+
+/// Smooth surfaces made of NURBS surfaces, such as bicubic Bezier patches, like the surfaces of a model exported from a
+/// CAD program. Rays are intersected with the surfaces directly by Newton's method, rather than with triangles, so
+/// silhouettes and reflections are smooth, and normals come from the derivatives of the surfaces. The surfaces are
+/// split into nearly flat pieces in a bounding volume hierarchy to find where to start.
+#[derive(Clone)]
+pub struct Patches {
+    surfaces: Vec<NurbsSurface>,
+    pieces: Vec<Piece>,
+    bvh: Bvh,
+    bounds: AABB<Scalar>,
+    // The distance from a surface within which a ray is taken to hit it.
+    tolerance: Scalar,
+}
+
+// A rectangle of parameters of one of the surfaces.
+#[derive(Debug, Copy, Clone)]
+struct Piece {
+    surface: usize,
+    u: (Scalar, Scalar),
+    v: (Scalar, Scalar),
+}
+
+impl Patches {
+    pub fn new(surfaces: Vec<NurbsSurface>) -> Patches {
+        let mut pieces = vec![];
+        let mut piece_bounds = vec![];
+        for (index, surface) in surfaces.iter().enumerate() {
+            // Each knot span is a separate polynomial, which can have a kink where it meets the next.
+            let (u_breaks, v_breaks) = (surface.breaks(0), surface.breaks(1));
+            for u in u_breaks.windows(2) {
+                for v in v_breaks.windows(2) {
+                    let piece = Piece {
+                        surface: index,
+                        u: (u[0], u[1]),
+                        v: (v[0], v[1]),
+                    };
+                    piece.subdivide(surface, 0, &mut pieces, &mut piece_bounds);
+                }
+            }
+        }
+
+        let bounds = piece_bounds
+            .iter()
+            .fold(AABB::new_invalid(), |bounds: AABB<Scalar>, piece| bounds.merged(piece));
+        let bounds = match pieces.is_empty() {
+            true => AABB::new(Point::origin(), Point::origin()),
+            false => bounds,
+        };
+        let tolerance = (bounds.maxs - bounds.mins).norm().max(1.0) * 1.0e-5;
+
+        return Patches {
+            surfaces,
+            bvh: Bvh::new(&piece_bounds),
+            pieces,
+            bounds,
+            tolerance,
+        };
+    }
+
+    pub fn surfaces(&self) -> &[NurbsSurface] {
+        return &self.surfaces;
+    }
+
+    // The intersection with the surface within a piece, starting Newton's method from the middle of the piece.
+    fn intersect_piece(&self, piece: &Piece, ray: &Ray, max_toi: Scalar) -> Option<(Scalar, Scalar, Scalar)> {
+        let surface = &self.surfaces[piece.surface];
+        let (mut u, mut v) = ((piece.u.0 + piece.u.1) / 2.0, (piece.v.0 + piece.v.1) / 2.0);
+        let mut toi = (surface.evaluate(u, v).point - ray.origin).dot(&ray.dir) / ray.dir.norm_squared();
+
+        // Steps may leave the piece a little, as long as they finish inside it, but not the surface, as beyond its
+        // edges the parameters would change without moving the point.
+        let (width, depth) = (piece.u.1 - piece.u.0, piece.v.1 - piece.v.0);
+        let (u_domain, v_domain) = (surface.domain(0), surface.domain(1));
+        let u_range = ((piece.u.0 - width).max(u_domain.0), (piece.u.1 + width).min(u_domain.1));
+        let v_range = ((piece.v.0 - depth).max(v_domain.0), (piece.v.1 + depth).min(v_domain.1));
+
+        for _ in 0..NEWTON_ITERATIONS {
+            let surface_point = surface.evaluate(u, v);
+            let residual = surface_point.point - ray.point_at(toi);
+            if residual.norm() <= self.tolerance {
+                let (u_slack, v_slack) = (width * 1.0e-3, depth * 1.0e-3);
+                let inside = (piece.u.0 - u_slack..=piece.u.1 + u_slack).contains(&u) && (piece.v.0 - v_slack..=piece.v.1 + v_slack).contains(&v);
+                return match inside && toi >= 0.0 && toi < max_toi {
+                    true => Some((toi, u, v)),
+                    false => None,
+                };
+            }
+
+            let jacobian = Matrix3::from_columns(&[surface_point.du, surface_point.dv, -ray.dir]);
+            let step = jacobian.try_inverse()? * residual;
+            u = (u - step.x).max(u_range.0).min(u_range.1);
+            v = (v - step.y).max(v_range.0).min(v_range.1);
+            toi -= step.z;
+        }
+
+        return None;
+    }
+}
+
+impl Piece {
+    fn subdivide(&self, surface: &NurbsSurface, depth: usize, pieces: &mut Vec<Piece>, piece_bounds: &mut Vec<AABB<Scalar>>) {
+        let parameter = |(start, end): (Scalar, Scalar), step: usize| start + (end - start) * step as Scalar / (SAMPLES - 1) as Scalar;
+        let mut samples = [[Point::origin(); SAMPLES]; SAMPLES];
+        let mut normals = [[Vector::zeros(); SAMPLES]; SAMPLES];
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let (u, v) = (parameter(self.u, i), parameter(self.v, j));
+                let surface_point = surface.evaluate(u, v);
+                samples[i][j] = surface_point.point;
+                normals[i][j] = surface.normal_at(u, v, &surface_point);
+            }
+        }
+        let bounds = AABB::from_points(samples.iter().flatten());
+        let size = (bounds.maxs - bounds.mins).norm();
+
+        let last = SAMPLES - 1;
+        let corners = [samples[0][0], samples[0][last], samples[last][0], samples[last][last]];
+        let centre_normal = normals[last / 2][last / 2];
+        let (mut deviation, mut flat): (Scalar, bool) = (0.0, true);
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let (s, t) = (i as Scalar / last as Scalar, j as Scalar / last as Scalar);
+                let bilinear = corners[0].coords * ((1.0 - s) * (1.0 - t))
+                    + corners[1].coords * ((1.0 - s) * t)
+                    + corners[2].coords * (s * (1.0 - t))
+                    + corners[3].coords * (s * t);
+                deviation = deviation.max((samples[i][j].coords - bilinear).norm());
+                flat &= normals[i][j].dot(&centre_normal) >= FLAT_COSINE;
+            }
+        }
+
+        if depth >= MAXIMUM_DEPTH || (flat && deviation <= FLATNESS * size) {
+            // The surface can bulge a little between the samples.
+            pieces.push(*self);
+            piece_bounds.push(bounds.loosened(deviation + size * FLATNESS));
+            return;
+        }
+
+        // Only split across the direction the surface bends along, or both if it bends along both, e.g. around a pole.
+        let (mut u_bend, mut v_bend): (Scalar, Scalar) = (1.0, 1.0);
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                u_bend = u_bend.min(normals[i][j].dot(&normals[last / 2][j]));
+                v_bend = v_bend.min(normals[i][j].dot(&normals[i][last / 2]));
+            }
+        }
+        let halves = |(start, end): (Scalar, Scalar), split: bool| match split {
+            true => vec![(start, (start + end) / 2.0), ((start + end) / 2.0, end)],
+            false => vec![(start, end)],
+        };
+        for &u in &halves(self.u, u_bend <= v_bend || u_bend < FLAT_COSINE) {
+            for &v in &halves(self.v, v_bend <= u_bend || v_bend < FLAT_COSINE) {
+                let piece = Piece { surface: self.surface, u, v };
+                piece.subdivide(surface, depth + 1, pieces, piece_bounds);
+            }
+        }
+    }
+}
+
+impl RayCast<Scalar> for Patches {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, _: bool) -> Option<RayIntersection> {
+        let ray = ray.inverse_transform_by(m);
+        let mut parameters = (0.0, 0.0);
+        let (index, intersection) = self.bvh.first_intersection(&ray, max_toi, |index, max_toi| {
+            let (toi, u, v) = self.intersect_piece(&self.pieces[index], &ray, max_toi)?;
+            parameters = (u, v);
+            return Some(RayIntersection::new(toi, Vector::zeros(), FeatureId::Face(self.pieces[index].surface)));
+        })?;
+
+        // Only the closest hit needs a normal. Normals face against the ray, whichever side the surface is hit from.
+        let normal = self.surfaces[self.pieces[index].surface].normal(parameters.0, parameters.1);
+        let normal = match normal.dot(&ray.dir) > 0.0 {
+            true => -normal,
+            false => normal,
+        };
+        return Some(RayIntersection::new(intersection.toi, m * normal, intersection.feature));
+    }
+}
+
+impl Shape<Scalar> for Patches {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.bounds.transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        return self.bounds;
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::teapot;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    // A patch over the square from -1 to 1 in the x–z plane, bulging up to a height of 0.5625 in the middle.
+    fn bump() -> Patches {
+        let control_points = [0, 1, 2, 3].map(|i| {
+            return [0, 1, 2, 3].map(|j| {
+                let height = if (1..=2).contains(&i) && (1..=2).contains(&j) { 1.0 } else { 0.0 };
+                return Point::new(i as Scalar * 2.0 / 3.0 - 1.0, height, j as Scalar * 2.0 / 3.0 - 1.0);
+            });
+        });
+        return Patches::new(vec![NurbsSurface::bezier(&control_points)]);
+    }
+
+    fn cast(patches: &Patches, origin: Point, direction: Vector) -> Option<RayIntersection> {
+        return patches.toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true);
+    }
+
+    #[rstest(x, z, case(0.0, 0.0), case(0.3, -0.2), case(-0.9, 0.9), case(0.99, 0.5))]
+    fn hits_lie_on_the_surface(x: Scalar, z: Scalar) {
+        let patches = bump();
+
+        let intersection = cast(&patches, Point::new(x, 5.0, z), -Vector::y()).unwrap();
+
+        // The patch is a graph over x–z, with u along x and v along z, linearly, as its control points are evenly
+        // spaced.
+        let surface = &patches.surfaces()[0];
+        let (u, v) = ((x + 1.0) / 2.0, (z + 1.0) / 2.0);
+        assert_abs_diff_eq!(intersection.toi, 5.0 - surface.evaluate(u, v).point.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.dot(&surface.normal(u, v)).abs(), 1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn normals_face_the_ray() {
+        let intersection = cast(&bump(), Point::new(0.2, -5.0, 0.1), Vector::y()).unwrap();
+
+        assert!(intersection.normal.y < 0.0);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        case(Point::new(1.5, 5.0, 0.0), -Vector::y()),
+        case(Point::new(0.0, 0.6, -5.0), Vector::z()),
+        case(Point::new(0.0, 5.0, 0.0), Vector::y())
+    )]
+    fn miss(origin: Point, direction: Vector) {
+        assert!(cast(&bump(), origin, direction).is_none());
+    }
+
+    #[test]
+    fn grazing_the_top() {
+        // Just under the highest point, so the ray passes through the bump twice.
+        let intersection = cast(&bump(), Point::new(-5.0, 0.56, 0.0), Vector::x()).unwrap();
+
+        assert!(intersection.toi > 4.0 && intersection.toi < 5.0);
+        assert!(intersection.normal.x < 0.0);
+    }
+
+    #[test]
+    fn teapot_is_watertight() {
+        let patches = Patches::new(teapot());
+
+        // Rays towards the axis from all around the body, including along the seams between the quarters, all hit it.
+        for step in 0..64 {
+            let angle = step as Scalar * crate::maths::consts::TWO_PI / 64.0;
+            let direction = Vector::new(angle.cos(), 0.0, angle.sin());
+            let origin = Point::new(0.0, 1.0, 0.0) + direction * 10.0;
+
+            let intersection = cast(&patches, origin, -direction).unwrap();
+
+            let point = origin - direction * intersection.toi;
+            assert!(point.coords.norm() < 3.5);
+            assert!(intersection.normal.dot(&direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn teapot_lid() {
+        let patches = Patches::new(teapot());
+
+        let intersection = cast(&patches, Point::new(0.0, 10.0, 0.0), -Vector::y()).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 10.0 - 3.15, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal, Vector::y(), epsilon = 0.001);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Vector};
+use nalgebra::Unit;
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape, ShapeHandle};
+
+/// A shape scaled along each of its local axes, before its position and rotation are applied. Rays are cast against
+/// the unscaled shape by scaling them down; they are not normalised afterwards so times of impact are unchanged.
+#[derive(Clone)]
+pub struct Scaled {
+    shape: ShapeHandle<Scalar>,
+    scale: Vector,
+}
+
+impl Scaled {
+    pub fn new(shape: ShapeHandle<Scalar>, scale: Vector) -> Scaled {
+        assert!(shape.as_ray_cast().is_some(), "Scaled shapes must support ray casting.");
+        return Scaled { shape, scale };
+    }
+
+    /// The shape before scaling.
+    pub fn shape(&self) -> &ShapeHandle<Scalar> {
+        return &self.shape;
+    }
+
+    pub fn scale(&self) -> Vector {
+        return self.scale;
+    }
+
+    fn unscaled_ray(&self, m: &Isometry, ray: &Ray) -> Ray {
+        let local = ray.inverse_transform_by(m);
+        return Ray::new(
+            Point::from(local.origin.coords.component_div(&self.scale)),
+            local.dir.component_div(&self.scale),
+        );
+    }
+}
+
+impl RayCast<Scalar> for Scaled {
+    fn toi_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<Scalar> {
+        let ray = self.unscaled_ray(m, ray);
+        return self.shape.as_ray_cast()?.toi_with_ray(&Isometry::identity(), &ray, max_toi, solid);
+    }
+
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        let ray = self.unscaled_ray(m, ray);
+        let mut intersection = self.shape.as_ray_cast()?.toi_and_normal_with_ray(&Isometry::identity(), &ray, max_toi, solid)?;
+
+        // Normals transform by the inverse transpose of the scale.
+        intersection.normal = m * intersection.normal.component_div(&self.scale).normalize();
+        return Some(intersection);
+    }
+
+    fn toi_and_normal_and_uv_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        let ray = self.unscaled_ray(m, ray);
+        let mut intersection = self
+            .shape
+            .as_ray_cast()?
+            .toi_and_normal_and_uv_with_ray(&Isometry::identity(), &ray, max_toi, solid)?;
+
+        intersection.normal = m * intersection.normal.component_div(&self.scale).normalize();
+        return Some(intersection);
+    }
+}
+
+impl Shape<Scalar> for Scaled {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let aabb = self.shape.local_aabb();
+        let a = aabb.mins.coords.component_mul(&self.scale);
+        let b = aabb.maxs.coords.component_mul(&self.scale);
+        return AABB::new(Point::from(a.inf(&b)), Point::from(a.sup(&b)));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return self.aabb(m).bounding_sphere();
+    }
+
+    fn tangent_cone_contains_dir(&self, feature: FeatureId, m: &Isometry, deformations: Option<&[Scalar]>, dir: &Unit<Vector>) -> bool {
+        // Directions in the tangent cone stay in it when scaled, as the cone is scaled along with the shape.
+        let local = m.inverse_transform_vector(dir).component_div(&self.scale);
+        return self
+            .shape
+            .tangent_cone_contains_dir(feature, &Isometry::identity(), deformations, &Unit::new_normalize(local));
+    }
+
+    fn subshape_containing_feature(&self, feature: FeatureId) -> usize {
+        return self.shape.subshape_containing_feature(feature);
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::Sphere;
+    use approx::assert_abs_diff_eq;
+
+    fn ellipsoid() -> Scaled {
+        return Scaled::new(ShapeHandle::new(Sphere::new(1.0)), Vector::new(2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ray_along_scaled_axis() {
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::x());
+
+        let intersection = ellipsoid().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 3.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn normal_uses_inverse_transpose() {
+        // Hits the ellipsoid x²/4 + y² = 1 at (√2, √½), where the gradient is (x/2, 2y) ∝ (1, 2).
+        let point = Point::new(Scalar::sqrt(2.0), Scalar::sqrt(0.5), 0.0);
+        let ray = Ray::new(point + Vector::new(0.0, 5.0, 0.0), -Vector::y());
+
+        let intersection = ellipsoid().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 5.0, epsilon = 0.0001);
+        let expected = Vector::new(1.0, 2.0, 0.0).normalize();
+        assert_abs_diff_eq!(intersection.normal.x, expected.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, expected.y, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn local_aabb() {
+        let aabb = Scaled::new(ShapeHandle::new(Sphere::new(1.0)), Vector::new(-2.0, 1.0, 0.5)).local_aabb();
+
+        assert_eq!(aabb.mins, Point::new(-2.0, -1.0, -0.5));
+        assert_eq!(aabb.maxs, Point::new(2.0, 1.0, 0.5));
+    }
+}
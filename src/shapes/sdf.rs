@@ -0,0 +1,313 @@
+use crate::maths::{Isometry, Point, Rotation, Scalar, Vector};
+use nalgebra::{Translation3, Vector2};
+use ncollide3d::bounding_volume::{BoundingVolume, AABB};
+
+/// A signed distance field; the distance from a point to the nearest surface, negative inside. Fields built from the
+/// distortions, `Repeat` and `Twist`, and the fractals only estimate the distance, and `Twist` can overestimate it. The
+/// formulae are mostly from https://iquilezles.org/articles/distfunctions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sdf {
+    Sphere {
+        radius: Scalar,
+    },
+    /// A box with its edges rounded off by `rounding`, which doesn't change its overall size.
+    Cuboid {
+        half_extents: Vector,
+        rounding: Scalar,
+    },
+    /// A ring around the y axis.
+    Torus {
+        major_radius: Scalar,
+        minor_radius: Scalar,
+    },
+    /// A capped cylinder along the y axis.
+    Cylinder {
+        half_height: Scalar,
+        radius: Scalar,
+    },
+    /// A capsule along the y axis; `half_height` is half the length of the cylindrical part.
+    Capsule {
+        half_height: Scalar,
+        radius: Scalar,
+    },
+    /// Blends between the fields over `smoothness`, or joins them with a sharp edge if it is zero.
+    Union {
+        left: Box<Sdf>,
+        right: Box<Sdf>,
+        smoothness: Scalar,
+    },
+    /// The left field with the right field cut away.
+    Subtraction {
+        left: Box<Sdf>,
+        right: Box<Sdf>,
+        smoothness: Scalar,
+    },
+    Intersection {
+        left: Box<Sdf>,
+        right: Box<Sdf>,
+        smoothness: Scalar,
+    },
+    Translate {
+        offset: Vector,
+        field: Box<Sdf>,
+    },
+    Rotate {
+        rotation: Rotation,
+        field: Box<Sdf>,
+    },
+    /// Repeats the field infinitely along each axis with a non-zero period. The field should fit within one period.
+    Repeat {
+        period: Vector,
+        field: Box<Sdf>,
+    },
+    /// Twists the field around the y axis by `rate` radians per unit of height.
+    Twist {
+        rate: Scalar,
+        field: Box<Sdf>,
+    },
+    /// The Mandelbulb fractal, about a unit across, with its poles along the z axis.
+    Mandelbulb {
+        power: Scalar,
+        iterations: usize,
+    },
+    /// The Menger sponge fractal, a cube with square holes cut recursively through it.
+    Menger {
+        half_size: Scalar,
+        iterations: usize,
+    },
+}
+
+// Points further than this from the origin are considered to have escaped the Mandelbulb, which lies well within it.
+const MANDELBULB_BAILOUT: Scalar = 2.0;
+
+impl Sdf {
+    pub fn distance(&self, point: &Point) -> Scalar {
+        return match self {
+            Sdf::Sphere { radius } => point.coords.norm() - radius,
+            Sdf::Cuboid { half_extents, rounding } => {
+                let q = point.coords.abs() - half_extents + Vector::repeat(*rounding);
+                q.sup(&Vector::zeros()).norm() + q.max().min(0.0) - rounding
+            }
+            Sdf::Torus { major_radius, minor_radius } => Vector2::new(point.coords.xz().norm() - major_radius, point.y).norm() - minor_radius,
+            Sdf::Cylinder { half_height, radius } => {
+                let d = Vector2::new(point.coords.xz().norm() - radius, point.y.abs() - half_height);
+                d.max().min(0.0) + d.sup(&Vector2::zeros()).norm()
+            }
+            Sdf::Capsule { half_height, radius } => {
+                let y = point.y - point.y.clamp(-half_height, *half_height);
+                Vector::new(point.x, y, point.z).norm() - radius
+            }
+            Sdf::Union { left, right, smoothness } => smooth_minimum(left.distance(point), right.distance(point), *smoothness),
+            Sdf::Subtraction { left, right, smoothness } => -smooth_minimum(-left.distance(point), right.distance(point), *smoothness),
+            Sdf::Intersection { left, right, smoothness } => -smooth_minimum(-left.distance(point), -right.distance(point), *smoothness),
+            Sdf::Translate { offset, field } => field.distance(&(point - offset)),
+            Sdf::Rotate { rotation, field } => field.distance(&rotation.inverse_transform_point(point)),
+            Sdf::Repeat { period, field } => {
+                let repeated = point.coords.zip_map(period, |x, period| match period > 0.0 {
+                    true => x - period * (x / period).round(),
+                    false => x,
+                });
+                field.distance(&Point::from(repeated))
+            }
+            Sdf::Twist { rate, field } => {
+                let (sin, cos) = (rate * point.y).sin_cos();
+                field.distance(&Point::new(cos * point.x - sin * point.z, point.y, sin * point.x + cos * point.z))
+            }
+            Sdf::Mandelbulb { power, iterations } => mandelbulb(point, *power, *iterations),
+            Sdf::Menger { half_size, iterations } => menger(&(point / *half_size), *iterations) * half_size,
+        };
+    }
+
+    /// A box containing the surface, or `None` if it is unbounded.
+    pub fn bounds(&self) -> Option<AABB<Scalar>> {
+        let centred = |half_extents: Vector| AABB::new(Point::from(-half_extents), Point::from(half_extents));
+
+        return match self {
+            Sdf::Sphere { radius } => Some(centred(Vector::repeat(*radius))),
+            Sdf::Cuboid { half_extents, .. } => Some(centred(*half_extents)),
+            Sdf::Torus { major_radius, minor_radius } => {
+                let radius = major_radius + minor_radius;
+                Some(centred(Vector::new(radius, *minor_radius, radius)))
+            }
+            Sdf::Cylinder { half_height, radius } => Some(centred(Vector::new(*radius, *half_height, *radius))),
+            Sdf::Capsule { half_height, radius } => Some(centred(Vector::new(*radius, half_height + radius, *radius))),
+            // Smooth unions bulge out between the fields by up to a quarter of the smoothness.
+            Sdf::Union { left, right, smoothness } => Some(left.bounds()?.merged(&right.bounds()?).loosened(smoothness / 4.0)),
+            Sdf::Subtraction { left, .. } => left.bounds(),
+            Sdf::Intersection { left, right, .. } => match (left.bounds(), right.bounds()) {
+                (Some(left), Some(right)) => {
+                    let mins = left.mins.sup(&right.mins);
+                    Some(AABB::new(mins, left.maxs.inf(&right.maxs).sup(&mins)))
+                }
+                (left, right) => left.or(right),
+            },
+            Sdf::Translate { offset, field } => Some(
+                field
+                    .bounds()?
+                    .transform_by(&Isometry::from_parts(Translation3::from(*offset), Rotation::identity())),
+            ),
+            Sdf::Rotate { rotation, field } => Some(field.bounds()?.transform_by(&Isometry::from_parts(Translation3::identity(), *rotation))),
+            Sdf::Repeat { period, field } => match period.iter().all(|&period| period <= 0.0) {
+                true => field.bounds(),
+                false => None,
+            },
+            // Twisting sweeps the field around the y axis.
+            Sdf::Twist { field, .. } => {
+                let bounds = field.bounds()?;
+                let radius = bounds.mins.coords.xz().abs().sup(&bounds.maxs.coords.xz().abs()).norm();
+                Some(AABB::new(
+                    Point::new(-radius, bounds.mins.y, -radius),
+                    Point::new(radius, bounds.maxs.y, radius),
+                ))
+            }
+            Sdf::Mandelbulb { .. } => Some(centred(Vector::repeat(MANDELBULB_BAILOUT))),
+            Sdf::Menger { half_size, .. } => Some(centred(Vector::repeat(*half_size))),
+        };
+    }
+}
+
+/// The polynomial smooth minimum, which is the ordinary minimum when `smoothness` is zero.
+fn smooth_minimum(a: Scalar, b: Scalar, smoothness: Scalar) -> Scalar {
+    if smoothness <= 0.0 {
+        return a.min(b);
+    }
+
+    let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0.0, 1.0);
+    return b + (a - b) * h - smoothness * h * (1.0 - h);
+}
+
+// The distance estimate from https://iquilezles.org/articles/mandelbulb, in spherical coordinates.
+fn mandelbulb(point: &Point, power: Scalar, iterations: usize) -> Scalar {
+    let mut z = point.coords;
+    let mut derivative = 1.0;
+    let mut radius = z.norm();
+
+    for _ in 0..iterations {
+        if radius > MANDELBULB_BAILOUT || radius == 0.0 {
+            break;
+        }
+
+        let theta = (z.z / radius).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        derivative = power * radius.powf(power - 1.0) * derivative + 1.0;
+
+        z = radius.powf(power) * Vector::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()) + point.coords;
+        radius = z.norm();
+    }
+
+    // Points that never escape are inside, but the estimate breaks down at the very centre.
+    return match radius == 0.0 {
+        true => -1.0,
+        false => 0.5 * radius.ln() * radius / derivative,
+    };
+}
+
+// The sponge in a cube two units across, from https://iquilezles.org/articles/menger.
+fn menger(point: &Point, iterations: usize) -> Scalar {
+    let q = point.coords.abs() - Vector::repeat(1.0);
+    let mut distance = q.sup(&Vector::zeros()).norm() + q.max().min(0.0);
+
+    let mut scale = 1.0;
+    for _ in 0..iterations {
+        let a = (point.coords * scale).map(|x| x - 2.0 * (x / 2.0).floor()) - Vector::repeat(1.0);
+        scale *= 3.0;
+        let r = (Vector::repeat(1.0) - 3.0 * a.abs()).abs();
+
+        let cross = (r.x.max(r.y).min(r.y.max(r.z)).min(r.z.max(r.x)) - 1.0) / scale;
+        distance = distance.max(cross);
+    }
+
+    return distance;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::consts;
+    use approx::assert_abs_diff_eq;
+    use rstest::rstest;
+
+    fn sphere(radius: Scalar) -> Box<Sdf> {
+        return Box::new(Sdf::Sphere { radius });
+    }
+
+    fn translated(x: Scalar, field: Box<Sdf>) -> Box<Sdf> {
+        return Box::new(Sdf::Translate {
+            offset: Vector::new(x, 0.0, 0.0),
+            field,
+        });
+    }
+
+    #[rstest(
+        field,
+        point,
+        expected,
+        case(Sdf::Sphere { radius: 1.0 }, Point::new(0.0, 3.0, 0.0), 2.0),
+        case(Sdf::Cuboid { half_extents: Vector::new(1.0, 2.0, 3.0), rounding: 0.0 }, Point::new(4.0, 6.0, 0.0), 5.0),
+        case(Sdf::Cuboid { half_extents: Vector::new(1.0, 2.0, 3.0), rounding: 0.0 }, Point::new(0.5, 0.0, 0.0), -0.5),
+        case(Sdf::Cuboid { half_extents: Vector::new(1.0, 1.0, 1.0), rounding: 0.5 }, Point::new(2.0, 2.0, 0.0), Scalar::sqrt(2.0) * 1.5 - 0.5),
+        case(Sdf::Torus { major_radius: 2.0, minor_radius: 0.5 }, Point::new(0.0, 1.0, 2.0), 0.5),
+        case(Sdf::Cylinder { half_height: 1.0, radius: 1.0 }, Point::new(0.0, 3.0, 0.5), 2.0),
+        case(Sdf::Capsule { half_height: 1.0, radius: 0.5 }, Point::new(0.0, 3.0, 0.0), 1.5),
+        case(Sdf::Union { left: translated(-2.0, sphere(1.0)), right: translated(2.0, sphere(1.0)), smoothness: 0.0 }, Point::new(3.5, 0.0, 0.0), 0.5),
+        case(Sdf::Subtraction { left: sphere(2.0), right: sphere(1.0), smoothness: 0.0 }, Point::origin(), 1.0),
+        case(Sdf::Intersection { left: translated(-1.0, sphere(2.0)), right: translated(1.0, sphere(2.0)), smoothness: 0.0 }, Point::new(2.0, 0.0, 0.0), 1.0),
+        case(Sdf::Repeat { period: Vector::new(4.0, 0.0, 0.0), field: sphere(1.0) }, Point::new(7.5, 0.0, 0.0), -0.5),
+        case(Sdf::Twist { rate: consts::FRAC_PI_2, field: translated(2.0, sphere(0.5)) }, Point::new(0.0, 1.0, -2.0), 0.5)
+    )]
+    fn distance(field: Sdf, point: Point, expected: Scalar) {
+        assert_abs_diff_eq!(field.distance(&point), expected, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn smooth_union_blends() {
+        let field = Sdf::Union {
+            left: translated(-1.0, sphere(0.9)),
+            right: translated(1.0, sphere(0.9)),
+            smoothness: 0.5,
+        };
+
+        // The spheres don't touch, but the blend joins them.
+        assert!(field.distance(&Point::origin()) < 0.0);
+        assert_abs_diff_eq!(field.distance(&Point::new(-2.5, 0.0, 0.0)), 0.6, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn menger() {
+        let field = Sdf::Menger { half_size: 2.0, iterations: 3 };
+
+        // The middle of each face has a hole through to the centre.
+        assert!(field.distance(&Point::origin()) > 0.0);
+        assert!(field.distance(&Point::new(0.0, 0.0, 1.9)) > 0.0);
+        assert!(field.distance(&Point::new(1.9, 1.9, 1.9)) < 0.0);
+        assert_abs_diff_eq!(field.distance(&Point::new(5.0, 1.9, 1.9)), 3.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn mandelbulb() {
+        let field = Sdf::Mandelbulb { power: 8.0, iterations: 10 };
+
+        assert!(field.distance(&Point::new(0.1, 0.1, 0.1)) < 0.0);
+        assert!(field.distance(&Point::new(0.0, 0.0, 1.5)) > 0.0);
+        // The estimate is conservative.
+        assert!(field.distance(&Point::new(0.0, 3.0, 0.0)) < 3.0);
+    }
+
+    #[test]
+    fn bounds() {
+        let field = Sdf::Twist {
+            rate: 1.0,
+            field: translated(2.0, sphere(1.0)),
+        };
+
+        let bounds = field.bounds().unwrap();
+        assert_abs_diff_eq!(bounds.maxs.x, Scalar::sqrt(10.0), epsilon = 0.0001);
+        assert_abs_diff_eq!(bounds.mins.y, -1.0);
+
+        let field = Sdf::Repeat {
+            period: Vector::new(4.0, 0.0, 0.0),
+            field: sphere(1.0),
+        };
+        assert_eq!(field.bounds(), None);
+    }
+}
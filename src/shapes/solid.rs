@@ -0,0 +1,73 @@
+use crate::maths::{Ray, Scalar, Sphere, Vector};
+use crate::shapes::{quadratic_interval, Interval};
+use ncollide3d::shape::{Cuboid, FeatureId, Shape};
+
+/// A closed shape that can report every part of a ray inside it, as needed for constructive solid geometry. Rays are
+/// in the shape's local space, and intervals are in order and don't overlap.
+pub trait Solid: Shape<Scalar> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval>;
+}
+
+impl Solid for Sphere {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let offset = ray.origin.coords;
+        let coefficients = (
+            ray.dir.norm_squared(),
+            2.0 * offset.dot(&ray.dir),
+            offset.norm_squared() - self.radius * self.radius,
+        );
+
+        return quadratic_interval(coefficients, |_| true, |toi| ray.point_at(toi).coords / self.radius, FeatureId::Face(0))
+            .into_iter()
+            .collect();
+    }
+}
+
+impl Solid for Cuboid<Scalar> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let half_extents = self.half_extents;
+        let slab = |axis: usize| {
+            let features = [FeatureId::Face(axis), FeatureId::Face(axis + 3)];
+            return Interval::slab(ray, &Vector::ith(axis, 1.0), half_extents[axis], features);
+        };
+
+        return slab(0)
+            .and_then(|interval| interval.intersection(&slab(1)?))
+            .and_then(|interval| interval.intersection(&slab(2)?))
+            .into_iter()
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::Point;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn sphere() {
+        let intervals = Sphere::new(2.0).intervals(&Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::x()));
+
+        assert_eq!(intervals.len(), 1);
+        assert_abs_diff_eq!(intervals[0].entry.toi, 3.0);
+        assert_abs_diff_eq!(intervals[0].exit.toi, 7.0);
+        assert_abs_diff_eq!(intervals[0].entry.normal.x, -1.0);
+        assert_abs_diff_eq!(intervals[0].exit.normal.x, 1.0);
+    }
+
+    #[test]
+    fn cuboid() {
+        let cuboid = Cuboid::new(Vector::new(1.0, 2.0, 3.0));
+
+        let intervals = cuboid.intervals(&Ray::new(Point::new(0.5, -5.0, 0.0), Vector::y()));
+
+        assert_eq!(intervals.len(), 1);
+        assert_abs_diff_eq!(intervals[0].entry.toi, 3.0);
+        assert_abs_diff_eq!(intervals[0].exit.toi, 7.0);
+        assert_eq!(intervals[0].entry.normal, -Vector::y());
+        assert_eq!(intervals[0].exit.normal, Vector::y());
+
+        assert!(cuboid.intervals(&Ray::new(Point::new(1.5, -5.0, 0.0), Vector::y())).is_empty());
+    }
+}
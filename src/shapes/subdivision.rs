@@ -0,0 +1,729 @@
+use crate::maths::{consts, Point, Scalar, Vector};
+use crate::shapes::{Mesh, Shading};
+use std::collections::{HashMap, HashSet};
+
+/// The rule used to subdivide the faces of a mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SubdivisionScheme {
+    /// Splits each face of n sides into n quadrilaterals; best for meshes made mostly of quadrilaterals.
+    CatmullClark,
+    /// Splits each triangle into four; other polygons are split into triangles first.
+    Loop,
+}
+
+/// Smooths a polygon mesh by repeatedly subdividing its faces, then moving its vertices onto the limit surface the
+/// subdivision converges to, with that surface's normals. Crease edges, and the boundaries of open meshes, stay sharp;
+/// vertices where more than two meet, or of only one face, stay where they are.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::manual_non_exhaustive)]
+pub struct Subdivision {
+    pub scheme: SubdivisionScheme,
+    pub levels: usize,
+    /// Pairs of vertex indices of edges to keep sharp. Pairs that are not edges of the mesh are ignored, but the indices
+    /// must be of vertices.
+    pub creases: Vec<[usize; 2]>,
+    _private: (),
+}
+
+impl Subdivision {
+    /// # Panics
+    ///
+    /// Panics if `levels` is zero.
+    pub fn new(scheme: SubdivisionScheme, levels: usize, creases: Vec<[usize; 2]>) -> Subdivision {
+        assert!(levels > 0, "Subdivision needs at least one level.");
+        return Subdivision {
+            scheme,
+            levels,
+            creases,
+            _private: (),
+        };
+    }
+
+    /// Subdivides polygons, each of the indices of its vertices in `positions`, into a triangle mesh. Also returns the
+    /// index of the polygon each triangle came from, e.g. to give it the polygon's material. Vertices at the same
+    /// position are joined, so meshes split to give their corners different normals are still smoothed. Fails if
+    /// there are no polygons with at least three different vertices, or a crease's vertex is out of range.
+    pub fn to_mesh<F: AsRef<[usize]>>(&self, positions: &[Point], polygons: &[F], shading: Shading) -> Result<(Mesh, Vec<usize>), String> {
+        let mut cage = Cage::new(positions, polygons, &self.creases, self.scheme == SubdivisionScheme::Loop)?;
+        if cage.faces.is_empty() {
+            return Err("there are no faces with three different vertices to subdivide".to_string());
+        }
+        for _ in 0..self.levels {
+            cage = match self.scheme {
+                SubdivisionScheme::CatmullClark => cage.catmull_clark(),
+                SubdivisionScheme::Loop => cage.loop_subdivision(),
+            };
+        }
+        return Ok(cage.into_mesh(self.scheme, shading));
+    }
+}
+
+/// A polygon mesh part way through subdivision.
+struct Cage {
+    positions: Vec<Vector>,
+    faces: Vec<Vec<usize>>,
+    // The index of the original polygon each face came from.
+    origins: Vec<usize>,
+    creases: HashSet<[usize; 2]>,
+}
+
+/// Which faces meet at each edge and vertex of a cage.
+struct Topology {
+    edges: Vec<[usize; 2]>,
+    edge_indices: HashMap<[usize; 2], usize>,
+    edge_faces: Vec<Vec<usize>>,
+    vertex_edges: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+/// How a vertex moves, decided by the sharp edges that meet at it.
+enum VertexKind {
+    Smooth,
+    /// On a curve of sharp edges, to the vertices at the other ends of the two edges.
+    Crease(usize, usize),
+    Corner,
+}
+
+impl Cage {
+    fn new<F: AsRef<[usize]>>(positions: &[Point], polygons: &[F], creases: &[[usize; 2]], triangulate: bool) -> Result<Cage, String> {
+        if let Some(index) = creases.iter().flatten().find(|&&index| index >= positions.len()) {
+            return Err(format!("vertex {} of a crease is out of range", index));
+        }
+
+        // Vertices at the same position are joined, and only those used by a face kept.
+        let mut welded = vec![];
+        let mut vertices = HashMap::new();
+        let mut remap = vec![None; positions.len()];
+        for &index in polygons.iter().flat_map(|polygon| polygon.as_ref()) {
+            if remap[index].is_none() {
+                // Adding zero turns negative zeros positive, so they match.
+                let key = positions[index].coords.map(|coordinate| (coordinate + 0.0).to_bits());
+                remap[index] = Some(*vertices.entry(key).or_insert_with(|| {
+                    welded.push(positions[index].coords);
+                    welded.len() - 1
+                }));
+            }
+        }
+
+        let mut faces = vec![];
+        let mut origins = vec![];
+        for (origin, polygon) in polygons.iter().enumerate() {
+            let mut face: Vec<usize> = polygon.as_ref().iter().map(|&index| remap[index].unwrap()).collect();
+            face.dedup();
+            while face.len() > 1 && face.first() == face.last() {
+                face.pop();
+            }
+            if face.len() < 3 {
+                continue;
+            }
+
+            if triangulate {
+                for pair in face[1..].windows(2) {
+                    faces.push(vec![face[0], pair[0], pair[1]]);
+                    origins.push(origin);
+                }
+            } else {
+                faces.push(face);
+                origins.push(origin);
+            }
+        }
+
+        // Vertices not used by any face can't be on an edge.
+        let creases = creases.iter().filter_map(|[a, b]| Some(key(remap[*a]?, remap[*b]?))).collect();
+
+        return Ok(Cage {
+            positions: welded,
+            faces,
+            origins,
+            creases,
+        });
+    }
+
+    fn topology(&self) -> Topology {
+        let mut topology = Topology {
+            edges: vec![],
+            edge_indices: HashMap::new(),
+            edge_faces: vec![],
+            vertex_edges: vec![vec![]; self.positions.len()],
+            vertex_faces: vec![vec![]; self.positions.len()],
+        };
+
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for (index, &vertex) in face.iter().enumerate() {
+                topology.vertex_faces[vertex].push(face_index);
+
+                let edge_key = key(vertex, face[(index + 1) % face.len()]);
+                let edge = match topology.edge_indices.get(&edge_key) {
+                    Some(&edge) => edge,
+                    None => {
+                        let edge = topology.edges.len();
+                        topology.edges.push(edge_key);
+                        topology.edge_indices.insert(edge_key, edge);
+                        topology.edge_faces.push(vec![]);
+                        topology.vertex_edges[edge_key[0]].push(edge);
+                        topology.vertex_edges[edge_key[1]].push(edge);
+                        edge
+                    }
+                };
+                topology.edge_faces[edge].push(face_index);
+            }
+        }
+
+        return topology;
+    }
+
+    /// Boundary edges, and those shared by more than two faces, are sharp as well as creases.
+    fn is_sharp(&self, topology: &Topology, edge: usize) -> bool {
+        return topology.edge_faces[edge].len() != 2 || self.creases.contains(&topology.edges[edge]);
+    }
+
+    fn vertex_kind(&self, topology: &Topology, vertex: usize) -> VertexKind {
+        let sharp: Vec<usize> = topology.vertex_edges[vertex]
+            .iter()
+            .filter(|&&edge| self.is_sharp(topology, edge))
+            .map(|&edge| other_end(topology.edges[edge], vertex))
+            .collect();
+
+        return match sharp.len() {
+            _ if topology.vertex_faces[vertex].len() == 1 => VertexKind::Corner,
+            0 | 1 => VertexKind::Smooth,
+            2 => VertexKind::Crease(sharp[0], sharp[1]),
+            _ => VertexKind::Corner,
+        };
+    }
+
+    /// The position of a vertex on a crease or at a corner after subdividing, or `None` if it is smooth.
+    fn sharp_vertex_point(&self, topology: &Topology, vertex: usize) -> Option<Vector> {
+        let position = self.positions[vertex];
+        return match self.vertex_kind(topology, vertex) {
+            VertexKind::Smooth => None,
+            VertexKind::Crease(a, b) => Some((position * 6.0 + self.positions[a] + self.positions[b]) / 8.0),
+            VertexKind::Corner => Some(position),
+        };
+    }
+
+    /// Old vertices come first, then a vertex on each edge, then one in the middle of each face.
+    fn catmull_clark(&self) -> Cage {
+        let topology = self.topology();
+
+        let face_points: Vec<Vector> = self.faces.iter().map(|face| self.centroid(face)).collect();
+        let edge_points = topology.edges.iter().enumerate().map(|(edge, &[a, b])| {
+            let mid_point = (self.positions[a] + self.positions[b]) / 2.0;
+            if self.is_sharp(&topology, edge) {
+                return mid_point;
+            }
+            let faces = &topology.edge_faces[edge];
+            return (mid_point + (face_points[faces[0]] + face_points[faces[1]]) / 2.0) / 2.0;
+        });
+        let vertex_points = (0..self.positions.len()).map(|vertex| {
+            return self.sharp_vertex_point(&topology, vertex).unwrap_or_else(|| {
+                let edges = &topology.vertex_edges[vertex];
+                let faces = &topology.vertex_faces[vertex];
+                let valence = edges.len() as Scalar;
+                let face_average = faces.iter().map(|&face| face_points[face]).sum::<Vector>() / faces.len() as Scalar;
+                let edge_average = edges
+                    .iter()
+                    .map(|&edge| (self.positions[topology.edges[edge][0]] + self.positions[topology.edges[edge][1]]) / 2.0)
+                    .sum::<Vector>()
+                    / valence;
+                return (face_average + edge_average * 2.0 + self.positions[vertex] * (valence - 3.0)) / valence;
+            });
+        });
+
+        let edge_offset = self.positions.len();
+        let face_offset = edge_offset + topology.edges.len();
+        let edge_point = |a: usize, b: usize| edge_offset + topology.edge_indices[&key(a, b)];
+
+        let mut faces = vec![];
+        let mut origins = vec![];
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for (index, &vertex) in face.iter().enumerate() {
+                let next = face[(index + 1) % face.len()];
+                let previous = face[(index + face.len() - 1) % face.len()];
+                faces.push(vec![vertex, edge_point(vertex, next), face_offset + face_index, edge_point(previous, vertex)]);
+                origins.push(self.origins[face_index]);
+            }
+        }
+
+        return Cage {
+            positions: vertex_points.chain(edge_points).chain(face_points.iter().copied()).collect(),
+            faces,
+            origins,
+            creases: self.split_creases(&topology),
+        };
+    }
+
+    /// Old vertices come first, then a vertex on each edge.
+    fn loop_subdivision(&self) -> Cage {
+        let topology = self.topology();
+
+        let edge_points = topology.edges.iter().enumerate().map(|(edge, &[a, b])| {
+            let ends = self.positions[a] + self.positions[b];
+            if self.is_sharp(&topology, edge) {
+                return ends / 2.0;
+            }
+            let opposite: Vector = topology.edge_faces[edge]
+                .iter()
+                .map(|&face| self.faces[face].iter().find(|&&vertex| vertex != a && vertex != b).unwrap())
+                .map(|&vertex| self.positions[vertex])
+                .sum();
+            return ends * 3.0 / 8.0 + opposite / 8.0;
+        });
+        let vertex_points = (0..self.positions.len()).map(|vertex| {
+            return self.sharp_vertex_point(&topology, vertex).unwrap_or_else(|| {
+                let neighbours = self.neighbour_sum(&topology, vertex);
+                let valence = topology.vertex_edges[vertex].len() as Scalar;
+                let beta = loop_beta(valence);
+                return self.positions[vertex] * (1.0 - valence * beta) + neighbours * beta;
+            });
+        });
+
+        let edge_offset = self.positions.len();
+        let edge_point = |a: usize, b: usize| edge_offset + topology.edge_indices[&key(a, b)];
+
+        let mut faces = vec![];
+        let mut origins = vec![];
+        for (face, origin) in self.faces.iter().zip(self.origins.iter()) {
+            let [a, b, c] = [face[0], face[1], face[2]];
+            let [ab, bc, ca] = [edge_point(a, b), edge_point(b, c), edge_point(c, a)];
+            faces.extend([vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]);
+            origins.extend([*origin; 4]);
+        }
+
+        return Cage {
+            positions: vertex_points.chain(edge_points).collect(),
+            faces,
+            origins,
+            creases: self.split_creases(&topology),
+        };
+    }
+
+    /// Each crease split in two at the new vertex on it, which subdivision puts after the old vertices.
+    fn split_creases(&self, topology: &Topology) -> HashSet<[usize; 2]> {
+        return self
+            .creases
+            .iter()
+            .filter_map(|crease| topology.edge_indices.get(crease).map(|&edge| (crease, self.positions.len() + edge)))
+            .flat_map(|(&[a, b], middle)| [key(a, middle), key(middle, b)])
+            .collect();
+    }
+
+    /// Triangulates the cage with its vertices moved to the limit surface. Vertices on creases and at corners are
+    /// split, one for each sector of faces between the sharp edges, so each side of a crease gets its own normal.
+    fn into_mesh(self, scheme: SubdivisionScheme, shading: Shading) -> (Mesh, Vec<usize>) {
+        let topology = self.topology();
+
+        // Each corner of each face, numbered consecutively, joined into sectors across edges that aren't sharp.
+        let mut offsets = vec![0];
+        offsets.extend(self.faces.iter().scan(0, |offset, face| {
+            *offset += face.len();
+            return Some(*offset);
+        }));
+        let corner = |face: usize, vertex: usize| offsets[face] + self.faces[face].iter().position(|&other| other == vertex).unwrap();
+        let mut sectors = DisjointSets::new(offsets[self.faces.len()]);
+        for (edge, vertices) in topology.edges.iter().enumerate() {
+            if !self.is_sharp(&topology, edge) {
+                let faces = &topology.edge_faces[edge];
+                for &vertex in vertices {
+                    sectors.union(corner(faces[0], vertex), corner(faces[1], vertex));
+                }
+            }
+        }
+
+        // A vertex of the mesh for each sector, with the sum of the normals of its faces.
+        let mut sector_vertices = HashMap::new();
+        let mut vertices: Vec<(usize, Vector)> = vec![];
+        let mut indices = vec![];
+        let mut origins = vec![];
+        for (face_index, face) in self.faces.iter().enumerate() {
+            let normal = self.face_normal(face);
+            let corners: Vec<usize> = face
+                .iter()
+                .map(|&vertex| {
+                    let sector = sectors.find(corner(face_index, vertex));
+                    let index = *sector_vertices.entry(sector).or_insert_with(|| {
+                        vertices.push((vertex, Vector::zeros()));
+                        vertices.len() - 1
+                    });
+                    vertices[index].1 += normal;
+                    return index;
+                })
+                .collect();
+            for pair in corners[1..].windows(2) {
+                indices.push([corners[0], pair[0], pair[1]]);
+                origins.push(self.origins[face_index]);
+            }
+        }
+
+        let mut directed_edges = HashMap::new();
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for (index, &vertex) in face.iter().enumerate() {
+                directed_edges.insert((vertex, face[(index + 1) % face.len()]), (face_index, index));
+            }
+        }
+
+        let (positions, normals) = vertices
+            .iter()
+            .map(|&(vertex, sector_normal)| {
+                let position = self.positions[vertex];
+                let (limit, normal) = match self.vertex_kind(&topology, vertex) {
+                    VertexKind::Smooth => (
+                        self.smooth_limit(scheme, &topology, vertex),
+                        self.limit_normal(scheme, &topology, &directed_edges, vertex).unwrap_or(sector_normal),
+                    ),
+                    VertexKind::Crease(a, b) => {
+                        // The limit curve of the crease, with the normal made perpendicular to it.
+                        let tangent = self.positions[b] - self.positions[a];
+                        let across = sector_normal - tangent * sector_normal.dot(&tangent) / tangent.norm_squared();
+                        ((self.positions[a] + position * 4.0 + self.positions[b]) / 6.0, across)
+                    }
+                    VertexKind::Corner => (position, sector_normal),
+                };
+                let normal = normal.try_normalize(0.0).unwrap_or(sector_normal);
+                let normal = match normal.dot(&sector_normal) < 0.0 {
+                    true => -normal,
+                    false => normal,
+                };
+                return (Point::from(limit), normal);
+            })
+            .unzip();
+
+        return (Mesh::new(positions, indices, Some(normals), None, shading), origins);
+    }
+
+    fn smooth_limit(&self, scheme: SubdivisionScheme, topology: &Topology, vertex: usize) -> Vector {
+        let position = self.positions[vertex];
+        let valence = topology.vertex_edges[vertex].len() as Scalar;
+        let neighbours = self.neighbour_sum(topology, vertex);
+
+        return match scheme {
+            SubdivisionScheme::CatmullClark => {
+                // All faces are quadrilaterals once subdivided.
+                let diagonals: Vector = topology.vertex_faces[vertex]
+                    .iter()
+                    .map(|&face| {
+                        let face = &self.faces[face];
+                        let index = face.iter().position(|&other| other == vertex).unwrap();
+                        return self.positions[face[(index + 2) % 4]];
+                    })
+                    .sum();
+                (position * valence * valence + neighbours * 4.0 + diagonals) / (valence * (valence + 5.0))
+            }
+            SubdivisionScheme::Loop => {
+                let chi = 1.0 / (3.0 / (8.0 * loop_beta(valence)) + valence);
+                position * (1.0 - valence * chi) + neighbours * chi
+            }
+        };
+    }
+
+    /// The normal of the limit surface at a smooth vertex, from the cross product of its two limit tangents. `None`
+    /// if its faces don't close up into a single fan around it.
+    fn limit_normal(
+        &self,
+        scheme: SubdivisionScheme,
+        topology: &Topology,
+        directed_edges: &HashMap<(usize, usize), (usize, usize)>,
+        vertex: usize,
+    ) -> Option<Vector> {
+        // The faces in order around the vertex, and where the vertex is in each; each face's next vertex after it is
+        // the previous face's last.
+        let start = *topology.vertex_faces[vertex].first()?;
+        let mut ring = vec![(start, self.faces[start].iter().position(|&other| other == vertex)?)];
+        loop {
+            let (face, index) = *ring.last().unwrap();
+            let face = &self.faces[face];
+            let previous = face[(index + face.len() - 1) % face.len()];
+            let &next = directed_edges.get(&(vertex, previous))?;
+            if next.0 == start {
+                break;
+            }
+            if ring.len() == topology.vertex_faces[vertex].len() {
+                return None;
+            }
+            ring.push(next);
+        }
+        if ring.len() != topology.vertex_faces[vertex].len() {
+            return None;
+        }
+
+        let valence = ring.len() as Scalar;
+        let angle = |index: usize| 2.0 * consts::PI * index as Scalar / valence;
+        let mut tangents = [Vector::zeros(), Vector::zeros()];
+        for (index, &(face, corner)) in ring.iter().enumerate() {
+            let face = &self.faces[face];
+            let neighbour = self.positions[face[(corner + 1) % face.len()]];
+            let (sin, cos) = angle(index).sin_cos();
+            match scheme {
+                SubdivisionScheme::CatmullClark => {
+                    let diagonal = self.positions[face[(corner + 2) % face.len()]];
+                    let (next_sin, next_cos) = angle(index + 1).sin_cos();
+                    let cos_step = angle(1).cos();
+                    let edge_weight = 1.0 + cos_step + (angle(1) / 2.0).cos() * (2.0 * (9.0 + cos_step)).sqrt();
+                    tangents[0] += neighbour * edge_weight * cos + diagonal * (cos + next_cos);
+                    tangents[1] += neighbour * edge_weight * sin + diagonal * (sin + next_sin);
+                }
+                SubdivisionScheme::Loop => {
+                    tangents[0] += neighbour * cos;
+                    tangents[1] += neighbour * sin;
+                }
+            }
+        }
+
+        return Some(tangents[0].cross(&tangents[1]));
+    }
+
+    fn neighbour_sum(&self, topology: &Topology, vertex: usize) -> Vector {
+        return topology.vertex_edges[vertex]
+            .iter()
+            .map(|&edge| self.positions[other_end(topology.edges[edge], vertex)])
+            .sum();
+    }
+
+    fn centroid(&self, face: &[usize]) -> Vector {
+        return face.iter().map(|&vertex| self.positions[vertex]).sum::<Vector>() / face.len() as Scalar;
+    }
+
+    /// Newell's method, which is weighted by area and works for polygons that aren't flat.
+    fn face_normal(&self, face: &[usize]) -> Vector {
+        return face
+            .iter()
+            .zip(face.iter().cycle().skip(1))
+            .map(|(&a, &b)| self.positions[a].cross(&self.positions[b]))
+            .sum();
+    }
+}
+
+fn key(a: usize, b: usize) -> [usize; 2] {
+    return [a.min(b), a.max(b)];
+}
+
+fn other_end(edge: [usize; 2], vertex: usize) -> usize {
+    return match edge[0] == vertex {
+        true => edge[1],
+        false => edge[0],
+    };
+}
+
+/// Loop's weight for each neighbour of a smooth vertex.
+fn loop_beta(valence: Scalar) -> Scalar {
+    let cosine_term = 3.0 / 8.0 + (2.0 * consts::PI / valence).cos() / 4.0;
+    return (5.0 / 8.0 - cosine_term * cosine_term) / valence;
+}
+
+/// Union–find over indices, for joining face corners into sectors.
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> DisjointSets {
+        return DisjointSets { parents: (0..count).collect() };
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // Point everything on the way straight at the root, so later finds are quick.
+        let mut current = index;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+        return root;
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a] = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths::{Isometry, Ray};
+    use approx::assert_abs_diff_eq;
+    use ncollide3d::query::RayCast;
+    use rstest::rstest;
+
+    fn cube() -> (Vec<Point>, Vec<[usize; 4]>) {
+        let positions = (0..8)
+            .map(|index| Point::new([-0.5, 0.5][index & 1], [-0.5, 0.5][(index >> 1) & 1], [-0.5, 0.5][index >> 2]))
+            .collect();
+        let faces = vec![[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        return (positions, faces);
+    }
+
+    fn octahedron() -> (Vec<Point>, Vec<[usize; 3]>) {
+        let positions = vec![
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(0.0, -1.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 0.0, -1.0),
+        ];
+        let faces = vec![[0, 2, 4], [2, 1, 4], [1, 3, 4], [3, 0, 4], [2, 0, 5], [1, 2, 5], [3, 1, 5], [0, 3, 5]];
+        return (positions, faces);
+    }
+
+    fn smooth() -> Shading {
+        return Shading::Smooth { crease_angle: consts::PI };
+    }
+
+    fn cast(mesh: &Mesh, origin: Point, target: Point) -> (Point, Vector) {
+        let ray = Ray::new(origin, (target - origin).normalize());
+        let intersection = mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        return (ray.point_at(intersection.toi), intersection.normal);
+    }
+
+    // Directions from the centre of a shape through its faces, edges and corners.
+    fn directions() -> Vec<Vector> {
+        return vec![
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(1.0, 0.0, 1.0),
+            Vector::new(-1.0, 1.0, 1.0),
+            Vector::new(0.3, -0.8, 0.2),
+        ];
+    }
+
+    #[test]
+    fn catmull_clark_rounds_a_cube() {
+        let (positions, faces) = cube();
+
+        let (mesh, origins) = Subdivision::new(SubdivisionScheme::CatmullClark, 3, vec![])
+            .to_mesh(&positions, &faces, smooth())
+            .unwrap();
+
+        assert_eq!(mesh.triangles().faces().len(), 6 * 4 * 4 * 4 * 2);
+        assert_eq!(origins.len(), mesh.triangles().faces().len());
+        for direction in directions() {
+            let (point, normal) = cast(&mesh, Point::from(direction * 5.0), Point::origin());
+            // Inside the cube but well outside its inscribed sphere's core, and facing outwards much like a sphere.
+            assert!(point.coords.amax() < 0.5 && point.coords.norm() > 0.3);
+            assert!(normal.dot(&direction.normalize()) > 0.9);
+        }
+        // Symmetry puts the normal in the middle of each face straight out.
+        assert_abs_diff_eq!(cast(&mesh, Point::new(0.0, 0.0, 5.0), Point::origin()).1, Vector::z(), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn loop_rounds_an_octahedron() {
+        let (positions, faces) = octahedron();
+
+        let (mesh, _) = Subdivision::new(SubdivisionScheme::Loop, 3, vec![])
+            .to_mesh(&positions, &faces, smooth())
+            .unwrap();
+
+        assert_eq!(mesh.triangles().faces().len(), 8 * 4 * 4 * 4);
+        for direction in directions() {
+            let (point, normal) = cast(&mesh, Point::from(direction * 5.0), Point::origin());
+            assert!(point.coords.amax() < 1.0 && point.coords.norm() > 0.4);
+            assert!(normal.dot(&direction.normalize()) > 0.9);
+        }
+        assert_abs_diff_eq!(cast(&mesh, Point::new(0.0, 5.0, 0.0), Point::origin()).1, Vector::y(), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn grid_keeps_its_boundary() {
+        // Two by two squares on the x–z plane.
+        let positions: Vec<Point> = (0..9)
+            .map(|index| Point::new((index % 3) as Scalar - 1.0, 0.0, (index / 3) as Scalar - 1.0))
+            .collect();
+        let faces = [[0, 3, 4, 1], [1, 4, 5, 2], [3, 6, 7, 4], [4, 7, 8, 5]];
+
+        let (mesh, _) = Subdivision::new(SubdivisionScheme::CatmullClark, 2, vec![])
+            .to_mesh(&positions, &faces, smooth())
+            .unwrap();
+
+        let points = mesh.triangles().points();
+        assert!(points.iter().all(|point| point.y == 0.0));
+        // The corners stay put, and the edges stay straight.
+        assert!(points.contains(&Point::new(1.0, 0.0, 1.0)));
+        assert!(points.iter().all(|point| point.coords.amax() <= 1.0));
+        assert_eq!(points.iter().filter(|point| point.x == 1.0).count(), 9);
+        let (_, normal) = cast(&mesh, Point::new(0.9, 1.0, -0.3), Point::new(0.9, 0.0, -0.3));
+        assert_abs_diff_eq!(normal, Vector::y(), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn creased_cube_stays_a_cube() {
+        let (positions, faces) = cube();
+        let creases = faces
+            .iter()
+            .flat_map(|face| (0..4).map(move |index| [face[index], face[(index + 1) % 4]]))
+            .collect();
+
+        let (mesh, _) = Subdivision::new(SubdivisionScheme::CatmullClark, 2, creases)
+            .to_mesh(&positions, &faces, smooth())
+            .unwrap();
+
+        assert!(mesh.triangles().points().iter().all(|point| {
+            return (point.coords.amax() - 0.5).abs() < 0.0001;
+        }));
+        // Each face keeps its own normal right up to the edges.
+        for target in [Point::new(0.49, 0.49, 0.5), Point::new(0.0, 0.0, 0.5), Point::new(-0.49, 0.2, 0.5)] {
+            let (_, normal) = cast(&mesh, target + Vector::new(0.0, 0.0, 1.0), target);
+            assert_abs_diff_eq!(normal, Vector::z(), epsilon = 0.0001);
+        }
+    }
+
+    #[test]
+    fn triangles_know_their_polygon() {
+        // A quadrilateral and a triangle sharing an edge.
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(2.0, 0.5, 0.0),
+        ];
+        let polygons = vec![vec![0, 1, 2, 3], vec![1, 4, 2]];
+
+        let (mesh, origins) = Subdivision::new(SubdivisionScheme::CatmullClark, 1, vec![])
+            .to_mesh(&positions, &polygons, smooth())
+            .unwrap();
+
+        assert_eq!(origins, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1]);
+        let ray = Ray::new(Point::new(1.6, 0.5, 1.0), -Vector::z());
+        let intersection = mesh.toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, true).unwrap();
+        assert_eq!(origins[mesh.face(intersection.feature).unwrap()], 1);
+    }
+
+    #[rstest(
+        polygons,
+        creases,
+        expected,
+        case(vec![vec![0, 0, 1]], vec![], "no faces"),
+        case(vec![vec![0, 1]], vec![], "no faces"),
+        case(vec![vec![0, 1, 2]], vec![[0, 3]], "vertex 3 of a crease")
+    )]
+    fn invalid(polygons: Vec<Vec<usize>>, creases: Vec<[usize; 2]>, expected: &str) {
+        let positions = vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(0.0, 1.0, 0.0)];
+
+        for scheme in [SubdivisionScheme::CatmullClark, SubdivisionScheme::Loop] {
+            let error = Subdivision::new(scheme, 1, creases.clone())
+                .to_mesh(&positions, &polygons, smooth())
+                .err()
+                .unwrap();
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn split_vertices_are_joined() {
+        // The cube with separate vertices for each face, as exported for flat shading.
+        let (positions, faces) = cube();
+        let split_positions: Vec<Point> = faces.iter().flatten().map(|&index| positions[index]).collect();
+        let split_faces: Vec<[usize; 4]> = (0..6).map(|face| [0, 1, 2, 3].map(|corner| face * 4 + corner)).collect();
+        let subdivision = Subdivision::new(SubdivisionScheme::CatmullClark, 2, vec![]);
+
+        let (joined, _) = subdivision.to_mesh(&positions, &faces, smooth()).unwrap();
+        let (split, _) = subdivision.to_mesh(&split_positions, &split_faces, smooth()).unwrap();
+
+        assert_eq!(split.triangles().points().len(), joined.triangles().points().len());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::maths::polynomial;
+use crate::maths::{Isometry, Point, Ray, RayIntersection, Scalar, Unit, Vector};
+use crate::shapes::{Crossing, Interval, Solid};
+use ncollide3d::bounding_volume::{BoundingSphere, AABB};
+use ncollide3d::query::RayCast;
+use ncollide3d::shape::{FeatureId, Shape};
+
+/// A ring around the y axis, centred on the origin. The tube's centre is `major_radius` from the axis and its radius is
+/// `minor_radius`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Torus {
+    pub major_radius: Scalar,
+    pub minor_radius: Scalar,
+}
+
+impl Torus {
+    pub fn new(major_radius: Scalar, minor_radius: Scalar) -> Torus {
+        assert!(
+            minor_radius > 0.0 && major_radius >= 0.0,
+            "Tori must have a positive minor radius and a non-negative major radius."
+        );
+        return Torus { major_radius, minor_radius };
+    }
+
+    fn normal(&self, point: &Point) -> Vector {
+        // Away from the nearest point on the circle through the centre of the tube.
+        let around = Vector::new(point.x, 0.0, point.z).try_normalize(0.0).unwrap_or_else(Vector::x);
+        return (point.coords - around * self.major_radius) / self.minor_radius;
+    }
+}
+
+impl Solid for Torus {
+    // There are at most two intervals, one for each side of the ring.
+    #[cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let radius = (self.major_radius + self.minor_radius) as f64;
+
+        // The quartic's coefficients grow with the distance from the ray's origin to the torus, losing precision, so
+        // solve from where the ray enters the bounding sphere.
+        let (origin, direction) = (ray.origin.coords.map(|c| c as f64), ray.dir.map(|c| c as f64));
+        let sphere_roots = polynomial::solve_quadratic(direction.norm_squared(), 2.0 * origin.dot(&direction), origin.norm_squared() - radius * radius);
+        if sphere_roots.len() < 2 {
+            return vec![];
+        }
+        let start = sphere_roots[0].max(0.0).min(sphere_roots[1]);
+        let origin = origin + direction * start;
+
+        // The surface is (|p|² + R² - r²)² = 4R²(x² + z²).
+        let major_squared = (self.major_radius as f64).powi(2);
+        let direction_squared = direction.norm_squared();
+        let origin_direction = origin.dot(&direction);
+        let k = origin.norm_squared() + major_squared - (self.minor_radius as f64).powi(2);
+        let coefficients = [
+            direction_squared * direction_squared,
+            4.0 * direction_squared * origin_direction,
+            4.0 * origin_direction * origin_direction + 2.0 * direction_squared * k
+                - 4.0 * major_squared * (direction.x * direction.x + direction.z * direction.z),
+            4.0 * origin_direction * k - 8.0 * major_squared * (origin.x * direction.x + origin.z * direction.z),
+            k * k - 4.0 * major_squared * (origin.x * origin.x + origin.z * origin.z),
+        ];
+
+        let roots = polynomial::solve_quartic(coefficients[0], coefficients[1], coefficients[2], coefficients[3], coefficients[4]);
+
+        // Roots close together can be lost or merged where the ray grazes the surface, so rather than pairing them up,
+        // check whether the ray is inside the torus between each of them.
+        let crossing = |toi: f64| {
+            let toi = (toi + start) as Scalar;
+            return Crossing::new(toi, self.normal(&ray.point_at(toi)), FeatureId::Face(0));
+        };
+        return roots
+            .windows(2)
+            .filter(|pair| polynomial::evaluate((pair[0] + pair[1]) / 2.0, &coefficients).0 < 0.0)
+            .map(|pair| Interval::new(crossing(pair[0]), crossing(pair[1])))
+            .collect();
+    }
+}
+
+impl RayCast<Scalar> for Torus {
+    fn toi_and_normal_with_ray(&self, m: &Isometry, ray: &Ray, max_toi: Scalar, solid: bool) -> Option<RayIntersection> {
+        return Interval::first_ray_intersection(&self.intervals(&ray.inverse_transform_by(m)), m, max_toi, solid);
+    }
+}
+
+impl Shape<Scalar> for Torus {
+    fn aabb(&self, m: &Isometry) -> AABB<Scalar> {
+        return self.local_aabb().transform_by(m);
+    }
+
+    fn local_aabb(&self) -> AABB<Scalar> {
+        let radius = self.major_radius + self.minor_radius;
+        let half_extents = Vector::new(radius, self.minor_radius, radius);
+        return AABB::new(Point::from(-half_extents), Point::from(half_extents));
+    }
+
+    fn bounding_sphere(&self, m: &Isometry) -> BoundingSphere<Scalar> {
+        return BoundingSphere::new(m * Point::origin(), self.major_radius + self.minor_radius);
+    }
+
+    fn tangent_cone_contains_dir(&self, _: FeatureId, _: &Isometry, _: Option<&[Scalar]>, _: &Unit<Vector>) -> bool {
+        return false;
+    }
+
+    fn as_ray_cast(&self) -> Option<&dyn RayCast<Scalar>> {
+        return Some(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use nalgebra::Matrix;
+    use rstest::rstest;
+
+    fn torus() -> Torus {
+        return Torus::new(2.0, 0.5);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        toi,
+        normal,
+        case(Point::new(-10.0, 0.0, 0.0), Vector::x(), 7.5, -Vector::x()),
+        case(Point::new(0.0, 10.0, 2.0), -Vector::y(), 9.5, Vector::y()),
+        case(Point::new(0.0, 0.25, -10.0), Vector::new(0.0, 0.0, 2.0), 3.7834936, Matrix::normalize(&Vector::new(0.0, 0.25, -0.4330127))),
+        // From the hole in the middle.
+        case(Point::origin(), Vector::z(), 1.5, -Vector::z())
+    )]
+    fn hit(origin: Point, direction: Vector, toi: Scalar, normal: Vector) {
+        let intersection = torus()
+            .toi_and_normal_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, toi, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, normal.x, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.y, normal.y, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.z, normal.z, epsilon = 0.0001);
+    }
+
+    #[rstest(
+        origin,
+        direction,
+        case(Point::new(0.0, 10.0, 0.0), -Vector::y()),
+        case(Point::new(-10.0, 0.6, 0.0), Vector::x()),
+        case(Point::new(-10.0, 0.0, 0.0), -Vector::x())
+    )]
+    fn miss(origin: Point, direction: Vector) {
+        assert!(torus()
+            .toi_with_ray(&Isometry::identity(), &Ray::new(origin, direction), Scalar::MAX, true)
+            .is_none());
+    }
+
+    #[test]
+    fn intervals_through_both_sides() {
+        let intervals = torus().intervals(&Ray::new(Point::new(-10.0, 0.0, 0.0), Vector::x()));
+
+        assert_eq!(intervals.len(), 2);
+        assert_abs_diff_eq!(intervals[0].entry.toi, 7.5, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[0].exit.toi, 8.5, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[1].entry.toi, 11.5, epsilon = 0.0001);
+        assert_abs_diff_eq!(intervals[1].exit.toi, 12.5, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn exit_from_inside_tube() {
+        let ray = Ray::new(Point::new(2.0, 0.0, 0.0), Vector::x());
+
+        let intersection = torus().toi_and_normal_with_ray(&Isometry::identity(), &ray, Scalar::MAX, false).unwrap();
+
+        assert_abs_diff_eq!(intersection.toi, 0.5, epsilon = 0.0001);
+        assert_abs_diff_eq!(intersection.normal.x, -1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn grazing_rays_hit_or_miss_cleanly() {
+        // Rays skimming the top of the tube, from just inside to just outside.
+        for offset in [-0.001, -0.0001, 0.0, 0.0001, 0.001] {
+            let ray = Ray::new(Point::new(-10.0, 0.5 + offset, 0.0), Vector::x());
+
+            let intervals = torus().intervals(&ray);
+
+            for interval in &intervals {
+                assert!(interval.entry.toi <= interval.exit.toi);
+                assert_abs_diff_eq!(ray.point_at(interval.entry.toi).y, 0.5, epsilon = 0.01);
+                assert!(interval.entry.normal.iter().all(|component| component.is_finite()));
+            }
+            if offset < 0.0 {
+                assert_eq!(intervals.len(), 2);
+            }
+            if offset > 0.0 {
+                assert!(intervals.is_empty());
+            }
+        }
+    }
+}